@@ -0,0 +1,104 @@
+//! Benchmarks for lowering large, synthetically generated functions.
+//!
+//! Run them with `cargo bench -p analysis`. Only lowering is measured, parsing happens beforehand.
+
+use std::{
+    fmt::Write,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use analysis::LoweringCx;
+
+const ITERATIONS: usize = 30;
+
+fn main() {
+    bench("many_statements", &many_statements(5000));
+    bench("many_locals", &many_locals(5000));
+    bench("deep_nesting", &deep_nesting(200));
+    bench("many_scopes", &many_scopes(300, 2000));
+}
+
+fn bench(name: &str, src: &str) {
+    let ast =
+        parser::parse_file(src).unwrap_or_else(|err| panic!("{name} failed to parse: {err:?}"));
+
+    let mut times = Vec::with_capacity(ITERATIONS);
+    let mut allocated = 0;
+    for _ in 0..ITERATIONS {
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+
+        let start = Instant::now();
+        let ir = analysis::lower_translation_unit(&mut lcx, &ast)
+            .unwrap_or_else(|err| panic!("{name} failed to lower: {err:?}"));
+        times.push(start.elapsed());
+
+        black_box(ir);
+        allocated = arena.allocated_bytes();
+    }
+
+    times.sort();
+    let median = times[ITERATIONS / 2];
+    let min = times[0];
+    println!(
+        "{name:<16} median {:>9.3}ms   min {:>9.3}ms   arena {:>9} bytes",
+        ms(median),
+        ms(min),
+        allocated
+    );
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// A single local that gets assigned over and over again.
+fn many_statements(n: usize) -> String {
+    let mut src = String::from("int f() {\n    int x = 0;\n");
+    for i in 0..n {
+        writeln!(src, "    x = x + {i};").unwrap();
+    }
+    src.push_str("    return x;\n}\n");
+    src
+}
+
+/// Lots of locals in the same scope.
+fn many_locals(n: usize) -> String {
+    let mut src = String::from("int f() {\n");
+    for i in 0..n {
+        writeln!(src, "    int x{i} = {i};").unwrap();
+    }
+    src.push_str("    return x0;\n}\n");
+    src
+}
+
+/// Deeply nested `if`s, each with their own block.
+fn deep_nesting(depth: usize) -> String {
+    let mut src = String::from("int f(int x) {\n");
+    for _ in 0..depth {
+        src.push_str("if (x) {\nx = x + 1;\n");
+    }
+    for _ in 0..depth {
+        src.push_str("}\n");
+    }
+    src.push_str("return x;\n}\n");
+    src
+}
+
+/// Many nested scopes with a variable from the outermost scope being used in the innermost one,
+/// so every use has to walk through all scopes in `resolve_ident`.
+fn many_scopes(depth: usize, uses: usize) -> String {
+    let mut src = String::from("int f() {\nint outer = 0;\n");
+    for i in 0..depth {
+        writeln!(src, "if (1) {{\nint v{i} = {i};").unwrap();
+    }
+    for _ in 0..uses {
+        src.push_str("outer = outer + 1;\n");
+    }
+    for _ in 0..depth {
+        src.push_str("}\n");
+    }
+    src.push_str("return outer;\n}\n");
+    src
+}
@@ -1,28 +1,101 @@
 use std::{
-    cell::{Cell, RefCell},
+    cell::{Cell, OnceCell, RefCell},
     fmt::Debug,
 };
 
+use indexmap::IndexMap;
 use parser::{
     ast::{self, IntSign, IntTy, IntTyKind},
-    Symbol,
+    Error, Span, Symbol,
 };
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
-    ir::{DefId, Layout, TyLayout, VariableInfo},
-    ty::{Ty, TyKind},
+    ir::{DefId, Layout, Relocation, TyLayout, VariableInfo},
+    lower::eval_const_expr,
+    ty::{StructTy, Ty, TyKind},
 };
 
+/// The `DefId`, initial contents and relocations of a static.
+type StaticData<'cx> = (DefId, &'cx [u8], Vec<Relocation>);
+
 pub struct LoweringCx<'cx> {
     tys: RefCell<FxHashSet<&'cx TyKind<'cx>>>,
     layouts: RefCell<FxHashSet<&'cx Layout>>,
+    intern_stats: Cell<InternStats>,
+    /// Layouts of complete types, incomplete types might be completed later.
+    ty_layouts: RefCell<FxHashMap<Ty<'cx>, TyLayout<'cx>>>,
+    #[cfg(test)]
+    layout_computations: Cell<usize>,
     string_literals: RefCell<FxHashMap<&'cx [u8], DefId>>,
+    /// The data and relocations of `static` local variables, which are lowered inside of
+    /// functions but end up next to the globals.
+    static_locals: RefCell<Vec<StaticData<'cx>>>,
     pub(crate) arena: &'cx bumpalo::Bump,
     next_def_id: Cell<DefId>,
     pub types: CommonTypes<'cx>,
     /**/
     pub(crate) global_decls: FxHashMap<Symbol, VariableInfo<'cx>>,
+    /// Struct tags. Tags aren't scoped yet, a struct defined in a function is visible after it in
+    /// the whole file.
+    struct_tags: RefCell<FxHashMap<Symbol, (Ty<'cx>, Span)>>,
+    warnings: RefCell<Vec<Error>>,
+    pub target: TargetLayout,
+    /// Report functions that can reach their end without returning a value as errors instead of
+    /// warnings. That's only undefined behavior if the caller uses the value, so it's allowed by
+    /// default.
+    pub strict: bool,
+}
+
+/// The sizes and alignments of the scalar types on the target. Everything else is computed from
+/// these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetLayout {
+    /// Also used for function pointers.
+    pub pointer: Layout,
+    pub short: Layout,
+    pub int: Layout,
+    pub long: Layout,
+    pub long_long: Layout,
+    pub float: Layout,
+    pub double: Layout,
+    pub long_double: Layout,
+}
+
+impl TargetLayout {
+    pub fn x86_64_linux() -> Self {
+        Self {
+            pointer: Layout::size_align(8, 8),
+            short: Layout::size_align(2, 2),
+            int: Layout::size_align(4, 4),
+            long: Layout::size_align(8, 8),
+            long_long: Layout::size_align(8, 8),
+            float: Layout::size_align(4, 4),
+            double: Layout::size_align(8, 8),
+            // long double is lowered like double for now.
+            long_double: Layout::size_align(8, 8),
+        }
+    }
+
+    pub fn i386_linux() -> Self {
+        Self {
+            pointer: Layout::size_align(4, 4),
+            short: Layout::size_align(2, 2),
+            int: Layout::size_align(4, 4),
+            long: Layout::size_align(4, 4),
+            long_long: Layout::size_align(8, 4),
+            float: Layout::size_align(4, 4),
+            double: Layout::size_align(8, 4),
+            // long double is lowered like double for now.
+            long_double: Layout::size_align(8, 4),
+        }
+    }
+}
+
+impl Default for TargetLayout {
+    fn default() -> Self {
+        Self::x86_64_linux()
+    }
 }
 
 impl Debug for LoweringCx<'_> {
@@ -36,6 +109,20 @@ pub struct CommonInt<'cx> {
     pub unsigned: Ty<'cx>,
 }
 
+/// How the type and layout interners have been used, to tune the capacities passed to
+/// [`LoweringCx::with_capacity`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InternStats {
+    /// Types that were already interned.
+    pub ty_hits: usize,
+    /// Types that had to be allocated.
+    pub ty_misses: usize,
+    pub layout_hits: usize,
+    pub layout_misses: usize,
+    /// How often one of the interner sets had to grow.
+    pub resizes: usize,
+}
+
 pub struct CommonTypes<'cx> {
     pub void: Ty<'cx>,
     pub char: Ty<'cx>,
@@ -47,31 +134,53 @@ pub struct CommonTypes<'cx> {
 
 pub(crate) fn intern_ty_inner<'cx>(
     tys: &RefCell<FxHashSet<&'cx TyKind<'cx>>>,
+    stats: &Cell<InternStats>,
     arena: &'cx bumpalo::Bump,
     kind: TyKind<'cx>,
 ) -> Ty<'cx> {
+    let mut new_stats = stats.get();
     let opt_kind = tys.borrow().get(&kind).copied();
-    match opt_kind {
-        Some(ty) => Ty::new_unchecked(ty),
+    let ty = match opt_kind {
+        Some(ty) => {
+            new_stats.ty_hits += 1;
+            Ty::new_unchecked(ty)
+        }
         None => {
+            new_stats.ty_misses += 1;
             let kind = arena.alloc(kind);
-            tys.borrow_mut().insert(kind);
+            if insert_growing(&mut tys.borrow_mut(), kind) {
+                new_stats.resizes += 1;
+            }
             Ty::new_unchecked(kind)
         }
-    }
+    };
+    stats.set(new_stats);
+    ty
+}
+
+/// Inserts into an interner set, returning whether the set had to grow for it.
+fn insert_growing<T: Eq + std::hash::Hash>(set: &mut FxHashSet<T>, value: T) -> bool {
+    let capacity = set.capacity();
+    set.insert(value);
+    set.capacity() != capacity
 }
 
 impl<'cx> CommonTypes<'cx> {
-    fn new(tys: &RefCell<FxHashSet<&'cx TyKind<'cx>>>, arena: &'cx bumpalo::Bump) -> Self {
-        let int = |sign, kind| intern_ty_inner(tys, arena, TyKind::Int(IntTy(sign, kind)));
+    fn new(
+        tys: &RefCell<FxHashSet<&'cx TyKind<'cx>>>,
+        stats: &Cell<InternStats>,
+        arena: &'cx bumpalo::Bump,
+    ) -> Self {
+        let intern = |kind| intern_ty_inner(tys, stats, arena, kind);
+        let int = |sign, kind| intern(TyKind::Int(IntTy(sign, kind)));
         let int_pair = |kind| CommonInt {
             signed: int(IntSign::Signed, kind),
             unsigned: int(IntSign::Unsigned, kind),
         };
 
         Self {
-            void: intern_ty_inner(tys, arena, TyKind::Void),
-            char: intern_ty_inner(tys, arena, TyKind::Char),
+            void: intern(TyKind::Void),
+            char: intern(TyKind::Char),
             su_char: int_pair(IntTyKind::Char),
             short: int_pair(IntTyKind::Short),
             int: int_pair(IntTyKind::Int),
@@ -82,26 +191,67 @@ impl<'cx> CommonTypes<'cx> {
 
 impl<'cx> LoweringCx<'cx> {
     pub fn new(arena: &'cx bumpalo::Bump) -> Self {
-        let tys = RefCell::default();
-        let types = CommonTypes::new(&tys, arena);
+        Self::with_target(arena, TargetLayout::default())
+    }
+
+    pub fn with_target(arena: &'cx bumpalo::Bump, target: TargetLayout) -> Self {
+        Self::with_capacity(arena, target, 0, 0)
+    }
+
+    /// Creates a context for `target` whose interners have room for `ty_cap` types and
+    /// `layout_cap` layouts before they have to grow, which avoids rehashing them repeatedly for
+    /// large inputs.
+    pub fn with_capacity(
+        arena: &'cx bumpalo::Bump,
+        target: TargetLayout,
+        ty_cap: usize,
+        layout_cap: usize,
+    ) -> Self {
+        let tys = RefCell::new(FxHashSet::with_capacity_and_hasher(
+            ty_cap,
+            Default::default(),
+        ));
+        let intern_stats = Cell::default();
+        let types = CommonTypes::new(&tys, &intern_stats, arena);
         LoweringCx {
             tys,
-            layouts: RefCell::default(),
+            layouts: RefCell::new(FxHashSet::with_capacity_and_hasher(
+                layout_cap,
+                Default::default(),
+            )),
+            intern_stats,
+            ty_layouts: RefCell::default(),
+            #[cfg(test)]
+            layout_computations: Cell::new(0),
             string_literals: RefCell::default(),
+            static_locals: RefCell::default(),
             arena,
             next_def_id: Cell::new(DefId(0)),
             global_decls: FxHashMap::default(),
+            struct_tags: RefCell::default(),
             types,
+            warnings: RefCell::default(),
+            target,
+            strict: false,
         }
     }
 
+    pub(crate) fn emit_warning(&self, warning: Error) {
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    /// Returns all warnings that have been emitted so far.
+    pub fn take_warnings(&self) -> Vec<Error> {
+        self.warnings.take()
+    }
+
     pub(crate) fn next_def_id(&self) -> DefId {
         let def_id = self.next_def_id.get();
         self.next_def_id.set(DefId(def_id.0 + 1));
         def_id
     }
 
-    pub(crate) fn lower_ty(&self, ty: &ast::TypeSpecifier) -> Ty<'cx> {
+    pub(crate) fn lower_ty(&self, ty: &ast::TypeSpecifier) -> Result<Ty<'cx>, Error> {
         let kind = match ty {
             ast::TypeSpecifier::Void => TyKind::Void,
             ast::TypeSpecifier::Char => TyKind::Char,
@@ -109,26 +259,202 @@ impl<'cx> LoweringCx<'cx> {
             ast::TypeSpecifier::Float => TyKind::Float,
             ast::TypeSpecifier::Double => TyKind::Double,
             ast::TypeSpecifier::LongDouble => TyKind::LongDouble,
+            ast::TypeSpecifier::Struct(spec) => return self.lower_struct_ty(spec),
+        };
+        Ok(self.intern_ty(kind))
+    }
+
+    /// Looks up the struct that `spec` refers to, or defines a new one if it has fields.
+    fn lower_struct_ty(&self, spec: &ast::StructSpecifier) -> Result<Ty<'cx>, Error> {
+        let Some(fields) = &spec.fields else {
+            let (name, name_span) = spec
+                .name
+                .expect("parser ensures that a name or fields exist");
+            return match self.struct_tags.borrow().get(&name) {
+                Some(&(ty, _)) => Ok(ty),
+                None => Err(Error::new(format!("cannot find struct {name}"), name_span)),
+            };
         };
-        self.intern_ty(kind)
+
+        // The struct is visible in its own fields, so that they can point to it.
+        let lowered_fields = self.arena.alloc(OnceCell::new());
+        let ty = self.intern_ty(TyKind::Struct(StructTy {
+            def_id: self.next_def_id(),
+            name: spec.name.map(|(name, _)| name),
+            fields: lowered_fields,
+        }));
+
+        if let Some((name, name_span)) = spec.name {
+            let prev = self.struct_tags.borrow_mut().insert(name, (ty, name_span));
+            if let Some((_, prev_span)) = prev {
+                return Err(Error::new(
+                    format!("struct {name} has already been defined"),
+                    name_span,
+                )
+                .note_spanned("previously defined here", prev_span));
+            }
+        }
+
+        let mut fields_map = IndexMap::default();
+        for (field, _) in fields {
+            let base = self.lower_ty(&field.decl_spec.0.ty)?;
+            for (declarator, _) in &field.declarators {
+                let ty = self.lower_declarator_ty(base, field.decl_spec.0.quals, declarator)?;
+                let (name, name_span) = declarator.decl.name();
+                // This also rejects structs containing themselves, which would be infinitely big.
+                if !ty.is_complete() {
+                    return Err(Error::new(
+                        format!("field {name} has incomplete type {ty}"),
+                        name_span,
+                    ));
+                }
+                if fields_map.insert(name, ty).is_some() {
+                    return Err(Error::new(format!("duplicate field {name}"), name_span));
+                }
+            }
+        }
+        lowered_fields
+            .set(fields_map)
+            .expect("struct fields are only set once");
+
+        Ok(ty)
+    }
+
+    /// Returns the byte offset and the layout of a field, `None` if the struct has no such field.
+    pub(crate) fn field_offset(
+        &self,
+        strukt: &StructTy<'cx>,
+        field: Symbol,
+    ) -> Option<(u64, TyLayout<'cx>)> {
+        let mut offset = 0_u64;
+        for (&name, &ty) in strukt.fields.get()? {
+            let tyl = self.layout_of(ty);
+            offset = offset.next_multiple_of(tyl.layout.align);
+            if name == field {
+                return Some((offset, tyl));
+            }
+            offset += tyl.layout.size;
+        }
+        None
+    }
+
+    /// Computes the type declared by `declarator`, applying its pointer and array modifiers to
+    /// the `base` type from the declaration specifiers. The `quals` of the declaration specifiers
+    /// end up on the pointee for pointer declarators.
+    pub(crate) fn lower_declarator_ty(
+        &self,
+        base: Ty<'cx>,
+        quals: ast::Qualifiers,
+        declarator: &ast::Declarator,
+    ) -> Result<Ty<'cx>, Error> {
+        let ty = if declarator.pointer {
+            self.intern_ty(TyKind::Ptr(base, quals))
+        } else {
+            base
+        };
+        self.lower_direct_declarator_ty(ty, &declarator.decl)
+    }
+
+    /// Computes the type of a function parameter.
+    pub(crate) fn lower_param_ty(&self, param: &ast::FunctionParamDecl) -> Result<Ty<'cx>, Error> {
+        let decl_spec = &param.decl_spec.0;
+        let ty = self.lower_declarator_ty(
+            self.lower_ty(&decl_spec.ty)?,
+            decl_spec.quals,
+            &param.declarator.0,
+        )?;
+        // (6.7.6.3) Array parameters are adjusted to pointers to their element type.
+        Ok(match *ty {
+            TyKind::Array { elem, .. } => self.intern_ty(TyKind::Ptr(*elem, decl_spec.quals)),
+            _ => ty,
+        })
+    }
+
+    /// The qualifiers of the object declared by `declarator`. The qualifiers of the declaration
+    /// specifiers apply to the pointee of pointers, which are qualified after the `*` instead.
+    pub(crate) fn declarator_quals(
+        quals: ast::Qualifiers,
+        declarator: &ast::Declarator,
+    ) -> ast::Qualifiers {
+        if declarator.pointer {
+            declarator.pointer_quals
+        } else {
+            quals
+        }
+    }
+
+    fn lower_direct_declarator_ty(
+        &self,
+        ty: Ty<'cx>,
+        decl: &ast::DirectDeclarator,
+    ) -> Result<Ty<'cx>, Error> {
+        match decl {
+            ast::DirectDeclarator::Ident(_) | ast::DirectDeclarator::WithParams { .. } => Ok(ty),
+            ast::DirectDeclarator::Array { decl, size } => {
+                // `int a[2][3]` is parsed as `(a[2])[3]`, so the outermost size applies first:
+                // `a` is an array of 2 arrays of 3 ints.
+                let len = size
+                    .as_deref()
+                    .map(|(size, span)| self.array_len(size, *span))
+                    .transpose()?;
+                let ty = self.intern_ty(TyKind::Array { elem: ty, len });
+                self.lower_direct_declarator_ty(ty, decl)
+            }
+        }
+    }
+
+    fn array_len(&self, size: &ast::Expr, span: Span) -> Result<usize, Error> {
+        let len = eval_const_expr(self, size, span)?;
+        usize::try_from(len).map_err(|_| Error::new(format!("array size {len} is invalid"), span))
+    }
+
+    /// Checks a `_Static_assert`, failing with its message if the condition is zero.
+    pub(crate) fn check_static_assert(
+        &self,
+        assert: &ast::StaticAssertDecl,
+        span: Span,
+    ) -> Result<(), Error> {
+        let (cond, cond_span) = &*assert.cond;
+        if eval_const_expr(self, cond, *cond_span)? == 0 {
+            return Err(Error::new(
+                format!("static assertion failed: {}", assert.msg),
+                span,
+            ));
+        }
+        Ok(())
     }
 
-    pub(crate) fn intern_ty(&self, kind: TyKind<'cx>) -> Ty<'cx> {
-        intern_ty_inner(&self.tys, self.arena, kind)
+    pub fn intern_ty(&self, kind: TyKind<'cx>) -> Ty<'cx> {
+        intern_ty_inner(&self.tys, &self.intern_stats, self.arena, kind)
     }
 
     fn intern_layout(&self, layout: Layout) -> &'cx Layout {
+        let mut stats = self.intern_stats.get();
         let opt_layout = self.layouts.borrow().get(&layout).copied();
-        match opt_layout {
-            Some(layout) => layout,
+        let layout = match opt_layout {
+            Some(layout) => {
+                stats.layout_hits += 1;
+                layout
+            }
             None => {
-                let layout = self.arena.alloc(layout);
-                self.layouts.borrow_mut().insert(layout);
+                stats.layout_misses += 1;
+                let layout = &*self.arena.alloc(layout);
+                if insert_growing(&mut self.layouts.borrow_mut(), layout) {
+                    stats.resizes += 1;
+                }
                 layout
             }
-        }
+        };
+        self.intern_stats.set(stats);
+        layout
     }
 
+    /// The hits and misses of the type and layout interners so far.
+    pub fn intern_stats(&self) -> InternStats {
+        self.intern_stats.get()
+    }
+
+    /// Interns the bytes of a string literal, identical literals share the same `DefId`.
     pub(crate) fn intern_str_lit(&self, str: &[u8]) -> DefId {
         let opt_str = self.string_literals.borrow().get(str).copied();
         match opt_str {
@@ -142,28 +468,291 @@ impl<'cx> LoweringCx<'cx> {
         }
     }
 
-    pub(crate) fn layout_of(&self, ty: Ty<'cx>) -> TyLayout<'cx> {
+    /// All interned string literals with their null terminator.
+    pub(crate) fn str_lit_data(&self) -> FxHashMap<DefId, &'cx [u8]> {
+        self.string_literals
+            .borrow()
+            .iter()
+            .map(|(&str, &def_id)| {
+                let data = self
+                    .arena
+                    .alloc_slice_fill_with(str.len() + 1, |i| str.get(i).copied().unwrap_or(0));
+                (def_id, &*data)
+            })
+            .collect()
+    }
+
+    /// Allocates a new static for a `static` local variable with the given initial contents.
+    pub(crate) fn add_static_local(&self, data: &[u8], relocations: Vec<Relocation>) -> DefId {
+        let def_id = self.next_def_id();
+        let data = self.arena.alloc_slice_copy(data);
+        self.static_locals
+            .borrow_mut()
+            .push((def_id, data, relocations));
+        def_id
+    }
+
+    /// All `static` local variables that were lowered so far.
+    pub(crate) fn take_static_locals(&self) -> Vec<StaticData<'cx>> {
+        self.static_locals.take()
+    }
+
+    pub fn layout_of(&self, ty: Ty<'cx>) -> TyLayout<'cx> {
+        if let Some(&tyl) = self.ty_layouts.borrow().get(&ty) {
+            return tyl;
+        }
+        let tyl = self.compute_layout(ty);
+        if ty.is_complete() {
+            self.ty_layouts.borrow_mut().insert(ty, tyl);
+        }
+        tyl
+    }
+
+    fn compute_layout(&self, ty: Ty<'cx>) -> TyLayout<'cx> {
+        #[cfg(test)]
+        self.layout_computations
+            .set(self.layout_computations.get() + 1);
+
         let layout = match *ty {
             TyKind::Void => Layout::size_align(0, 1),
             TyKind::Char => Layout::size_align(1, 1),
             TyKind::Int(int) => match int.1 {
                 IntTyKind::Bool => Layout::size_align(1, 1),
                 IntTyKind::Char => Layout::size_align(1, 1),
-                IntTyKind::Short => Layout::size_align(2, 2),
-                IntTyKind::Int => Layout::size_align(4, 4),
-                IntTyKind::Long => Layout::size_align(8, 8),
-                IntTyKind::LongLong => Layout::size_align(8, 8),
+                IntTyKind::Short => self.target.short,
+                IntTyKind::Int => self.target.int,
+                IntTyKind::Long => self.target.long,
+                IntTyKind::LongLong => self.target.long_long,
             },
-            TyKind::Float => Layout::size_align(4, 4),
-            TyKind::Double => Layout::size_align(8, 8),
-            TyKind::LongDouble => Layout::size_align(8, 8),
-            TyKind::Func(_, _) => Layout::size_align(8, 8),
-            TyKind::Struct(_) => todo!("layout_of struct"),
+            TyKind::Float => self.target.float,
+            TyKind::Double => self.target.double,
+            TyKind::LongDouble => self.target.long_double,
+            // Function pointers.
+            TyKind::Func { .. } => self.target.pointer,
+            // Structs are incomplete while they're defined, their size isn't known yet.
+            TyKind::Struct(StructTy { fields, .. }) if fields.get().is_none() => {
+                Layout::size_align(0, 1)
+            }
+            TyKind::Struct(StructTy { fields, .. }) => {
+                // Every field is aligned, and the size is rounded up so that arrays of the struct
+                // keep all fields aligned.
+                let fields = fields.get().unwrap();
+                let (size, align) =
+                    fields
+                        .values()
+                        .fold((0_u64, 1_u64), |(size, align), &field| {
+                            let field = self.layout_of(field).layout;
+                            let size = size.next_multiple_of(field.align) + field.size;
+                            (size, align.max(field.align))
+                        });
+                Layout::size_align(size.next_multiple_of(align), align)
+            }
             TyKind::Union(_) => todo!("layout_of union"),
             TyKind::Enum(_) => todo!("layout_of enum"),
-            TyKind::Ptr(_) => Layout::size_align(8, 8),
+            TyKind::Ptr(..) => self.target.pointer,
+            TyKind::Array { elem, len } => {
+                let elem = self.layout_of(*elem).layout;
+                // Arrays of unknown size are incomplete and don't take up any space.
+                let len = len.unwrap_or(0) as u64;
+                Layout::size_align(elem.size * len, elem.align)
+            }
         };
         let layout = self.intern_layout(layout);
         TyLayout { ty, layout }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::OnceCell;
+
+    use indexmap::IndexMap;
+    use parser::{
+        ast::{self, IntSign, IntTy, IntTyKind},
+        Span, Symbol,
+    };
+
+    use crate::{
+        ir::Layout,
+        ty::{StructTy, TyKind},
+        LoweringCx, TargetLayout,
+    };
+
+    #[test]
+    fn array_layout() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let ty = lcx.intern_ty(TyKind::Array {
+            elem: lcx.types.int.signed,
+            len: Some(4),
+        });
+
+        assert_eq!(*lcx.layout_of(ty).layout, Layout::size_align(16, 4));
+    }
+
+    #[test]
+    fn struct_layout() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let fields = [
+            ("a", lcx.types.char),
+            ("b", lcx.types.long.signed),
+            ("c", lcx.types.short.signed),
+        ];
+        let strukt = StructTy {
+            def_id: lcx.next_def_id(),
+            name: None,
+            fields: arena.alloc(OnceCell::from(
+                fields
+                    .into_iter()
+                    .map(|(name, ty)| (Symbol::intern(name), ty))
+                    .collect::<IndexMap<_, _>>(),
+            )),
+        };
+        assert_eq!(
+            lcx.field_offset(&strukt, Symbol::intern("c"))
+                .map(|(offset, _)| offset),
+            Some(16)
+        );
+
+        let ty = lcx.intern_ty(TyKind::Struct(strukt));
+        assert_eq!(*lcx.layout_of(ty).layout, Layout::size_align(24, 8));
+    }
+
+    #[test]
+    fn layout_cache() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let strukt = lcx.intern_ty(TyKind::Struct(StructTy {
+            def_id: lcx.next_def_id(),
+            name: None,
+            fields: arena.alloc(OnceCell::from(
+                [(Symbol::intern("a"), lcx.types.int.signed)]
+                    .into_iter()
+                    .collect::<IndexMap<_, _>>(),
+            )),
+        }));
+        let first = lcx.layout_of(strukt);
+        let computations = lcx.layout_computations.get();
+        let second = lcx.layout_of(strukt);
+        assert!(std::ptr::eq(first.layout, second.layout));
+        assert_eq!(lcx.layout_computations.get(), computations);
+
+        // Arrays of unknown size are incomplete and aren't cached.
+        let unsized_array = lcx.intern_ty(TyKind::Array {
+            elem: lcx.types.int.signed,
+            len: None,
+        });
+        lcx.layout_of(unsized_array);
+        let computations = lcx.layout_computations.get();
+        lcx.layout_of(unsized_array);
+        assert_eq!(lcx.layout_computations.get(), computations + 1);
+    }
+
+    #[test]
+    fn target_layouts() {
+        let arena = bumpalo::Bump::new();
+        for (target, long, ptr) in [
+            (TargetLayout::x86_64_linux(), 8, 8),
+            (TargetLayout::i386_linux(), 4, 4),
+        ] {
+            let lcx = LoweringCx::with_target(&arena, target);
+            let long_ptr =
+                lcx.intern_ty(TyKind::Ptr(lcx.types.long.signed, ast::Qualifiers::empty()));
+            assert_eq!(
+                *lcx.layout_of(lcx.types.long.unsigned).layout,
+                Layout::size_align(long, long)
+            );
+            assert_eq!(
+                *lcx.layout_of(long_ptr).layout,
+                Layout::size_align(ptr, ptr)
+            );
+        }
+
+        // long long is only 4-aligned on i386, which affects struct layouts.
+        let lcx = LoweringCx::with_target(&arena, TargetLayout::i386_linux());
+        let strukt = lcx.intern_ty(TyKind::Struct(StructTy {
+            def_id: lcx.next_def_id(),
+            name: None,
+            fields: arena.alloc(OnceCell::from(
+                [
+                    (Symbol::intern("a"), lcx.types.int.signed),
+                    (
+                        Symbol::intern("b"),
+                        lcx.intern_ty(TyKind::Int(IntTy(IntSign::Signed, IntTyKind::LongLong))),
+                    ),
+                ]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+            )),
+        }));
+        assert_eq!(*lcx.layout_of(strukt).layout, Layout::size_align(12, 4));
+    }
+
+    #[test]
+    fn int_constant_types_per_target() {
+        let arena = bumpalo::Bump::new();
+        let constant = |value, decimal| ast::IntConstant {
+            value,
+            unsigned: false,
+            kind: IntTyKind::Int,
+            decimal,
+        };
+        for (target, decimal_ty, hex_ty) in [
+            (TargetLayout::x86_64_linux(), "long", "unsigned long"),
+            (
+                TargetLayout::i386_linux(),
+                "long long",
+                "unsigned long long",
+            ),
+        ] {
+            let lcx = LoweringCx::with_target(&arena, target);
+            let ty_of = |int| {
+                lcx.int_constant_ty(&int, Span::dummy())
+                    .unwrap()
+                    .to_string()
+            };
+            assert_eq!(ty_of(constant(5, true)), "int");
+            assert_eq!(ty_of(constant(2147483648, true)), decimal_ty);
+            assert_eq!(ty_of(constant(0xFFFF_FFFF_FFFF_FFFF, false)), hex_ty);
+        }
+    }
+
+    #[test]
+    fn intern_stats() {
+        let arena = bumpalo::Bump::new();
+        let arrays = |lcx: &LoweringCx<'_>| {
+            for len in 0..100 {
+                lcx.intern_ty(TyKind::Array {
+                    elem: lcx.types.int.signed,
+                    len: Some(len),
+                });
+            }
+        };
+
+        let lcx = LoweringCx::with_capacity(&arena, TargetLayout::default(), 128, 16);
+        let before = lcx.intern_stats();
+        arrays(&lcx);
+        let after = lcx.intern_stats();
+        assert_eq!(after.ty_misses - before.ty_misses, 100);
+        assert_eq!(after.resizes, 0);
+
+        // Interning them again only hits.
+        arrays(&lcx);
+        let again = lcx.intern_stats();
+        assert_eq!(again.ty_hits - after.ty_hits, 100);
+        assert_eq!(again.ty_misses, after.ty_misses);
+
+        let lcx = LoweringCx::new(&arena);
+        arrays(&lcx);
+        assert!(lcx.intern_stats().resizes > 0);
+
+        lcx.layout_of(lcx.types.int.signed);
+        lcx.layout_of(lcx.types.int.unsigned);
+        let stats = lcx.intern_stats();
+        assert_eq!((stats.layout_hits, stats.layout_misses), (1, 1));
+    }
+}
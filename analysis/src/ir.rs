@@ -32,10 +32,15 @@
 //! ```
 
 mod custom;
+pub mod dom;
 pub mod info;
+pub mod liveness;
+pub mod owned;
 pub mod pretty;
+mod stats;
+pub mod uninit;
 mod validate;
-mod visit;
+pub mod visit;
 
 use std::fmt::{Debug, Display};
 
@@ -46,7 +51,8 @@ use either::Either;
 use parser::{ast, Span, Symbol};
 pub use pretty::{func_to_string, ir_to_string};
 use rustc_hash::FxHashMap;
-pub use validate::validate;
+pub use stats::{stats, IrStats};
+pub use validate::{validate, validate_static_ptrs, verify, VerifyError, VerifyErrorKind};
 
 use crate::ty::Ty;
 
@@ -55,6 +61,7 @@ pub struct VariableInfo<'cx> {
     pub def_span: Span,
     // TODO: a static is definitely not a "local" in any way!! deal with that stuff!!!
     pub decl_attr: ast::DeclAttr,
+    pub quals: ast::Qualifiers,
     pub tyl: TyLayout<'cx>,
     pub kind: VariableInfoKind,
 }
@@ -88,28 +95,44 @@ pub struct Location {
     pub stmt: Option<usize>,
 }
 
+#[derive(Debug)]
 pub struct Ir<'cx> {
     pub funcs: FxHashMap<DefId, Func<'cx>>,
+    /// The initial contents of statics, like the bytes of string literals or global variables.
+    pub data: FxHashMap<DefId, &'cx [u8]>,
+    /// Addresses in the data of statics, like the one in `int *p = &g;`.
+    pub relocations: FxHashMap<DefId, Vec<Relocation>>,
+    /// Statics that are only declared with `extern` and defined in another translation unit, with
+    /// the name of the symbol that they have to be linked against.
+    pub externs: FxHashMap<DefId, Symbol>,
 }
 
+/// An address in the data of a static as `(offset, target, addend)`. The pointer at the offset (in
+/// bytes) into the data has to be filled in with the address of the target static or function
+/// plus the addend.
+pub type Relocation = (u64, DefId, u64);
+
 #[derive(Debug, Clone)]
 pub struct Func<'cx> {
     pub regs: Vec<RegisterData<'cx>>,
-    pub bbs: Vec<BasicBlock>,
+    pub bbs: Vec<BasicBlock<'cx>>,
     pub name: Symbol,
     pub def_span: Span,
     pub ret_ty: Ty<'cx>,
-    /// The amount of function parameters. regs[..arity] are the parameters.
-    pub arity: usize,
+    /// The incoming arguments, one for every parameter. Their registers are defined when entering
+    /// the function.
+    pub args: Vec<(Register, TyLayout<'cx>)>,
+    /// The storage class and function specifiers of the definition, like `static` or `_Noreturn`.
+    pub decl_attr: ast::DeclAttr,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct BbIdx(pub u32);
 
 #[derive(Debug, Clone)]
-pub struct BasicBlock {
-    pub statements: Vec<Statement>,
-    pub term: Branch,
+pub struct BasicBlock<'cx> {
+    pub statements: Vec<Statement<'cx>>,
+    pub term: Branch<'cx>,
 }
 
 #[derive(Debug, Clone)]
@@ -122,85 +145,112 @@ pub struct RegisterData<'cx> {
 pub struct Register(pub u32);
 
 #[derive(Debug, Clone)]
-pub struct Statement {
+pub struct Statement<'cx> {
     pub span: Span,
-    pub kind: StatementKind,
+    pub kind: StatementKind<'cx>,
 }
 
 #[derive(Debug, Clone)]
-pub enum StatementKind {
+pub enum StatementKind<'cx> {
     Alloca {
         result: Register,
         size: u64,
         align: u64,
+        /// The variable that this is the storage for, only used for debugging. The name of the
+        /// result register can differ, as register names have to be unique.
+        name: Option<Symbol>,
     },
     Store {
-        ptr: Operand,
-        value: Operand,
+        ptr: Operand<'cx>,
+        value: Operand<'cx>,
         /// Amount of bytes to store.
         size: u64,
         align: u64,
+        /// Volatile accesses must not be removed or reordered by optimizations.
+        volatile: bool,
     },
     Load {
         result: Register,
-        ptr: Operand,
+        ptr: Operand<'cx>,
         /// Amount of bytes to load.
         size: u64,
         align: u64,
+        volatile: bool,
     },
     BinOp {
         result: Register,
         kind: BinKind,
-        lhs: Operand,
-        rhs: Operand,
+        lhs: Operand<'cx>,
+        rhs: Operand<'cx>,
     },
     UnaryOperation {
         result: Register,
         kind: UnaryKind,
-        rhs: Operand,
+        rhs: Operand<'cx>,
     },
     PtrOffset {
         result: Register,
-        ptr: Operand,
-        amount: Operand,
+        ptr: Operand<'cx>,
+        /// Offset in bytes.
+        amount: Operand<'cx>,
     },
     Call {
         result: Register,
-        func: Operand,
-        args: Vec<Operand>,
+        func: Operand<'cx>,
+        args: Vec<Operand<'cx>>,
+    },
+    /// Takes the value of the incoming operand of the predecessor that control came from.
+    ///
+    /// Phis must come before all other statements of their block, and there must be exactly one
+    /// incoming value for every predecessor of the block. A predecessor that branches to the block
+    /// twice, like `switch %0, then bb1, else bb1`, has two incoming values.
+    Phi {
+        result: Register,
+        incomings: Vec<(BbIdx, Operand<'cx>)>,
     },
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Operand {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operand<'cx> {
     Reg(Register),
-    Const(ConstValue),
+    Const(ConstValue<'cx>),
 }
 
 #[derive(Debug, Clone)]
-pub enum Branch {
+pub enum Branch<'cx> {
     Goto(BbIdx),
     Switch {
-        cond: Operand,
+        cond: Operand<'cx>,
         yes: BbIdx,
         no: BbIdx,
     },
-    Ret(Operand),
+    Ret(Operand<'cx>),
+    /// Control never reaches the end of the block, for example after a call to a `_Noreturn`
+    /// function. Reaching it anyways is undefined behavior.
+    Unreachable,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Operations whose result depends on the signedness of the operands come in a signed (`S`)
+/// and an unsigned (`U`) variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BinKind {
     Add,
     Sub,
     Mul,
-    Div,
-    Mod,
+    SDiv,
+    UDiv,
+    SMod,
+    UMod,
     Eq,
     Neq,
-    Gt,
-    Geq,
-    Lt,
-    Leq,
+    SGt,
+    UGt,
+    SGeq,
+    UGeq,
+    SLt,
+    ULt,
+    SLeq,
+    ULeq,
     Shl,
     Shr,
     BitAnd,
@@ -208,27 +258,37 @@ pub enum BinKind {
     BitXor,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UnaryKind {
     Zext,
     Sext,
+    Trunc,
     Negate,
     BitNot,
     LogicalNot,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum ConstValue {
+/// Constants are equal when they are the same value of the same kind. `Undef` is equal to itself,
+/// which is fine as it never makes it into a finished function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConstValue<'cx> {
     Void,
     Int(u128),
-    StaticPtr(DefId),
+    /// The address of a static or function plus an offset in bytes.
+    StaticPtr(DefId, u64),
+    /// The null pointer constant of a pointer type.
+    Null(Ty<'cx>),
+    /// A placeholder for the value of an expression that failed to lower, so lowering can continue
+    /// and report more errors. It can be used as any type. Functions with errors are never
+    /// lowered completely, so it must not appear in a finished function.
+    Undef,
 }
 
-impl Func<'_> {
-    pub fn bb(&self, i: BbIdx) -> &BasicBlock {
+impl<'cx> Func<'cx> {
+    pub fn bb(&self, i: BbIdx) -> &BasicBlock<'cx> {
         &self.bbs[i.as_usize()]
     }
-    pub fn bb_mut(&mut self, i: BbIdx) -> &mut BasicBlock {
+    pub fn bb_mut(&mut self, i: BbIdx) -> &mut BasicBlock<'cx> {
         &mut self.bbs[i.as_usize()]
     }
 }
@@ -268,36 +328,42 @@ impl Layout {
     }
 }
 
-impl ConstValue {
+impl ConstValue<'_> {
     pub fn u64(int: u64) -> Self {
         Self::Int(int.into())
     }
 
     pub fn as_i32(self) -> i32 {
         match self {
-            Self::StaticPtr(_) => panic!("StaticPtr cannot be converted to integer"),
+            Self::StaticPtr(..) => panic!("StaticPtr cannot be converted to integer"),
             Self::Void => panic!("Void cannot be converted to integer"),
+            // Any value is fine for an undef.
+            Self::Undef | Self::Null(_) => 0,
             Self::Int(int) => int.try_into().unwrap(),
         }
     }
 }
 
-impl Operand {
+impl Operand<'_> {
     pub fn const_u64(int: u64) -> Self {
         Self::Const(ConstValue::u64(int))
     }
 }
 
-impl Branch {
+impl Branch<'_> {
     pub fn dummy() -> Self {
         Branch::Goto(BbIdx(u32::MAX))
     }
 
+    pub fn is_dummy(&self) -> bool {
+        matches!(self, Branch::Goto(BbIdx(u32::MAX)))
+    }
+
     pub fn successors(&self) -> impl Iterator<Item = BbIdx> {
         match self {
             Branch::Goto(bb) => Either::Left(Some(*bb).into_iter()),
             Branch::Switch { cond: _, yes, no } => Either::Right([*yes, *no].into_iter()),
-            Branch::Ret(_) => Either::Left(None.into_iter()),
+            Branch::Ret(_) | Branch::Unreachable => Either::Left(None.into_iter()),
         }
     }
 }
@@ -2,26 +2,40 @@ pub mod help {
     use crate::ir::{ConstValue, Operand, Register};
 
     pub trait ToOperand {
-        fn to_operand(self) -> Operand;
+        fn to_operand<'cx>(self) -> Operand<'cx>;
     }
 
-    pub fn op(o: impl ToOperand) -> Operand {
+    pub fn op<'cx>(o: impl ToOperand) -> Operand<'cx> {
         o.to_operand()
     }
 
     impl ToOperand for Register {
-        fn to_operand(self) -> Operand {
+        fn to_operand<'cx>(self) -> Operand<'cx> {
             Operand::Reg(self)
         }
     }
 
     impl ToOperand for u64 {
-        fn to_operand(self) -> Operand {
+        fn to_operand<'cx>(self) -> Operand<'cx> {
             Operand::Const(ConstValue::u64(self))
         }
     }
 }
 
+/// Defines a [`Func`](crate::ir::Func) with explicit basic blocks, for testing IR passes
+/// without going through lowering. Every register is an `unsigned int`, the first `arity` of them
+/// are the arguments. The function is validated after it has been built.
+///
+/// ```ignore
+/// define_ir_func! {
+///     def(lcx) name (1), regs(2) {
+///         0: {
+///             BinOp { result: Register(1), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(2) }
+///             => Ret(op(Register(1)))
+///         }
+///     }
+/// }
+/// ```
 #[macro_export]
 macro_rules! define_ir_func {
     (@bbs($bb:ident) { $( $stmt:expr );* }) => {
@@ -56,20 +70,23 @@ macro_rules! define_ir_func {
 
     // entrypoint
     (
-        def($lcx:ident) $name:ident (), regs($regs:literal) {
+        def($lcx:ident) $name:ident ($($arity:literal)?), regs($regs:literal) {
             $($body:tt)*
         }
     ) => {{
+        let tyl = $lcx.layout_of($lcx.types.int.unsigned);
         let mut f = $crate::ir::Func {
             name: ::parser::Symbol::intern(stringify!($name)),
             def_span: ::parser::Span::dummy(),
-            arity: 0,
-            ret_ty: $lcx.intern_ty($crate::ty::TyKind::Void),
+            args: (0..0 $(+ $arity)?)
+                .map(|i| ($crate::ir::Register(i), tyl))
+                .collect(),
+            decl_attr: ::parser::ast::DeclAttr::empty(),
+            ret_ty: $lcx.types.int.unsigned,
             bbs: Vec::new(),
-            regs: vec![$crate::ir::RegisterData {
-                tyl: $lcx.layout_of($lcx.types.int.unsigned),
-                name: None,
-            }; $regs],
+            regs: (0..$regs)
+                .map(|_| $crate::ir::RegisterData { tyl, name: None })
+                .collect(),
         };
         $crate::ir::define_ir_func! { @body(f) { $($body)* } };
         $crate::ir::validate(&f);
@@ -81,7 +98,10 @@ pub use define_ir_func;
 
 #[cfg(test)]
 mod tests {
-    use crate::LoweringCx;
+    use crate::{
+        ir::{verify, BbIdx, Branch, Register},
+        LoweringCx,
+    };
 
     #[test]
     fn define() {
@@ -97,4 +117,31 @@ mod tests {
             }
         };
     }
+
+    #[test]
+    fn params_and_blocks() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (2), regs(3) {
+                0: {
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(Register(1)) }
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    => Ret(op(Register(2)))
+                }
+            }
+        };
+
+        assert_eq!(f.args.len(), 2);
+        assert_eq!(f.bbs.len(), 2);
+        assert!(matches!(f.bbs[1].term, Branch::Ret(_)));
+        assert_eq!(verify(&f), Ok(()));
+        assert_eq!(
+            f.regs[Register(2).as_usize()].tyl.ty,
+            lcx.types.int.unsigned
+        );
+    }
 }
@@ -0,0 +1,273 @@
+//! Dominator information for the control flow graph of a function.
+
+use rustc_hash::FxHashSet;
+
+use super::{
+    info::{predecessors, traverse_postorder},
+    BbIdx, Func,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dominators {
+    /// The immediate dominator of every block. The entry block is its own immediate dominator,
+    /// unreachable blocks have none.
+    idoms: Vec<Option<BbIdx>>,
+}
+
+impl Dominators {
+    /// Computes the dominator tree with "A Simple, Fast Dominance Algorithm" by Cooper, Harvey
+    /// and Kennedy.
+    pub fn compute(func: &Func<'_>) -> Self {
+        let preds = predecessors(func);
+        let postorder = traverse_postorder(func);
+
+        // A dominator is always discovered before the blocks it dominates, so it has a higher
+        // number.
+        let mut order = vec![None; func.bbs.len()];
+        for (i, bb) in postorder.iter().enumerate() {
+            order[bb.as_usize()] = Some(i);
+        }
+
+        let mut idoms = vec![None; func.bbs.len()];
+        idoms[0] = Some(BbIdx::ZERO);
+
+        let intersect = |idoms: &[Option<BbIdx>], mut a: BbIdx, mut b: BbIdx| {
+            while a != b {
+                while order[a.as_usize()] < order[b.as_usize()] {
+                    a = idoms[a.as_usize()].unwrap();
+                }
+                while order[b.as_usize()] < order[a.as_usize()] {
+                    b = idoms[b.as_usize()].unwrap();
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &bb in postorder.iter().rev().skip(1) {
+                let mut processed = preds[bb.as_usize()]
+                    .iter()
+                    .copied()
+                    .filter(|pred| idoms[pred.as_usize()].is_some());
+                let first = processed
+                    .next()
+                    .expect("reachable block without processed predecessor");
+                let new_idom = processed.fold(first, |idom, pred| intersect(&idoms, idom, pred));
+
+                if idoms[bb.as_usize()] != Some(new_idom) {
+                    idoms[bb.as_usize()] = Some(new_idom);
+                    changed = true;
+                }
+            }
+        }
+
+        Self { idoms }
+    }
+
+    /// The immediate dominator of a block, `None` if the block is unreachable.
+    pub fn idom(&self, bb: BbIdx) -> Option<BbIdx> {
+        self.idoms[bb.as_usize()]
+    }
+
+    pub fn is_reachable(&self, bb: BbIdx) -> bool {
+        self.idoms[bb.as_usize()].is_some()
+    }
+
+    /// Whether `dom` dominates `bb`. Every block dominates itself.
+    pub fn dominates(&self, dom: BbIdx, mut bb: BbIdx) -> bool {
+        loop {
+            if bb == dom {
+                return true;
+            }
+            match self.idom(bb) {
+                Some(idom) if idom != bb => bb = idom,
+                _ => return false,
+            }
+        }
+    }
+
+    /// The blocks that are immediately dominated by every block.
+    pub fn children(&self) -> Vec<Vec<BbIdx>> {
+        let mut children = vec![Vec::new(); self.idoms.len()];
+        for (i, idom) in self.idoms.iter().enumerate() {
+            let bb = BbIdx::from_usize(i);
+            match *idom {
+                Some(idom) if idom != bb => children[idom.as_usize()].push(bb),
+                _ => {}
+            }
+        }
+        children
+    }
+
+    /// The dominance frontier of every block: the blocks where its dominance ends.
+    pub fn frontiers(&self, func: &Func<'_>) -> Vec<FxHashSet<BbIdx>> {
+        let preds = predecessors(func);
+        let mut frontiers = vec![FxHashSet::default(); func.bbs.len()];
+
+        for (i, preds) in preds.iter().enumerate() {
+            let bb = BbIdx::from_usize(i);
+            // The entry block is also entered from outside the function, so it is always a join
+            // and part of its own frontier.
+            let is_entry = bb == BbIdx::ZERO;
+            if preds.len() < 2 && !is_entry {
+                continue;
+            }
+            for &pred in preds.iter().filter(|&&pred| self.is_reachable(pred)) {
+                let mut runner = pred;
+                while is_entry || Some(runner) != self.idom(bb) {
+                    frontiers[runner.as_usize()].insert(bb);
+                    match self.idom(runner) {
+                        Some(idom) if idom != runner => runner = idom,
+                        _ => break,
+                    }
+                }
+            }
+        }
+
+        frontiers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dominators;
+    use crate::{
+        define_ir_func,
+        ir::{BbIdx, Func},
+        LoweringCx,
+    };
+
+    fn idoms(f: &Func<'_>) -> Vec<Option<BbIdx>> {
+        let doms = Dominators::compute(f);
+        (0..f.bbs.len())
+            .map(|i| doms.idom(BbIdx::from_usize(i)))
+            .collect()
+    }
+
+    #[test]
+    fn diamond() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(0) {
+                0: {
+                    => Switch { cond: op(0), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    => Goto(BbIdx(3))
+                }
+                2: {
+                    => Goto(BbIdx(3))
+                }
+                3: {
+                    => Ret(op(0))
+                }
+            }
+        };
+
+        assert_eq!(
+            idoms(&f),
+            vec![
+                Some(BbIdx(0)),
+                Some(BbIdx(0)),
+                Some(BbIdx(0)),
+                Some(BbIdx(0))
+            ]
+        );
+
+        let doms = Dominators::compute(&f);
+        assert!(doms.dominates(BbIdx(0), BbIdx(3)));
+        assert!(!doms.dominates(BbIdx(1), BbIdx(3)));
+        assert_eq!(
+            doms.children(),
+            vec![vec![BbIdx(1), BbIdx(2), BbIdx(3)], vec![], vec![], vec![]]
+        );
+    }
+
+    #[test]
+    fn while_loop() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        // bb1 is the loop header, bb2 the body and bb3 the exit.
+        let f = define_ir_func! {
+            def(lcx) name (), regs(0) {
+                0: {
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    => Switch { cond: op(0), yes: BbIdx(2), no: BbIdx(3) }
+                }
+                2: {
+                    => Goto(BbIdx(1))
+                }
+                3: {
+                    => Ret(op(0))
+                }
+            }
+        };
+
+        assert_eq!(
+            idoms(&f),
+            vec![
+                Some(BbIdx(0)),
+                Some(BbIdx(0)),
+                Some(BbIdx(1)),
+                Some(BbIdx(1))
+            ]
+        );
+
+        let frontiers = Dominators::compute(&f).frontiers(&f);
+        assert_eq!(
+            frontiers[1].iter().copied().collect::<Vec<_>>(),
+            vec![BbIdx(1)]
+        );
+        assert_eq!(
+            frontiers[2].iter().copied().collect::<Vec<_>>(),
+            vec![BbIdx(1)]
+        );
+        assert!(frontiers[3].is_empty());
+    }
+
+    #[test]
+    fn loop_to_entry() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(0) {
+                0: {
+                    => Switch { cond: op(0), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    => Goto(BbIdx(0))
+                }
+                2: {
+                    => Ret(op(0))
+                }
+                3: {
+                    => Goto(BbIdx(2))
+                }
+            }
+        };
+
+        assert_eq!(
+            idoms(&f),
+            vec![Some(BbIdx(0)), Some(BbIdx(0)), Some(BbIdx(0)), None]
+        );
+
+        let frontiers = Dominators::compute(&f).frontiers(&f);
+        assert_eq!(
+            frontiers[0].iter().copied().collect::<Vec<_>>(),
+            vec![BbIdx(0)]
+        );
+        assert_eq!(
+            frontiers[1].iter().copied().collect::<Vec<_>>(),
+            vec![BbIdx(0)]
+        );
+        assert!(frontiers[2].is_empty());
+    }
+}
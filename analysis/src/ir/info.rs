@@ -1,7 +1,8 @@
 use rustc_hash::FxHashSet;
 
-use super::{BbIdx, Branch, Func, Location, Operand};
+use super::{BbIdx, Branch, ConstValue, Func, Location, Operand, StatementKind};
 use crate::ir::visit::Visitor;
+use crate::ty::TyKind;
 
 pub fn traverse_postorder(func: &Func<'_>) -> Vec<BbIdx> {
     // the final traversal, backwards.
@@ -35,7 +36,7 @@ pub fn last_register_uses(func: &Func<'_>) -> Vec<Option<Location>> {
     for bb in traverse_postorder(func) {
         let uses = &mut *uses;
 
-        let mut check_op = |op: Operand, stmt| match op {
+        let mut check_op = |op: Operand<'_>, stmt| match op {
             Operand::Reg(reg) => {
                 if uses[reg.as_usize()].is_none() {
                     uses[reg.as_usize()] = Some(Location { bb, stmt })
@@ -48,15 +49,15 @@ pub fn last_register_uses(func: &Func<'_>) -> Vec<Option<Location>> {
             check_op(op, None);
         }
         for (i, stmt) in func.bb(bb).statements.iter().enumerate() {
-            let check_op = |op| {
+            let check_op = |op: Operand<'_>| {
                 check_op(op, Some(i));
             };
 
             struct StmtVisitor<F> {
                 check_op: F,
             }
-            impl<F: FnMut(Operand)> Visitor for StmtVisitor<F> {
-                fn visit_operand(&mut self, op: Operand) {
+            impl<F: FnMut(Operand<'_>)> Visitor for StmtVisitor<F> {
+                fn visit_operand(&mut self, op: Operand<'_>) {
                     (self.check_op)(op);
                 }
             }
@@ -67,6 +68,61 @@ pub fn last_register_uses(func: &Func<'_>) -> Vec<Option<Location>> {
     uses
 }
 
+/// Whether each block can be reached from the entry block. Blocks that still have the dummy
+/// terminator, which is how a function that is being built falls through, have no successors.
+pub fn reachable_blocks(func: &Func<'_>) -> Vec<bool> {
+    let mut reachable = vec![false; func.bbs.len()];
+    let mut worklist = vec![BbIdx(0)];
+    while let Some(bb) = worklist.pop() {
+        if std::mem::replace(&mut reachable[bb.as_usize()], true) {
+            continue;
+        }
+        let term = &func.bb(bb).term;
+        if !term.is_dummy() {
+            worklist.extend(term.successors());
+        }
+    }
+    reachable
+}
+
+/// The predecessors of every block.
+pub fn predecessors(func: &Func<'_>) -> Vec<Vec<BbIdx>> {
+    let mut preds = vec![Vec::new(); func.bbs.len()];
+    for (i, bb) in func.bbs.iter().enumerate() {
+        for succ in bb.term.successors() {
+            preds[succ.as_usize()].push(BbIdx::from_usize(i));
+        }
+    }
+    preds
+}
+
+/// The calls in tail position, whose result is returned right after them. These are the last
+/// statement of a block that returns the result of the call, or returns nothing from a call to a
+/// function returning `void`.
+pub fn tail_calls(func: &Func<'_>) -> Vec<Location> {
+    let mut calls = Vec::new();
+    for (i, bb) in func.bbs.iter().enumerate() {
+        let Some(StatementKind::Call { result, .. }) = bb.statements.last().map(|stmt| &stmt.kind)
+        else {
+            continue;
+        };
+        let is_tail = match bb.term {
+            Branch::Ret(Operand::Reg(ret)) => ret == *result,
+            Branch::Ret(Operand::Const(ConstValue::Void)) => {
+                matches!(*func.regs[result.as_usize()].tyl.ty, TyKind::Void)
+            }
+            _ => false,
+        };
+        if is_tail {
+            calls.push(Location::stmt(
+                BbIdx::from_usize(i),
+                bb.statements.len() - 1,
+            ));
+        }
+    }
+    calls
+}
+
 pub fn dominates_location(f: &Func<'_>, dom: Location, sub: Location) -> bool {
     // TODO: Can this be made more efficient by caching renumberings of bbs?
     if dom.bb == sub.bb {
@@ -94,7 +150,7 @@ pub fn dominates_location(f: &Func<'_>, dom: Location, sub: Location) -> bool {
 mod tests {
     use crate::{
         define_ir_func,
-        ir::{BbIdx, Location},
+        ir::{BbIdx, Branch, Location},
         LoweringCx,
     };
 
@@ -125,6 +181,65 @@ mod tests {
         assert_eq!(traverse, vec![BbIdx(3), BbIdx(2), BbIdx(1), BbIdx(0)]);
     }
 
+    #[test]
+    fn reachable_blocks_stop_at_dummy() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(0) {
+                0: {
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    => Ret(op(0))
+                }
+                2: {
+                    => Goto(BbIdx(1))
+                }
+            }
+        };
+        assert_eq!(super::reachable_blocks(&f), [true, true, false]);
+
+        f.bbs[0].term = Branch::dummy();
+        assert_eq!(super::reachable_blocks(&f), [true, false, false]);
+    }
+
+    #[test]
+    fn predecessors_diamond() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(0) {
+                0: {
+                    => Switch { cond: op(0), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    => Goto(BbIdx(3))
+                }
+                2: {
+                    => Goto(BbIdx(3))
+                }
+                3: {
+                    => Ret(op(0))
+                }
+            }
+        };
+
+        let preds = super::predecessors(&f);
+
+        assert_eq!(
+            preds,
+            vec![
+                vec![],
+                vec![BbIdx(0)],
+                vec![BbIdx(0)],
+                vec![BbIdx(1), BbIdx(2)]
+            ]
+        );
+    }
+
     #[test]
     fn single_bb() {
         let arena = bumpalo::Bump::new();
@@ -162,4 +277,31 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn tail_calls() {
+        let ast = parser::parse_file(
+            "int g() { return 1; }
+            int f() { return g(); }
+            int h() { int x = g(); return x + 1; }
+            void v() { v(); }",
+        )
+        .unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+
+        let tail_calls = |name: &str| {
+            let func = ir
+                .funcs
+                .values()
+                .find(|func| func.name == parser::Symbol::intern(name))
+                .unwrap();
+            super::tail_calls(func)
+        };
+        assert_eq!(tail_calls("f"), [Location::stmt(BbIdx(0), 0)]);
+        assert_eq!(tail_calls("g"), []);
+        assert_eq!(tail_calls("h"), []);
+        assert_eq!(tail_calls("v"), [Location::stmt(BbIdx(0), 0)]);
+    }
 }
@@ -0,0 +1,199 @@
+//! Computes which registers are live at the start and end of every block.
+
+use rustc_hash::FxHashSet;
+
+use super::{
+    info::predecessors, visit::Visitor, BbIdx, Func, Operand, Register, Statement, StatementKind,
+};
+
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    live_in: Vec<FxHashSet<Register>>,
+    live_out: Vec<FxHashSet<Register>>,
+}
+
+impl Liveness {
+    /// Computes liveness with a backwards dataflow analysis.
+    ///
+    /// The incoming values of a phi are used at the end of their predecessor, not in the block
+    /// of the phi.
+    pub fn compute(func: &Func<'_>) -> Self {
+        let blocks = func
+            .bbs
+            .iter()
+            .map(|bb| {
+                let mut collector = UseDefCollector::default();
+                collector.visit_bb(bb);
+                collector
+            })
+            .collect::<Vec<_>>();
+        let preds = predecessors(func);
+
+        let mut live_in = vec![FxHashSet::default(); func.bbs.len()];
+        let mut live_out = vec![FxHashSet::default(); func.bbs.len()];
+
+        let mut worklist = (0..func.bbs.len())
+            .map(BbIdx::from_usize)
+            .collect::<Vec<_>>();
+        while let Some(bb) = worklist.pop() {
+            let mut out = FxHashSet::default();
+            for succ in func.bb(bb).term.successors() {
+                out.extend(&live_in[succ.as_usize()]);
+                for &(pred, reg) in &blocks[succ.as_usize()].phi_uses {
+                    if pred == bb {
+                        out.insert(reg);
+                    }
+                }
+            }
+
+            let block = &blocks[bb.as_usize()];
+            let mut new_in = block.uses.clone();
+            new_in.extend(out.iter().filter(|reg| !block.defs.contains(reg)));
+            live_out[bb.as_usize()] = out;
+
+            if new_in != live_in[bb.as_usize()] {
+                live_in[bb.as_usize()] = new_in;
+                for &pred in &preds[bb.as_usize()] {
+                    if !worklist.contains(&pred) {
+                        worklist.push(pred);
+                    }
+                }
+            }
+        }
+
+        Self { live_in, live_out }
+    }
+
+    /// The registers that are live when entering the block.
+    pub fn live_in(&self, bb: BbIdx) -> &FxHashSet<Register> {
+        &self.live_in[bb.as_usize()]
+    }
+
+    /// The registers that are live when leaving the block.
+    pub fn live_out(&self, bb: BbIdx) -> &FxHashSet<Register> {
+        &self.live_out[bb.as_usize()]
+    }
+}
+
+/// Collects the registers of a block that are used before being defined in it, and the ones
+/// that are defined in it.
+#[derive(Default)]
+struct UseDefCollector {
+    uses: FxHashSet<Register>,
+    defs: FxHashSet<Register>,
+    phi_uses: Vec<(BbIdx, Register)>,
+}
+
+impl Visitor for UseDefCollector {
+    fn visit_statement(&mut self, stmt: &Statement<'_>) {
+        match stmt.kind {
+            StatementKind::Phi {
+                result,
+                ref incomings,
+            } => {
+                self.defs.insert(result);
+                for &(pred, op) in incomings {
+                    if let Operand::Reg(reg) = op {
+                        self.phi_uses.push((pred, reg));
+                    }
+                }
+            }
+            _ => self.super_statement(stmt),
+        }
+    }
+
+    fn visit_reg(&mut self, reg: Register) {
+        self.defs.insert(reg);
+    }
+
+    fn visit_operand(&mut self, op: Operand<'_>) {
+        if let Operand::Reg(reg) = op {
+            if !self.defs.contains(&reg) {
+                self.uses.insert(reg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use super::Liveness;
+    use crate::{
+        define_ir_func,
+        ir::{BbIdx, Register},
+        LoweringCx,
+    };
+
+    fn set(regs: &[u32]) -> FxHashSet<Register> {
+        regs.iter().copied().map(Register).collect()
+    }
+
+    #[test]
+    fn live_across_back_edge() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(4) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    BinOp { result: Register(1), kind: BinKind::SLt, lhs: op(Register(0)), rhs: op(10) }
+                    => Switch { cond: op(Register(1)), yes: BbIdx(2), no: BbIdx(3) }
+                }
+                2: {
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(1) }
+                    => Goto(BbIdx(1))
+                }
+                3: {
+                    BinOp { result: Register(3), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(1) }
+                    => Ret(op(Register(3)))
+                }
+            }
+        };
+
+        let liveness = Liveness::compute(&f);
+
+        assert_eq!(*liveness.live_in(BbIdx(0)), set(&[]));
+        assert_eq!(*liveness.live_out(BbIdx(0)), set(&[0]));
+        assert_eq!(*liveness.live_in(BbIdx(1)), set(&[0]));
+        assert_eq!(*liveness.live_out(BbIdx(1)), set(&[0]));
+        assert_eq!(*liveness.live_in(BbIdx(2)), set(&[0]));
+        assert_eq!(*liveness.live_out(BbIdx(2)), set(&[0]));
+        assert_eq!(*liveness.live_in(BbIdx(3)), set(&[0]));
+        assert_eq!(*liveness.live_out(BbIdx(3)), set(&[]));
+    }
+
+    #[test]
+    fn phi() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(3) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    Phi { result: Register(1), incomings: vec![(BbIdx(0), op(Register(0))), (BbIdx(1), op(Register(2)))] };
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(Register(1)), rhs: op(1) }
+                    => Switch { cond: op(Register(2)), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                2: {
+                    => Ret(op(0))
+                }
+            }
+        };
+
+        let liveness = Liveness::compute(&f);
+
+        assert_eq!(*liveness.live_out(BbIdx(0)), set(&[0]));
+        assert_eq!(*liveness.live_in(BbIdx(1)), set(&[]));
+        assert_eq!(*liveness.live_out(BbIdx(1)), set(&[2]));
+    }
+}
@@ -0,0 +1,449 @@
+//! A copy of the IR that owns all of its data instead of borrowing it from the arena, so it can
+//! outlive the [`LoweringCx`](crate::LoweringCx), for example to cache it.
+//!
+//! Symbols are interned per thread, so all names are stored as strings. This makes the owned IR
+//! `Send`.
+
+use indexmap::IndexMap;
+use parser::{
+    ast::{DeclAttr, IntTy},
+    Span, Symbol,
+};
+use rustc_hash::FxHashMap;
+
+use super::{
+    BasicBlock, BbIdx, BinKind, Branch, ConstValue, DefId, Func, Ir, Layout, Operand, Register,
+    RegisterData, Statement, StatementKind, UnaryKind,
+};
+use crate::ty::{Ty, TyKind};
+
+#[derive(Debug, Clone)]
+pub struct OwnedIr {
+    pub funcs: FxHashMap<DefId, OwnedFunc>,
+    pub data: FxHashMap<DefId, Vec<u8>>,
+    pub relocations: FxHashMap<DefId, Vec<(u64, DefId, u64)>>,
+    pub externs: FxHashMap<DefId, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OwnedFunc {
+    pub regs: Vec<OwnedRegisterData>,
+    pub bbs: Vec<OwnedBasicBlock>,
+    pub name: String,
+    pub def_span: Span,
+    pub ret_ty: OwnedTy,
+    pub args: Vec<Register>,
+    pub decl_attr: DeclAttr,
+}
+
+#[derive(Debug, Clone)]
+pub struct OwnedRegisterData {
+    pub ty: OwnedTy,
+    pub layout: Layout,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OwnedBasicBlock {
+    pub statements: Vec<OwnedStatement>,
+    pub term: OwnedBranch,
+}
+
+#[derive(Debug, Clone)]
+pub struct OwnedStatement {
+    pub span: Span,
+    pub kind: OwnedStatementKind,
+}
+
+/// See [`StatementKind`].
+#[derive(Debug, Clone)]
+pub enum OwnedStatementKind {
+    Alloca {
+        result: Register,
+        size: u64,
+        align: u64,
+        name: Option<String>,
+    },
+    Store {
+        ptr: OwnedOperand,
+        value: OwnedOperand,
+        size: u64,
+        align: u64,
+        volatile: bool,
+    },
+    Load {
+        result: Register,
+        ptr: OwnedOperand,
+        size: u64,
+        align: u64,
+        volatile: bool,
+    },
+    BinOp {
+        kind: BinKind,
+        lhs: OwnedOperand,
+        rhs: OwnedOperand,
+        result: Register,
+    },
+    UnaryOperation {
+        rhs: OwnedOperand,
+        kind: UnaryKind,
+        result: Register,
+    },
+    PtrOffset {
+        result: Register,
+        ptr: OwnedOperand,
+        amount: OwnedOperand,
+    },
+    Call {
+        result: Register,
+        func: OwnedOperand,
+        args: Vec<OwnedOperand>,
+    },
+    Phi {
+        result: Register,
+        incomings: Vec<(BbIdx, OwnedOperand)>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum OwnedOperand {
+    Reg(Register),
+    Const(OwnedConstValue),
+}
+
+#[derive(Debug, Clone)]
+pub enum OwnedConstValue {
+    Void,
+    Int(u128),
+    StaticPtr(DefId, u64),
+    Null(OwnedTy),
+    Undef,
+}
+
+#[derive(Debug, Clone)]
+pub enum OwnedBranch {
+    Goto(BbIdx),
+    Switch {
+        cond: OwnedOperand,
+        yes: BbIdx,
+        no: BbIdx,
+    },
+    Ret(OwnedOperand),
+    Unreachable,
+}
+
+/// See [`TyKind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedTy {
+    Void,
+    Char,
+    Int(IntTy),
+    Float,
+    Double,
+    LongDouble,
+    Ptr(Box<OwnedTy>),
+    Array {
+        elem: Box<OwnedTy>,
+        len: Option<usize>,
+    },
+    Func {
+        ret: Box<OwnedTy>,
+        params: Option<Vec<OwnedTy>>,
+        variadic: bool,
+    },
+    Union {
+        def_id: DefId,
+        variants: IndexMap<String, OwnedTy>,
+    },
+    Struct {
+        def_id: DefId,
+        name: Option<String>,
+        fields: IndexMap<String, OwnedTy>,
+    },
+    Enum {
+        def_id: DefId,
+        variants: IndexMap<String, i128>,
+    },
+}
+
+impl Ir<'_> {
+    /// Copies the IR out of the arena.
+    pub fn to_owned(&self) -> OwnedIr {
+        OwnedIr {
+            funcs: self
+                .funcs
+                .iter()
+                .map(|(&def_id, func)| (def_id, func.to_owned()))
+                .collect(),
+            data: self
+                .data
+                .iter()
+                .map(|(&def_id, data)| (def_id, data.to_vec()))
+                .collect(),
+            relocations: self.relocations.clone(),
+            externs: self
+                .externs
+                .iter()
+                .map(|(&def_id, name)| (def_id, name.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Func<'_> {
+    /// Copies the function out of the arena.
+    pub fn to_owned(&self) -> OwnedFunc {
+        OwnedFunc {
+            regs: self.regs.iter().map(owned_reg).collect(),
+            bbs: self.bbs.iter().map(owned_bb).collect(),
+            name: self.name.to_string(),
+            def_span: self.def_span,
+            ret_ty: OwnedTy::from(self.ret_ty),
+            args: self.args.iter().map(|&(reg, _)| reg).collect(),
+            decl_attr: self.decl_attr,
+        }
+    }
+}
+
+fn owned_reg(reg: &RegisterData<'_>) -> OwnedRegisterData {
+    OwnedRegisterData {
+        ty: OwnedTy::from(reg.tyl.ty),
+        layout: *reg.tyl.layout,
+        name: reg.name.map(|name| name.to_string()),
+    }
+}
+
+fn owned_bb(bb: &BasicBlock<'_>) -> OwnedBasicBlock {
+    OwnedBasicBlock {
+        statements: bb.statements.iter().map(owned_stmt).collect(),
+        term: match bb.term {
+            Branch::Goto(bb) => OwnedBranch::Goto(bb),
+            Branch::Switch { cond, yes, no } => OwnedBranch::Switch {
+                cond: cond.into(),
+                yes,
+                no,
+            },
+            Branch::Ret(op) => OwnedBranch::Ret(op.into()),
+            Branch::Unreachable => OwnedBranch::Unreachable,
+        },
+    }
+}
+
+fn owned_stmt(stmt: &Statement<'_>) -> OwnedStatement {
+    let kind = match stmt.kind {
+        StatementKind::Alloca {
+            result,
+            size,
+            align,
+            name,
+        } => OwnedStatementKind::Alloca {
+            result,
+            size,
+            align,
+            name: name.map(|name| name.to_string()),
+        },
+        StatementKind::Store {
+            ptr,
+            value,
+            size,
+            align,
+            volatile,
+        } => OwnedStatementKind::Store {
+            ptr: ptr.into(),
+            value: value.into(),
+            size,
+            align,
+            volatile,
+        },
+        StatementKind::Load {
+            result,
+            ptr,
+            size,
+            align,
+            volatile,
+        } => OwnedStatementKind::Load {
+            result,
+            ptr: ptr.into(),
+            size,
+            align,
+            volatile,
+        },
+        StatementKind::BinOp {
+            kind,
+            lhs,
+            rhs,
+            result,
+        } => OwnedStatementKind::BinOp {
+            kind,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+            result,
+        },
+        StatementKind::UnaryOperation { rhs, kind, result } => OwnedStatementKind::UnaryOperation {
+            rhs: rhs.into(),
+            kind,
+            result,
+        },
+        StatementKind::PtrOffset {
+            result,
+            ptr,
+            amount,
+        } => OwnedStatementKind::PtrOffset {
+            result,
+            ptr: ptr.into(),
+            amount: amount.into(),
+        },
+        StatementKind::Call {
+            result,
+            func,
+            ref args,
+        } => OwnedStatementKind::Call {
+            result,
+            func: func.into(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+        },
+        StatementKind::Phi {
+            result,
+            ref incomings,
+        } => OwnedStatementKind::Phi {
+            result,
+            incomings: incomings.iter().map(|&(bb, op)| (bb, op.into())).collect(),
+        },
+    };
+    OwnedStatement {
+        span: stmt.span,
+        kind,
+    }
+}
+
+impl From<Operand<'_>> for OwnedOperand {
+    fn from(op: Operand<'_>) -> Self {
+        match op {
+            Operand::Reg(reg) => OwnedOperand::Reg(reg),
+            Operand::Const(value) => OwnedOperand::Const(match value {
+                ConstValue::Void => OwnedConstValue::Void,
+                ConstValue::Int(int) => OwnedConstValue::Int(int),
+                ConstValue::StaticPtr(def_id, offset) => OwnedConstValue::StaticPtr(def_id, offset),
+                ConstValue::Null(ty) => OwnedConstValue::Null(ty.into()),
+                ConstValue::Undef => OwnedConstValue::Undef,
+            }),
+        }
+    }
+}
+
+impl From<Ty<'_>> for OwnedTy {
+    fn from(ty: Ty<'_>) -> Self {
+        OwnedTy::new(ty, &mut Vec::new())
+    }
+}
+
+impl OwnedTy {
+    /// `structs` are the structs whose fields are currently being converted. When a struct
+    /// points to itself, the inner struct is converted without fields.
+    fn new(ty: Ty<'_>, structs: &mut Vec<DefId>) -> Self {
+        let names = |map: &IndexMap<Symbol, Ty<'_>>, structs: &mut Vec<DefId>| {
+            map.iter()
+                .map(|(name, &ty)| (name.to_string(), OwnedTy::new(ty, structs)))
+                .collect()
+        };
+        match *ty {
+            TyKind::Void => OwnedTy::Void,
+            TyKind::Char => OwnedTy::Char,
+            TyKind::Int(int) => OwnedTy::Int(*int),
+            TyKind::Float => OwnedTy::Float,
+            TyKind::Double => OwnedTy::Double,
+            TyKind::LongDouble => OwnedTy::LongDouble,
+            TyKind::Ptr(pointee, _) => OwnedTy::Ptr(Box::new(OwnedTy::new(*pointee, structs))),
+            TyKind::Array { elem, len } => OwnedTy::Array {
+                elem: Box::new(OwnedTy::new(*elem, structs)),
+                len: *len,
+            },
+            TyKind::Func {
+                ret,
+                params,
+                variadic,
+            } => OwnedTy::Func {
+                ret: Box::new(OwnedTy::new(*ret, structs)),
+                params: params.map(|params| {
+                    params
+                        .iter()
+                        .map(|&param| OwnedTy::new(param, structs))
+                        .collect()
+                }),
+                variadic: *variadic,
+            },
+            TyKind::Union(union) => OwnedTy::Union {
+                def_id: union.def_id,
+                variants: names(&union.variants, structs),
+            },
+            TyKind::Struct(strukt) => {
+                let fields = match strukt.fields.get() {
+                    Some(fields) if !structs.contains(&strukt.def_id) => {
+                        structs.push(strukt.def_id);
+                        let fields = names(fields, structs);
+                        structs.pop();
+                        fields
+                    }
+                    _ => IndexMap::default(),
+                };
+                OwnedTy::Struct {
+                    def_id: strukt.def_id,
+                    name: strukt.name.map(|name| name.to_string()),
+                    fields,
+                }
+            }
+            TyKind::Enum(enm) => OwnedTy::Enum {
+                def_id: enm.def_id,
+                variants: enm
+                    .variants
+                    .iter()
+                    .map(|(name, &value)| (name.to_string(), value))
+                    .collect(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::ast::{IntSign, IntTy, IntTyKind};
+
+    use super::{OwnedIr, OwnedOperand, OwnedStatementKind, OwnedTy, Register};
+    use crate::LoweringCx;
+
+    fn lower_owned(src: &str) -> OwnedIr {
+        let ast = parser::parse_file(src).unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        ir.to_owned()
+    }
+
+    #[test]
+    fn outlives_arena() {
+        let ir = lower_owned("long f(long *p, long n) { long *q = p; return n; }");
+
+        // The owned IR can be moved to another thread.
+        let func = std::thread::spawn(move || ir.funcs.into_values().next().unwrap())
+            .join()
+            .unwrap();
+
+        let long = OwnedTy::Int(IntTy(IntSign::Signed, IntTyKind::Long));
+        assert_eq!(func.name, "f");
+        assert_eq!(func.args, [Register(0), Register(1)]);
+        assert_eq!(func.ret_ty, long);
+        assert_eq!(func.regs[0].ty, OwnedTy::Ptr(Box::new(long)));
+        assert_eq!(func.regs[0].name.as_deref(), Some("p"));
+        assert!(matches!(
+            func.bbs[0].statements[0].kind,
+            OwnedStatementKind::Alloca { size: 8, .. }
+        ));
+        assert!(matches!(
+            func.bbs[0].statements[1].kind,
+            OwnedStatementKind::Store {
+                value: OwnedOperand::Reg(_),
+                ..
+            }
+        ));
+    }
+}
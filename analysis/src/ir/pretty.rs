@@ -8,28 +8,28 @@ use super::{
 };
 use crate::ir::Register;
 
-pub fn ir_to_string<'a>(ir: &'a Ir<'a>, custom: &impl Customizer<'a>) -> String {
+pub fn ir_to_string<'a, 'cx>(ir: &'a Ir<'cx>, custom: &impl Customizer<'a, 'cx>) -> String {
     let mut buf = String::new();
     PrettyPrinter { out: &mut buf }.ir(ir, custom).unwrap();
     buf
 }
 
-pub fn func_to_string<'a>(func: &'a Func<'a>, custom: &impl Customizer<'a>) -> String {
+pub fn func_to_string<'a, 'cx>(func: &'a Func<'cx>, custom: &impl Customizer<'a, 'cx>) -> String {
     let mut buf = String::new();
     PrettyPrinter { out: &mut buf }.func(func, custom).unwrap();
     buf
 }
 
-pub trait Customizer<'a> {
-    fn start_func(&self, func: &'a Func<'a>);
+pub trait Customizer<'a, 'cx> {
+    fn start_func(&self, func: &'a Func<'cx>);
     fn fmt_reg(&self, reg: Register, f: &mut fmt::Formatter<'_>, loc: Location) -> fmt::Result;
 }
 
 #[derive(Default)]
-pub struct DefaultCustomizer<'a>(Cell<Option<&'a Func<'a>>>);
+pub struct DefaultCustomizer<'a, 'cx>(Cell<Option<&'a Func<'cx>>>);
 
-impl<'a> Customizer<'a> for DefaultCustomizer<'a> {
-    fn start_func(&self, func: &'a Func<'a>) {
+impl<'a, 'cx> Customizer<'a, 'cx> for DefaultCustomizer<'a, 'cx> {
+    fn start_func(&self, func: &'a Func<'cx>) {
         self.0.set(Some(func));
     }
 
@@ -46,35 +46,53 @@ pub struct PrettyPrinter<W> {
 }
 
 impl<W: Write> PrettyPrinter<W> {
-    pub fn ir<'a>(&mut self, ir: &'a Ir<'a>, custom: &impl Customizer<'a>) -> Result {
-        for func in ir.funcs.values() {
+    /// Prints the functions and statics in the order they were defined.
+    pub fn ir<'a, 'cx>(&mut self, ir: &'a Ir<'cx>, custom: &impl Customizer<'a, 'cx>) -> Result {
+        let mut funcs = ir.funcs.iter().collect::<Vec<_>>();
+        funcs.sort_by_key(|(def_id, _)| def_id.0);
+        for (_, func) in funcs {
             self.func(func, custom)?;
         }
+
+        let mut statics = ir.data.iter().collect::<Vec<_>>();
+        statics.sort_by_key(|(def_id, _)| def_id.0);
+        for (def_id, data) in statics {
+            write!(self.out, "static {{{}}} = {data:?}", def_id.0)?;
+            for &(offset, target, addend) in ir.relocations.get(def_id).into_iter().flatten() {
+                let target = ConstValue::StaticPtr(target, addend);
+                write!(self.out, ", {target} at {offset}")?;
+            }
+            writeln!(self.out)?;
+        }
+
+        let mut externs = ir.externs.iter().collect::<Vec<_>>();
+        externs.sort_by_key(|(def_id, _)| def_id.0);
+        for (def_id, name) in externs {
+            writeln!(self.out, "extern {{{}}} {name}", def_id.0)?;
+        }
         Ok(())
     }
 
-    pub fn func<'a>(&mut self, func: &'a Func<'a>, custom: &impl Customizer<'a>) -> Result {
+    pub fn func<'a, 'cx>(
+        &mut self,
+        func: &'a Func<'cx>,
+        custom: &impl Customizer<'a, 'cx>,
+    ) -> Result {
         custom.start_func(func);
 
         let print_reg =
             |reg: Register, loc: Location| display_fn(move |f| custom.fmt_reg(reg, f, loc));
 
         write!(self.out, "def {}(", func.name)?;
-        for param in 0..func.arity {
-            let reg = &func.regs[param];
-            write!(
-                self.out,
-                "{} {}",
-                reg.tyl.ty,
-                print_reg(Register(param as _), Location::start())
-            )?;
-            if (param + 1) != func.arity {
+        for (i, &(reg, tyl)) in func.args.iter().enumerate() {
+            write!(self.out, "{} {}", tyl.ty, print_reg(reg, Location::start()))?;
+            if (i + 1) != func.args.len() {
                 write!(self.out, ", ")?;
             }
         }
         writeln!(self.out, ") {{",)?;
 
-        let print_op = |op: Operand, loc: Location| {
+        let print_op = |op: Operand<'cx>, loc: Location| {
             display_fn(move |f| match op {
                 Operand::Const(c) => Display::fmt(&c, f),
                 Operand::Reg(reg) => Display::fmt(&print_reg(reg, loc), f),
@@ -98,34 +116,46 @@ impl<W: Write> PrettyPrinter<W> {
                         result: reg,
                         size,
                         align,
+                        name,
                     } => {
-                        writeln!(
+                        write!(
                             self.out,
                             "    {} = alloca, size={size}, align={align}",
                             print_reg(reg),
-                        )
+                        )?;
+                        // Only mention the variable if the register name doesn't already.
+                        match name {
+                            Some(name) if func.regs[reg.as_usize()].name != Some(name) => {
+                                writeln!(self.out, " ; {name}")
+                            }
+                            _ => writeln!(self.out),
+                        }
                     }
                     StatementKind::Store {
                         ptr: ptr_reg,
                         value,
                         size,
                         align,
+                        volatile,
                     } => writeln!(
                         self.out,
-                        "    store {}, {}, size={size}, align={align}",
+                        "    store {}, {}, size={size}, align={align}{}",
                         print_op(ptr_reg),
-                        print_op(value),                        
+                        print_op(value),
+                        if volatile { ", volatile" } else { "" },
                     ),
                     StatementKind::Load {
                         result,
                         ptr: ptr_reg,
                         size,
                         align,
+                        volatile,
                     } => writeln!(
                         self.out,
-                        "    {} = load {}, size={size}, align={align}",
+                        "    {} = load {}, size={size}, align={align}{}",
                         print_reg(result),
                         print_op(ptr_reg),
+                        if volatile { ", volatile" } else { "" },
                     ),
                     StatementKind::BinOp {
                         kind,
@@ -140,14 +170,20 @@ impl<W: Write> PrettyPrinter<W> {
                             BinKind::Add => "add",
                             BinKind::Sub => "sub",
                             BinKind::Mul => "mul",
-                            BinKind::Div => "div",
-                            BinKind::Mod => "mod",
+                            BinKind::SDiv => "sdiv",
+                            BinKind::UDiv => "udiv",
+                            BinKind::SMod => "smod",
+                            BinKind::UMod => "umod",
                             BinKind::Eq => "eq",
                             BinKind::Neq => "neq",
-                            BinKind::Gt => "gt",
-                            BinKind::Geq => "geq",
-                            BinKind::Lt => "gl",
-                            BinKind::Leq => "leq",
+                            BinKind::SGt => "sgt",
+                            BinKind::UGt => "ugt",
+                            BinKind::SGeq => "sgeq",
+                            BinKind::UGeq => "ugeq",
+                            BinKind::SLt => "slt",
+                            BinKind::ULt => "ult",
+                            BinKind::SLeq => "sleq",
+                            BinKind::ULeq => "uleq",
                             BinKind::Shl => "shl",
                             BinKind::Shr => "shr",
                             BinKind::BitAnd => "bitand",
@@ -164,6 +200,7 @@ impl<W: Write> PrettyPrinter<W> {
                         match kind {
                             UnaryKind::Zext => "zext",
                             UnaryKind::Sext => "sext",
+                            UnaryKind::Trunc => "trunc",
                             UnaryKind::Negate => "negate",
                             UnaryKind::BitNot => "bitnot",
                             UnaryKind::LogicalNot => "logicalnot",
@@ -197,6 +234,21 @@ impl<W: Write> PrettyPrinter<W> {
                                 .join(", ")
                         )
                     }
+                    StatementKind::Phi {
+                        result,
+                        ref incomings,
+                    } => {
+                        writeln!(
+                            self.out,
+                            "    {} = phi {}",
+                            print_reg(result),
+                            incomings
+                                .iter()
+                                .map(|(bb, op)| format!("[{bb}: {}]", print_op(*op)))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )
+                    }
                 }?;
             }
 
@@ -209,6 +261,7 @@ impl<W: Write> PrettyPrinter<W> {
                     print_op(cond, loc)
                 )?,
                 Branch::Ret(op) => writeln!(self.out, "    ret {}", print_op(op, loc))?,
+                Branch::Unreachable => writeln!(self.out, "    unreachable")?,
             }
         }
 
@@ -218,12 +271,15 @@ impl<W: Write> PrettyPrinter<W> {
     }
 }
 
-impl Display for ConstValue {
+impl Display for ConstValue<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             ConstValue::Int(int) => <_ as Display>::fmt(int, f),
             ConstValue::Void => f.write_str("void"),
-            ConstValue::StaticPtr(def_id) => write!(f, "{{{}}}", def_id.0),
+            ConstValue::StaticPtr(def_id, 0) => write!(f, "{{{}}}", def_id.0),
+            ConstValue::StaticPtr(def_id, offset) => write!(f, "{{{}}}+{offset}", def_id.0),
+            ConstValue::Null(_) => f.write_str("null"),
+            ConstValue::Undef => f.write_str("undef"),
         }
     }
 }
@@ -241,3 +297,43 @@ fn display_fn<F: Fn(&mut Formatter<'_>) -> Result>(f: F) -> impl Display {
 
     DisplayFn { f }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{func_to_string, DefaultCustomizer};
+    use crate::LoweringCx;
+
+    #[test]
+    fn print_func() {
+        let ast = parser::parse_file("int f(int x) { int y = 1; if (x < 2) { y = x; } return y; }")
+            .unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        let f = ir.funcs.values().next().unwrap();
+
+        assert_eq!(
+            func_to_string(f, &DefaultCustomizer::default()),
+            "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %y = alloca, size=4, align=4
+    store %y, 1, size=4, align=4
+    %3 = load %x.local, size=4, align=4
+    %4 = slt %3, 2
+    switch %4, then bb1, else bb2
+
+  bb1:
+    %5 = load %x.local, size=4, align=4
+    store %y, %5, size=4, align=4
+    goto bb2
+
+  bb2:
+    %6 = load %y, size=4, align=4
+    ret %6
+}
+"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use super::{visit::Visitor, BasicBlock, Func, Ir, Statement, StatementKind};
+
+/// Counts of the things in an [`Ir`], cheap enough to compare before and after optimizations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IrStats {
+    pub num_funcs: usize,
+    pub num_bbs: usize,
+    pub num_statements: usize,
+    pub num_regs: usize,
+    /// The number of statements of each [`StatementKind`], keyed by a lowercase name for the kind.
+    /// All binary and unary operations are counted together as `binop` and `unary`.
+    pub statements: BTreeMap<&'static str, usize>,
+}
+
+pub fn stats(ir: &Ir<'_>) -> IrStats {
+    let mut stats = IrStats::default();
+    for func in ir.funcs.values() {
+        stats.visit_func(func);
+    }
+    stats
+}
+
+impl Visitor for IrStats {
+    fn visit_func(&mut self, func: &Func<'_>) {
+        self.num_funcs += 1;
+        self.num_regs += func.regs.len();
+        self.super_func(func);
+    }
+
+    fn visit_bb(&mut self, bb: &BasicBlock<'_>) {
+        self.num_bbs += 1;
+        self.super_bb(bb);
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement<'_>) {
+        let name = match stmt.kind {
+            StatementKind::Alloca { .. } => "alloca",
+            StatementKind::Store { .. } => "store",
+            StatementKind::Load { .. } => "load",
+            StatementKind::BinOp { .. } => "binop",
+            StatementKind::UnaryOperation { .. } => "unary",
+            StatementKind::PtrOffset { .. } => "ptroffset",
+            StatementKind::Call { .. } => "call",
+            StatementKind::Phi { .. } => "phi",
+        };
+        self.num_statements += 1;
+        *self.statements.entry(name).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stats;
+    use crate::{
+        opt::{const_fold, dce},
+        LoweringCx,
+    };
+
+    #[test]
+    fn const_fold_and_dce() {
+        let ast = parser::parse_file(
+            "int f(int x) { int y = 2 * 3; return x + y; } int g() { return 1; }",
+        )
+        .unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let mut ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+
+        let before = stats(&ir);
+        assert_eq!(before.num_funcs, 2);
+        for func in ir.funcs.values_mut() {
+            const_fold(func);
+            dce(func);
+        }
+        let after = stats(&ir);
+
+        assert_eq!(after.num_funcs, 2);
+        assert_eq!(after.num_bbs, before.num_bbs);
+        assert_eq!(after.num_regs, before.num_regs);
+        // `2 * 3` is folded and removed, `x + y` stays.
+        assert_eq!(after.num_statements, before.num_statements - 1);
+        assert_eq!(before.statements["binop"], 2);
+        assert_eq!(after.statements["binop"], 1);
+    }
+}
@@ -0,0 +1,219 @@
+//! Finds loads from locals that haven't been stored to on every path leading to them.
+
+use rustc_hash::FxHashSet;
+
+use super::{
+    info::{predecessors, reachable_blocks},
+    visit::Visitor,
+    BbIdx, Func, Location, Operand, Register, Statement, StatementKind,
+};
+
+/// Returns the locations of loads from an alloca that may not have been stored to before, together
+/// with the alloca.
+///
+/// This is a forward dataflow analysis of the allocas that are definitely initialized. Allocas
+/// whose address is used for anything else than loading and storing are assumed to be initialized
+/// through it. Unreachable blocks are not checked.
+pub fn uninitialized_loads(func: &Func<'_>) -> Vec<(Location, Register)> {
+    let slots = tracked_slots(func);
+    if slots.is_empty() {
+        return Vec::new();
+    }
+
+    let reachable = reachable_blocks(func);
+    let flow = InitFlow::compute(func, &slots, &[BbIdx::ZERO], &[]);
+
+    let mut uninit = Vec::new();
+    for (i, bb) in func.bbs.iter().enumerate() {
+        let bb_idx = BbIdx::from_usize(i);
+        if !reachable[i] {
+            continue;
+        }
+        let mut init = flow.init_in(bb_idx);
+        for (stmt_idx, stmt) in bb.statements.iter().enumerate() {
+            match stmt.kind {
+                StatementKind::Store {
+                    ptr: Operand::Reg(ptr),
+                    ..
+                } => {
+                    init.insert(ptr);
+                }
+                StatementKind::Load {
+                    ptr: Operand::Reg(ptr),
+                    ..
+                } if slots.contains(&ptr) && !init.contains(&ptr) => {
+                    uninit.push((Location::stmt(bb_idx, stmt_idx), ptr));
+                }
+                _ => {}
+            }
+        }
+    }
+    uninit
+}
+
+/// Returns the `goto`s that jump past the initialization of an alloca which is then loaded
+/// uninitialized, as the block ending in the `goto` and the alloca.
+///
+/// `gotos` are the blocks that end in a `goto` statement, unlike the jumps that lowering inserts to
+/// fall through into the next block. A `goto` skips an initialization if the alloca is
+/// initialized at its target when only falling through, but not at the `goto`.
+pub fn gotos_skipping_init(func: &Func<'_>, gotos: &[BbIdx]) -> Vec<(BbIdx, Register)> {
+    let uninit = uninitialized_loads(func);
+    if uninit.is_empty() || gotos.is_empty() {
+        return Vec::new();
+    }
+    let slots = tracked_slots(func);
+    let flow = InitFlow::compute(func, &slots, &[BbIdx::ZERO], &[]);
+
+    // Code after a `goto` is only reachable through labels, so every block that isn't jumped to
+    // anymore starts the fallthrough paths.
+    let preds = predecessors(func);
+    let entries = (0..func.bbs.len())
+        .map(BbIdx::from_usize)
+        .filter(|bb| preds[bb.as_usize()].iter().all(|pred| gotos.contains(pred)))
+        .collect::<Vec<_>>();
+    let fallthrough = InitFlow::compute(func, &slots, &entries, gotos);
+
+    let mut skipping = Vec::new();
+    for &goto in gotos {
+        let Some(init) = &flow.init_out[goto.as_usize()] else {
+            continue;
+        };
+        let target = func.bb(goto).term.successors().next().unwrap();
+        let reachable = reachable_from(func, target);
+        for &(loc, slot) in &uninit {
+            let skipped = !init.contains(&slot)
+                && fallthrough.init_in(target).contains(&slot)
+                && reachable[loc.bb.as_usize()];
+            if skipped && !skipping.contains(&(goto, slot)) {
+                skipping.push((goto, slot));
+            }
+        }
+    }
+    skipping
+}
+
+/// The allocas of the function whose address isn't taken.
+fn tracked_slots(func: &Func<'_>) -> FxHashSet<Register> {
+    let mut finder = AddressTakenFinder {
+        allocas: FxHashSet::default(),
+        taken: FxHashSet::default(),
+    };
+    for stmt in &func.bb(BbIdx::ZERO).statements {
+        if let StatementKind::Alloca { result, .. } = stmt.kind {
+            finder.allocas.insert(result);
+        }
+    }
+    finder.visit_func(func);
+    &finder.allocas - &finder.taken
+}
+
+fn reachable_from(func: &Func<'_>, start: BbIdx) -> Vec<bool> {
+    let mut reachable = vec![false; func.bbs.len()];
+    let mut worklist = vec![start];
+    while let Some(bb) = worklist.pop() {
+        if std::mem::replace(&mut reachable[bb.as_usize()], true) {
+            continue;
+        }
+        worklist.extend(func.bb(bb).term.successors());
+    }
+    reachable
+}
+
+/// The allocas that are definitely initialized at the end of every block.
+struct InitFlow {
+    /// `None` for blocks that haven't been reached, which don't constrain their successors.
+    init_out: Vec<Option<FxHashSet<Register>>>,
+    preds: Vec<Vec<BbIdx>>,
+    entries: Vec<BbIdx>,
+}
+
+impl InitFlow {
+    /// Runs the dataflow from `entries`, where nothing is initialized. The edges leaving the
+    /// blocks in `cut` are ignored.
+    fn compute(
+        func: &Func<'_>,
+        slots: &FxHashSet<Register>,
+        entries: &[BbIdx],
+        cut: &[BbIdx],
+    ) -> Self {
+        let mut preds = predecessors(func);
+        for preds in &mut preds {
+            preds.retain(|pred| !cut.contains(pred));
+        }
+        let mut flow = Self {
+            init_out: vec![None; func.bbs.len()],
+            preds,
+            entries: entries.to_vec(),
+        };
+
+        let mut worklist = entries.to_vec();
+        while let Some(bb) = worklist.pop() {
+            let mut init = flow.init_in(bb);
+            for stmt in &func.bb(bb).statements {
+                if let StatementKind::Store {
+                    ptr: Operand::Reg(ptr),
+                    ..
+                } = stmt.kind
+                {
+                    if slots.contains(&ptr) {
+                        init.insert(ptr);
+                    }
+                }
+            }
+            if flow.init_out[bb.as_usize()].as_ref() != Some(&init) {
+                flow.init_out[bb.as_usize()] = Some(init);
+                if cut.contains(&bb) {
+                    continue;
+                }
+                for succ in func.bb(bb).term.successors() {
+                    if !worklist.contains(&succ) {
+                        worklist.push(succ);
+                    }
+                }
+            }
+        }
+        flow
+    }
+
+    fn init_in(&self, bb: BbIdx) -> FxHashSet<Register> {
+        if self.entries.contains(&bb) {
+            return FxHashSet::default();
+        }
+        let mut outs = self.preds[bb.as_usize()]
+            .iter()
+            .filter_map(|pred| self.init_out[pred.as_usize()].as_ref());
+        let first = outs.next().cloned().unwrap_or_default();
+        outs.fold(first, |init, out| &init & out)
+    }
+}
+
+/// Finds allocas whose address is used for anything else than loading and storing.
+struct AddressTakenFinder {
+    allocas: FxHashSet<Register>,
+    taken: FxHashSet<Register>,
+}
+
+impl Visitor for AddressTakenFinder {
+    fn visit_statement(&mut self, stmt: &Statement<'_>) {
+        match stmt.kind {
+            StatementKind::Alloca { .. }
+            | StatementKind::Load {
+                ptr: Operand::Reg(_),
+                ..
+            } => {}
+            StatementKind::Store {
+                ptr: Operand::Reg(_),
+                value,
+                ..
+            } => self.visit_operand(value),
+            _ => self.super_statement(stmt),
+        }
+    }
+
+    fn visit_reg(&mut self, reg: Register) {
+        if self.allocas.contains(&reg) {
+            self.taken.insert(reg);
+        }
+    }
+}
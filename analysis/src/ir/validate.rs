@@ -1,7 +1,14 @@
-use rustc_hash::FxHashSet;
+use parser::ast::{IntTy, IntTyKind};
+use rustc_hash::{FxHashMap, FxHashSet};
 
-use super::{visit::Visitor, Branch, Func, Register, StatementKind};
-use crate::ir::BbIdx;
+use super::{
+    dom::Dominators, info, visit::Visitor, Branch, ConstValue, DefId, Func, Ir, Layout, Location,
+    Operand, Register, Statement, StatementKind,
+};
+use crate::{
+    ir::BbIdx,
+    ty::{Ty, TyKind},
+};
 
 pub fn validate(func: &Func<'_>) {
     for (i, bb) in func.bbs.iter().enumerate() {
@@ -24,6 +31,20 @@ pub fn validate(func: &Func<'_>) {
         }
     }
 
+    for (i, bb) in func.bbs.iter().enumerate() {
+        let first_non_phi = bb
+            .statements
+            .iter()
+            .position(|stmt| !matches!(stmt.kind, StatementKind::Phi { .. }))
+            .unwrap_or(bb.statements.len());
+        if bb.statements[first_non_phi..]
+            .iter()
+            .any(|stmt| matches!(stmt.kind, StatementKind::Phi { .. }))
+        {
+            panic!("phis must be at the start of the block, found one later in block {i}")
+        }
+    }
+
     let mut reg_names = FxHashSet::default();
     for reg in &func.regs {
         if let Some(name) = reg.name {
@@ -37,11 +58,52 @@ pub fn validate(func: &Func<'_>) {
     ValidationVisitor { func }.visit_func(func);
 }
 
-struct ValidationVisitor<'a> {
-    func: &'a Func<'a>,
+/// Checks that all static pointers, in the functions and in the data of statics, point to a
+/// function, static or extern of the IR.
+pub fn validate_static_ptrs(ir: &Ir<'_>) {
+    let exists = |def_id: DefId| {
+        ir.funcs.contains_key(&def_id)
+            || ir.data.contains_key(&def_id)
+            || ir.externs.contains_key(&def_id)
+    };
+
+    for (def_id, relocations) in &ir.relocations {
+        for &(offset, target, _) in relocations {
+            if !exists(target) {
+                panic!(
+                    "static {{{}}} points to unknown static {{{}}} at offset {offset}",
+                    def_id.0, target.0
+                );
+            }
+        }
+    }
+
+    struct StaticPtrVisitor<F>(F);
+    impl<F: Fn(DefId) -> bool> Visitor for StaticPtrVisitor<F> {
+        fn visit_const(&mut self, c: ConstValue<'_>) {
+            if let ConstValue::StaticPtr(def_id, _) = c {
+                if !(self.0)(def_id) {
+                    panic!("pointer to unknown static {{{}}}", def_id.0);
+                }
+            }
+        }
+    }
+    for func in ir.funcs.values() {
+        StaticPtrVisitor(exists).visit_func(func);
+    }
+}
+
+struct ValidationVisitor<'a, 'cx> {
+    func: &'a Func<'cx>,
 }
 
-impl Visitor for ValidationVisitor<'_> {
+impl Visitor for ValidationVisitor<'_, '_> {
+    fn visit_const(&mut self, c: ConstValue<'_>) {
+        if let ConstValue::Undef = c {
+            panic!("undef operand in {}", self.func.name);
+        }
+    }
+
     fn visit_reg(&mut self, reg: Register) {
         if self.func.regs.len() <= reg.as_usize() {
             panic!(
@@ -53,3 +115,700 @@ impl Visitor for ValidationVisitor<'_> {
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub kind: VerifyErrorKind,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyErrorKind {
+    /// The block still has the dummy terminator from lowering.
+    MissingTerminator,
+    BranchOutOfBounds(BbIdx),
+    RegisterOutOfBounds(Register),
+    RegisterDefinedTwice(Register),
+    UndefinedRegister(Register),
+    /// The register is defined, but the definition doesn't dominate this use.
+    UseBeforeDef(Register),
+    /// A phi comes after a statement that isn't a phi.
+    PhiNotAtTop(Register),
+    /// The incoming blocks of the phi are not the predecessors of its block.
+    PhiIncomingsMismatch(Register),
+    /// The returned value doesn't have the return type of the function.
+    RetTypeMismatch,
+    /// An `undef` placeholder from lowering a function with errors.
+    Undef,
+    /// The size or alignment of a load or store differs from the layout of the loaded or stored
+    /// register.
+    AccessLayoutMismatch(Register),
+    /// A load or store directly through an alloca that is less aligned than the access.
+    MisalignedAccess(Register),
+    /// A load or store directly through an alloca that is smaller than the access.
+    AccessOutOfBounds(Register),
+}
+
+/// Checks that the function is well-formed. Unlike [`validate`], this returns all problems
+/// instead of panicking on the first one.
+pub fn verify(func: &Func<'_>) -> Result<(), Vec<VerifyError>> {
+    let mut errors = Vec::new();
+    let mut error = |kind, location| errors.push(VerifyError { kind, location });
+
+    let mut branches_in_bounds = true;
+    for (i, bb) in func.bbs.iter().enumerate() {
+        let location = Location::terminator(BbIdx::from_usize(i));
+        if bb.term.is_dummy() {
+            error(VerifyErrorKind::MissingTerminator, location);
+            branches_in_bounds = false;
+            continue;
+        }
+        for succ in bb.term.successors() {
+            if succ.as_usize() >= func.bbs.len() {
+                error(VerifyErrorKind::BranchOutOfBounds(succ), location);
+                branches_in_bounds = false;
+            }
+        }
+    }
+
+    for (i, bb) in func.bbs.iter().enumerate() {
+        if let Branch::Ret(op) = bb.term {
+            if !is_valid_ret(func, op) {
+                error(
+                    VerifyErrorKind::RetTypeMismatch,
+                    Location::terminator(BbIdx::from_usize(i)),
+                );
+            }
+        }
+    }
+
+    // Predecessors can't be computed if the CFG is broken.
+    let preds = branches_in_bounds.then(|| info::predecessors(func));
+    for (i, bb) in func.bbs.iter().enumerate() {
+        let bb_idx = BbIdx::from_usize(i);
+        let preds = preds.as_ref().map(|preds| {
+            let mut preds = preds[i].clone();
+            preds.sort_by_key(|bb| bb.0);
+            preds
+        });
+        let mut seen_non_phi = false;
+
+        for (stmt_idx, stmt) in bb.statements.iter().enumerate() {
+            let StatementKind::Phi {
+                result,
+                ref incomings,
+            } = stmt.kind
+            else {
+                seen_non_phi = true;
+                continue;
+            };
+            let location = Location::stmt(bb_idx, stmt_idx);
+            if seen_non_phi {
+                error(VerifyErrorKind::PhiNotAtTop(result), location);
+            }
+            let mut incoming_bbs = incomings.iter().map(|&(bb, _)| bb).collect::<Vec<_>>();
+            incoming_bbs.sort_by_key(|bb| bb.0);
+            if preds.as_ref().is_some_and(|preds| *preds != incoming_bbs) {
+                error(VerifyErrorKind::PhiIncomingsMismatch(result), location);
+            }
+        }
+    }
+
+    let allocas = func
+        .bbs
+        .iter()
+        .flat_map(|bb| &bb.statements)
+        .filter_map(|stmt| match stmt.kind {
+            StatementKind::Alloca {
+                result,
+                size,
+                align,
+                ..
+            } => Some((result, Layout { size, align })),
+            _ => None,
+        })
+        .collect::<FxHashMap<_, _>>();
+    for (i, bb) in func.bbs.iter().enumerate() {
+        for (stmt_idx, stmt) in bb.statements.iter().enumerate() {
+            let (ptr, value, access) = match stmt.kind {
+                StatementKind::Store {
+                    ptr,
+                    value,
+                    size,
+                    align,
+                    ..
+                } => (ptr, value, Layout { size, align }),
+                StatementKind::Load {
+                    result,
+                    ptr,
+                    size,
+                    align,
+                    ..
+                } => (ptr, Operand::Reg(result), Layout { size, align }),
+                _ => continue,
+            };
+            let location = Location::stmt(BbIdx::from_usize(i), stmt_idx);
+            // Constants are untyped and out of bounds registers are reported separately.
+            if let Operand::Reg(reg) = value {
+                if func
+                    .regs
+                    .get(reg.as_usize())
+                    .is_some_and(|data| *data.tyl.layout != access)
+                {
+                    error(VerifyErrorKind::AccessLayoutMismatch(reg), location);
+                }
+            }
+            if let Some((ptr, alloca)) = match ptr {
+                Operand::Reg(ptr) => allocas.get(&ptr).map(|alloca| (ptr, alloca)),
+                Operand::Const(_) => None,
+            } {
+                if alloca.align < access.align {
+                    error(VerifyErrorKind::MisalignedAccess(ptr), location);
+                }
+                if alloca.size < access.size {
+                    error(VerifyErrorKind::AccessOutOfBounds(ptr), location);
+                }
+            }
+        }
+    }
+
+    let mut collector = RegCollector {
+        location: Location::start(),
+        defs: Vec::new(),
+        uses: Vec::new(),
+        undefs: Vec::new(),
+    };
+    for (i, bb) in func.bbs.iter().enumerate() {
+        let bb_idx = BbIdx::from_usize(i);
+        for (stmt_idx, stmt) in bb.statements.iter().enumerate() {
+            collector.location = Location::stmt(bb_idx, stmt_idx);
+            collector.visit_statement(stmt);
+        }
+        collector.location = Location::terminator(bb_idx);
+        collector.visit_terminator(&bb.term);
+    }
+
+    for &location in &collector.undefs {
+        error(VerifyErrorKind::Undef, location);
+    }
+
+    // The arguments are defined when entering the function, `None` marks that.
+    let mut def_counts = vec![0; func.regs.len()];
+    let mut def_locations = vec![None; func.regs.len()];
+    for &(reg, _) in &func.args {
+        match def_counts.get_mut(reg.as_usize()) {
+            None => error(VerifyErrorKind::RegisterOutOfBounds(reg), Location::start()),
+            Some(count) => {
+                *count += 1;
+                if *count > 1 {
+                    error(
+                        VerifyErrorKind::RegisterDefinedTwice(reg),
+                        Location::start(),
+                    );
+                }
+            }
+        }
+    }
+
+    for (reg, location) in collector.defs {
+        let Some(count) = def_counts.get_mut(reg.as_usize()) else {
+            error(VerifyErrorKind::RegisterOutOfBounds(reg), location);
+            continue;
+        };
+        *count += 1;
+        if *count > 1 {
+            error(VerifyErrorKind::RegisterDefinedTwice(reg), location);
+        } else {
+            def_locations[reg.as_usize()] = Some(location);
+        }
+    }
+
+    let doms = branches_in_bounds.then(|| Dominators::compute(func));
+
+    for (reg, location) in collector.uses {
+        match def_counts.get(reg.as_usize()) {
+            None => error(VerifyErrorKind::RegisterOutOfBounds(reg), location),
+            Some(0) => error(VerifyErrorKind::UndefinedRegister(reg), location),
+            // Phis may refer to blocks that don't exist, which was reported above.
+            Some(_) if location.bb.as_usize() >= func.bbs.len() => {}
+            Some(_) => {
+                if let (Some(def), Some(doms)) = (def_locations[reg.as_usize()], &doms) {
+                    if !dominates(doms, def, location) {
+                        error(VerifyErrorKind::UseBeforeDef(reg), location);
+                    }
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Collects the registers defined and used by statements.
+struct RegCollector {
+    location: Location,
+    defs: Vec<(Register, Location)>,
+    uses: Vec<(Register, Location)>,
+    undefs: Vec<Location>,
+}
+
+impl Visitor for RegCollector {
+    fn visit_statement(&mut self, stmt: &Statement<'_>) {
+        match stmt.kind {
+            // The incoming values are used at the end of their block, not where the phi is.
+            StatementKind::Phi {
+                result,
+                ref incomings,
+            } => {
+                self.visit_reg(result);
+                let location = self.location;
+                for &(bb, op) in incomings {
+                    self.location = Location::terminator(bb);
+                    self.visit_operand(op);
+                }
+                self.location = location;
+            }
+            _ => self.super_statement(stmt),
+        }
+    }
+
+    // Statement results are the only registers that are visited directly, all uses are operands.
+    fn visit_reg(&mut self, reg: Register) {
+        self.defs.push((reg, self.location));
+    }
+
+    fn visit_operand(&mut self, op: Operand<'_>) {
+        match op {
+            Operand::Reg(reg) => self.uses.push((reg, self.location)),
+            Operand::Const(ConstValue::Undef) => self.undefs.push(self.location),
+            Operand::Const(_) => {}
+        }
+    }
+}
+
+/// Whether `op` has the return type of `func`, or one that is represented the same way. Integer
+/// constants are untyped, so they can be returned from any function returning an integer.
+fn is_valid_ret<'cx>(func: &Func<'cx>, op: Operand<'cx>) -> bool {
+    let ret_ty = func.ret_ty;
+    match op {
+        // Out of bounds registers are reported separately.
+        Operand::Reg(reg) => func
+            .regs
+            .get(reg.as_usize())
+            .is_none_or(|reg| same_repr(reg.tyl.ty, ret_ty)),
+        Operand::Const(ConstValue::Void) => matches!(*ret_ty, TyKind::Void),
+        Operand::Const(ConstValue::Int(_)) => ret_ty.is_integer(),
+        Operand::Const(ConstValue::StaticPtr(..) | ConstValue::Null(_)) => ret_ty.is_pointer(),
+        // Reported separately.
+        Operand::Const(ConstValue::Undef) => true,
+    }
+}
+
+/// Whether values of the types are represented the same way, so that lowering converts between
+/// them without a statement. This is the case for integers that only differ in signedness, like
+/// `int` and `unsigned`, and for all pointers.
+fn same_repr(a: Ty<'_>, b: Ty<'_>) -> bool {
+    let int_kind = |ty: Ty<'_>| match *ty {
+        TyKind::Char => Some(IntTyKind::Char),
+        TyKind::Int(IntTy(_, kind)) => Some(kind),
+        _ => None,
+    };
+    match (int_kind(a), int_kind(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b || (a.is_pointer() && b.is_pointer()),
+    }
+}
+
+fn dominates(doms: &Dominators, def: Location, usage: Location) -> bool {
+    if def.bb == usage.bb {
+        return match (def.stmt, usage.stmt) {
+            (Some(def), Some(usage)) => def < usage,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+    }
+    // Nothing is defined for unreachable blocks, so everything is fine there.
+    !doms.is_reachable(usage.bb) || doms.dominates(def.bb, usage.bb)
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::Span;
+
+    use super::{verify, VerifyError, VerifyErrorKind};
+    use crate::{
+        ir::{
+            define_ir_func, BbIdx, Branch, ConstValue, Location, Operand, Register, Statement,
+            StatementKind,
+        },
+        LoweringCx,
+    };
+
+    #[test]
+    fn valid() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(2) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Switch { cond: op(Register(0)), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    BinOp { result: Register(1), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(2) }
+                    => Goto(BbIdx(2))
+                }
+                2: {
+                    => Ret(op(Register(0)))
+                }
+            }
+        };
+
+        assert_eq!(verify(&f), Ok(()));
+    }
+
+    #[test]
+    fn ret_type_mismatch() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(0) {
+                0: {
+                    => Ret(op(0))
+                }
+            }
+        };
+        assert_eq!(verify(&f), Ok(()));
+
+        f.ret_ty = lcx.types.void;
+        assert_eq!(
+            verify(&f),
+            Err(vec![VerifyError {
+                kind: VerifyErrorKind::RetTypeMismatch,
+                location: Location::terminator(BbIdx(0)),
+            }])
+        );
+
+        f.bbs[0].term = Branch::Ret(Operand::Const(ConstValue::Void));
+        assert_eq!(verify(&f), Ok(()));
+    }
+
+    #[test]
+    fn ret_same_repr() {
+        // Converting to `unsigned` or to a pointer to const doesn't need a statement, so the
+        // returned registers keep the type of the parameters.
+        let ast = parser::parse_file(
+            "unsigned f(int x) { return x; } const int *g(int *p) { return p; }",
+        )
+        .unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        for func in ir.funcs.values() {
+            assert_eq!(verify(func), Ok(()));
+        }
+    }
+
+    #[test]
+    fn unreachable_has_no_successors() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (1), regs(1) {
+                0: {
+                    => Switch { cond: op(Register(0)), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    => Ret(op(0))
+                }
+                2: {
+                    => Unreachable
+                }
+            }
+        };
+        assert_eq!(verify(&f), Ok(()));
+        assert_eq!(f.bbs[2].term.successors().count(), 0);
+    }
+
+    #[test]
+    fn undef() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(1) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Ret(op(Register(0)))
+                }
+            }
+        };
+        let StatementKind::BinOp { lhs, .. } = &mut f.bbs[0].statements[0].kind else {
+            unreachable!()
+        };
+        *lhs = Operand::Const(ConstValue::Undef);
+
+        assert_eq!(
+            verify(&f),
+            Err(vec![VerifyError {
+                kind: VerifyErrorKind::Undef,
+                location: Location::stmt(BbIdx(0), 0),
+            }])
+        );
+    }
+
+    #[test]
+    fn access_layout() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (1), regs(3) {
+                0: {
+                    Alloca { result: Register(1), size: 8, align: 8, name: None };
+                    Store { ptr: op(Register(1)), value: op(Register(0)), size: 4, align: 4, volatile: false };
+                    Load { result: Register(2), ptr: op(Register(1)), size: 4, align: 4, volatile: false }
+                    => Ret(op(Register(2)))
+                }
+            }
+        };
+        assert_eq!(verify(&f), Ok(()));
+
+        // An i64 stored with the layout of an i32.
+        f.regs[0].tyl = lcx.layout_of(lcx.types.long.signed);
+        assert_eq!(
+            verify(&f),
+            Err(vec![VerifyError {
+                kind: VerifyErrorKind::AccessLayoutMismatch(Register(0)),
+                location: Location::stmt(BbIdx(0), 1),
+            }])
+        );
+        f.regs[0].tyl = f.regs[2].tyl;
+
+        let StatementKind::Alloca { size, align, .. } = &mut f.bbs[0].statements[0].kind else {
+            unreachable!()
+        };
+        *size = 2;
+        *align = 2;
+        assert_eq!(
+            verify(&f),
+            Err(vec![
+                VerifyError {
+                    kind: VerifyErrorKind::MisalignedAccess(Register(1)),
+                    location: Location::stmt(BbIdx(0), 1),
+                },
+                VerifyError {
+                    kind: VerifyErrorKind::AccessOutOfBounds(Register(1)),
+                    location: Location::stmt(BbIdx(0), 1),
+                },
+                VerifyError {
+                    kind: VerifyErrorKind::MisalignedAccess(Register(1)),
+                    location: Location::stmt(BbIdx(0), 2),
+                },
+                VerifyError {
+                    kind: VerifyErrorKind::AccessOutOfBounds(Register(1)),
+                    location: Location::stmt(BbIdx(0), 2),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn missing_terminator() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(1) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Ret(op(Register(0)))
+                }
+            }
+        };
+        f.bbs[0].term = Branch::dummy();
+
+        assert_eq!(
+            verify(&f),
+            Err(vec![VerifyError {
+                kind: VerifyErrorKind::MissingTerminator,
+                location: Location::terminator(BbIdx(0)),
+            }])
+        );
+    }
+
+    #[test]
+    fn broken_registers_and_branches() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(3) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(Register(2)), rhs: op(2) }
+                    => Switch { cond: op(Register(0)), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    BinOp { result: Register(1), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Goto(BbIdx(2))
+                }
+                2: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(Register(1)), rhs: op(2) }
+                    => Goto(BbIdx(7))
+                }
+            }
+        };
+
+        assert_eq!(
+            verify(&f),
+            Err(vec![
+                VerifyError {
+                    kind: VerifyErrorKind::BranchOutOfBounds(BbIdx(7)),
+                    location: Location::terminator(BbIdx(2)),
+                },
+                VerifyError {
+                    kind: VerifyErrorKind::RegisterDefinedTwice(Register(0)),
+                    location: Location::stmt(BbIdx(2), 0),
+                },
+                VerifyError {
+                    kind: VerifyErrorKind::UndefinedRegister(Register(2)),
+                    location: Location::stmt(BbIdx(0), 0),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn args() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (2), regs(3) {
+                0: {
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(Register(1)) }
+                    => Ret(op(Register(2)))
+                }
+            }
+        };
+        assert_eq!(verify(&f), Ok(()));
+
+        // Arguments can't be defined again in the body.
+        let tyl = f.args[0].1;
+        f.args.push((Register(2), tyl));
+        f.args.push((Register(5), tyl));
+        assert_eq!(
+            verify(&f),
+            Err(vec![
+                VerifyError {
+                    kind: VerifyErrorKind::RegisterOutOfBounds(Register(5)),
+                    location: Location::start(),
+                },
+                VerifyError {
+                    kind: VerifyErrorKind::RegisterDefinedTwice(Register(2)),
+                    location: Location::stmt(BbIdx(0), 0),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn use_before_def() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        // %1 is only defined on one of the paths to bb2.
+        let f = define_ir_func! {
+            def(lcx) name (), regs(2) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Switch { cond: op(Register(0)), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    BinOp { result: Register(1), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Goto(BbIdx(2))
+                }
+                2: {
+                    => Ret(op(Register(1)))
+                }
+            }
+        };
+
+        assert_eq!(
+            verify(&f),
+            Err(vec![VerifyError {
+                kind: VerifyErrorKind::UseBeforeDef(Register(1)),
+                location: Location::terminator(BbIdx(2)),
+            }])
+        );
+    }
+
+    #[test]
+    fn phi() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(3) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) }
+                    => Switch { cond: op(Register(0)), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    BinOp { result: Register(1), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(2) }
+                    => Goto(BbIdx(2))
+                }
+                2: {
+                    Phi { result: Register(2), incomings: vec![(BbIdx(0), op(0)), (BbIdx(1), op(Register(1)))] }
+                    => Ret(op(Register(2)))
+                }
+            }
+        };
+
+        assert_eq!(verify(&f), Ok(()));
+
+        // The value from bb1 is not available when coming from bb0.
+        f.bbs[2].statements[0].kind = StatementKind::Phi {
+            result: Register(2),
+            incomings: vec![
+                (BbIdx(0), Operand::Reg(Register(1))),
+                (BbIdx(1), Operand::Reg(Register(1))),
+            ],
+        };
+        assert_eq!(
+            verify(&f),
+            Err(vec![VerifyError {
+                kind: VerifyErrorKind::UseBeforeDef(Register(1)),
+                location: Location::terminator(BbIdx(0)),
+            }])
+        );
+
+        f.bbs[2].statements[0].kind = StatementKind::Phi {
+            result: Register(2),
+            incomings: vec![(BbIdx(1), Operand::Reg(Register(1)))],
+        };
+        f.bbs[1].statements.push(Statement {
+            span: Span::dummy(),
+            kind: StatementKind::Phi {
+                result: Register(2),
+                incomings: vec![(BbIdx(0), Operand::Reg(Register(0)))],
+            },
+        });
+        let errors = verify(&f).unwrap_err();
+        assert!(errors.contains(&VerifyError {
+            kind: VerifyErrorKind::PhiNotAtTop(Register(2)),
+            location: Location::stmt(BbIdx(1), 1),
+        }));
+        assert!(errors.contains(&VerifyError {
+            kind: VerifyErrorKind::PhiIncomingsMismatch(Register(2)),
+            location: Location::stmt(BbIdx(2), 0),
+        }));
+    }
+}
@@ -1,20 +1,23 @@
-use super::{BasicBlock, ConstValue, Func, Operand, Register, Statement, StatementKind};
+use super::{BasicBlock, Branch, ConstValue, Func, Operand, Register, Statement, StatementKind};
 
 pub trait Visitor {
     fn visit_func(&mut self, func: &Func<'_>) {
         self.super_func(func);
     }
-    fn visit_bb(&mut self, bb: &BasicBlock) {
+    fn visit_bb(&mut self, bb: &BasicBlock<'_>) {
         self.super_bb(bb);
     }
-    fn visit_statement(&mut self, stmt: &Statement) {
+    fn visit_statement(&mut self, stmt: &Statement<'_>) {
         self.super_statement(stmt);
     }
-    fn visit_operand(&mut self, op: Operand) {
+    fn visit_terminator(&mut self, term: &Branch<'_>) {
+        self.super_terminator(term);
+    }
+    fn visit_operand(&mut self, op: Operand<'_>) {
         self.super_operand(op);
     }
     fn visit_reg(&mut self, _: Register) {}
-    fn visit_const(&mut self, _: ConstValue) {}
+    fn visit_const(&mut self, _: ConstValue<'_>) {}
 
     fn super_func(&mut self, func: &Func<'_>) {
         for bb in &func.bbs {
@@ -22,18 +25,20 @@ pub trait Visitor {
         }
     }
 
-    fn super_bb(&mut self, bb: &BasicBlock) {
+    fn super_bb(&mut self, bb: &BasicBlock<'_>) {
         for stmt in &bb.statements {
             self.visit_statement(stmt);
         }
+        self.visit_terminator(&bb.term);
     }
 
-    fn super_statement(&mut self, stmt: &Statement) {
+    fn super_statement(&mut self, stmt: &Statement<'_>) {
         match stmt.kind {
             StatementKind::Alloca {
                 result,
                 size: _,
                 align: _,
+                name: _,
             } => {
                 self.visit_reg(result);
             }
@@ -42,6 +47,7 @@ pub trait Visitor {
                 value,
                 size: _,
                 align: _,
+                volatile: _,
             } => {
                 self.visit_operand(ptr);
                 self.visit_operand(value);
@@ -51,6 +57,7 @@ pub trait Visitor {
                 ptr,
                 size: _,
                 align: _,
+                volatile: _,
             } => {
                 self.visit_reg(result);
                 self.visit_operand(ptr);
@@ -93,13 +100,232 @@ pub trait Visitor {
                     self.visit_operand(arg);
                 }
             }
+            StatementKind::Phi {
+                result,
+                ref incomings,
+            } => {
+                self.visit_reg(result);
+                for &(_, op) in incomings {
+                    self.visit_operand(op);
+                }
+            }
+        }
+    }
+
+    fn super_terminator(&mut self, term: &Branch<'_>) {
+        match *term {
+            Branch::Goto(_) | Branch::Unreachable => {}
+            Branch::Switch {
+                cond,
+                yes: _,
+                no: _,
+            } => self.visit_operand(cond),
+            Branch::Ret(op) => self.visit_operand(op),
         }
     }
 
-    fn super_operand(&mut self, op: Operand) {
+    fn super_operand(&mut self, op: Operand<'_>) {
         match op {
             Operand::Reg(reg) => self.visit_reg(reg),
             Operand::Const(c) => self.visit_const(c),
         }
     }
 }
+
+/// Like [`Visitor`], but allows changing the IR in place.
+pub trait VisitorMut<'cx> {
+    fn visit_func(&mut self, func: &mut Func<'cx>) {
+        self.super_func(func);
+    }
+    fn visit_bb(&mut self, bb: &mut BasicBlock<'cx>) {
+        self.super_bb(bb);
+    }
+    fn visit_statement(&mut self, stmt: &mut Statement<'cx>) {
+        self.super_statement(stmt);
+    }
+    fn visit_terminator(&mut self, term: &mut Branch<'cx>) {
+        self.super_terminator(term);
+    }
+    fn visit_operand(&mut self, op: &mut Operand<'cx>) {
+        self.super_operand(op);
+    }
+    fn visit_reg(&mut self, _: &mut Register) {}
+    fn visit_const(&mut self, _: &mut ConstValue<'cx>) {}
+
+    fn super_func(&mut self, func: &mut Func<'cx>) {
+        for bb in &mut func.bbs {
+            self.visit_bb(bb);
+        }
+    }
+
+    fn super_bb(&mut self, bb: &mut BasicBlock<'cx>) {
+        for stmt in &mut bb.statements {
+            self.visit_statement(stmt);
+        }
+        self.visit_terminator(&mut bb.term);
+    }
+
+    fn super_statement(&mut self, stmt: &mut Statement<'cx>) {
+        match &mut stmt.kind {
+            StatementKind::Alloca {
+                result,
+                size: _,
+                align: _,
+                name: _,
+            } => {
+                self.visit_reg(result);
+            }
+            StatementKind::Store {
+                ptr,
+                value,
+                size: _,
+                align: _,
+                volatile: _,
+            } => {
+                self.visit_operand(ptr);
+                self.visit_operand(value);
+            }
+            StatementKind::Load {
+                result,
+                ptr,
+                size: _,
+                align: _,
+                volatile: _,
+            } => {
+                self.visit_reg(result);
+                self.visit_operand(ptr);
+            }
+            StatementKind::BinOp {
+                kind: _,
+                lhs,
+                rhs,
+                result,
+            } => {
+                self.visit_reg(result);
+                self.visit_operand(lhs);
+                self.visit_operand(rhs);
+            }
+            StatementKind::UnaryOperation {
+                rhs,
+                kind: _,
+                result,
+            } => {
+                self.visit_reg(result);
+                self.visit_operand(rhs);
+            }
+            StatementKind::PtrOffset {
+                result,
+                ptr,
+                amount,
+            } => {
+                self.visit_reg(result);
+                self.visit_operand(ptr);
+                self.visit_operand(amount);
+            }
+            StatementKind::Call { result, func, args } => {
+                self.visit_reg(result);
+                self.visit_operand(func);
+                for arg in args {
+                    self.visit_operand(arg);
+                }
+            }
+            StatementKind::Phi { result, incomings } => {
+                self.visit_reg(result);
+                for (_, op) in incomings {
+                    self.visit_operand(op);
+                }
+            }
+        }
+    }
+
+    fn super_terminator(&mut self, term: &mut Branch<'cx>) {
+        match term {
+            Branch::Goto(_) | Branch::Unreachable => {}
+            Branch::Switch {
+                cond,
+                yes: _,
+                no: _,
+            } => self.visit_operand(cond),
+            Branch::Ret(op) => self.visit_operand(op),
+        }
+    }
+
+    fn super_operand(&mut self, op: &mut Operand<'cx>) {
+        match op {
+            Operand::Reg(reg) => self.visit_reg(reg),
+            Operand::Const(c) => self.visit_const(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VisitorMut;
+    use crate::{
+        ir::{Branch, ConstValue, Operand, StatementKind},
+        LoweringCx,
+    };
+
+    struct DoubleInts;
+
+    impl VisitorMut<'_> for DoubleInts {
+        fn visit_const(&mut self, c: &mut ConstValue<'_>) {
+            if let ConstValue::Int(int) = c {
+                *int *= 2;
+            }
+        }
+    }
+
+    #[test]
+    fn double_ints() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = crate::ir::define_ir_func! {
+            def(lcx) name (), regs(2) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(2) };
+                    Call { result: Register(1), func: op(Register(0)), args: vec![op(3)] };
+                    => Ret(op(Register(1)))
+                }
+            }
+        };
+
+        DoubleInts.visit_func(&mut f);
+
+        let stmts = &f.bbs[0].statements;
+        let StatementKind::BinOp {
+            lhs: Operand::Const(ConstValue::Int(2)),
+            rhs: Operand::Const(ConstValue::Int(4)),
+            ..
+        } = stmts[0].kind
+        else {
+            panic!("constants were not doubled: {:?}", stmts[0]);
+        };
+        let StatementKind::Call { ref args, .. } = stmts[1].kind else {
+            unreachable!()
+        };
+        assert!(matches!(args[..], [Operand::Const(ConstValue::Int(6))]));
+    }
+
+    #[test]
+    fn visits_terminators() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = crate::ir::define_ir_func! {
+            def(lcx) name (), regs(0) {
+                0: {
+                    => Ret(op(5))
+                }
+            }
+        };
+
+        DoubleInts.visit_func(&mut f);
+
+        assert!(matches!(
+            f.bbs[0].term,
+            Branch::Ret(Operand::Const(ConstValue::Int(10)))
+        ));
+    }
+}
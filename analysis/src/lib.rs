@@ -4,7 +4,18 @@
 mod ctxt;
 pub mod ir;
 mod lower;
+pub mod opt;
 pub mod ty;
 
-pub use ctxt::LoweringCx;
+pub use ctxt::{InternStats, LoweringCx, TargetLayout};
 pub use lower::lower_translation_unit;
+
+/// Parses and lowers a whole translation unit. The warnings of lowering are collected in `lcx`,
+/// see [`LoweringCx::take_warnings`].
+pub fn compile<'cx>(
+    lcx: &mut LoweringCx<'cx>,
+    src: &str,
+) -> Result<ir::Ir<'cx>, Vec<parser::Error>> {
+    let ast = parser::parse_file(src).map_err(|err| vec![err])?;
+    lower_translation_unit(lcx, &ast)
+}
@@ -1,96 +1,541 @@
 mod builder;
+mod consteval;
+mod initializer;
+#[cfg(test)]
+mod tests;
 mod typeck;
 
 use parser::{
     ast::{self, ExprBinary},
-    Error, Span, Symbol,
+    Error, Span, Spanned, Symbol,
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
-use self::{builder::FuncBuilder, typeck::Coercion};
+use self::builder::FuncBuilder;
+pub(crate) use self::consteval::eval_const_expr;
+use self::consteval::{check_array_index, eval_const_addr, eval_const_expr_scoped};
+use self::initializer::{flatten_initializer, string_init_bytes, InitElem};
 use crate::{
     ctxt::LoweringCx,
     ir::{
-        self, BbIdx, BinKind, Branch, ConstValue, Func, Ir, Operand, Register, TyLayout, UnaryKind,
-        VariableInfo, VariableInfoKind,
+        self, BbIdx, BinKind, Branch, ConstValue, Func, Ir, Operand, Register, Relocation,
+        TyLayout, UnaryKind, VariableInfo, VariableInfoKind,
     },
-    ty::{Ty, TyKind},
+    ty::{StructTy, Ty, TyKind},
 };
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Lowers all functions of the translation unit. Lowering continues after errors where possible,
+/// so all of them are returned.
 pub fn lower_translation_unit<'cx>(
     lcx: &mut LoweringCx<'cx>,
     ast: &ast::TranslationUnit,
-) -> Result<Ir<'cx>, Error> {
+) -> Result<Ir<'cx>, Vec<Error>> {
     let mut ir = Ir {
         funcs: FxHashMap::default(),
+        data: FxHashMap::default(),
+        relocations: FxHashMap::default(),
+        externs: FxHashMap::default(),
     };
+    let mut errors = Vec::new();
 
-    for (decl, _) in ast {
+    for (decl, span) in ast {
         match decl {
-            ast::ExternalDecl::Decl(_) => todo!("decl is unsupported"),
+            ast::ExternalDecl::Decl(ast::Decl::StaticAssert(assert)) => {
+                if let Err(err) = lcx.check_static_assert(assert, *span) {
+                    errors.push(err);
+                }
+            }
+            ast::ExternalDecl::Decl(ast::Decl::Normal(decl)) => {
+                // The specifiers are lowered once, so that a struct they define is shared by all
+                // declarators. Declarations like `struct S { int a; };` have none.
+                let base_ty = match lcx.lower_ty(&decl.decl_spec.ty) {
+                    Ok(ty) => ty,
+                    Err(err) => {
+                        errors.push(err);
+                        continue;
+                    }
+                };
+                for init_decl in &decl.init_declarators {
+                    if let Err(err) = lower_global(lcx, &mut ir, decl, base_ty, init_decl) {
+                        errors.push(err);
+                    }
+                }
+            }
             ast::ExternalDecl::FunctionDef(def) => {
                 let decl = def.decl.unwrap_normal();
                 let body = &def.body;
-                let ret_ty = lcx.lower_ty(&decl.decl_spec.ty);
-
-                let (ref declarator, def_span) = decl.init_declarators[0];
+                let &(ref declarator, def_span) = match &decl.init_declarators[..] {
+                    [init_decl @ (
+                        ast::InitDecl {
+                            declarator:
+                                ast::Declarator {
+                                    decl: ast::DirectDeclarator::WithParams { .. },
+                                    ..
+                                },
+                            init: None,
+                        },
+                        _,
+                    )] => init_decl,
+                    _ => {
+                        errors.push(Error::new(
+                            "function definition must declare exactly one function",
+                            *span,
+                        ));
+                        continue;
+                    }
+                };
 
-                let ast::DirectDeclarator::WithParams { ident, params } =
+                let fn_ty = lcx
+                    .lower_ty(&decl.decl_spec.ty)
+                    .and_then(|base_ty| lower_fn_ty(lcx, base_ty, decl, &declarator.declarator));
+                let (ty, ret_ty, param_tys) = match fn_ty {
+                    Ok(fn_ty) => fn_ty,
+                    Err(err) => {
+                        errors.push(err);
+                        continue;
+                    }
+                };
+                let ast::DirectDeclarator::WithParams { ident, params, .. } =
                     &declarator.declarator.decl
                 else {
                     unreachable!("function def needs withparams declarator");
                 };
+                let param_decls = params.as_deref().unwrap_or_default();
 
-                let def_id = lcx.next_def_id();
-                let func = lower_func(lcx, body, def_span, ident.0, ret_ty, params)?;
-
-                let args = &*lcx
-                    .arena
-                    .alloc_slice_fill_iter(func.regs[..func.arity].iter().map(|data| data.tyl.ty));
-
-                let ty = lcx.intern_ty(TyKind::Func(args, func.ret_ty));
-
-                let def_span = func.def_span;
-
-                ir.funcs.insert(def_id, func);
+                // The definition takes over the `DefId` of a prototype, which earlier calls
+                // already refer to.
+                let (def_id, ty) = match lcx.global_decls.get(&ident.0) {
+                    Some(predeclared) => match predeclared.kind {
+                        VariableInfoKind::FnDef { def_id } if ir.externs.contains_key(&def_id) => {
+                            // (6.7.6.3p15) Empty parentheses in a definition mean that there are
+                            // no parameters, unlike in a declaration.
+                            let composite =
+                                composite_ty(lcx, predeclared.tyl.ty, ty).filter(|_| {
+                                    params.is_some()
+                                        || matches!(
+                                            *predeclared.tyl.ty,
+                                            TyKind::Func {
+                                                params: None | Some([]),
+                                                ..
+                                            }
+                                        )
+                                });
+                            let Some(composite) = composite else {
+                                errors.push(conflicting_types(ident.0, predeclared, ty, def_span));
+                                continue;
+                            };
+                            ir.externs.remove(&def_id);
+                            (def_id, composite)
+                        }
+                        VariableInfoKind::FnDef { def_id } if !ir.externs.contains_key(&def_id) => {
+                            errors.push(
+                                Error::new(
+                                    format!("function {} has already been defined", ident.0),
+                                    def_span,
+                                )
+                                .note_spanned("already defined here", predeclared.def_span),
+                            );
+                            continue;
+                        }
+                        _ => {
+                            errors.push(conflicting_types(ident.0, predeclared, ty, def_span));
+                            continue;
+                        }
+                    },
+                    None => (lcx.next_def_id(), ty),
+                };
 
+                // The signature is registered before the body is lowered so that the function can
+                // call itself.
                 lcx.global_decls.insert(
                     ident.0,
                     VariableInfo {
                         def_span,
-                        decl_attr: ast::DeclAttr::empty(),
+                        decl_attr: decl.decl_spec.attrs,
+                        quals: ast::Qualifiers::empty(),
                         tyl: lcx.layout_of(ty),
                         kind: VariableInfoKind::FnDef { def_id },
                     },
                 );
+
+                match lower_func(lcx, body, def_span, ident.0, ret_ty, param_decls, param_tys) {
+                    Ok(mut func) => {
+                        func.decl_attr = decl.decl_spec.attrs;
+                        ir.funcs.insert(def_id, func);
+                    }
+                    Err(errs) => errors.extend(errs),
+                }
             }
         }
     }
 
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    ir.data.extend(lcx.str_lit_data());
+    for (def_id, data, relocations) in lcx.take_static_locals() {
+        ir.data.insert(def_id, data);
+        if !relocations.is_empty() {
+            ir.relocations.insert(def_id, relocations);
+        }
+    }
+
     for func in ir.funcs.values() {
         ir::validate(func);
     }
+    ir::validate_static_ptrs(&ir);
 
     Ok(ir)
 }
 
+/// Declares a variable at file scope. Its initial value is stored in the data of the IR, it's
+/// zeroed if there is no initializer.
+fn lower_global<'cx>(
+    lcx: &mut LoweringCx<'cx>,
+    ir: &mut Ir<'cx>,
+    decl: &ast::NormalDecl,
+    base_ty: Ty<'cx>,
+    (init_decl, def_span): &Spanned<ast::InitDecl>,
+) -> Result<()> {
+    if let ast::DirectDeclarator::WithParams { ident, .. } = init_decl.declarator.decl {
+        return declare_function(lcx, ir, decl, base_ty, ident, init_decl, *def_span);
+    }
+
+    check_function_specifiers(decl.decl_spec.attrs, *def_span)?;
+    let ty = lcx.lower_declarator_ty(base_ty, decl.decl_spec.quals, &init_decl.declarator)?;
+    let (ty, init_elems) = match &init_decl.init {
+        Some(init) => flatten_initializer(lcx, ty, init, &|ident| {
+            lcx.global_decls.get(&ident).map(|var| var.tyl)
+        })?,
+        None => (ty, Vec::new()),
+    };
+    let tyl = lcx.layout_of(ty);
+    let (name, name_span) = init_decl.declarator.decl.name();
+    // (6.9.2) An `extern` declaration without an initializer refers to a definition that may be in
+    // another translation unit.
+    let is_extern =
+        decl.decl_spec.attrs.contains(ast::DeclAttr::EXTERN) && init_decl.init.is_none();
+
+    let (def_id, tyl) = match lcx.global_decls.get(&name) {
+        Some(predeclared) => {
+            let extern_def_id = match predeclared.kind {
+                VariableInfoKind::Static { def_id } if ir.externs.contains_key(&def_id) => {
+                    Some(def_id)
+                }
+                _ => None,
+            };
+            if !is_extern && extern_def_id.is_none() {
+                return Err(Error::new(
+                    format!("variable {name} has already been declared"),
+                    name_span,
+                )
+                .note_spanned("already declared here", predeclared.def_span));
+            }
+            let composite = match predeclared.kind {
+                VariableInfoKind::Static { .. } => composite_ty(lcx, predeclared.tyl.ty, ty),
+                _ => None,
+            };
+            let Some(composite) = composite else {
+                return Err(conflicting_types(name, predeclared, ty, name_span));
+            };
+            let tyl = lcx.layout_of(composite);
+            match extern_def_id {
+                // The definition takes over the `DefId` that earlier uses already refer to.
+                Some(def_id) if !is_extern => {
+                    ir.externs.remove(&def_id);
+                    (def_id, tyl)
+                }
+                // Redeclaring something that is already declared only completes its type.
+                _ => {
+                    lcx.global_decls.get_mut(&name).unwrap().tyl = tyl;
+                    return Ok(());
+                }
+            }
+        }
+        None => (lcx.next_def_id(), tyl),
+    };
+
+    let variable_info = VariableInfo {
+        def_span: *def_span,
+        decl_attr: decl.decl_spec.attrs,
+        quals: LoweringCx::declarator_quals(decl.decl_spec.quals, &init_decl.declarator),
+        tyl,
+        kind: VariableInfoKind::Static { def_id },
+    };
+    lcx.global_decls.insert(name, variable_info);
+    if is_extern {
+        ir.externs.insert(def_id, name);
+        return Ok(());
+    }
+
+    let (data, relocations) = static_data(lcx, tyl, init_elems)?;
+    ir.data.insert(def_id, lcx.arena.alloc_slice_copy(&data));
+    if !relocations.is_empty() {
+        ir.relocations.insert(def_id, relocations);
+    }
+    Ok(())
+}
+
+/// Computes the type of a function from its declaration and the type of its specifiers, returning
+/// it together with the return type and the parameter types.
+fn lower_fn_ty<'cx>(
+    lcx: &LoweringCx<'cx>,
+    base_ty: Ty<'cx>,
+    decl: &ast::NormalDecl,
+    declarator: &ast::Declarator,
+) -> Result<(Ty<'cx>, Ty<'cx>, &'cx [Ty<'cx>])> {
+    let ast::DirectDeclarator::WithParams {
+        params, variadic, ..
+    } = &declarator.decl
+    else {
+        unreachable!("function declaration needs withparams declarator");
+    };
+
+    let ret_ty = lcx.lower_declarator_ty(base_ty, decl.decl_spec.quals, declarator)?;
+    let param_tys = params
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|param| lcx.lower_param_ty(param))
+        .collect::<Result<Vec<_>>>()?;
+    let param_tys = &*lcx.arena.alloc_slice_copy(&param_tys);
+
+    let ty = lcx.intern_ty(TyKind::Func {
+        ret: ret_ty,
+        params: params.is_some().then_some(param_tys),
+        variadic: *variadic,
+    });
+    Ok((ty, ret_ty, param_tys))
+}
+
+/// Lowers a function prototype like `int f(int);`. Until the function is defined, it's an extern
+/// that calls refer to.
+fn declare_function<'cx>(
+    lcx: &mut LoweringCx<'cx>,
+    ir: &mut Ir<'cx>,
+    decl: &ast::NormalDecl,
+    base_ty: Ty<'cx>,
+    (name, name_span): ast::Ident,
+    init_decl: &ast::InitDecl,
+    def_span: Span,
+) -> Result<()> {
+    if init_decl.init.is_some() {
+        return Err(Error::new(
+            format!("function {name} cannot have an initializer"),
+            def_span,
+        ));
+    }
+    let (ty, _, _) = lower_fn_ty(lcx, base_ty, decl, &init_decl.declarator)?;
+
+    if let Some(predeclared) = lcx.global_decls.get(&name) {
+        // Redeclaring a function only combines the types, as long as they are compatible.
+        let composite = match predeclared.kind {
+            VariableInfoKind::FnDef { .. } => composite_ty(lcx, predeclared.tyl.ty, ty),
+            _ => None,
+        };
+        let Some(composite) = composite else {
+            return Err(conflicting_types(name, predeclared, ty, name_span));
+        };
+        let tyl = lcx.layout_of(composite);
+        lcx.global_decls.get_mut(&name).unwrap().tyl = tyl;
+        return Ok(());
+    }
+
+    let def_id = lcx.next_def_id();
+    let variable_info = VariableInfo {
+        def_span,
+        decl_attr: decl.decl_spec.attrs,
+        quals: ast::Qualifiers::empty(),
+        tyl: lcx.layout_of(ty),
+        kind: VariableInfoKind::FnDef { def_id },
+    };
+    lcx.global_decls.insert(name, variable_info);
+    ir.externs.insert(def_id, name);
+    Ok(())
+}
+
+fn conflicting_types(
+    name: Symbol,
+    predeclared: &VariableInfo<'_>,
+    ty: Ty<'_>,
+    span: Span,
+) -> Error {
+    Error::new(
+        format!(
+            "conflicting types for {name}: {} and {ty}",
+            predeclared.tyl.ty
+        ),
+        span,
+    )
+    .note_spanned("previously declared here", predeclared.def_span)
+}
+
+/// (6.2.7) The composite type of two declarations of the same variable or function, or `None` if
+/// their types aren't compatible. An array of unknown size is compatible with an array of the same
+/// element type that has a size, and the composite has the size.
+///
+/// (6.7.6.3p15) A function with unspecified parameters is compatible with a prototype that isn't
+/// variadic and whose parameters are unchanged by the default argument promotions, the composite
+/// is the prototype.
+fn composite_ty<'cx>(lcx: &LoweringCx<'cx>, a: Ty<'cx>, b: Ty<'cx>) -> Option<Ty<'cx>> {
+    match (&*a, &*b) {
+        (
+            TyKind::Func {
+                ret: a_ret,
+                params: a_params,
+                variadic: a_variadic,
+            },
+            TyKind::Func {
+                ret: b_ret,
+                params: b_params,
+                variadic: b_variadic,
+            },
+        ) => {
+            let ret = composite_ty(lcx, *a_ret, *b_ret)?;
+            let (params, variadic) = match (a_params, b_params) {
+                (Some(a_params), Some(b_params)) => {
+                    if a_params.len() != b_params.len() || a_variadic != b_variadic {
+                        return None;
+                    }
+                    let params = a_params
+                        .iter()
+                        .zip(*b_params)
+                        .map(|(&a, &b)| composite_ty(lcx, a, b))
+                        .collect::<Option<Vec<_>>>()?;
+                    (Some(&*lcx.arena.alloc_slice_copy(&params)), *a_variadic)
+                }
+                (Some(params), None) | (None, Some(params)) => {
+                    let promotable =
+                        |ty: Ty<'cx>| lcx.promote(ty) != ty || matches!(*ty, TyKind::Float);
+                    if *a_variadic || *b_variadic || params.iter().any(|&ty| promotable(ty)) {
+                        return None;
+                    }
+                    (Some(*params), false)
+                }
+                (None, None) => (None, false),
+            };
+            Some(lcx.intern_ty(TyKind::Func {
+                ret,
+                params,
+                variadic,
+            }))
+        }
+        (
+            TyKind::Array {
+                elem: a_elem,
+                len: a_len,
+            },
+            TyKind::Array {
+                elem: b_elem,
+                len: b_len,
+            },
+        ) => {
+            let elem = composite_ty(lcx, *a_elem, *b_elem)?;
+            let len = match (a_len, b_len) {
+                (Some(a_len), Some(b_len)) if a_len != b_len => return None,
+                (Some(len), _) | (_, Some(len)) => Some(*len),
+                (None, None) => None,
+            };
+            Some(lcx.intern_ty(TyKind::Array { elem, len }))
+        }
+        _ => (a == b).then_some(a),
+    }
+}
+
+/// Computes the initial contents and relocations of a static from its flattened initializer,
+/// which has to consist of constant expressions.
+fn static_data<'cx>(
+    lcx: &LoweringCx<'cx>,
+    tyl: TyLayout<'cx>,
+    init_elems: Vec<InitElem<'_, 'cx>>,
+) -> Result<(Vec<u8>, Vec<Relocation>)> {
+    // Parts without an initializer stay zero.
+    let mut data = vec![0; tyl.layout.size as usize];
+    let mut relocations = Vec::new();
+    for elem in init_elems {
+        let Some((init, init_span)) = elem.init else {
+            continue;
+        };
+        if let TyKind::Array { .. } = **elem.ty {
+            let bytes = string_init_bytes(elem.ty, init, init_span)?;
+            data[elem.offset as usize..][..bytes.len()].copy_from_slice(&bytes);
+        } else if elem.ty.is_pointer() {
+            if let Some((target, addend)) = eval_const_addr(lcx, init, init_span, elem.ty)? {
+                relocations.push((elem.offset, target, addend));
+            }
+        } else if !elem.ty.is_integer() {
+            return Err(Error::new(
+                format!("cannot initialize static variable of type {} yet", elem.ty),
+                init_span,
+            ));
+        } else {
+            let value = eval_const_expr(lcx, init, init_span)?;
+            let size = lcx.layout_of(elem.ty).layout.size as usize;
+            let offset = elem.offset as usize;
+            data[offset..][..size].copy_from_slice(&value.to_le_bytes()[..size]);
+        }
+    }
+    Ok((data, relocations))
+}
+
+/// (6.7.4) Function specifiers can only be used in the declaration of a function.
+fn check_function_specifiers(decl_attr: ast::DeclAttr, span: Span) -> Result<()> {
+    for (attr, name) in [
+        (ast::DeclAttr::INLINE, "inline"),
+        (ast::DeclAttr::NORETURN, "_Noreturn"),
+    ] {
+        if decl_attr.contains(attr) {
+            return Err(Error::new(
+                format!("{name} can only be used on functions"),
+                span,
+            ));
+        }
+    }
+    Ok(())
+}
+
 struct FnLoweringCtxt<'a, 'cx> {
     scopes: Vec<FxHashMap<Symbol, VariableInfo<'cx>>>,
     build: FuncBuilder<'a, 'cx>,
     lcx: &'a LoweringCx<'cx>,
+    /// Errors that lowering recovered from.
+    errors: Vec<Error>,
+    /// The blocks that `break` jumps to, innermost last.
+    break_targets: Vec<BbIdx>,
+    /// The blocks that `continue` jumps to, innermost last. Unlike `break`, `continue` ignores
+    /// `switch` statements.
+    continue_targets: Vec<BbIdx>,
+    /// The labels of the `switch` statements that are being lowered, innermost last.
+    switches: Vec<SwitchLabels<'cx>>,
+    /// The blocks of all `goto` labels of the function.
+    labels: FxHashMap<Symbol, (BbIdx, Span)>,
+    /// The blocks ending in a reachable `goto` statement, with the span of the statement.
+    gotos: Vec<(BbIdx, Span)>,
 }
 
-impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
-    fn dummy_tyl(&self) -> TyLayout<'cx> {
-        self.ty_layout(TyKind::Void)
-    }
+struct SwitchLabels<'cx> {
+    /// The promoted type of the condition.
+    tyl: TyLayout<'cx>,
+    /// The value, block and span of every `case` in source order.
+    cases: Vec<(u128, BbIdx, Span)>,
+    default: Option<(BbIdx, Span)>,
+}
 
+impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
     fn ty_layout(&self, ty_kind: TyKind<'cx>) -> TyLayout<'cx> {
         self.lcx.layout_of(self.lcx.intern_ty(ty_kind))
     }
 
+    /// The placeholder for an expression that already had an error. It's typed as `int`, and
+    /// expressions using it evaluate to it as well instead of reporting follow-up errors.
+    fn undef(&self) -> (Operand<'cx>, TyLayout<'cx>) {
+        let int = self.lcx.types.int.signed;
+        (Operand::Const(ConstValue::Undef), self.lcx.layout_of(int))
+    }
+
     fn resolve_ident(&self, ident: Symbol) -> Option<&VariableInfo<'cx>> {
         self.scopes
             .iter()
@@ -99,30 +544,264 @@ impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
             .or_else(|| self.lcx.global_decls.get(&ident))
     }
 
-    fn lower_block(&mut self, body: &[(ast::Stmt, Span)]) -> Result<()> {
+    /// Evaluates a constant expression in the current scope, so that `sizeof` sees locals.
+    fn eval_const_expr_in_scope(&self, expr: &ast::Expr, span: Span) -> Result<(i128, Ty<'cx>)> {
+        eval_const_expr_scoped(self.lcx, expr, span, &|ident| {
+            self.resolve_ident(ident).map(|var| var.tyl)
+        })
+    }
+
+    /// (6.3.2.3) An integer constant expression with the value 0, like `0`, `1 - 1` or `'\0'`, is a
+    /// null pointer constant.
+    fn is_null_pointer_constant(&self, expr: &ast::Expr, span: Span) -> bool {
+        matches!(self.eval_const_expr_in_scope(expr, span), Ok((0, _)))
+    }
+
+    /// Whether `callee` names a function declared `_Noreturn`.
+    fn is_noreturn_fn(&self, callee: &ast::Expr) -> bool {
+        let ast::Expr::Atom(ast::Atom::Ident((ident, _))) = callee else {
+            return false;
+        };
+        self.resolve_ident(*ident).is_some_and(|info| {
+            matches!(**info.tyl.ty, TyKind::Func { .. })
+                && info.decl_attr.contains(ast::DeclAttr::NORETURN)
+        })
+    }
+
+    fn lower_block(&mut self, body: &[(ast::Stmt, Span)]) {
         self.scopes.push(Default::default());
         for (stmt, stmt_span) in body {
-            self.lower_stmt(stmt, *stmt_span)?;
+            // Give up on the broken statement, but keep checking the rest of the block.
+            if let Err(err) = self.lower_stmt(stmt, *stmt_span) {
+                self.errors.push(err);
+            }
         }
         self.scopes.pop();
+    }
+
+    /// Creates a block for every label in `body`. Labels are visible in the entire function, so
+    /// this is done before lowering it to allow jumping forward.
+    fn collect_labels(&mut self, body: &[(ast::Stmt, Span)]) {
+        for (stmt, _) in body {
+            self.collect_labels_stmt(stmt);
+        }
+    }
+
+    fn collect_labels_stmt(&mut self, stmt: &ast::Stmt) {
+        match stmt {
+            ast::Stmt::Labeled {
+                label: (label, label_span),
+                stmt,
+            } => {
+                if let Some(&(_, prev_span)) = self.labels.get(label) {
+                    self.errors.push(
+                        Error::new(
+                            format!("label {label} has already been defined"),
+                            *label_span,
+                        )
+                        .note_spanned("previously defined here", prev_span),
+                    );
+                } else {
+                    let bb = self.build.new_block();
+                    self.labels.insert(*label, (bb, *label_span));
+                }
+                self.collect_labels_stmt(&stmt.0);
+            }
+            ast::Stmt::Case { stmt, .. } | ast::Stmt::Default(stmt) => {
+                self.collect_labels_stmt(&stmt.0)
+            }
+            ast::Stmt::Compound(body)
+            | ast::Stmt::Switch { body, .. }
+            | ast::Stmt::While { body, .. }
+            | ast::Stmt::DoWhile { body, .. }
+            | ast::Stmt::For { body, .. } => self.collect_labels(body),
+            ast::Stmt::If {
+                then, otherwise, ..
+            } => {
+                self.collect_labels(then);
+                if let Some(otherwise) = otherwise {
+                    self.collect_labels(otherwise);
+                }
+            }
+            ast::Stmt::Decl(_)
+            | ast::Stmt::Goto(_)
+            | ast::Stmt::Continue
+            | ast::Stmt::Break
+            | ast::Stmt::Return(_)
+            | ast::Stmt::Expr(_) => {}
+        }
+    }
+
+    fn lower_for(
+        &mut self,
+        init_decl: &Option<Spanned<ast::Decl>>,
+        init_expr: &Option<Spanned<ast::Expr>>,
+        cond: Option<&Spanned<ast::Expr>>,
+        post: &Option<Spanned<ast::Expr>>,
+        body: &[Spanned<ast::Stmt>],
+    ) -> Result<()> {
+        match init_decl {
+            Some((ast::Decl::Normal(decl), span)) => self.declare_local(decl, *span)?,
+            Some((ast::Decl::StaticAssert(assert), span)) => {
+                self.lcx.check_static_assert(assert, *span)?;
+            }
+            None => {}
+        }
+        if let Some((init, span)) = init_expr {
+            self.lower_expr(init, *span)?;
+        }
+
+        let cond_bb = self.build.new_block();
+        let body_bb = self.build.new_block();
+        // Without a post expression, like in a `while` loop, `continue` goes straight back to the
+        // condition.
+        let post_bb = match post {
+            Some(_) => self.build.new_block(),
+            None => cond_bb,
+        };
+        let cont = self.build.new_block();
+
+        self.goto_if_unterminated(cond_bb);
+        self.build.current_bb = cond_bb;
+        // A missing condition is always true.
+        let term = match cond {
+            Some((cond, cond_span)) => {
+                let (cond, _) = self.lower_expr(cond, *cond_span)?;
+                Branch::Switch {
+                    cond,
+                    yes: body_bb,
+                    no: cont,
+                }
+            }
+            None => Branch::Goto(body_bb),
+        };
+        self.build.cur_bb_mut().term = term;
+
+        self.build.current_bb = body_bb;
+        self.break_targets.push(cont);
+        self.continue_targets.push(post_bb);
+        self.lower_block(body);
+        self.continue_targets.pop();
+        self.break_targets.pop();
+        self.goto_if_unterminated(post_bb);
+
+        if let Some((post, span)) = post {
+            self.build.current_bb = post_bb;
+            self.lower_expr(post, *span)?;
+            self.build.cur_bb_mut().term = Branch::Goto(cond_bb);
+        }
+
+        self.build.current_bb = cont;
         Ok(())
     }
 
-    fn declare_local(&mut self, decl: &ast::Decl, span: Span) -> Result<()> {
-        let decl = decl.unwrap_normal();
-        let ty = self.lcx.lower_ty(&decl.decl_spec.ty);
+    /// Lowers an `if` that continues at `cont`, or a new block if it's `None`. An `else if` is
+    /// an `if` alone in the `else` branch, it continues where the outer `if` does instead of
+    /// getting its own block that only jumps there.
+    fn lower_if(
+        &mut self,
+        cond: &Spanned<ast::Expr>,
+        then_body: &[Spanned<ast::Stmt>],
+        otherwise: Option<&[Spanned<ast::Stmt>]>,
+        cont: Option<BbIdx>,
+    ) -> Result<()> {
+        let cond = self.lower_expr(&cond.0, cond.1)?;
+        let pred = self.build.current_bb;
+        let then = self.build.new_block();
+        let els = otherwise.map(|oth| (oth, self.build.new_block()));
+        let cont = cont.unwrap_or_else(|| self.build.new_block());
+
+        self.build.current_bb = then;
+        self.lower_block(then_body);
+        self.goto_if_unterminated(cont);
+
+        let false_branch = match els {
+            Some((
+                [(
+                    ast::Stmt::If {
+                        cond,
+                        then,
+                        otherwise,
+                    },
+                    _,
+                )],
+                els,
+            )) => {
+                self.build.current_bb = els;
+                if let Err(err) = self.lower_if(cond, then, otherwise.as_deref(), Some(cont)) {
+                    self.errors.push(err);
+                }
+                els
+            }
+            Some((otherwise, els)) => {
+                self.build.current_bb = els;
+                self.lower_block(otherwise);
+                self.goto_if_unterminated(cont);
+                els
+            }
+            None => cont,
+        };
+        self.build.bb_mut(pred).term = Branch::Switch {
+            cond: cond.0,
+            yes: then,
+            no: false_branch,
+        };
+        self.build.current_bb = cont;
+        Ok(())
+    }
+
+    /// Jumps to `target` unless the current block already ended, for example with a `return`.
+    fn goto_if_unterminated(&mut self, target: BbIdx) {
+        let bb = self.build.cur_bb_mut();
+        if bb.term.is_dummy() {
+            bb.term = Branch::Goto(target);
+        }
+    }
+
+    fn declare_local(&mut self, decl: &ast::NormalDecl, span: Span) -> Result<()> {
+        let base_ty = self.lcx.lower_ty(&decl.decl_spec.ty)?;
         let decl_attr = decl.decl_spec.attrs;
+        let quals = decl.decl_spec.quals;
 
         for (var, def_span) in &decl.init_declarators {
+            check_function_specifiers(decl_attr, *def_span)?;
+            let ty = self
+                .lcx
+                .lower_declarator_ty(base_ty, quals, &var.declarator)?;
+            let (ty, init) = match &var.init {
+                Some(init) => {
+                    let (ty, elems) = flatten_initializer(self.lcx, ty, init, &|ident| {
+                        self.resolve_ident(ident).map(|var| var.tyl)
+                    })?;
+                    (ty, Some((elems, init.1)))
+                }
+                None => (ty, None),
+            };
             let tyl = self.lcx.layout_of(ty);
+            let var_quals = LoweringCx::declarator_quals(quals, &var.declarator);
             let (name, name_span) = var.declarator.decl.name();
-            let ptr_to = self.build.reserve_local(tyl.layout, name, span);
+
+            // (6.2.4) A static local lives for the whole program and is initialized once before
+            // it starts, so it's lowered like a global that only this scope can name.
+            let (kind, init) = if decl_attr.contains(ast::DeclAttr::STATIC) {
+                let (data, relocations) = match init {
+                    Some((elems, _)) => static_data(self.lcx, tyl, elems)?,
+                    None => (vec![0; tyl.layout.size as usize], Vec::new()),
+                };
+                let def_id = self.lcx.add_static_local(&data, relocations);
+                (VariableInfoKind::Static { def_id }, None)
+            } else {
+                let ptr_to = self.build.reserve_local(tyl.layout, Some(name), span);
+                let init = init.map(|init| (ptr_to, init));
+                (VariableInfoKind::Local { ptr_to }, init)
+            };
 
             let variable_info = VariableInfo {
                 def_span: *def_span,
                 decl_attr,
+                quals: var_quals,
                 tyl,
-                kind: VariableInfoKind::Local { ptr_to },
+                kind,
             };
             let predeclared = self.scopes.last_mut().unwrap().insert(name, variable_info);
             if let Some(predeclared) = predeclared {
@@ -132,102 +811,513 @@ impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
                 )
                 .note_spanned("already declared here", predeclared.def_span));
             }
-            if let Some((init, init_span)) = &var.init {
-                let init = self.lower_expr(init, *init_span)?;
-                self.build
-                    .store(Operand::Reg(ptr_to), init.0, tyl.layout, *init_span);
+            if let Some((ptr_to, (elems, init_span))) = init {
+                let volatile = var_quals.contains(ast::Qualifiers::VOLATILE);
+                for elem in elems {
+                    // A `char` array initialized by a string literal is stored byte by byte.
+                    if let (&TyKind::Array { elem: char_ty, .. }, Some((expr, span))) =
+                        (*elem.ty, elem.init)
+                    {
+                        let bytes = string_init_bytes(elem.ty, expr, span)?;
+                        for (i, byte) in bytes.into_iter().enumerate() {
+                            let value = Operand::Const(ConstValue::Int(byte.into()));
+                            let offset = elem.offset + i as u64;
+                            self.store_at(ptr_to, offset, char_ty, value, volatile, span);
+                        }
+                        continue;
+                    }
+                    let (value, span) = match elem.init {
+                        Some((expr, span)) => {
+                            (self.lower_expr_as_assigned(expr, span, elem.ty)?, span)
+                        }
+                        None if elem.ty.is_pointer() => {
+                            (Operand::Const(ConstValue::Null(elem.ty)), init_span)
+                        }
+                        None => (Operand::Const(ConstValue::Int(0)), init_span),
+                    };
+                    self.store_at(ptr_to, elem.offset, elem.ty, value, volatile, span);
+                }
             }
         }
         Ok(())
     }
 
-    fn expr_as_lvalue(&mut self, expr: &ast::Expr) -> Result<(Operand, TyLayout<'cx>)> {
-        let ast::Expr::Atom(ast::Atom::Ident((ident, ident_span))) = *expr else {
-            todo!("complex lvalues")
+    /// Stores `value` of type `ty` at `offset` bytes into the local at `ptr_to`.
+    fn store_at(
+        &mut self,
+        ptr_to: Register,
+        offset: u64,
+        ty: Ty<'cx>,
+        value: Operand<'cx>,
+        volatile: bool,
+        span: Span,
+    ) {
+        let ptr = if offset == 0 {
+            Operand::Reg(ptr_to)
+        } else {
+            let ptr_tyl = self.ty_layout(TyKind::Ptr(ty, ast::Qualifiers::empty()));
+            let offset = Operand::Const(ConstValue::Int(offset.into()));
+            Operand::Reg(
+                self.build
+                    .ptr_offset(Operand::Reg(ptr_to), offset, span, ptr_tyl),
+            )
         };
-        let Some(var) = self.resolve_ident(ident) else {
+        let layout = self.lcx.layout_of(ty).layout;
+        self.build.store(ptr, value, layout, volatile, span);
+    }
+
+    /// Computes the type of an expression in the current scope without evaluating it, as
+    /// required for `sizeof`. Arrays don't decay here.
+    fn type_of_expr(&self, expr: &ast::Expr, span: Span) -> Result<TyLayout<'cx>> {
+        let ty = self.lcx.type_of_expr(expr, span, &|ident| {
+            self.resolve_ident(ident).map(|var| var.tyl)
+        })?;
+        Ok(self.lcx.layout_of(ty))
+    }
+
+    /// Lowers an lvalue to a pointer to it, returning the pointer, the type of the lvalue and its
+    /// qualifiers.
+    fn lower_place(
+        &mut self,
+        expr: &ast::Expr,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>, ast::Qualifiers)> {
+        match expr {
+            &ast::Expr::Atom(ast::Atom::Ident((ident, ident_span))) => {
+                let Some(var) = self.resolve_ident(ident) else {
+                    return Err(Error::new(
+                        format!("cannot find variable {ident}"),
+                        ident_span,
+                    ));
+                };
+                let ptr = match var.kind {
+                    VariableInfoKind::Local { ptr_to } => Operand::Reg(ptr_to),
+                    VariableInfoKind::FnDef { def_id } | VariableInfoKind::Static { def_id } => {
+                        Operand::Const(ConstValue::StaticPtr(def_id, 0))
+                    }
+                };
+                Ok((ptr, var.tyl, var.quals))
+            }
+            ast::Expr::Postfix(ast::ExprPostfix {
+                lhs,
+                op: ast::PostfixOp::Member(field),
+            }) => {
+                let (ptr, tyl, quals) = if is_lvalue(&lhs.0) {
+                    self.lower_place(&lhs.0, lhs.1)?
+                } else {
+                    // The struct is a value like `f().x`, which is spilled to a temporary to
+                    // access the field.
+                    let (value, tyl) = self.lower_expr(&lhs.0, lhs.1)?;
+                    if is_undef(value) {
+                        return Ok((value, tyl, ast::Qualifiers::empty()));
+                    }
+                    let tmp = self.build.reserve_local(tyl.layout, None, lhs.1);
+                    self.build
+                        .store(Operand::Reg(tmp), value, tyl.layout, false, lhs.1);
+                    (Operand::Reg(tmp), tyl, ast::Qualifiers::empty())
+                };
+                if is_undef(ptr) {
+                    return Ok((ptr, tyl, quals));
+                }
+                let TyKind::Struct(strukt) = &**tyl.ty else {
+                    return Err(Error::new(
+                        format!(
+                            "cannot access field {} of non-struct type {}",
+                            field.0, tyl.ty
+                        ),
+                        lhs.1,
+                    ));
+                };
+                let (field_ptr, field_tyl) = self.field_ptr(ptr, tyl.ty, strukt, *field, span)?;
+                Ok((field_ptr, field_tyl, quals))
+            }
+            ast::Expr::Postfix(ast::ExprPostfix {
+                lhs,
+                op: ast::PostfixOp::ArrowMember(field),
+            }) => {
+                // `p->field` is `(*p).field`.
+                let (ptr, ptr_tyl) = self.lower_expr(&lhs.0, lhs.1)?;
+                if is_undef(ptr) {
+                    return Ok((ptr, ptr_tyl, ast::Qualifiers::empty()));
+                }
+                let TyKind::Ptr(pointee, quals) = **ptr_tyl.ty else {
+                    return Err(Error::new(
+                        format!("cannot use -> on non-pointer type {}", ptr_tyl.ty),
+                        field.1,
+                    ));
+                };
+                let TyKind::Struct(strukt) = &**pointee else {
+                    return Err(Error::new(
+                        format!("cannot use -> on pointer to non-struct type {pointee}"),
+                        field.1,
+                    ));
+                };
+                let (field_ptr, field_tyl) = self.field_ptr(ptr, pointee, strukt, *field, span)?;
+                Ok((field_ptr, field_tyl, quals))
+            }
+            ast::Expr::Unary(ast::ExprUnary {
+                op: ast::UnaryOp::Deref,
+                rhs,
+            }) => {
+                let (ptr, ptr_tyl) = self.lower_expr(&rhs.0, rhs.1)?;
+                if is_undef(ptr) {
+                    return Ok((ptr, ptr_tyl, ast::Qualifiers::empty()));
+                }
+                let TyKind::Ptr(pointee, quals) = **ptr_tyl.ty else {
+                    return Err(Error::new(
+                        format!("cannot dereference value of type {}", ptr_tyl.ty),
+                        rhs.1,
+                    ));
+                };
+                if !pointee.is_complete() {
+                    return Err(Error::new(
+                        format!("cannot dereference pointer to incomplete type {pointee}"),
+                        span,
+                    ));
+                }
+                Ok((ptr, self.lcx.layout_of(pointee), quals))
+            }
+            ast::Expr::Binary(ExprBinary {
+                op: ast::BinaryOp::Index,
+                lhs,
+                rhs,
+            }) => {
+                if let ast::Expr::Binary(ExprBinary {
+                    op: ast::BinaryOp::Comma,
+                    rhs: comma_rhs,
+                    ..
+                }) = &rhs.0
+                {
+                    self.lcx.emit_warning(
+                        Error::new(
+                            "left operand of comma operator in array subscript is discarded",
+                            rhs.1,
+                        )
+                        .note_spanned(
+                            "multidimensional arrays are indexed like `a[i][j]`",
+                            comma_rhs.1,
+                        ),
+                    );
+                }
+
+                // A constant index into an array with a known size can be checked before the
+                // array decays to a pointer.
+                if let (Ok(array_tyl), Ok((index, _))) = (
+                    self.type_of_expr(&lhs.0, lhs.1),
+                    self.eval_const_expr_in_scope(&rhs.0, rhs.1),
+                ) {
+                    check_array_index(array_tyl.ty, index, rhs.1)?;
+                }
+
+                let (ptr, ptr_tyl) = self.lower_expr(&lhs.0, lhs.1)?;
+                if is_undef(ptr) {
+                    // The index is still lowered to report its errors.
+                    self.lower_expr(&rhs.0, rhs.1)?;
+                    return Ok((ptr, ptr_tyl, ast::Qualifiers::empty()));
+                }
+                let TyKind::Ptr(elem, quals) = **ptr_tyl.ty else {
+                    return Err(Error::new(
+                        format!("cannot index into value of type {}", ptr_tyl.ty),
+                        lhs.1,
+                    ));
+                };
+                let (index, index_tyl) = self.lower_expr(&rhs.0, rhs.1)?;
+                if !index_tyl.ty.is_integer() {
+                    return Err(Error::new(
+                        format!("array subscript must be an integer, found {}", index_tyl.ty),
+                        rhs.1,
+                    ));
+                }
+
+                // (6.5.2.1) `a[i]` is `*(a + i)`.
+                let (elem_ptr, _) = self.lower_ptr_arith(
+                    false,
+                    ((ptr, ptr_tyl), lhs.1),
+                    ((index, index_tyl), rhs.1),
+                    span,
+                )?;
+                Ok((elem_ptr, self.lcx.layout_of(elem), quals))
+            }
+            _ => Err(Error::new("expression is not assignable", span)),
+        }
+    }
+
+    /// Computes a pointer to a field from a pointer to the struct.
+    fn field_ptr(
+        &mut self,
+        ptr: Operand<'cx>,
+        struct_ty: Ty<'cx>,
+        strukt: &StructTy<'cx>,
+        (field, field_span): ast::Ident,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>)> {
+        let Some((offset, field_tyl)) = self.lcx.field_offset(strukt, field) else {
             return Err(Error::new(
-                format!("cannot find variable {ident}"),
-                ident_span,
+                format!("no field {field} on type {struct_ty}"),
+                field_span,
             ));
         };
-        Ok(match var.kind {
-            VariableInfoKind::Local { ptr_to } => (Operand::Reg(ptr_to), var.tyl),
-            VariableInfoKind::FnDef { def_id } => {
-                (Operand::Const(ConstValue::StaticPtr(def_id)), var.tyl)
-            }
-            VariableInfoKind::Static { def_id } => {
-                (Operand::Const(ConstValue::StaticPtr(def_id)), var.tyl)
-            }
-        })
+        let field_ptr = self.build.ptr_offset(
+            ptr,
+            Operand::Const(ConstValue::Int(offset.into())),
+            span,
+            self.ty_layout(TyKind::Ptr(field_tyl.ty, ast::Qualifiers::empty())),
+        );
+        Ok((Operand::Reg(field_ptr), field_tyl))
+    }
+
+    /// Lowers an lvalue that is written to, returning a pointer to it, its type and whether it's
+    /// volatile.
+    fn expr_as_lvalue(
+        &mut self,
+        expr: &ast::Expr,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>, bool)> {
+        if !is_lvalue(expr) {
+            return Err(Error::new("expression is not assignable", span));
+        }
+        let (ptr, tyl, quals) = self.lower_place(expr, span)?;
+        if quals.contains(ast::Qualifiers::CONST) {
+            return Err(match *expr {
+                ast::Expr::Atom(ast::Atom::Ident((ident, ident_span))) => Error::new(
+                    format!("cannot assign to const variable {ident}"),
+                    ident_span,
+                )
+                .note_spanned("declared here", self.resolve_ident(ident).unwrap().def_span),
+                _ => Error::new("cannot assign to const-qualified location", span),
+            });
+        }
+        Ok((ptr, tyl, quals.contains(ast::Qualifiers::VOLATILE)))
     }
 
     fn lower_stmt(&mut self, stmt: &ast::Stmt, stmt_span: Span) -> Result<()> {
         match stmt {
-            ast::Stmt::Decl(decl) => {
+            ast::Stmt::Decl(ast::Decl::StaticAssert(assert)) => {
+                self.lcx.check_static_assert(assert, stmt_span)?;
+            }
+            ast::Stmt::Decl(ast::Decl::Normal(decl)) => {
                 self.declare_local(decl, stmt_span)?;
             }
-            ast::Stmt::Labeled { .. } => todo!("labels are not implemented"),
+            ast::Stmt::Labeled {
+                label: (label, _),
+                stmt,
+            } => {
+                let (bb, _) = self.labels[label];
+                self.goto_if_unterminated(bb);
+                self.build.current_bb = bb;
+                self.lower_stmt(&stmt.0, stmt.1)?;
+            }
             ast::Stmt::Compound(block) => {
-                self.lower_block(block)?;
+                self.lower_block(block);
             }
             ast::Stmt::If {
                 cond,
-                then: then_body,
+                then,
                 otherwise,
+            } => self.lower_if(cond, then, otherwise.as_deref(), None)?,
+            ast::Stmt::Switch {
+                cond: (cond, cond_span),
+                body,
             } => {
-                let cond = self.lower_expr(&cond.0, cond.1)?;
-                let pred = self.build.current_bb;
-                let then = self.build.new_block();
-                let els = otherwise
-                    .as_deref()
-                    .map(|oth| (oth, self.build.new_block()));
+                let (cond, cond_tyl) = self.lower_expr(cond, *cond_span)?;
+                if !cond_tyl.ty.is_integer() {
+                    return Err(Error::new(
+                        format!("switch condition must be an integer, found {}", cond_tyl.ty),
+                        *cond_span,
+                    ));
+                }
+                let ty = self.lcx.promote(cond_tyl.ty);
+                let cond = self.convert(cond, cond_tyl.ty, ty, *cond_span)?;
+
+                let dispatch = self.build.current_bb;
                 let cont = self.build.new_block();
+                // Statements before the first label are unreachable.
+                self.build.current_bb = self.build.new_block();
 
-                self.build.current_bb = then;
-                self.lower_block(then_body)?;
-                self.build.cur_bb_mut().term = Branch::Goto(cont);
+                self.switches.push(SwitchLabels {
+                    tyl: self.lcx.layout_of(ty),
+                    cases: Vec::new(),
+                    default: None,
+                });
+                self.break_targets.push(cont);
+                self.lower_block(body);
+                self.goto_if_unterminated(cont);
+                self.break_targets.pop();
+                let labels = self.switches.pop().unwrap();
 
-                let false_branch = match els {
-                    Some((otherwise, els)) => {
-                        self.build.current_bb = els;
-                        self.lower_block(otherwise)?;
-                        self.build.cur_bb_mut().term = Branch::Goto(cont);
-                        els
-                    }
-                    None => cont,
+                // Compare against the cases one after another, the default is taken if none
+                // of them match.
+                self.build.current_bb = dispatch;
+                let int = self.lcx.layout_of(self.lcx.types.int.signed);
+                for (value, target, span) in labels.cases {
+                    let value = Operand::Const(ConstValue::Int(value));
+                    let is_eq = self.build.binary(BinKind::Eq, cond, value, span, int);
+                    let next = self.build.new_block();
+                    self.build.cur_bb_mut().term = Branch::Switch {
+                        cond: Operand::Reg(is_eq),
+                        yes: target,
+                        no: next,
+                    };
+                    self.build.current_bb = next;
+                }
+                let default = labels.default.map_or(cont, |(bb, _)| bb);
+                self.build.cur_bb_mut().term = Branch::Goto(default);
+
+                self.build.current_bb = cont;
+            }
+            ast::Stmt::Case {
+                value: (value, value_span),
+                stmt,
+            } => {
+                let Some(switch) = self.switches.last() else {
+                    return Err(Error::new(
+                        "case label not within a switch statement",
+                        stmt_span,
+                    ));
+                };
+                // The value is converted to the type of the condition.
+                let (value, _) = self.eval_const_expr_in_scope(value, *value_span)?;
+                let value = value as u128;
+                let bits = switch.tyl.layout.size * 8;
+                let value = value & (u128::MAX >> (128 - bits));
+                if let Some(&(_, _, prev_span)) = switch.cases.iter().find(|c| c.0 == value) {
+                    return Err(
+                        Error::new(format!("duplicate case value {value}"), *value_span)
+                            .note_spanned("previously used here", prev_span),
+                    );
+                }
+
+                let bb = self.build.new_block();
+                self.switches
+                    .last_mut()
+                    .unwrap()
+                    .cases
+                    .push((value, bb, *value_span));
+                self.goto_if_unterminated(bb);
+                self.build.current_bb = bb;
+                self.lower_stmt(&stmt.0, stmt.1)?;
+            }
+            ast::Stmt::Default(stmt) => {
+                let Some(switch) = self.switches.last() else {
+                    return Err(Error::new(
+                        "default label not within a switch statement",
+                        stmt_span,
+                    ));
                 };
-                self.build.bb_mut(pred).term = Branch::Switch {
-                    cond: cond.0,
-                    yes: then,
-                    no: false_branch,
+                if let Some((_, prev_span)) = switch.default {
+                    return Err(
+                        Error::new("multiple default labels in one switch", stmt_span)
+                            .note_spanned("previous default label here", prev_span),
+                    );
+                }
+
+                let bb = self.build.new_block();
+                self.switches.last_mut().unwrap().default = Some((bb, stmt_span));
+                self.goto_if_unterminated(bb);
+                self.build.current_bb = bb;
+                self.lower_stmt(&stmt.0, stmt.1)?;
+            }
+            // A `while` loop is a `for` loop with only a condition.
+            ast::Stmt::While { cond, body } => {
+                self.lower_for(&None, &None, Some(cond), &None, body)?;
+            }
+            ast::Stmt::DoWhile {
+                body,
+                cond: (cond, cond_span),
+            } => {
+                let body_bb = self.build.new_block();
+                let cond_bb = self.build.new_block();
+                let cont = self.build.new_block();
+
+                self.goto_if_unterminated(body_bb);
+                self.build.current_bb = body_bb;
+                self.break_targets.push(cont);
+                self.continue_targets.push(cond_bb);
+                self.lower_block(body);
+                self.continue_targets.pop();
+                self.break_targets.pop();
+                self.goto_if_unterminated(cond_bb);
+
+                self.build.current_bb = cond_bb;
+                let (cond, _) = self.lower_expr(cond, *cond_span)?;
+                self.build.cur_bb_mut().term = Branch::Switch {
+                    cond,
+                    yes: body_bb,
+                    no: cont,
                 };
                 self.build.current_bb = cont;
             }
-            ast::Stmt::Switch => todo!(),
-            ast::Stmt::While { .. } => todo!(),
-            ast::Stmt::For { .. } => todo!(),
-            ast::Stmt::Goto(_) => todo!(),
-            ast::Stmt::Continue => todo!(),
-            ast::Stmt::Break => todo!(),
+            ast::Stmt::For {
+                init_decl,
+                init_expr,
+                cond,
+                post,
+                body,
+            } => {
+                // Variables declared in the init clause are only visible in the loop.
+                self.scopes.push(Default::default());
+                let result = self.lower_for(init_decl, init_expr, cond.as_ref(), post, body);
+                self.scopes.pop();
+                result?;
+            }
+            ast::Stmt::Goto((label, _)) => {
+                let Some(&(target, _)) = self.labels.get(label) else {
+                    return Err(Error::new(
+                        format!("use of undeclared label {label}"),
+                        stmt_span,
+                    ));
+                };
+                if self.build.cur_bb_mut().term.is_dummy() {
+                    self.gotos.push((self.build.current_bb, stmt_span));
+                }
+                self.goto_if_unterminated(target);
+                // Anything after the `goto` is unreachable.
+                self.build.current_bb = self.build.new_block();
+            }
+            ast::Stmt::Continue => {
+                let Some(&target) = self.continue_targets.last() else {
+                    return Err(Error::new(
+                        "continue statement not within a loop",
+                        stmt_span,
+                    ));
+                };
+                self.goto_if_unterminated(target);
+                // Anything after the `continue` is unreachable.
+                self.build.current_bb = self.build.new_block();
+            }
+            ast::Stmt::Break => {
+                let Some(&target) = self.break_targets.last() else {
+                    return Err(Error::new(
+                        "break statement not within a loop or switch",
+                        stmt_span,
+                    ));
+                };
+                self.goto_if_unterminated(target);
+                // Anything after the `break` is unreachable.
+                self.build.current_bb = self.build.new_block();
+            }
             ast::Stmt::Return(expr) => {
                 let ret = match expr {
-                    Some(expr) => self.lower_expr(&expr.0, expr.1)?.0,
+                    Some(expr) => {
+                        let ret_ty = self.build.ir.ret_ty;
+                        self.lower_expr_as_assigned(&expr.0, expr.1, ret_ty)?
+                    }
                     None => Operand::Const(ConstValue::Void),
                 };
-                self.build.cur_bb_mut().term = Branch::Ret(ret);
+                self.build.diverge(Branch::Ret(ret));
             }
             ast::Stmt::Expr(ast::Expr::Binary(ast::ExprBinary {
                 op: ast::BinaryOp::Assign(assign),
                 lhs,
-                rhs,
+                rhs: rhs_expr,
             })) => {
                 if assign.is_some() {
                     todo!("assign operation");
                 }
-                let rhs = self.lower_expr(&rhs.0, rhs.1)?;
-                let (ptr_to, tyl) = self.expr_as_lvalue(&lhs.0)?;
-                self.build.store(ptr_to, rhs.0, tyl.layout, stmt_span);
+                let (ptr_to, tyl, volatile) = self.expr_as_lvalue(&lhs.0, lhs.1)?;
+                let rhs = self.lower_expr_as_assigned(&rhs_expr.0, rhs_expr.1, tyl.ty)?;
+                self.build
+                    .store(ptr_to, rhs, tyl.layout, volatile, stmt_span);
             }
             ast::Stmt::Expr(expr) => {
                 self.lower_expr(expr, stmt_span)?;
@@ -237,17 +1327,216 @@ impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
         Ok(())
     }
 
-    fn lower_expr(&mut self, expr: &ast::Expr, span: Span) -> Result<(Operand, TyLayout<'cx>)> {
+    /// Lowers an expression whose value is converted to `ty`.
+    /// Null pointer constants become typed null pointers if `ty` is a pointer.
+    fn lower_expr_to_ty(
+        &mut self,
+        expr: &ast::Expr,
+        span: Span,
+        ty: Ty<'cx>,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>)> {
+        if let TyKind::Ptr(..) = **ty {
+            if self.is_null_pointer_constant(expr, span) {
+                return Ok((Operand::Const(ConstValue::Null(ty)), self.lcx.layout_of(ty)));
+            }
+        }
+        self.lower_expr(expr, span)
+    }
+
+    /// (6.5.2.4, 6.5.3.1) Lowers `++x`, `--x`, `x++` and `x--`. The prefix forms evaluate to the
+    /// new value, the postfix forms to the old one. Pointers move by one element.
+    fn lower_incr_decr(
+        &mut self,
+        expr: &Spanned<ast::Expr>,
+        is_incr: bool,
+        postfix: bool,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>)> {
+        let (lvalue, tyl, volatile) = self.expr_as_lvalue(&expr.0, expr.1)?;
+        if !tyl.ty.is_integer() && !tyl.ty.is_pointer() {
+            return Err(Error::new(
+                format!(
+                    "cannot {} {}",
+                    if is_incr { "increment" } else { "decrement" },
+                    tyl.ty
+                ),
+                expr.1,
+            ));
+        }
+
+        let bin_kind = if is_incr { BinKind::Add } else { BinKind::Sub };
+        let old = self.build.load(tyl, lvalue, volatile, span);
+        let one = Operand::Const(ConstValue::Int(1));
+        let int = self.lcx.layout_of(self.lcx.types.int.signed);
+        let new = if tyl.ty.is_pointer() {
+            self.lower_ptr_arith(
+                !is_incr,
+                ((Operand::Reg(old), tyl), expr.1),
+                ((one, int), span),
+                span,
+            )?
+            .0
+        } else if tyl.ty.is_bool() {
+            // `b++` is `b += 1`, so the result is converted back to _Bool. Incrementing always
+            // gives 1 and decrementing flips the value.
+            let old = self.convert(Operand::Reg(old), tyl.ty, int.ty, span)?;
+            let new = self.build.binary(bin_kind, old, one, span, int);
+            self.convert(Operand::Reg(new), int.ty, tyl.ty, span)?
+        } else {
+            Operand::Reg(
+                self.build
+                    .binary(bin_kind, Operand::Reg(old), one, span, tyl),
+            )
+        };
+        self.build.store(lvalue, new, tyl.layout, volatile, span);
+
+        let result = if postfix { Operand::Reg(old) } else { new };
+        Ok((result, tyl))
+    }
+
+    /// (6.5.15) Lowers `cond ? then : otherwise`. Only the chosen operand is evaluated, the values
+    /// of both meet in a phi.
+    fn lower_conditional(
+        &mut self,
+        conditional: &ast::ExprConditional,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>)> {
+        let ast::ExprConditional {
+            cond,
+            then,
+            otherwise,
+        } = conditional;
+
+        let (cond, _) = self.lower_expr(&cond.0, cond.1)?;
+        let then_bb = self.build.new_block();
+        let otherwise_bb = self.build.new_block();
+        let cont = self.build.new_block();
+        self.build.cur_bb_mut().term = Branch::Switch {
+            cond,
+            yes: then_bb,
+            no: otherwise_bb,
+        };
+
+        // Each operand is lowered once into its own block. The result type is only known after
+        // both have been lowered, so the conversions are appended to the blocks afterwards.
+        self.build.current_bb = then_bb;
+        let (then_op, then_tyl) = self.lower_expr(&then.0, then.1)?;
+        let then_end = self.build.current_bb;
+        self.build.current_bb = otherwise_bb;
+        let (otherwise_op, otherwise_tyl) = self.lower_expr(&otherwise.0, otherwise.1)?;
+        let otherwise_end = self.build.current_bb;
+
+        // The type of an operand with an error is unknown, so the result is unknown as well.
+        if is_undef(then_op) || is_undef(otherwise_op) {
+            for bb in [then_end, otherwise_end] {
+                self.build.current_bb = bb;
+                self.goto_if_unterminated(cont);
+            }
+            self.build.current_bb = cont;
+            return Ok(self.undef());
+        }
+
+        let (then_ty, otherwise_ty) = (then_tyl.ty, otherwise_tyl.ty);
+        let result = if then_ty.is_arithmetic() && otherwise_ty.is_arithmetic() {
+            self.lcx.common_arith_type(then_ty, otherwise_ty, span)?
+        } else if otherwise_ty.is_pointer() && self.is_null_pointer_constant(&then.0, then.1) {
+            otherwise_ty
+        } else if then_ty == otherwise_ty
+            || (then_ty.is_pointer() && self.is_null_pointer_constant(&otherwise.0, otherwise.1))
+        {
+            then_ty
+        } else if let Some(ptr) = self.lcx.composite_ptr_ty(then_ty, otherwise_ty) {
+            ptr
+        } else {
+            return Err(Error::new(
+                format!("incompatible operand types {then_ty} and {otherwise_ty}"),
+                span,
+            ));
+        };
+        let result_tyl = self.lcx.layout_of(result);
+
+        let mut incomings = Vec::new();
+        let arms = [
+            (then_end, then_op, then_ty, &**then),
+            (otherwise_end, otherwise_op, otherwise_ty, &**otherwise),
+        ];
+        for (bb, op, ty, (expr, expr_span)) in arms {
+            self.build.current_bb = bb;
+            if !matches!(*result, TyKind::Void) {
+                let op = if result.is_pointer() && self.is_null_pointer_constant(expr, *expr_span) {
+                    Operand::Const(ConstValue::Null(result))
+                } else {
+                    self.convert(op, ty, result, *expr_span)?
+                };
+                incomings.push((self.build.current_bb, op));
+            }
+            self.goto_if_unterminated(cont);
+        }
+        self.build.current_bb = cont;
+
+        if incomings.is_empty() {
+            return Ok((Operand::Const(ConstValue::Void), result_tyl));
+        }
+        let reg = self.build.phi(incomings, result_tyl, span);
+        Ok((Operand::Reg(reg), result_tyl))
+    }
+
+    /// Lowers an expression and converts it to `ty` as if by assignment, warning if that changes
+    /// its value.
+    fn lower_expr_as_assigned(
+        &mut self,
+        expr: &ast::Expr,
+        span: Span,
+        ty: Ty<'cx>,
+    ) -> Result<Operand<'cx>> {
+        let (op, tyl) = self.lower_expr_to_ty(expr, span, ty)?;
+        if !is_undef(op) {
+            self.warn_narrowing(expr, tyl.ty, ty, span);
+        }
+        self.convert(op, tyl.ty, ty, span)
+    }
+
+    /// Lowers an expression. If it has an error, the error is reported and the expression becomes
+    /// [`Self::undef`], so that the rest of the statement is still checked.
+    fn lower_expr(
+        &mut self,
+        expr: &ast::Expr,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>)> {
+        match self.lower_expr_unrecovered(expr, span) {
+            Ok(op_tyl) => Ok(op_tyl),
+            Err(err) => {
+                self.errors.push(err);
+                Ok(self.undef())
+            }
+        }
+    }
+
+    fn lower_expr_unrecovered(
+        &mut self,
+        expr: &ast::Expr,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>)> {
         let op_tyl = match expr {
             ast::Expr::Atom(ast::Atom::Char(c)) => (
                 Operand::Const(ConstValue::Int((*c).into())),
                 self.lcx.layout_of(self.lcx.types.char),
             ),
-            ast::Expr::Atom(ast::Atom::Int(i)) => (
-                Operand::Const(ConstValue::Int(*i as _)),
-                self.lcx.layout_of(self.lcx.types.int.signed),
+            ast::Expr::Atom(ast::Atom::Int(int)) => (
+                Operand::Const(ConstValue::Int(int.value)),
+                self.lcx.layout_of(self.lcx.int_constant_ty(int, span)?),
             ),
-            ast::Expr::Atom(ast::Atom::Float(_)) => todo!("no floats"),
+            ast::Expr::Atom(ast::Atom::Float(float)) => {
+                let ty = self.lcx.intern_ty(match float.kind {
+                    ast::FloatKind::Float => TyKind::Float,
+                    ast::FloatKind::Double => TyKind::Double,
+                    ast::FloatKind::LongDouble => TyKind::LongDouble,
+                });
+                return Err(Error::new(
+                    format!("constants of type {ty} are not supported yet"),
+                    span,
+                ));
+            }
             ast::Expr::Atom(ast::Atom::Ident((ident, ident_span))) => {
                 let Some(var) = self.resolve_ident(*ident) else {
                     return Err(Error::new(
@@ -256,59 +1545,80 @@ impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
                     ));
                 };
                 let tyl = var.tyl;
-                match var.kind {
-                    VariableInfoKind::Local { ptr_to } => {
-                        let op = self.build.load(var.tyl, Operand::Reg(ptr_to), span);
-                        (Operand::Reg(op), tyl)
+                let volatile = var.quals.contains(ast::Qualifiers::VOLATILE);
+                // Arrays decay to a pointer to their first element instead of being loaded.
+                let decayed = match **tyl.ty {
+                    TyKind::Array { elem, .. } => {
+                        Some(self.ty_layout(TyKind::Ptr(elem, var.quals)))
                     }
+                    _ => None,
+                };
+                match var.kind {
+                    VariableInfoKind::Local { ptr_to } => match decayed {
+                        Some(ptr_tyl) => (Operand::Reg(ptr_to), ptr_tyl),
+                        None => {
+                            let op = self
+                                .build
+                                .load(var.tyl, Operand::Reg(ptr_to), volatile, span);
+                            (Operand::Reg(op), tyl)
+                        }
+                    },
                     VariableInfoKind::FnDef { def_id } => {
-                        (Operand::Const(ConstValue::StaticPtr(def_id)), tyl)
+                        (Operand::Const(ConstValue::StaticPtr(def_id, 0)), tyl)
                     }
                     VariableInfoKind::Static { def_id } => {
-                        let op = self.build.load(
-                            var.tyl,
-                            Operand::Const(ConstValue::StaticPtr(def_id)),
-                            span,
-                        );
-                        (Operand::Reg(op), tyl)
+                        let ptr = Operand::Const(ConstValue::StaticPtr(def_id, 0));
+                        match decayed {
+                            Some(ptr_tyl) => (ptr, ptr_tyl),
+                            None => {
+                                let op = self.build.load(var.tyl, ptr, volatile, span);
+                                (Operand::Reg(op), tyl)
+                            }
+                        }
                     }
                 }
             }
             ast::Expr::Atom(ast::Atom::String(string)) => {
                 let lit_def_id = self.lcx.intern_str_lit(string);
                 (
-                    Operand::Const(ConstValue::StaticPtr(lit_def_id)),
-                    self.ty_layout(TyKind::Ptr(self.lcx.types.char)),
+                    Operand::Const(ConstValue::StaticPtr(lit_def_id, 0)),
+                    self.ty_layout(TyKind::Ptr(self.lcx.types.char, ast::Qualifiers::empty())),
                 )
             }
+            ast::Expr::Postfix(ast::ExprPostfix {
+                op: ast::PostfixOp::Member(_) | ast::PostfixOp::ArrowMember(_),
+                ..
+            })
+            | ast::Expr::Unary(ast::ExprUnary {
+                op: ast::UnaryOp::Deref,
+                ..
+            })
+            | ast::Expr::Binary(ExprBinary {
+                op: ast::BinaryOp::Index,
+                ..
+            }) => {
+                let (ptr, tyl, quals) = self.lower_place(expr, span)?;
+                if is_undef(ptr) {
+                    return Ok((ptr, tyl));
+                }
+                match **tyl.ty {
+                    // Arrays decay to a pointer to their first element.
+                    TyKind::Array { elem, .. } => (ptr, self.ty_layout(TyKind::Ptr(elem, quals))),
+                    // Dereferencing a function pointer yields the function, which decays again.
+                    TyKind::Func { .. } => (ptr, self.ty_layout(TyKind::Ptr(tyl.ty, quals))),
+                    _ => {
+                        let volatile = quals.contains(ast::Qualifiers::VOLATILE);
+                        let value = self.build.load(tyl, ptr, volatile, span);
+                        (Operand::Reg(value), tyl)
+                    }
+                }
+            }
             ast::Expr::Unary(ast::ExprUnary {
                 op: op @ (ast::UnaryOp::Increment | ast::UnaryOp::Decrement),
                 rhs: rhs_expr,
             }) => {
-                // First increment/decrement, then return the value.
-                let (rhs, rhs_tyl) = self.lower_expr(&rhs_expr.0, rhs_expr.1)?;
                 let is_incr = matches!(op, ast::UnaryOp::Increment);
-
-                if !rhs_tyl.ty.is_integral() {
-                    return Err(Error::new(
-                        format!(
-                            "cannot {} {}",
-                            if is_incr { "increment" } else { "decrement" },
-                            rhs_tyl.ty
-                        ),
-                        rhs_expr.1,
-                    ));
-                }
-
-                let (lvalue, tyl) = self.expr_as_lvalue(&rhs_expr.0)?;
-                let bin_kind = if is_incr { BinKind::Add } else { BinKind::Sub };
-                let lhs = self.build.load(tyl, lvalue, span);
-                let result = self
-                    .build
-                    .binary(bin_kind, Operand::Reg(lhs), rhs, span, tyl);
-                self.build.store(Operand::Reg(lhs), rhs, tyl.layout, span);
-
-                (Operand::Reg(result), tyl)
+                self.lower_incr_decr(rhs_expr, is_incr, false, span)?
             }
             ast::Expr::Unary(unary) => {
                 let rhs = self.lower_expr(&unary.rhs.0, unary.rhs.1)?;
@@ -316,61 +1626,81 @@ impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
                     ast::UnaryOp::Increment => unreachable!("handled prefix increment above"),
                     ast::UnaryOp::Decrement => unreachable!("handled prefix increment above"),
                     ast::UnaryOp::AddrOf => todo!("addr of"),
-                    ast::UnaryOp::Deref => todo!("deref?"),
+                    ast::UnaryOp::Deref => unreachable!("handled deref above"),
                     ast::UnaryOp::Plus => todo!("unary plus lol"),
                     ast::UnaryOp::Minus => UnaryKind::Negate,
                     ast::UnaryOp::Tilde => UnaryKind::BitNot,
                     ast::UnaryOp::Bang => UnaryKind::LogicalNot,
                 };
 
-                let reg = self.build.unary(kind, rhs.0, span, self.dummy_tyl());
-                (Operand::Reg(reg), self.dummy_tyl())
+                // (6.5.3.3) The result of `!` is an int.
+                let tyl = match kind {
+                    UnaryKind::LogicalNot => self.lcx.layout_of(self.lcx.types.int.signed),
+                    _ => rhs.1,
+                };
+                let reg = self.build.unary(kind, rhs.0, span, tyl);
+                (Operand::Reg(reg), tyl)
             }
             ast::Expr::Binary(ast::ExprBinary {
                 lhs,
-                rhs,
+                rhs: rhs_expr,
                 op: ast::BinaryOp::Assign(assign),
             }) => {
                 if assign.is_some() {
                     todo!("assign operation");
                 }
-                let rhs = self.lower_expr(&rhs.0, rhs.1)?;
+                let (ptr_to, tyl, volatile) = self.expr_as_lvalue(&lhs.0, lhs.1)?;
+                let rhs = self.lower_expr_as_assigned(&rhs_expr.0, rhs_expr.1, tyl.ty)?;
 
-                let (ptr_to, tyl) = self.expr_as_lvalue(&lhs.0)?;
-                self.build.store(ptr_to, rhs.0, tyl.layout, span);
-                rhs
+                self.build.store(ptr_to, rhs, tyl.layout, volatile, span);
+                // (6.5.16) The value of an assignment has the type of the left operand.
+                (rhs, tyl)
             }
             ast::Expr::Binary(ExprBinary {
                 op: ast::BinaryOp::Arith(arith),
                 lhs: lhs_expr,
                 rhs: rhs_expr,
             }) => {
-                let (mut lhs, lhs_tyl) = self.lower_expr(&lhs_expr.0, lhs_expr.1)?;
-                let (mut rhs, rhs_tyl) = self.lower_expr(&rhs_expr.0, rhs_expr.1)?;
-
-                let (result, lhs_coerce, rhs_coerce) =
-                    self.arith_op(lhs_tyl.ty, rhs_tyl.ty, span)?;
-
-                let mut do_coerce = |reg, coerce, span, ty| {
-                    let kind = match coerce {
-                        Coercion::ZeroExt => UnaryKind::Zext,
-                        Coercion::SignExt => UnaryKind::Sext,
-                        Coercion::SignToUnsigned => todo!("hm, what should this do"),
-                    };
-                    Operand::Reg(self.build.unary(kind, reg, span, self.lcx.layout_of(ty)))
-                };
+                let (lhs, lhs_tyl) = self.lower_expr(&lhs_expr.0, lhs_expr.1)?;
+                let (rhs, rhs_tyl) = self.lower_expr(&rhs_expr.0, rhs_expr.1)?;
 
-                for (coerce, ty) in lhs_coerce {
-                    lhs = do_coerce(lhs, coerce, span, ty);
+                if matches!(arith, ast::ArithOpKind::Add | ast::ArithOpKind::Sub)
+                    && (lhs_tyl.ty.is_pointer() || rhs_tyl.ty.is_pointer())
+                {
+                    let is_sub = matches!(arith, ast::ArithOpKind::Sub);
+                    return self.lower_ptr_arith(
+                        is_sub,
+                        ((lhs, lhs_tyl), lhs_expr.1),
+                        ((rhs, rhs_tyl), rhs_expr.1),
+                        span,
+                    );
                 }
-                for (coerce, ty) in rhs_coerce {
-                    rhs = do_coerce(rhs, coerce, span, ty);
+
+                let is_shift = matches!(arith, ast::ArithOpKind::Shl | ast::ArithOpKind::Shr);
+                for (ty, span) in [(lhs_tyl.ty, lhs_expr.1), (rhs_tyl.ty, rhs_expr.1)] {
+                    if !ty.is_arithmetic() || (is_shift && !ty.is_integer()) {
+                        return Err(Error::new(format!("cannot convert {ty} to integer"), span));
+                    }
                 }
 
+                // (6.5.7) The operands of a shift are promoted separately, the result has the
+                // type of the left one. The amount is converted to that type as well, amounts
+                // that don't fit are undefined anyway.
+                let result = if is_shift {
+                    self.lcx.promote(lhs_tyl.ty)
+                } else {
+                    self.lcx.common_arith_type(lhs_tyl.ty, rhs_tyl.ty, span)?
+                };
+                let lhs = self.convert(lhs, lhs_tyl.ty, result, span)?;
+                let rhs = self.convert(rhs, rhs_tyl.ty, result, span)?;
+
+                let signed = result.is_signed();
                 let kind = match arith {
                     ast::ArithOpKind::Mul => BinKind::Mul,
-                    ast::ArithOpKind::Div => BinKind::Div,
-                    ast::ArithOpKind::Mod => BinKind::Mod,
+                    ast::ArithOpKind::Div if signed => BinKind::SDiv,
+                    ast::ArithOpKind::Div => BinKind::UDiv,
+                    ast::ArithOpKind::Mod if signed => BinKind::SMod,
+                    ast::ArithOpKind::Mod => BinKind::UMod,
                     ast::ArithOpKind::Add => BinKind::Add,
                     ast::ArithOpKind::Sub => BinKind::Sub,
                     ast::ArithOpKind::Shl => BinKind::Shl,
@@ -387,25 +1717,47 @@ impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
             }
             ast::Expr::Binary(ExprBinary {
                 op: ast::BinaryOp::Comparison(comp),
-                lhs,
-                rhs,
+                lhs: lhs_expr,
+                rhs: rhs_expr,
             }) => {
-                let lhs = self.lower_expr(&lhs.0, lhs.1)?;
-                let rhs = self.lower_expr(&rhs.0, rhs.1)?;
+                // A null pointer constant compared against a pointer is a pointer as well.
+                let (mut lhs, mut rhs) = if self.is_null_pointer_constant(&lhs_expr.0, lhs_expr.1) {
+                    let rhs = self.lower_expr(&rhs_expr.0, rhs_expr.1)?;
+                    let lhs = self.lower_expr_to_ty(&lhs_expr.0, lhs_expr.1, rhs.1.ty)?;
+                    (lhs, rhs)
+                } else {
+                    let lhs = self.lower_expr(&lhs_expr.0, lhs_expr.1)?;
+                    let rhs = self.lower_expr_to_ty(&rhs_expr.0, rhs_expr.1, lhs.1.ty)?;
+                    (lhs, rhs)
+                };
+
+                // Pointers are compared as unsigned addresses.
+                let mut signed = false;
+                if lhs.1.ty.is_arithmetic() && rhs.1.ty.is_arithmetic() {
+                    let common = self.lcx.common_arith_type(lhs.1.ty, rhs.1.ty, span)?;
+                    lhs.0 = self.convert(lhs.0, lhs.1.ty, common, span)?;
+                    rhs.0 = self.convert(rhs.0, rhs.1.ty, common, span)?;
+                    signed = common.is_signed();
+                }
+
                 let kind = match comp {
-                    ast::ComparisonKind::Lt => BinKind::Lt,
-                    ast::ComparisonKind::Gt => BinKind::Gt,
-                    ast::ComparisonKind::LtEq => BinKind::Leq,
-                    ast::ComparisonKind::GtEq => BinKind::Geq,
+                    ast::ComparisonKind::Lt if signed => BinKind::SLt,
+                    ast::ComparisonKind::Lt => BinKind::ULt,
+                    ast::ComparisonKind::Gt if signed => BinKind::SGt,
+                    ast::ComparisonKind::Gt => BinKind::UGt,
+                    ast::ComparisonKind::LtEq if signed => BinKind::SLeq,
+                    ast::ComparisonKind::LtEq => BinKind::ULeq,
+                    ast::ComparisonKind::GtEq if signed => BinKind::SGeq,
+                    ast::ComparisonKind::GtEq => BinKind::UGeq,
                     ast::ComparisonKind::Eq => BinKind::Eq,
                     ast::ComparisonKind::Neq => BinKind::Neq,
                 };
 
-                let reg = self
-                    .build
-                    .binary(kind, lhs.0, rhs.0, span, self.dummy_tyl());
+                // (6.5.8) Comparisons result in an int.
+                let result = self.lcx.layout_of(self.lcx.types.int.signed);
+                let reg = self.build.binary(kind, lhs.0, rhs.0, span, result);
 
-                (Operand::Reg(reg), self.dummy_tyl())
+                (Operand::Reg(reg), result)
             }
             ast::Expr::Binary(ExprBinary {
                 op: ast::BinaryOp::Comma,
@@ -417,29 +1769,229 @@ impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
                 self.lower_expr(&rhs.0, rhs.1)?
             }
             ast::Expr::Binary(_) => todo!("other binary"),
+            ast::Expr::Conditional(conditional) => self.lower_conditional(conditional, span)?,
+            ast::Expr::SizeOfExpr(expr) => {
+                let tyl = self.type_of_expr(&expr.0, expr.1)?;
+                (
+                    Operand::Const(ConstValue::Int(tyl.layout.size.into())),
+                    self.lcx.layout_of(self.lcx.types.long.unsigned),
+                )
+            }
+            ast::Expr::SizeOfType(type_name) => {
+                let ty = self.lcx.lower_ty(&type_name.decl_spec.0.ty)?;
+                let ty = if type_name.pointer {
+                    self.lcx
+                        .intern_ty(TyKind::Ptr(ty, type_name.decl_spec.0.quals))
+                } else {
+                    ty
+                };
+                (
+                    Operand::Const(ConstValue::Int(self.lcx.layout_of(ty).layout.size.into())),
+                    self.lcx.layout_of(self.lcx.types.long.unsigned),
+                )
+            }
+            ast::Expr::Postfix(ast::ExprPostfix {
+                lhs,
+                op: op @ (ast::PostfixOp::Increment | ast::PostfixOp::Decrement),
+            }) => {
+                let is_incr = matches!(op, ast::PostfixOp::Increment);
+                self.lower_incr_decr(lhs, is_incr, true, span)?
+            }
             ast::Expr::Postfix(postfix) => {
                 let lhs = self.lower_expr(&postfix.lhs.0, postfix.lhs.1)?;
                 match &postfix.op {
                     ast::PostfixOp::Call(args) => {
+                        let (func, func_tyl) = lhs;
+                        if is_undef(func) {
+                            // The arguments are still lowered to report their errors.
+                            for (arg, arg_span) in args {
+                                self.lower_expr(arg, *arg_span)?;
+                            }
+                            return Ok(self.undef());
+                        }
+                        let TyKind::Func {
+                            ret,
+                            params,
+                            variadic,
+                        } = **func_tyl.ty
+                        else {
+                            return Err(Error::new(
+                                format!("cannot call value of type {}", func_tyl.ty),
+                                postfix.lhs.1,
+                            ));
+                        };
+                        // Functions declared with `()` can be called with any arguments, which get
+                        // the default argument promotions like variadic ones.
+                        let (params, variadic) = match params {
+                            Some(params) => (params, variadic),
+                            None => (&[][..], true),
+                        };
+                        if args.len() < params.len() || (!variadic && args.len() > params.len()) {
+                            return Err(Error::new(
+                                format!(
+                                    "function takes {}{} arguments, but {} were supplied",
+                                    if variadic { "at least " } else { "" },
+                                    params.len(),
+                                    args.len()
+                                ),
+                                span,
+                            ));
+                        }
+
                         let args = args
                             .iter()
-                            .map(|(arg, sp)| self.lower_expr(arg, *sp).map(|o| o.0))
+                            .enumerate()
+                            .map(|(i, (arg, arg_span))| match params.get(i) {
+                                Some(&param_ty) => {
+                                    self.lower_expr_as_assigned(arg, *arg_span, param_ty)
+                                }
+                                None => self.lower_promoted_arg(arg, *arg_span),
+                            })
                             .collect::<Result<_, _>>()?;
 
-                        let reg = self.build.call(self.dummy_tyl(), lhs.0, args, span);
-                        (Operand::Reg(reg), self.dummy_tyl())
+                        let ret_tyl = self.lcx.layout_of(ret);
+                        let reg = self.build.call(ret_tyl, func, args, span);
+                        if self.is_noreturn_fn(&postfix.lhs.0) {
+                            self.build.diverge(Branch::Unreachable);
+                        }
+                        (Operand::Reg(reg), ret_tyl)
+                    }
+                    ast::PostfixOp::Member(_) | ast::PostfixOp::ArrowMember(_) => {
+                        unreachable!("handled above")
                     }
-                    ast::PostfixOp::Member(_) => todo!("member expr"),
-                    ast::PostfixOp::ArrowMember(_) => todo!("arrow member expr"),
-                    ast::PostfixOp::Increment => {
-                        todo!("gotta have lvalues")
+                    ast::PostfixOp::Increment | ast::PostfixOp::Decrement => {
+                        unreachable!("handled above")
                     }
-                    ast::PostfixOp::Decrement => todo!(),
                 }
             }
         };
         Ok(op_tyl)
     }
+
+    /// (6.5.2.2) Lowers an argument without a parameter type. It gets the default argument
+    /// promotions, which promote integers and turn `float` into `double`.
+    fn lower_promoted_arg(&mut self, arg: &ast::Expr, span: Span) -> Result<Operand<'cx>> {
+        let (arg, tyl) = self.lower_expr(arg, span)?;
+        if let TyKind::Float = **tyl.ty {
+            return Err(Error::new(
+                "passing float without a parameter type is not supported yet",
+                span,
+            ));
+        }
+        if !tyl.ty.is_integer() {
+            return Ok(arg);
+        }
+        let promoted = self.lcx.promote(tyl.ty);
+        self.convert(arg, tyl.ty, promoted, span)
+    }
+
+    /// (6.5.6) Lowers `+` or `-` where at least one operand is a pointer. The integer operand is
+    /// scaled by the size of the pointee, and subtracting two pointers gives the number of
+    /// elements between them as a `long`.
+    fn lower_ptr_arith(
+        &mut self,
+        is_sub: bool,
+        ((lhs, lhs_tyl), lhs_span): Spanned<(Operand<'cx>, TyLayout<'cx>)>,
+        ((rhs, rhs_tyl), rhs_span): Spanned<(Operand<'cx>, TyLayout<'cx>)>,
+        span: Span,
+    ) -> Result<(Operand<'cx>, TyLayout<'cx>)> {
+        let long = self.lcx.layout_of(self.lcx.types.long.signed);
+
+        if let (&TyKind::Ptr(lhs_pointee, _), &TyKind::Ptr(rhs_pointee, _)) =
+            (*lhs_tyl.ty, *rhs_tyl.ty)
+        {
+            if !is_sub {
+                return Err(Error::new("cannot add two pointers", span));
+            }
+            if lhs_pointee != rhs_pointee {
+                return Err(Error::new(
+                    format!("cannot subtract {} from {}", rhs_tyl.ty, lhs_tyl.ty),
+                    span,
+                ));
+            }
+            let elem_size = self.pointee_size(lhs_pointee, lhs_span)?;
+            let diff = self.build.binary(BinKind::Sub, lhs, rhs, span, long);
+            let count = self
+                .build
+                .binary(BinKind::SDiv, Operand::Reg(diff), elem_size, span, long);
+            return Ok((Operand::Reg(count), long));
+        }
+
+        let ((ptr, ptr_tyl, ptr_span), (int, int_tyl, int_span)) = if lhs_tyl.ty.is_pointer() {
+            ((lhs, lhs_tyl, lhs_span), (rhs, rhs_tyl, rhs_span))
+        } else if is_sub {
+            return Err(Error::new(
+                format!("cannot subtract {} from {}", rhs_tyl.ty, lhs_tyl.ty),
+                span,
+            ));
+        } else {
+            ((rhs, rhs_tyl, rhs_span), (lhs, lhs_tyl, lhs_span))
+        };
+        if !int_tyl.ty.is_integer() {
+            return Err(Error::new(
+                format!("pointer offset must be an integer, found {}", int_tyl.ty),
+                int_span,
+            ));
+        }
+
+        let TyKind::Ptr(pointee, _) = **ptr_tyl.ty else {
+            unreachable!("one operand is a pointer");
+        };
+        let elem_size = self.pointee_size(pointee, ptr_span)?;
+        let int = self.convert(int, int_tyl.ty, long.ty, int_span)?;
+        let amount = self.build.binary(BinKind::Mul, int, elem_size, span, long);
+        let amount = if is_sub {
+            self.build
+                .unary(UnaryKind::Negate, Operand::Reg(amount), span, long)
+        } else {
+            amount
+        };
+        let result = self
+            .build
+            .ptr_offset(ptr, Operand::Reg(amount), span, ptr_tyl);
+        Ok((Operand::Reg(result), ptr_tyl))
+    }
+
+    /// The size of the elements a pointer points to, which must be known for pointer arithmetic.
+    fn pointee_size(&self, pointee: Ty<'cx>, span: Span) -> Result<Operand<'cx>> {
+        if let TyKind::Void = *pointee {
+            return Err(Error::new(
+                "cannot do arithmetic on a pointer to void",
+                span,
+            ));
+        }
+        let size = self.lcx.layout_of(pointee).layout.size;
+        Ok(Operand::Const(ConstValue::Int(size.into())))
+    }
+}
+
+fn is_undef(op: Operand<'_>) -> bool {
+    matches!(op, Operand::Const(ConstValue::Undef))
+}
+
+/// (6.3.2.1) Whether an expression designates an object. Members of struct values like `f().x`
+/// don't.
+fn is_lvalue(expr: &ast::Expr) -> bool {
+    match expr {
+        ast::Expr::Atom(ast::Atom::Ident(_))
+        | ast::Expr::Postfix(ast::ExprPostfix {
+            op: ast::PostfixOp::ArrowMember(_),
+            ..
+        })
+        | ast::Expr::Unary(ast::ExprUnary {
+            op: ast::UnaryOp::Deref,
+            ..
+        })
+        | ast::Expr::Binary(ExprBinary {
+            op: ast::BinaryOp::Index,
+            ..
+        }) => true,
+        ast::Expr::Postfix(ast::ExprPostfix {
+            lhs,
+            op: ast::PostfixOp::Member(_),
+        }) => is_lvalue(&lhs.0),
+        _ => false,
+    }
 }
 
 fn lower_func<'cx>(
@@ -450,45 +2002,48 @@ fn lower_func<'cx>(
     name: Symbol,
     ret_ty: Ty<'cx>,
     params: &[ast::FunctionParamDecl],
-) -> Result<Func<'cx>, Error> {
+    param_tys: &[Ty<'cx>],
+) -> Result<Func<'cx>, Vec<Error>> {
+    let args = params
+        .iter()
+        .zip(param_tys)
+        .map(|(param, &ty)| (Some(param.declarator.0.decl.name().0), lcx.layout_of(ty)))
+        .collect::<Vec<_>>();
     let mut cx = FnLoweringCtxt {
         scopes: vec![Default::default()],
-        build: FuncBuilder::new(name, def_span, ret_ty, lcx, params.len()),
+        build: FuncBuilder::new(name, def_span, ret_ty, lcx, &args),
         lcx,
+        errors: Vec::new(),
+        break_targets: Vec::new(),
+        continue_targets: Vec::new(),
+        switches: Vec::new(),
+        labels: FxHashMap::default(),
+        gotos: Vec::new(),
     };
 
-    for param in params {
-        let decl_spec = &param.decl_spec.0;
-        let ty = lcx.lower_ty(&decl_spec.ty);
-        let tyl = lcx.layout_of(ty);
-        // Create all the parameter registers.
-        let _ = cx
-            .build
-            .new_reg(Some(param.declarator.0.decl.name().0), tyl);
-    }
-
     for (i, param) in params.iter().enumerate() {
-        // For every param, we create an allocation and store the register into it.
-        let param_reg_data = &cx.build.ir.regs[i];
+        // For every param, we create an allocation and store the argument into it.
+        let (arg, tyl) = cx.build.ir.args[i];
         let name = param.declarator.0.decl.name().0;
 
         let decl_spec = &param.decl_spec.0;
         let decl_attr = decl_spec.attrs;
-        let tyl = param_reg_data.tyl;
+        let quals = LoweringCx::declarator_quals(decl_spec.quals, &param.declarator.0);
         let span = param.declarator.1;
 
         let alloca_name = Symbol::intern(&format!("{}.local", name));
-        let ptr_to = cx.build.reserve_local(tyl.layout, alloca_name, span);
+        let ptr_to = cx.build.reserve_local(tyl.layout, Some(alloca_name), span);
 
         let variable_info = VariableInfo {
             def_span: span,
             decl_attr,
+            quals,
             tyl,
             kind: VariableInfoKind::Local { ptr_to },
         };
         let predeclared = cx.scopes.last_mut().unwrap().insert(name, variable_info);
         if let Some(predeclared) = predeclared {
-            return Err(
+            cx.errors.push(
                 Error::new(format!("parameter {name} has already been declared"), span)
                     .note_spanned("already declared here", predeclared.def_span),
             );
@@ -496,17 +2051,86 @@ fn lower_func<'cx>(
 
         cx.build.store(
             Operand::Reg(ptr_to),
-            Operand::Reg(Register(i as _)),
+            Operand::Reg(arg),
             tyl.layout,
+            quals.contains(ast::Qualifiers::VOLATILE),
             span,
         );
     }
 
-    cx.lower_block(body)?;
+    cx.collect_labels(body);
+    cx.lower_block(body);
+    if !cx.errors.is_empty() {
+        return Err(cx.errors);
+    }
 
-    if let Branch::Goto(BbIdx(u32::MAX)) = cx.build.cur_bb_mut().term {
-        cx.build.cur_bb_mut().term = Branch::Ret(Operand::Const(ConstValue::Void));
+    if cx.build.is_dead_tail(cx.build.current_bb) {
+        cx.build.ir.bbs.pop();
+    } else if !ir::info::reachable_blocks(&cx.build.ir)[cx.build.current_bb.as_usize()] {
+        // Every path returned before, like in `if (x) return 1; else return 2;`.
+        cx.build.cur_bb_mut().term = Branch::Unreachable;
+    } else if cx.build.cur_bb_mut().term.is_dummy() {
+        // Falling off the end of `main` returns 0, for other functions it's only undefined
+        // behavior if the caller uses the value.
+        if !matches!(*ret_ty, TyKind::Void) && name != Symbol::intern("main") {
+            let err = Error::new(
+                format!("function {name} can reach its end without returning a value"),
+                def_span,
+            );
+            if lcx.strict {
+                return Err(vec![err]);
+            }
+            lcx.emit_warning(err);
+        }
+        // Using the value of a function that fell off its end is undefined, but `main` returns 0.
+        let ret = match *ret_ty {
+            TyKind::Void => ConstValue::Void,
+            TyKind::Ptr(..) => ConstValue::Null(ret_ty),
+            _ => ConstValue::Int(0),
+        };
+        cx.build.cur_bb_mut().term = Branch::Ret(Operand::Const(ret));
     }
 
-    Ok(cx.build.finish())
+    let func = cx.build.finish();
+    warn_uninitialized_loads(lcx, &func);
+    warn_gotos_skipping_init(lcx, &func, &cx.gotos);
+    Ok(func)
+}
+
+/// Warns about the first read of every local that may not have been initialized.
+fn warn_uninitialized_loads(lcx: &LoweringCx<'_>, func: &Func<'_>) {
+    let mut reported = FxHashSet::default();
+    for (loc, slot) in ir::uninit::uninitialized_loads(func) {
+        if !reported.insert(slot) {
+            continue;
+        }
+        let Some(name) = variable_name(func, slot) else {
+            continue;
+        };
+        let span = func.bb(loc.bb).statements[loc.stmt.unwrap()].span;
+        lcx.emit_warning(Error::new(
+            format!("variable {name} is used uninitialized"),
+            span,
+        ));
+    }
+}
+
+fn warn_gotos_skipping_init(lcx: &LoweringCx<'_>, func: &Func<'_>, gotos: &[(BbIdx, Span)]) {
+    let blocks = gotos.iter().map(|&(bb, _)| bb).collect::<Vec<_>>();
+    for (bb, slot) in ir::uninit::gotos_skipping_init(func, &blocks) {
+        let Some(name) = variable_name(func, slot) else {
+            continue;
+        };
+        let span = gotos.iter().find(|&&(goto, _)| goto == bb).unwrap().1;
+        lcx.emit_warning(Error::new(
+            format!("goto skips the initialization of variable {name}"),
+            span,
+        ));
+    }
+}
+
+fn variable_name(func: &Func<'_>, slot: Register) -> Option<String> {
+    let name = func.regs[slot.as_usize()].name?;
+    // Shadowed variables get a numeric suffix in the IR.
+    Some(name.as_str(|name| name.split('.').next().unwrap().to_owned()))
 }
@@ -1,9 +1,13 @@
-use parser::{Span, Symbol};
+use parser::{
+    ast::{DeclAttr, Qualifiers},
+    Span, Symbol,
+};
+use rustc_hash::FxHashSet;
 
 use super::LoweringCx;
 use crate::{
     ir::{
-        self, BasicBlock, BbIdx, BinKind, Branch, Func, Layout, Operand, Register, RegisterData,
+        BasicBlock, BbIdx, BinKind, Branch, Func, Layout, Operand, Register, RegisterData,
         Statement, StatementKind, TyLayout, UnaryKind,
     },
     ty::{Ty, TyKind},
@@ -14,6 +18,9 @@ pub(super) struct FuncBuilder<'a, 'cx> {
     pub lcx: &'a LoweringCx<'cx>,
     pub ir: Func<'cx>,
     pub current_bb: BbIdx,
+    reg_names: FxHashSet<Symbol>,
+    /// The last block started by [`Self::diverge`].
+    diverged_to: Option<BbIdx>,
 }
 
 impl<'a, 'cx> FuncBuilder<'a, 'cx> {
@@ -22,9 +29,9 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
         def_span: Span,
         ret_ty: Ty<'cx>,
         lcx: &'a LoweringCx<'cx>,
-        arity: usize,
+        params: &[(Option<Symbol>, TyLayout<'cx>)],
     ) -> Self {
-        Self {
+        let mut build = Self {
             ir: Func {
                 regs: Vec::new(),
                 bbs: vec![BasicBlock {
@@ -34,32 +41,52 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
                 name,
                 def_span,
                 ret_ty,
-                arity,
+                args: Vec::new(),
+                decl_attr: DeclAttr::empty(),
             },
             current_bb: BbIdx(0),
             lcx,
+            reg_names: FxHashSet::default(),
+            diverged_to: None,
+        };
+        for &(name, tyl) in params {
+            let reg = build.new_reg(name, tyl);
+            build.ir.args.push((reg, tyl));
         }
+        build
     }
 
+    /// Creates a new register. Register names must be unique, so names that are already used by
+    /// another register get a numeric suffix, like `x.1` for a shadowed variable `x`.
     pub fn new_reg(&mut self, name: Option<Symbol>, tyl: TyLayout<'cx>) -> Register {
+        let name = name.map(|name| {
+            let mut unique = name;
+            let mut suffix = 0;
+            while !self.reg_names.insert(unique) {
+                suffix += 1;
+                unique = Symbol::intern(&format!("{name}.{suffix}"));
+            }
+            unique
+        });
         let reg = Register(self.ir.regs.len().try_into().unwrap());
         self.ir.regs.push(RegisterData { name, tyl });
         reg
     }
 
-    pub fn reserve_local(&mut self, layout: &Layout, name: Symbol, span: Span) -> Register {
+    pub fn reserve_local(&mut self, layout: &Layout, name: Option<Symbol>, span: Span) -> Register {
         // Every local is a singleton.
         let prev = self.current_bb;
         self.current_bb = BbIdx(0);
-        let reg = self.alloca(layout, Some(name), span);
+        let reg = self.alloca(layout, name, span);
         self.current_bb = prev;
         reg
     }
 
     pub fn alloca(&mut self, layout: &Layout, name: Option<Symbol>, span: Span) -> Register {
-        let void_ptr = self
-            .lcx
-            .intern_ty(TyKind::Ptr(self.lcx.intern_ty(TyKind::Void)));
+        let void_ptr = self.lcx.intern_ty(TyKind::Ptr(
+            self.lcx.intern_ty(TyKind::Void),
+            Qualifiers::empty(),
+        ));
         let reg = self.new_reg(name, self.lcx.layout_of(void_ptr));
         let stmt = Statement {
             span,
@@ -67,6 +94,7 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
                 result: reg,
                 size: layout.size,
                 align: layout.align,
+                name,
             },
         };
         self.cur_bb_mut().statements.push(stmt);
@@ -76,8 +104,8 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
     pub fn binary(
         &mut self,
         kind: BinKind,
-        lhs: Operand,
-        rhs: Operand,
+        lhs: Operand<'cx>,
+        rhs: Operand<'cx>,
         span: Span,
         result_tyl: TyLayout<'cx>,
     ) -> Register {
@@ -97,7 +125,7 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
     pub fn unary(
         &mut self,
         kind: UnaryKind,
-        rhs: Operand,
+        rhs: Operand<'cx>,
         span: Span,
         result_tyl: TyLayout<'cx>,
     ) -> Register {
@@ -113,13 +141,83 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
         reg
     }
 
-    pub fn load(&mut self, tyl: TyLayout<'cx>, ptr: Operand, span: Span) -> Register {
+    /// Converts an integer of type `from` to the integer type `to` of a different size. Bigger
+    /// types get a sign extension for signed and a zero extension for unsigned types, smaller
+    /// ones keep the low bytes.
+    pub fn convert(
+        &mut self,
+        from: TyLayout<'cx>,
+        to: TyLayout<'cx>,
+        op: Operand<'cx>,
+        span: Span,
+    ) -> Register {
+        assert!(
+            from.ty.is_integer() && to.ty.is_integer() && from.layout.size != to.layout.size,
+            "invalid conversion from {} to {}",
+            from.ty,
+            to.ty
+        );
+        let kind = if from.layout.size > to.layout.size {
+            UnaryKind::Trunc
+        } else if from.ty.is_signed() {
+            UnaryKind::Sext
+        } else {
+            UnaryKind::Zext
+        };
+        self.unary(kind, op, span, to)
+    }
+
+    /// Adds a phi to the current block, which must not have any non-phi statements yet.
+    pub fn phi(
+        &mut self,
+        incomings: Vec<(BbIdx, Operand<'cx>)>,
+        result_tyl: TyLayout<'cx>,
+        span: Span,
+    ) -> Register {
+        let reg = self.new_reg(None, result_tyl);
+        let stmt = StatementKind::Phi {
+            result: reg,
+            incomings,
+        };
+        self.cur_bb_mut()
+            .statements
+            .push(Statement { span, kind: stmt });
+        reg
+    }
+
+    pub fn ptr_offset(
+        &mut self,
+        ptr: Operand<'cx>,
+        amount: Operand<'cx>,
+        span: Span,
+        result_tyl: TyLayout<'cx>,
+    ) -> Register {
+        let reg = self.new_reg(None, result_tyl);
+        let stmt = StatementKind::PtrOffset {
+            result: reg,
+            ptr,
+            amount,
+        };
+        self.cur_bb_mut()
+            .statements
+            .push(Statement { span, kind: stmt });
+        reg
+    }
+
+    pub fn load(
+        &mut self,
+        tyl: TyLayout<'cx>,
+        ptr: Operand<'cx>,
+        volatile: bool,
+        span: Span,
+    ) -> Register {
         let reg = self.new_reg(None, tyl);
         let stmt = StatementKind::Load {
             result: reg,
             ptr,
             size: tyl.layout.size,
             align: tyl.layout.align,
+            volatile,
         };
         self.cur_bb_mut()
             .statements
@@ -127,12 +225,20 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
         reg
     }
 
-    pub fn store(&mut self, ptr: Operand, rhs: Operand, layout: &Layout, span: Span) {
+    pub fn store(
+        &mut self,
+        ptr: Operand<'cx>,
+        rhs: Operand<'cx>,
+        layout: &Layout,
+        volatile: bool,
+        span: Span,
+    ) {
         let stmt = StatementKind::Store {
             ptr,
             value: rhs,
             size: layout.size,
             align: layout.align,
+            volatile,
         };
         self.cur_bb_mut()
             .statements
@@ -142,8 +248,8 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
     pub fn call(
         &mut self,
         ret_tyl: TyLayout<'cx>,
-        func: Operand,
-        args: Vec<Operand>,
+        func: Operand<'cx>,
+        args: Vec<Operand<'cx>>,
         span: Span,
     ) -> Register {
         let reg = self.new_reg(None, ret_tyl);
@@ -158,11 +264,11 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
         reg
     }
 
-    pub fn bb_mut(&mut self, bb: BbIdx) -> &mut BasicBlock {
+    pub fn bb_mut(&mut self, bb: BbIdx) -> &mut BasicBlock<'cx> {
         self.ir.bb_mut(bb)
     }
 
-    pub fn cur_bb_mut(&mut self) -> &mut BasicBlock {
+    pub fn cur_bb_mut(&mut self) -> &mut BasicBlock<'cx> {
         &mut self.ir.bbs[self.current_bb.as_usize()]
     }
 
@@ -174,12 +280,148 @@ impl<'a, 'cx> FuncBuilder<'a, 'cx> {
         BbIdx::from_usize(self.ir.bbs.len() - 1)
     }
 
+    /// Ends the current block with `term`, which doesn't continue, and starts a new block for
+    /// the code after it.
+    pub fn diverge(&mut self, term: Branch<'cx>) {
+        self.cur_bb_mut().term = term;
+        self.current_bb = self.new_block();
+        self.diverged_to = Some(self.current_bb);
+    }
+
+    /// Whether `bb` is the empty block that was started after a `return` or `_Noreturn` call at the
+    /// very end of the function. Nothing can branch to it, so it can be removed.
+    pub fn is_dead_tail(&self, bb: BbIdx) -> bool {
+        self.diverged_to == Some(bb)
+            && bb.as_usize() == self.ir.bbs.len() - 1
+            && self.ir.bb(bb).statements.is_empty()
+            && self.ir.bbs.iter().all(|other| {
+                other.term.is_dummy() || other.term.successors().all(|succ| succ != bb)
+            })
+    }
+
     pub fn finish(self) -> Func<'cx> {
-        println!(
-            "{}",
-            ir::func_to_string(&self.ir, &ir::pretty::DefaultCustomizer::default())
+        self.ir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::{Span, Symbol};
+
+    use super::FuncBuilder;
+    use crate::{
+        ir::{BbIdx, ConstValue, Operand, Register, StatementKind, UnaryKind},
+        LoweringCx,
+    };
+
+    fn last_stmt<'a, 'cx>(build: &'a FuncBuilder<'_, 'cx>) -> &'a StatementKind<'cx> {
+        &build.ir.bbs[build.current_bb.as_usize()]
+            .statements
+            .last()
+            .unwrap()
+            .kind
+    }
+
+    #[test]
+    fn alloca_name() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+        let int = lcx.layout_of(lcx.types.int.signed);
+        let mut build = FuncBuilder::new(Symbol::intern("f"), Span::default(), int.ty, &lcx, &[]);
+
+        let x = Symbol::intern("x");
+        let first = build.alloca(int.layout, Some(x), Span::default());
+        let second = build.alloca(int.layout, Some(x), Span::default());
+        assert!(
+            matches!(last_stmt(&build), StatementKind::Alloca { name: Some(name), .. } if *name == x)
         );
+        assert_eq!(build.ir.regs[first.as_usize()].name, Some(x));
+        assert_eq!(
+            build.ir.regs[second.as_usize()].name,
+            Some(Symbol::intern("x.1"))
+        );
+    }
 
-        self.ir
+    #[test]
+    fn unary() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+        let int = lcx.layout_of(lcx.types.int.signed);
+        let mut build = FuncBuilder::new(Symbol::intern("f"), Span::default(), int.ty, &lcx, &[]);
+
+        let reg = build.unary(
+            UnaryKind::Negate,
+            Operand::Const(ConstValue::Int(1)),
+            Span::default(),
+            int,
+        );
+        assert_eq!(reg, Register(0));
+        assert!(matches!(
+            last_stmt(&build),
+            StatementKind::UnaryOperation {
+                kind: UnaryKind::Negate,
+                rhs: Operand::Const(ConstValue::Int(1)),
+                result: Register(0),
+            }
+        ));
+    }
+
+    #[test]
+    fn convert() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+        let int = lcx.layout_of(lcx.types.int.signed);
+        let uint = lcx.layout_of(lcx.types.int.unsigned);
+        let long = lcx.layout_of(lcx.types.long.signed);
+        let mut build = FuncBuilder::new(Symbol::intern("f"), Span::default(), long.ty, &lcx, &[]);
+        let arg = build.new_reg(None, int);
+
+        let sext = build.convert(int, long, Operand::Reg(arg), Span::default());
+        assert!(matches!(
+            last_stmt(&build),
+            &StatementKind::UnaryOperation { kind: UnaryKind::Sext, rhs: Operand::Reg(rhs), result }
+                if rhs == arg && result == sext
+        ));
+        assert_eq!(build.ir.regs[sext.as_usize()].tyl.ty, long.ty);
+
+        let zext = build.convert(uint, long, Operand::Reg(arg), Span::default());
+        assert!(matches!(
+            last_stmt(&build),
+            &StatementKind::UnaryOperation { kind: UnaryKind::Zext, result, .. } if result == zext
+        ));
+
+        let trunc = build.convert(long, int, Operand::Reg(sext), Span::default());
+        assert!(matches!(
+            last_stmt(&build),
+            &StatementKind::UnaryOperation { kind: UnaryKind::Trunc, rhs: Operand::Reg(rhs), result }
+                if rhs == sext && result == trunc
+        ));
+        assert_eq!(build.ir.regs[trunc.as_usize()].tyl.ty, int.ty);
+    }
+
+    #[test]
+    fn phi() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+        let int = lcx.layout_of(lcx.types.int.signed);
+        let mut build = FuncBuilder::new(Symbol::intern("f"), Span::default(), int.ty, &lcx, &[]);
+        let other = build.new_block();
+        let join = build.new_block();
+        build.current_bb = join;
+
+        let incomings = vec![
+            (BbIdx(0), Operand::Const(ConstValue::Int(1))),
+            (other, Operand::Const(ConstValue::Int(2))),
+        ];
+        let reg = build.phi(incomings, int, Span::default());
+        let StatementKind::Phi { result, incomings } = last_stmt(&build) else {
+            panic!("expected phi");
+        };
+        assert_eq!(*result, reg);
+        assert_eq!(incomings.len(), 2);
+        assert!(matches!(
+            incomings[1],
+            (bb, Operand::Const(ConstValue::Int(2))) if bb == other
+        ));
     }
 }
@@ -0,0 +1,286 @@
+use parser::{ast, Error, Span, Symbol};
+
+use crate::{
+    ir::{DefId, TyLayout, VariableInfo, VariableInfoKind},
+    ty::{Ty, TyKind},
+    LoweringCx,
+};
+
+/// (6.6) Evaluates an integer constant expression, as required for array sizes and
+/// `_Static_assert`. The value is in the range of the type of the expression, so unsigned
+/// values are never negative.
+pub(crate) fn eval_const_expr(
+    cx: &LoweringCx<'_>,
+    expr: &ast::Expr,
+    span: Span,
+) -> Result<i128, Error> {
+    let (value, _) = eval_const_expr_scoped(cx, expr, span, &|ident| {
+        cx.global_decls.get(&ident).map(|var| var.tyl)
+    })?;
+    Ok(value)
+}
+
+/// Like [`eval_const_expr`], but the variables in operands of `sizeof` are looked up with
+/// `resolve`, so that locals shadowing a global get their own size. The type of the expression
+/// is returned as well.
+///
+/// Like at runtime, operands are converted with the usual arithmetic conversions and results
+/// wrap around at the width of their type, so `-1u > 0` holds.
+pub(crate) fn eval_const_expr_scoped<'cx>(
+    cx: &LoweringCx<'cx>,
+    expr: &ast::Expr,
+    span: Span,
+    resolve: &dyn Fn(Symbol) -> Option<TyLayout<'cx>>,
+) -> Result<(i128, Ty<'cx>), Error> {
+    let eval = |expr: &ast::Expr, span: Span| eval_const_expr_scoped(cx, expr, span, resolve);
+    let not_constant = || Error::new("expression is not an integer constant expression", span);
+    let int = cx.types.int.signed;
+
+    match expr {
+        ast::Expr::Atom(ast::Atom::Int(int)) => {
+            let ty = cx.int_constant_ty(int, span)?;
+            Ok((int.value as i128, ty))
+        }
+        // (6.4.4.4) Character constants have type `int`.
+        ast::Expr::Atom(ast::Atom::Char(c)) => Ok(((*c).into(), int)),
+        ast::Expr::Atom(ast::Atom::Ident((ident, ident_span))) => Err(Error::new(
+            format!("cannot read variable {ident} in a constant expression"),
+            *ident_span,
+        )),
+        ast::Expr::Unary(ast::ExprUnary { rhs, op }) => {
+            let (value, ty) = eval(&rhs.0, rhs.1)?;
+            let ty = cx.promote(ty);
+            match op {
+                ast::UnaryOp::Plus => Ok((value, ty)),
+                ast::UnaryOp::Minus => Ok((wrap(cx, value.wrapping_neg(), ty), ty)),
+                ast::UnaryOp::Tilde => Ok((wrap(cx, !value, ty), ty)),
+                ast::UnaryOp::Bang => Ok(((value == 0).into(), int)),
+                _ => Err(not_constant()),
+            }
+        }
+        ast::Expr::Binary(ast::ExprBinary {
+            lhs,
+            rhs,
+            op: op @ (ast::BinaryOp::LogicalAnd | ast::BinaryOp::LogicalOr),
+        }) => {
+            // The rhs is only evaluated if it's needed, so `0 && 1 / 0` is fine.
+            let lhs = eval(&lhs.0, lhs.1)?.0 != 0;
+            let value = match op {
+                ast::BinaryOp::LogicalAnd => lhs && eval(&rhs.0, rhs.1)?.0 != 0,
+                _ => lhs || eval(&rhs.0, rhs.1)?.0 != 0,
+            };
+            Ok((value.into(), int))
+        }
+        ast::Expr::Conditional(ast::ExprConditional {
+            cond,
+            then,
+            otherwise,
+        }) => {
+            // Only the chosen operand is evaluated, so `1 ? 2 : 1 / 0` is fine. It's converted to
+            // the common type of both operands.
+            let (chosen, chosen_span) = if eval(&cond.0, cond.1)?.0 != 0 {
+                &**then
+            } else {
+                &**otherwise
+            };
+            let ty = cx.type_of_expr(expr, span, resolve)?;
+            let (value, _) = eval(chosen, *chosen_span)?;
+            Ok((wrap(cx, value, ty), ty))
+        }
+        ast::Expr::Binary(ast::ExprBinary { lhs, rhs, op }) => {
+            let (lhs, lhs_ty) = eval(&lhs.0, lhs.1)?;
+            let rhs_span = rhs.1;
+            let (rhs, rhs_ty) = eval(&rhs.0, rhs.1)?;
+            let (value, ty) = match op {
+                // (6.5.7) The operands of shifts are promoted on their own.
+                ast::BinaryOp::Arith(arith @ (ast::ArithOpKind::Shl | ast::ArithOpKind::Shr)) => {
+                    let ty = cx.promote(lhs_ty);
+                    let bits = cx.layout_of(ty).layout.size * 8;
+                    let amount = u32::try_from(rhs)
+                        .ok()
+                        .filter(|&amount| u64::from(amount) < bits)
+                        .ok_or_else(|| {
+                            Error::new(format!("invalid shift amount {rhs}"), rhs_span)
+                        })?;
+                    let value = if let ast::ArithOpKind::Shl = arith {
+                        lhs << amount
+                    } else {
+                        lhs >> amount
+                    };
+                    (value, ty)
+                }
+                ast::BinaryOp::Arith(arith) => {
+                    let ty = cx.common_arith_type(lhs_ty, rhs_ty, span)?;
+                    let (lhs, rhs) = (wrap(cx, lhs, ty), wrap(cx, rhs, ty));
+                    let value = match arith {
+                        ast::ArithOpKind::Add => lhs.wrapping_add(rhs),
+                        ast::ArithOpKind::Sub => lhs.wrapping_sub(rhs),
+                        ast::ArithOpKind::Mul => lhs.wrapping_mul(rhs),
+                        ast::ArithOpKind::Div | ast::ArithOpKind::Mod if rhs == 0 => {
+                            return Err(Error::new("division by zero", rhs_span));
+                        }
+                        ast::ArithOpKind::Div => lhs.wrapping_div(rhs),
+                        ast::ArithOpKind::Mod => lhs.wrapping_rem(rhs),
+                        ast::ArithOpKind::BitAnd => lhs & rhs,
+                        ast::ArithOpKind::BitXor => lhs ^ rhs,
+                        ast::ArithOpKind::BitOr => lhs | rhs,
+                        ast::ArithOpKind::Shl | ast::ArithOpKind::Shr => {
+                            unreachable!("handled above")
+                        }
+                    };
+                    (value, ty)
+                }
+                ast::BinaryOp::Comparison(comp) => {
+                    let ty = cx.common_arith_type(lhs_ty, rhs_ty, span)?;
+                    let (lhs, rhs) = (wrap(cx, lhs, ty), wrap(cx, rhs, ty));
+                    let value = match comp {
+                        ast::ComparisonKind::Lt => lhs < rhs,
+                        ast::ComparisonKind::Gt => lhs > rhs,
+                        ast::ComparisonKind::LtEq => lhs <= rhs,
+                        ast::ComparisonKind::GtEq => lhs >= rhs,
+                        ast::ComparisonKind::Eq => lhs == rhs,
+                        ast::ComparisonKind::Neq => lhs != rhs,
+                    };
+                    (value.into(), int)
+                }
+                ast::BinaryOp::LogicalAnd | ast::BinaryOp::LogicalOr => {
+                    unreachable!("handled above")
+                }
+                ast::BinaryOp::Comma | ast::BinaryOp::Index | ast::BinaryOp::Assign(_) => {
+                    return Err(not_constant());
+                }
+            };
+            Ok((wrap(cx, value, ty), ty))
+        }
+        ast::Expr::SizeOfType(type_name) => {
+            let ty = cx.lower_ty(&type_name.decl_spec.0.ty)?;
+            let ty = if type_name.pointer {
+                cx.intern_ty(TyKind::Ptr(ty, type_name.decl_spec.0.quals))
+            } else {
+                ty
+            };
+            Ok((cx.layout_of(ty).layout.size.into(), cx.types.long.unsigned))
+        }
+        ast::Expr::SizeOfExpr(expr) => {
+            let ty = cx.type_of_expr(&expr.0, expr.1, resolve)?;
+            Ok((cx.layout_of(ty).layout.size.into(), cx.types.long.unsigned))
+        }
+        ast::Expr::Atom(ast::Atom::Float(_) | ast::Atom::String(_)) | ast::Expr::Postfix(_) => {
+            Err(not_constant())
+        }
+    }
+}
+
+/// Wraps `value` around into the range of the integer type `ty`.
+fn wrap(cx: &LoweringCx<'_>, value: i128, ty: Ty<'_>) -> i128 {
+    let shift = 128 - cx.layout_of(ty).layout.size * 8;
+    if ty.is_signed() {
+        (value << shift) >> shift
+    } else {
+        (((value as u128) << shift) >> shift) as i128
+    }
+}
+
+/// (6.6) Evaluates an address constant, as used to initialize statics of pointer type `ty`.
+/// Returns the static or function that is pointed to and the offset into it in bytes, or `None`
+/// for a null pointer constant.
+pub(crate) fn eval_const_addr<'cx>(
+    cx: &LoweringCx<'cx>,
+    expr: &ast::Expr,
+    span: Span,
+    ty: Ty<'cx>,
+) -> Result<Option<(DefId, u64)>, Error> {
+    let global = |(ident, ident_span): (Symbol, Span)| match cx.global_decls.get(&ident) {
+        Some(var) => Ok(var),
+        None => Err(Error::new(
+            format!("cannot find variable {ident}"),
+            ident_span,
+        )),
+    };
+    let def_id = |var: &VariableInfo<'_>| match var.kind {
+        VariableInfoKind::FnDef { def_id } | VariableInfoKind::Static { def_id } => def_id,
+        VariableInfoKind::Local { .. } => unreachable!("local variable at file scope"),
+    };
+    let ptr_to = |pointee, quals| cx.intern_ty(TyKind::Ptr(pointee, quals));
+
+    let (target, offset, addr_ty) = match *expr {
+        ast::Expr::Unary(ast::ExprUnary {
+            op: ast::UnaryOp::AddrOf,
+            ref rhs,
+        }) => match rhs.0 {
+            ast::Expr::Atom(ast::Atom::Ident(ident)) => {
+                let var = global(ident)?;
+                (def_id(var), 0, ptr_to(var.tyl.ty, var.quals))
+            }
+            // `&array[i]`
+            ast::Expr::Binary(ast::ExprBinary {
+                op: ast::BinaryOp::Index,
+                lhs: ref array,
+                rhs: ref index,
+            }) => {
+                let ast::Expr::Atom(ast::Atom::Ident(ident)) = array.0 else {
+                    return Err(Error::new(
+                        "address of non-variable is not constant",
+                        array.1,
+                    ));
+                };
+                let var = global(ident)?;
+                let TyKind::Array { elem, .. } = **var.tyl.ty else {
+                    return Err(Error::new(
+                        format!("cannot index into {}", var.tyl.ty),
+                        array.1,
+                    ));
+                };
+                let (index, index_span) = (eval_const_expr(cx, &index.0, index.1)?, index.1);
+                check_array_index(var.tyl.ty, index, index_span)?;
+                let offset = (index as u64).wrapping_mul(cx.layout_of(elem).layout.size);
+                (def_id(var), offset, ptr_to(elem, var.quals))
+            }
+            _ => return Err(Error::new("address of non-variable is not constant", rhs.1)),
+        },
+        // Arrays and functions decay to a pointer to themselves.
+        ast::Expr::Atom(ast::Atom::Ident(ident)) => {
+            let var = global(ident)?;
+            match **var.tyl.ty {
+                TyKind::Array { elem, .. } => (def_id(var), 0, ptr_to(elem, var.quals)),
+                TyKind::Func { .. } => (def_id(var), 0, var.tyl.ty),
+                _ => {
+                    return Err(Error::new(
+                        format!("cannot read variable {} in a constant expression", ident.0),
+                        ident.1,
+                    ))
+                }
+            }
+        }
+        ast::Expr::Atom(ast::Atom::String(ref string)) => (
+            cx.intern_str_lit(string),
+            0,
+            ptr_to(cx.types.char, ast::Qualifiers::empty()),
+        ),
+        _ => {
+            return match eval_const_expr(cx, expr, span)? {
+                0 => Ok(None),
+                _ => Err(Error::new(
+                    "integer other than 0 is not an address constant",
+                    span,
+                )),
+            }
+        }
+    };
+    cx.check_assign(ty, addr_ty, span)?;
+    Ok(Some((target, offset)))
+}
+
+/// (6.5.6p8) Indexing into an array may only point up to one past its end, so a constant index
+/// into an array with a known size has to be at most its length.
+pub(super) fn check_array_index(array_ty: Ty<'_>, index: i128, span: Span) -> Result<(), Error> {
+    match **array_ty {
+        TyKind::Array { len: Some(len), .. } if index < 0 || index > len as i128 => {
+            Err(Error::new(
+                format!("array index {index} is out of bounds for type {array_ty}"),
+                span,
+            ))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,380 @@
+//! (6.7.9) Initialization of aggregates with brace-enclosed lists.
+
+use parser::{ast, Error, Span, Spanned, Symbol};
+
+use crate::{
+    ir::TyLayout,
+    lower::eval_const_expr,
+    ty::{Ty, TyKind},
+    LoweringCx,
+};
+
+/// A part of an initialized object at an offset into it. This is usually a scalar, but aggregates
+/// inside of other aggregates can be initialized by a single expression too, like a struct by
+/// another struct or a `char` array by a string literal.
+pub(super) struct InitElem<'a, 'cx> {
+    pub offset: u64,
+    pub ty: Ty<'cx>,
+    /// `None` for the parts that aren't mentioned by the initializer, they are zero-initialized.
+    pub init: Option<(&'a ast::Expr, Span)>,
+}
+
+/// The position in an initializer list. Designators of the current element are used one by one
+/// while descending into the designated subobject.
+struct Cursor<'a> {
+    elems: &'a [ast::InitListElem],
+    pos: usize,
+    /// The number of designators of the current element that have been used already.
+    designated: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(elems: &'a [ast::InitListElem]) -> Self {
+        Self {
+            elems,
+            pos: 0,
+            designated: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&'a ast::InitListElem> {
+        self.elems.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a ast::InitListElem> {
+        let elem = self.peek()?;
+        self.pos += 1;
+        self.designated = 0;
+        Some(elem)
+    }
+
+    /// The next unused designator of the current element.
+    fn designator(&self) -> Option<&'a ast::Designator> {
+        self.peek()?.designators.get(self.designated)
+    }
+}
+
+/// Flattens the initializer of an object of type `ty` into the parts it initializes. The parts
+/// mentioned by the initializer come first in order, followed by the zero-initialized rest.
+/// Arrays of unknown size get their size from the initializer, so the completed type of the
+/// object is returned as well. The variables of the initializer are looked up with `resolve`.
+pub(super) fn flatten_initializer<'a, 'cx>(
+    lcx: &LoweringCx<'cx>,
+    ty: Ty<'cx>,
+    (init, span): &'a Spanned<ast::Initializer>,
+    resolve: &dyn Fn(Symbol) -> Option<TyLayout<'cx>>,
+) -> Result<(Ty<'cx>, Vec<InitElem<'a, 'cx>>), Error> {
+    let list = match init {
+        ast::Initializer::Expr(expr) => {
+            let ty = match string_init_ty(lcx, ty, expr) {
+                Some(ty) => ty,
+                None if matches!(**ty, TyKind::Array { .. }) => {
+                    return Err(Error::new(
+                        format!("array of type {ty} must be initialized by a list"),
+                        *span,
+                    ));
+                }
+                None => ty,
+            };
+            let elem = InitElem {
+                offset: 0,
+                ty,
+                init: Some((expr, *span)),
+            };
+            return Ok((ty, vec![elem]));
+        }
+        ast::Initializer::List(list) => list,
+    };
+
+    let mut flattener = Flattener {
+        lcx,
+        resolve,
+        elems: Vec::new(),
+    };
+    let mut cursor = Cursor::new(list);
+    let ty = flattener.fill(ty, 0, &mut cursor, true, *span)?;
+    if let Some(elem) = cursor.next() {
+        return Err(excess_elements(elem.init.1));
+    }
+
+    let mut zeroed = Vec::new();
+    flattener.zero(&mut zeroed, ty, 0);
+    zeroed.retain(|zero| !flattener.overlaps_explicit(zero));
+    flattener.elems.extend(zeroed);
+    Ok((ty, flattener.elems))
+}
+
+fn excess_elements(span: Span) -> Error {
+    Error::new("excess elements in initializer", span)
+}
+
+/// (6.7.9p14) The type of a `char` array initialized by the string literal `expr`, completed
+/// with the length of the string and its null terminator if it's unknown. `None` if it isn't
+/// initialized by a string literal.
+pub(super) fn string_init_ty<'cx>(
+    lcx: &LoweringCx<'cx>,
+    ty: Ty<'cx>,
+    expr: &ast::Expr,
+) -> Option<Ty<'cx>> {
+    let (TyKind::Array { elem, len }, ast::Expr::Atom(ast::Atom::String(string))) = (&**ty, expr)
+    else {
+        return None;
+    };
+    if !matches!(
+        **elem,
+        TyKind::Char | TyKind::Int(ast::IntTy(_, ast::IntTyKind::Char))
+    ) {
+        return None;
+    }
+    Some(match len {
+        Some(_) => ty,
+        None => lcx.intern_ty(TyKind::Array {
+            elem: *elem,
+            len: Some(string.len() + 1),
+        }),
+    })
+}
+
+/// The bytes of a `char` array of type `ty` initialized by the string literal `expr`, padded with
+/// zeros. The null terminator is left out if the array is exactly as long as the string.
+pub(super) fn string_init_bytes(
+    ty: Ty<'_>,
+    expr: &ast::Expr,
+    span: Span,
+) -> Result<Vec<u8>, Error> {
+    let (&TyKind::Array { len: Some(len), .. }, ast::Expr::Atom(ast::Atom::String(string))) =
+        (*ty, expr)
+    else {
+        unreachable!("only char arrays are initialized by a string literal");
+    };
+    if string.len() > len {
+        return Err(Error::new(
+            format!("initializer string is too long for {ty}"),
+            span,
+        ));
+    }
+    let mut bytes = string.clone();
+    bytes.resize(len, 0);
+    Ok(bytes)
+}
+
+struct Flattener<'a, 'lcx, 'cx> {
+    lcx: &'lcx LoweringCx<'cx>,
+    resolve: &'lcx dyn Fn(Symbol) -> Option<TyLayout<'cx>>,
+    elems: Vec<InitElem<'a, 'cx>>,
+}
+
+impl<'a, 'cx> Flattener<'a, '_, 'cx> {
+    /// Initializes an object of type `ty` from the elements of a list, taking as many of them as
+    /// it needs. Returns the type completed with the length of arrays of unknown size.
+    ///
+    /// Designators always refer to the object of the `braced` list, so objects whose braces were
+    /// elided stop at the next designated element. The remaining designators of an element that
+    /// has already been partially designated are used by the object they designate into.
+    fn fill(
+        &mut self,
+        ty: Ty<'cx>,
+        offset: u64,
+        cursor: &mut Cursor<'a>,
+        braced: bool,
+        span: Span,
+    ) -> Result<Ty<'cx>, Error> {
+        let mut i = 0;
+        let mut len = 0;
+        while cursor.peek().is_some() {
+            if let Some(designator) = cursor.designator() {
+                if cursor.designated == 0 && !braced {
+                    break;
+                }
+                i = self.designated_member(ty, designator)?;
+                cursor.designated += 1;
+            } else if i >= self.num_members(ty, span)? {
+                break;
+            }
+            let (member_offset, member_ty) = self.member(ty, i);
+            self.fill_one(member_ty, offset + member_offset, cursor, span)?;
+            i += 1;
+            len = len.max(i);
+        }
+
+        Ok(match **ty {
+            TyKind::Array { elem, len: None } => self.lcx.intern_ty(TyKind::Array {
+                elem,
+                len: Some(len),
+            }),
+            _ => ty,
+        })
+    }
+
+    /// Initializes a part of type `ty` from the next element of a list. Aggregates without their
+    /// own braces take the next elements of the enclosing list instead.
+    fn fill_one(
+        &mut self,
+        ty: Ty<'cx>,
+        offset: u64,
+        cursor: &mut Cursor<'a>,
+        span: Span,
+    ) -> Result<(), Error> {
+        let Some(elem) = cursor.peek() else {
+            return Ok(());
+        };
+        match &elem.init {
+            // `.a.b = 1` designates further into `a`.
+            _ if cursor.designator().is_some() => {
+                self.fill(ty, offset, cursor, false, span)?;
+            }
+            (ast::Initializer::List(inner), inner_span) => {
+                cursor.next();
+                let mut inner = Cursor::new(inner);
+                self.fill(ty, offset, &mut inner, true, *inner_span)?;
+                if let Some(elem) = inner.next() {
+                    return Err(excess_elements(elem.init.1));
+                }
+            }
+            // (6.7.9p13, p14) Structs can be initialized by an expression of the struct type and
+            // `char` arrays by a string literal, otherwise the braces of aggregates are elided.
+            (ast::Initializer::Expr(expr), expr_span)
+                if self.is_aggregate(ty) && !self.initializes_whole(ty, expr, *expr_span) =>
+            {
+                self.fill(ty, offset, cursor, false, span)?;
+            }
+            (ast::Initializer::Expr(expr), expr_span) => {
+                cursor.next();
+                let size = self.lcx.layout_of(ty).layout.size;
+                // Initializers that are overridden by a later designator are never used.
+                self.elems.retain(|elem| {
+                    let elem_size = self.lcx.layout_of(elem.ty).layout.size;
+                    elem.offset < offset || elem.offset + elem_size > offset + size
+                });
+                self.elems.push(InitElem {
+                    offset,
+                    ty,
+                    init: Some((expr, *expr_span)),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn is_aggregate(&self, ty: Ty<'cx>) -> bool {
+        matches!(
+            **ty,
+            TyKind::Array { .. } | TyKind::Struct(_) | TyKind::Union(_)
+        )
+    }
+
+    /// Whether `expr` initializes the whole aggregate of type `ty` instead of its first member.
+    /// Expressions whose type can't be computed don't, their errors are reported once they are
+    /// lowered.
+    fn initializes_whole(&self, ty: Ty<'cx>, expr: &ast::Expr, span: Span) -> bool {
+        match **ty {
+            TyKind::Array { .. } => string_init_ty(self.lcx, ty, expr).is_some(),
+            _ => self
+                .lcx
+                .type_of_expr(expr, span, self.resolve)
+                .is_ok_and(|expr_ty| expr_ty == ty),
+        }
+    }
+
+    /// The number of members that are initialized one after another: the elements of an array,
+    /// the fields of a struct, the first variant of a union or a scalar itself.
+    fn num_members(&self, ty: Ty<'cx>, span: Span) -> Result<usize, Error> {
+        Ok(match **ty {
+            TyKind::Array { len, .. } => len.unwrap_or(usize::MAX),
+            TyKind::Struct(ref strukt) => match strukt.fields.get() {
+                Some(fields) => fields.len(),
+                None => {
+                    return Err(Error::new(
+                        format!("cannot initialize incomplete type {ty}"),
+                        span,
+                    ))
+                }
+            },
+            TyKind::Union(ref union) => union.variants.len().min(1),
+            _ => 1,
+        })
+    }
+
+    /// The offset and type of the `i`th member as counted by [`Self::num_members`].
+    fn member(&self, ty: Ty<'cx>, i: usize) -> (u64, Ty<'cx>) {
+        match **ty {
+            TyKind::Array { elem, .. } => (i as u64 * self.lcx.layout_of(elem).layout.size, elem),
+            TyKind::Struct(ref strukt) => {
+                let field = *strukt.fields.get().unwrap().keys().nth(i).unwrap();
+                let (field_offset, field_tyl) = self.lcx.field_offset(strukt, field).unwrap();
+                (field_offset, field_tyl.ty)
+            }
+            TyKind::Union(ref union) => (0, *union.variants.values().nth(i).unwrap()),
+            _ => (0, ty),
+        }
+    }
+
+    /// The member of `ty` designated by `designator`.
+    fn designated_member(&self, ty: Ty<'cx>, designator: &ast::Designator) -> Result<usize, Error> {
+        match (&**ty, designator) {
+            (TyKind::Array { len, .. }, ast::Designator::Index((index, index_span))) => {
+                let value = eval_const_expr(self.lcx, index, *index_span)?;
+                if value < 0 || len.is_some_and(|len| value >= len as i128) {
+                    return Err(Error::new(
+                        format!("array index {value} is out of bounds for type {ty}"),
+                        *index_span,
+                    ));
+                }
+                Ok(value as usize)
+            }
+            (TyKind::Struct(strukt), ast::Designator::Field((field, field_span))) => strukt
+                .fields
+                .get()
+                .and_then(|fields| fields.get_index_of(field))
+                .ok_or_else(|| Error::new(format!("no field {field} on type {ty}"), *field_span)),
+            (TyKind::Union(union), ast::Designator::Field((field, field_span))) => union
+                .variants
+                .get_index_of(field)
+                .ok_or_else(|| Error::new(format!("no field {field} on type {ty}"), *field_span)),
+            (_, ast::Designator::Index((_, span)) | ast::Designator::Field((_, span))) => Err(
+                Error::new(format!("designator does not match the type {ty}"), *span),
+            ),
+        }
+    }
+
+    /// Whether a zero-initialized part overlaps with a part mentioned by the initializer.
+    fn overlaps_explicit(&self, zero: &InitElem<'_, 'cx>) -> bool {
+        let size = self.lcx.layout_of(zero.ty).layout.size;
+        self.elems.iter().any(|elem| {
+            let elem_size = self.lcx.layout_of(elem.ty).layout.size;
+            elem.offset < zero.offset + size && zero.offset < elem.offset + elem_size
+        })
+    }
+
+    fn zero(&self, elems: &mut Vec<InitElem<'a, 'cx>>, ty: Ty<'cx>, offset: u64) {
+        match **ty {
+            TyKind::Array { elem, len } => {
+                let elem_size = self.lcx.layout_of(elem).layout.size;
+                for i in 0..len.unwrap_or(0) {
+                    self.zero(elems, elem, offset + i as u64 * elem_size);
+                }
+            }
+            TyKind::Struct(ref strukt) => {
+                for &field in strukt
+                    .fields
+                    .get()
+                    .into_iter()
+                    .flat_map(|fields| fields.keys())
+                {
+                    let (field_offset, field_tyl) = self.lcx.field_offset(strukt, field).unwrap();
+                    self.zero(elems, field_tyl.ty, offset + field_offset);
+                }
+            }
+            TyKind::Union(ref union) => {
+                if let Some(&variant) = union.variants.values().next() {
+                    self.zero(elems, variant, offset);
+                }
+            }
+            _ => elems.push(InitElem {
+                offset,
+                ty,
+                init: None,
+            }),
+        }
+    }
+}
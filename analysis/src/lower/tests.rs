@@ -0,0 +1,2239 @@
+use parser::{ast, Error, Span, Symbol};
+
+use crate::{
+    ir::{self, pretty::DefaultCustomizer},
+    ty::TyKind,
+    LoweringCx,
+};
+
+fn lower_with_warnings(src: &str) -> (String, Vec<Error>) {
+    let ast = parser::parse_file(src).unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+
+    let mut funcs = ir.funcs.iter().collect::<Vec<_>>();
+    funcs.sort_by_key(|(def_id, _)| def_id.0);
+    let ir = funcs
+        .into_iter()
+        .map(|(_, func)| ir::func_to_string(func, &DefaultCustomizer::default()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (ir, lcx.take_warnings())
+}
+
+fn lower_errors(src: &str) -> Vec<Error> {
+    let ast = parser::parse_file(src).unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+
+    match super::lower_translation_unit(&mut lcx, &ast) {
+        Ok(_) => panic!("expected lowering to fail"),
+        Err(errs) => errs,
+    }
+}
+
+fn lower_error(src: &str) -> Error {
+    let mut errs = lower_errors(src);
+    assert_eq!(errs.len(), 1, "expected a single error: {errs:?}");
+    errs.remove(0)
+}
+
+fn lower_to_string(src: &str) -> String {
+    let (ir, warnings) = lower_with_warnings(src);
+    assert!(warnings.is_empty(), "unexpected warnings: {warnings:?}");
+    ir
+}
+
+#[test]
+fn sizeof_type() {
+    let ir = lower_to_string("unsigned long f() { return sizeof(int) + sizeof(char*); }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %0 = add 4, 8
+    ret %0
+}
+"
+    );
+}
+
+#[test]
+fn sizeof_expr_is_not_evaluated() {
+    let ir = lower_to_string(
+        "unsigned long f() { int x = 0; long y[3]; return sizeof(x = 1) + sizeof y; }",
+    );
+    // The assignment must not show up, and the array must not decay.
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %x = alloca, size=4, align=4
+    store %x, 0, size=4, align=4
+    %y = alloca, size=24, align=8
+    %2 = add 4, 24
+    ret %2
+}
+"
+    );
+
+    // The operand has no side effects at compile time either: no warning and no string literal.
+    let ast =
+        parser::parse_file("unsigned long f() { char c; return sizeof(c = 300) + sizeof \"hi\"; }")
+            .unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    assert!(ir.data.is_empty());
+    assert!(lcx.take_warnings().is_empty());
+    let f = ir::func_to_string(
+        ir.funcs.values().next().unwrap(),
+        &DefaultCustomizer::default(),
+    );
+    assert!(f.contains("add 1, 3"), "{f}");
+}
+
+#[test]
+fn comma_in_subscript() {
+    let (ir, warnings) = lower_with_warnings("int f() { int a[3]; return a[1, 2]; }");
+    // The `1` is discarded and only `2` is used as the index.
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %a = alloca, size=12, align=4
+    %1 = mul 2, 4
+    %2 = ptroffset %a, %1
+    %3 = load %2, size=4, align=4
+    ret %3
+}
+"
+    );
+
+    let [warning] = &*warnings else {
+        panic!("expected a single warning, found {warnings:?}");
+    };
+    assert_eq!(
+        warning.msg,
+        "left operand of comma operator in array subscript is discarded"
+    );
+}
+
+#[test]
+fn subscript_index_is_widened() {
+    // The index is extended to pointer width before it is scaled, so it can't overflow.
+    let ir = lower_to_string("int f(int *p, char i) { return p[i]; }");
+    assert_eq!(
+        ir,
+        "def f(int * %p, char %i) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %i.local = alloca, size=1, align=1
+    store %i.local, %i, size=1, align=1
+    %4 = load %p.local, size=8, align=8
+    %5 = load %i.local, size=1, align=1
+    %6 = sext %5
+    %7 = mul %6, 4
+    %8 = ptroffset %4, %7
+    %9 = load %8, size=4, align=4
+    ret %9
+}
+"
+    );
+}
+
+#[test]
+fn subscript_assignment() {
+    let ir = lower_to_string("void f(int *p) { p[1] = 2; p[0]++; }");
+    assert_eq!(
+        ir,
+        "def f(int * %p) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %2 = load %p.local, size=8, align=8
+    %3 = mul 1, 4
+    %4 = ptroffset %2, %3
+    store %4, 2, size=4, align=4
+    %5 = load %p.local, size=8, align=8
+    %6 = mul 0, 4
+    %7 = ptroffset %5, %6
+    %8 = load %7, size=4, align=4
+    %9 = add %8, 1
+    store %7, %9, size=4, align=4
+    ret void
+}
+"
+    );
+
+    let err = lower_error("int f() { 1 = 2; return 0; }");
+    assert_eq!(err.msg, "expression is not assignable");
+}
+
+#[test]
+fn null_pointer_constant() {
+    let ir = lower_to_string("int *f() { return 0; }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    ret null
+}
+"
+    );
+
+    // Any integer constant expression with the value 0 is one.
+    let ir = lower_to_string(
+        "int f(int *q) { int *p = 1 - 1; p = '\\0'; return q == sizeof(int) - 4; }",
+    );
+    assert!(ir.contains("store %p, null"), "{ir}");
+    assert!(!ir.contains("store %p, 0"), "{ir}");
+    assert!(
+        ir.lines()
+            .any(|line| line.contains(" = eq %") && line.ends_with(", null")),
+        "{ir}"
+    );
+}
+
+#[test]
+fn null_pointer_comparison() {
+    let ir = lower_to_string("int f(int *p) { return p == 0; }");
+    assert_eq!(
+        ir,
+        "def f(int * %p) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %2 = load %p.local, size=8, align=8
+    %3 = eq %2, null
+    ret %3
+}
+"
+    );
+}
+
+#[test]
+fn int_to_pointer_without_cast() {
+    let err = lower_error("int f() { int *p = 5; return 0; }");
+    assert_eq!(err.msg, "cannot convert int to int * without a cast");
+
+    let err = lower_error("int f(int *p) { int x = p; return x; }");
+    assert_eq!(err.msg, "cannot convert int * to int without a cast");
+}
+
+#[test]
+fn null_pointer_initializer() {
+    let ir = lower_to_string("int f() { int *p = 0; return 0; }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %p = alloca, size=8, align=8
+    store %p, null, size=8, align=8
+    ret 0
+}
+"
+    );
+}
+
+#[test]
+fn string_literal_data() {
+    let ast = parser::parse_file(r#"char *f() { char *a = "hi"; return "hi"; }"#).unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+
+    let data = ir.data.values().collect::<Vec<_>>();
+    assert_eq!(data, [&&b"hi\0"[..]]);
+}
+
+#[test]
+fn call() {
+    let ir = lower_to_string(
+        "long add(long a, long b) { return a; }
+         long f(int x) { return add(x, 2); }",
+    );
+    // The argument gets extended to the parameter type and the call returns a long.
+    assert_eq!(
+        ir,
+        "def add(long %a, long %b) {
+  bb0:
+    %a.local = alloca, size=8, align=8
+    store %a.local, %a, size=8, align=8
+    %b.local = alloca, size=8, align=8
+    store %b.local, %b, size=8, align=8
+    %4 = load %a.local, size=8, align=8
+    ret %4
+}
+
+def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %2 = load %x.local, size=4, align=4
+    %3 = sext %2
+    %4 = call {0} (%3, 2)
+    ret %4
+}
+"
+    );
+}
+
+#[test]
+fn call_wrong_arity() {
+    let err = lower_error(
+        "int g(int a) { return a; }
+         int f() { return g(1, 2); }",
+    );
+    assert_eq!(err.msg, "function takes 1 arguments, but 2 were supplied");
+}
+
+#[test]
+fn call_non_function() {
+    let err = lower_error("int f(int x) { return x(1); }");
+    assert_eq!(err.msg, "cannot call value of type int");
+    assert_eq!(err.span, Some(parser::Span::start_end(22, 23)));
+}
+
+#[test]
+fn function_signature() {
+    let ast = parser::parse_file("long f(long a, int b[]) { return f(a, b); }").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+
+    super::lower_translation_unit(&mut lcx, &ast).unwrap();
+
+    let ptr_int = lcx.intern_ty(TyKind::Ptr(lcx.types.int.signed, ast::Qualifiers::empty()));
+    let params = [lcx.types.long.signed, ptr_int];
+    let expected = lcx.intern_ty(TyKind::Func {
+        ret: lcx.types.long.signed,
+        params: Some(&params),
+        variadic: false,
+    });
+
+    let tyl = lcx.global_decls[&Symbol::intern("f")].tyl;
+    assert_eq!(tyl.ty, expected);
+    assert_eq!(tyl.ty.to_string(), "long (long, int *)");
+    assert_eq!(tyl.layout.size, 8);
+}
+
+#[test]
+fn common_arith_type() {
+    let arena = bumpalo::Bump::new();
+    let lcx = LoweringCx::new(&arena);
+    let types = &lcx.types;
+
+    assert_eq!(lcx.promote(types.char), types.int.signed);
+    assert_eq!(lcx.promote(types.short.unsigned), types.int.signed);
+    assert_eq!(lcx.promote(types.long.unsigned), types.long.unsigned);
+
+    let common = |a, b| lcx.common_arith_type(a, b, Span::dummy()).unwrap();
+    assert_eq!(common(types.char, types.char), types.int.signed);
+    assert_eq!(
+        common(types.int.signed, types.int.unsigned),
+        types.int.unsigned
+    );
+    assert_eq!(
+        common(types.int.signed, types.long.signed),
+        types.long.signed
+    );
+    assert_eq!(
+        common(types.int.unsigned, types.long.signed),
+        types.long.signed
+    );
+    assert_eq!(
+        common(types.long.signed, types.long.unsigned),
+        types.long.unsigned
+    );
+
+    let err = lower_error("int f(double d, int i) { return d + i; }");
+    assert_eq!(err.msg, "arithmetic on double is not supported yet");
+}
+
+#[test]
+fn shift_types() {
+    // The result has the promoted type of the left operand, so `x >> 1u` is an arithmetic shift.
+    let ir = lower_to_string(
+        "int f(int x) { return x >> 1u; }
+         int g(char c, long n) { return c << n; }",
+    );
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %2 = load %x.local, size=4, align=4
+    %3 = shr %2, 1
+    ret %3
+}
+
+def g(char %c, long %n) {
+  bb0:
+    %c.local = alloca, size=1, align=1
+    store %c.local, %c, size=1, align=1
+    %n.local = alloca, size=8, align=8
+    store %n.local, %n, size=8, align=8
+    %4 = load %c.local, size=1, align=1
+    %5 = load %n.local, size=8, align=8
+    %6 = sext %4
+    %7 = trunc %5
+    %8 = shl %6, %7
+    ret %8
+}
+"
+    );
+}
+
+#[test]
+fn arith_conversions() {
+    let ir = lower_to_string("int f(int a, unsigned char b) { return a + b; }");
+    assert_eq!(
+        ir,
+        "def f(int %a, unsigned char %b) {
+  bb0:
+    %a.local = alloca, size=4, align=4
+    store %a.local, %a, size=4, align=4
+    %b.local = alloca, size=1, align=1
+    store %b.local, %b, size=1, align=1
+    %4 = load %a.local, size=4, align=4
+    %5 = load %b.local, size=1, align=1
+    %6 = zext %5
+    %7 = add %4, %6
+    ret %7
+}
+"
+    );
+}
+
+#[test]
+fn signed_and_unsigned_comparisons() {
+    let unsigned = lower_to_string("int f(unsigned int a, unsigned int b) { return a < b; }");
+    assert!(unsigned.contains("= ult %"), "{unsigned}");
+    let signed = lower_to_string("int f(int a, int b) { return a < b; }");
+    assert!(signed.contains("= slt %"), "{signed}");
+    // The signed operand is converted to unsigned int.
+    let mixed = lower_to_string("int f(int a, unsigned int b) { return a / b; }");
+    assert!(mixed.contains("= udiv %"), "{mixed}");
+
+    let unsigned = lower_to_string("int f() { return -1u < 1u; }");
+    assert!(unsigned.contains("= ult %"), "{unsigned}");
+    let signed = lower_to_string("int f() { return -1 < 1; }");
+    assert!(signed.contains("= slt %"), "{signed}");
+}
+
+#[test]
+fn multiple_errors() {
+    let errs = lower_errors("int f() { int a = x; a = y; return a; }\nint g() { return z; }");
+    let msgs = errs.iter().map(|err| &*err.msg).collect::<Vec<_>>();
+    assert_eq!(
+        msgs,
+        [
+            "cannot find variable x",
+            "cannot find variable y",
+            "cannot find variable z"
+        ]
+    );
+}
+
+#[test]
+fn static_assert() {
+    let ir = lower_to_string(
+        r#"_Static_assert(sizeof(int) == 4 && 1 << 3 == 8, "int is 4 bytes");
+int f() { _Static_assert(2 > 1, "inside"); return 0; }"#,
+    );
+    assert!(ir.starts_with("def f() {"), "{ir}");
+
+    let err = lower_error(r#"_Static_assert(sizeof(char*) == 4, "32 bit");"#);
+    assert_eq!(err.msg, "static assertion failed: 32 bit");
+    let err = lower_error(r#"int f(int x) { _Static_assert(x, "not constant"); return x; }"#);
+    assert_eq!(err.msg, "cannot read variable x in a constant expression");
+}
+
+fn eval_const(src: &str) -> Result<i128, Error> {
+    let ast = parser::parse_file(&format!("int x = {src};")).unwrap();
+    let ast::ExternalDecl::Decl(decl) = &ast[0].0 else {
+        unreachable!()
+    };
+    let Some((ast::Initializer::Expr(expr), span)) =
+        &decl.unwrap_normal().init_declarators[0].0.init
+    else {
+        unreachable!()
+    };
+
+    let arena = bumpalo::Bump::new();
+    let lcx = LoweringCx::new(&arena);
+    super::eval_const_expr(&lcx, expr, *span)
+}
+
+#[test]
+fn const_expr() {
+    assert_eq!(eval_const("2 * 3 + 1").unwrap(), 7);
+    assert_eq!(eval_const("(1 << 4) - sizeof(long) == 8").unwrap(), 1);
+    assert_eq!(eval_const("0 && 1 / 0").unwrap(), 0);
+    // Operands are converted to a common type and wrap around at its width.
+    assert_eq!(eval_const("-1u > 0").unwrap(), 1);
+    assert_eq!(eval_const("-1u").unwrap(), 0xffff_ffff);
+    assert_eq!(eval_const("0u - 1 == 4294967295u").unwrap(), 1);
+    assert_eq!(eval_const("-1 >> 1u").unwrap(), -1);
+    assert_eq!(eval_const("2147483647 + 1").unwrap(), -2147483648);
+    assert_eq!(eval_const("sizeof(int) - 5 > 0").unwrap(), 1);
+    assert_eq!(
+        eval_const("1 << 32").unwrap_err().msg,
+        "invalid shift amount 32"
+    );
+
+    let err = eval_const("1 + f()").unwrap_err();
+    assert_eq!(err.msg, "expression is not an integer constant expression");
+    // Points into `f()`.
+    let span = err.span.unwrap();
+    assert!(span.start >= 12 && span.end <= 15, "{span:?}");
+    assert_eq!(eval_const("1 / 0").unwrap_err().msg, "division by zero");
+}
+
+#[test]
+fn array_size_const_expr() {
+    let ir = lower_to_string("unsigned long f() { int a[2 * 3 + 1]; return sizeof a; }");
+    assert!(ir.contains("alloca, size=28, align=4"), "{ir}");
+}
+
+#[test]
+fn switch_fallthrough() {
+    let ir = lower_to_string(
+        "int f(int x) {
+            int y = 0;
+            switch (x) {
+                case 1: y = 1;
+                case 2: y = y + 2; break;
+                default: y = 5;
+            }
+            return y;
+        }",
+    );
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %y = alloca, size=4, align=4
+    store %y, 0, size=4, align=4
+    %3 = load %x.local, size=4, align=4
+    %6 = eq %3, 1
+    switch %6, then bb3, else bb7
+
+  bb1:
+    %8 = load %y, size=4, align=4
+    ret %8
+
+  bb2:
+    goto bb3
+
+  bb3:
+    store %y, 1, size=4, align=4
+    goto bb4
+
+  bb4:
+    %4 = load %y, size=4, align=4
+    %5 = add %4, 2
+    store %y, %5, size=4, align=4
+    goto bb1
+
+  bb5:
+    goto bb6
+
+  bb6:
+    store %y, 5, size=4, align=4
+    goto bb1
+
+  bb7:
+    %7 = eq %3, 2
+    switch %7, then bb4, else bb8
+
+  bb8:
+    goto bb6
+}
+"
+    );
+}
+
+#[test]
+fn switch_errors() {
+    let err = lower_error("int f(int x) { switch (x) { case 1: case 1: return 0; } return 1; }");
+    assert_eq!(err.msg, "duplicate case value 1");
+    let err = lower_error("int f(int x) { case 1: return 0; }");
+    assert_eq!(err.msg, "case label not within a switch statement");
+    let err = lower_error("int f(int x) { break; }");
+    assert_eq!(err.msg, "break statement not within a loop or switch");
+}
+
+#[test]
+fn goto_forward() {
+    let ir = lower_to_string(
+        "int f(int x) {
+            if (x) {
+                goto end;
+            }
+            x = 1;
+            end:
+            return x;
+        }",
+    );
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %2 = load %x.local, size=4, align=4
+    switch %2, then bb2, else bb3
+
+  bb1:
+    %3 = load %x.local, size=4, align=4
+    ret %3
+
+  bb2:
+    goto bb1
+
+  bb3:
+    store %x.local, 1, size=4, align=4
+    goto bb1
+
+  bb4:
+    goto bb3
+}
+"
+    );
+}
+
+#[test]
+fn goto_errors() {
+    let err = lower_error("int f() { goto nowhere; return 0; }");
+    assert_eq!(err.msg, "use of undeclared label nowhere");
+    assert_eq!(err.span, Some(parser::Span::start_end(10, 23)));
+    let err = lower_error("int f() { a: if (1) { a: return 0; } return 1; }");
+    assert_eq!(err.msg, "label a has already been defined");
+}
+
+#[test]
+fn call_variadic() {
+    let ir = lower_to_string(
+        "int sum(int n, ...) { return n; }
+        int f(long x) { return sum(2, x, 3); }",
+    );
+    assert!(ir.contains("def sum(int %n)"), "{ir}");
+    assert!(ir.contains("= call {0} (2, %2, 3)"), "{ir}");
+
+    let err = lower_error("int sum(int n, ...) { return n; } int f() { return sum(); }");
+    assert_eq!(
+        err.msg,
+        "function takes at least 1 arguments, but 0 were supplied"
+    );
+}
+
+#[test]
+fn default_argument_promotions() {
+    let ir = lower_to_string(
+        "int sum(int n, ...);
+        int f(char c, short s) { return sum(2, c, s); }",
+    );
+    assert_eq!(
+        ir,
+        "def f(char %c, short %s) {
+  bb0:
+    %c.local = alloca, size=1, align=1
+    store %c.local, %c, size=1, align=1
+    %s.local = alloca, size=2, align=2
+    store %s.local, %s, size=2, align=2
+    %4 = load %c.local, size=1, align=1
+    %5 = sext %4
+    %6 = load %s.local, size=2, align=2
+    %7 = sext %6
+    %8 = call {0} (2, %5, %7)
+    ret %8
+}
+"
+    );
+}
+
+#[test]
+fn assign_to_const() {
+    let err = lower_error("int f() { const int x = 0; x = 1; return x; }");
+    assert_eq!(err.msg, "cannot assign to const variable x");
+
+    let err = lower_error("int f(int const x) { return x = 1; }");
+    assert_eq!(err.msg, "cannot assign to const variable x");
+}
+
+#[test]
+fn nested_expr_types() {
+    let ast = parser::parse_file("long f(int a, long b, short c) { return (a + b) * c; }").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let func = ir.funcs.values().next().unwrap();
+    assert_eq!(
+        ir::func_to_string(func, &DefaultCustomizer::default()),
+        "def f(int %a, long %b, short %c) {
+  bb0:
+    %a.local = alloca, size=4, align=4
+    store %a.local, %a, size=4, align=4
+    %b.local = alloca, size=8, align=8
+    store %b.local, %b, size=8, align=8
+    %c.local = alloca, size=2, align=2
+    store %c.local, %c, size=2, align=2
+    %6 = load %a.local, size=4, align=4
+    %7 = load %b.local, size=8, align=8
+    %8 = sext %6
+    %9 = add %8, %7
+    %10 = load %c.local, size=2, align=2
+    %11 = sext %10
+    %12 = mul %9, %11
+    ret %12
+}
+"
+    );
+    // `a + b` is a long, so `c` is converted to long for the multiplication.
+    let long = lcx.types.long.signed;
+    for reg in [9, 11, 12] {
+        assert_eq!(func.regs[reg].tyl.ty, long);
+    }
+}
+
+#[test]
+fn volatile_survives_opts() {
+    let ast = parser::parse_file("int f() { volatile int x = 1; int y = 2; return x; }").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let mut ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    for func in ir.funcs.values_mut() {
+        crate::opt::mem2reg(func);
+        crate::opt::dce(func);
+    }
+    let func = ir.funcs.values().next().unwrap();
+    assert_eq!(
+        ir::func_to_string(func, &DefaultCustomizer::default()),
+        "def f() {
+  bb0:
+    %x = alloca, size=4, align=4
+    store %x, 1, size=4, align=4, volatile
+    %2 = load %x, size=4, align=4, volatile
+    ret %2
+}
+"
+    );
+}
+
+#[test]
+fn shadowed_local() {
+    let ir = lower_to_string("int f() { int x = 1; if (x) { int x = 2; } return x; }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %x = alloca, size=4, align=4
+    store %x, 1, size=4, align=4
+    %1 = load %x, size=4, align=4
+    %x.1 = alloca, size=4, align=4 ; x
+    switch %1, then bb1, else bb2
+
+  bb1:
+    store %x.1, 2, size=4, align=4
+    goto bb2
+
+  bb2:
+    %3 = load %x, size=4, align=4
+    ret %3
+}
+"
+    );
+}
+
+#[test]
+fn do_while() {
+    let ir = lower_to_string(
+        "int f(int n) { do { if (n == 3) continue; n = n - 1; } while (n); return n; }",
+    );
+    // The body comes first, the condition in bb2 jumps back to it.
+    assert_eq!(
+        ir,
+        "def f(int %n) {
+  bb0:
+    %n.local = alloca, size=4, align=4
+    store %n.local, %n, size=4, align=4
+    goto bb1
+
+  bb1:
+    %2 = load %n.local, size=4, align=4
+    %3 = eq %2, 3
+    switch %3, then bb4, else bb5
+
+  bb2:
+    %6 = load %n.local, size=4, align=4
+    switch %6, then bb1, else bb3
+
+  bb3:
+    %7 = load %n.local, size=4, align=4
+    ret %7
+
+  bb4:
+    goto bb2
+
+  bb5:
+    %4 = load %n.local, size=4, align=4
+    %5 = sub %4, 1
+    store %n.local, %5, size=4, align=4
+    goto bb2
+
+  bb6:
+    goto bb5
+}
+"
+    );
+
+    let err = lower_error("int f() { continue; }");
+    assert_eq!(err.msg, "continue statement not within a loop");
+    let err = lower_error("int f(int x) { switch (x) { default: continue; } return 0; }");
+    assert_eq!(err.msg, "continue statement not within a loop");
+}
+
+#[test]
+fn function_specifiers() {
+    let ast =
+        parser::parse_file("_Noreturn void f() { f(); } inline int g() { return 0; }").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let mut attrs = ir
+        .funcs
+        .values()
+        .map(|func| (func.name.to_string(), func.decl_attr))
+        .collect::<Vec<_>>();
+    attrs.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        attrs,
+        [
+            ("f".to_owned(), ast::DeclAttr::NORETURN),
+            ("g".to_owned(), ast::DeclAttr::INLINE)
+        ]
+    );
+
+    let err = lower_error("inline int x;");
+    assert_eq!(err.msg, "inline can only be used on functions");
+    let err = lower_error("int f() { _Noreturn int x; return 0; }");
+    assert_eq!(err.msg, "_Noreturn can only be used on functions");
+}
+
+#[test]
+fn struct_member_access() {
+    let ir = lower_to_string(
+        "struct P { char c; int x; };
+int f() { struct P p; p.x = 1; return p.x; }",
+    );
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %p = alloca, size=8, align=4
+    %1 = ptroffset %p, 4
+    store %1, 1, size=4, align=4
+    %2 = ptroffset %p, 4
+    %3 = load %2, size=4, align=4
+    ret %3
+}
+"
+    );
+}
+
+#[test]
+fn member_of_subscript() {
+    let ir = lower_to_string(
+        "struct P { char c; int x; };
+int f(int i) { struct P a[2]; a[i].x = 1; return a[i].x; }",
+    );
+    assert_eq!(
+        ir,
+        "def f(int %i) {
+  bb0:
+    %i.local = alloca, size=4, align=4
+    store %i.local, %i, size=4, align=4
+    %a = alloca, size=16, align=4
+    %3 = load %i.local, size=4, align=4
+    %4 = sext %3
+    %5 = mul %4, 8
+    %6 = ptroffset %a, %5
+    %7 = ptroffset %6, 4
+    store %7, 1, size=4, align=4
+    %8 = load %i.local, size=4, align=4
+    %9 = sext %8
+    %10 = mul %9, 8
+    %11 = ptroffset %a, %10
+    %12 = ptroffset %11, 4
+    %13 = load %12, size=4, align=4
+    ret %13
+}
+"
+    );
+}
+
+#[test]
+fn member_of_rvalue() {
+    // The returned struct is spilled to a temporary to access its field.
+    let ir = lower_to_string(
+        "struct P { char c; int x; };
+struct P g();
+int f() { return g().x; }",
+    );
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %0 = call {0} ()
+    %1 = alloca, size=8, align=4
+    store %1, %0, size=8, align=4
+    %2 = ptroffset %1, 4
+    %3 = load %2, size=4, align=4
+    ret %3
+}
+"
+    );
+
+    let err = lower_error("struct P { int x; }; struct P g(); void f() { g().x = 1; }");
+    assert_eq!(err.msg, "expression is not assignable");
+}
+
+#[test]
+fn struct_member_errors() {
+    let err = lower_error("struct P { int x; }; int f() { struct P p; return p.y; }");
+    assert_eq!(err.msg, "no field y on type struct P");
+
+    let err = lower_error("int f() { int i; return i.x; }");
+    assert_eq!(err.msg, "cannot access field x of non-struct type int");
+
+    let err = lower_error("int f() { struct Q q; return 0; }");
+    assert_eq!(err.msg, "cannot find struct Q");
+}
+
+#[test]
+fn recursive_struct() {
+    let err = lower_error("struct S { int x; struct S next; };");
+    assert_eq!(err.msg, "field next has incomplete type struct S");
+    assert_eq!(err.span, Some(parser::Span::start_end(27, 31)));
+
+    let ir = lower_to_string(
+        "struct S { int x; struct S *next; };
+int f(struct S *s) { return s->next->x; }",
+    );
+    assert_eq!(
+        ir,
+        "def f(struct S * %s) {
+  bb0:
+    %s.local = alloca, size=8, align=8
+    store %s.local, %s, size=8, align=8
+    %2 = load %s.local, size=8, align=8
+    %3 = ptroffset %2, 8
+    %4 = load %3, size=8, align=8
+    %5 = ptroffset %4, 0
+    %6 = load %5, size=4, align=4
+    ret %6
+}
+"
+    );
+}
+
+#[test]
+fn struct_arrow_member_access() {
+    let ir = lower_to_string(
+        "struct P { char c; int x; };
+int f(struct P *p) { p->x = 1; return p->x; }",
+    );
+    assert_eq!(
+        ir,
+        "def f(struct P * %p) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %2 = load %p.local, size=8, align=8
+    %3 = ptroffset %2, 4
+    store %3, 1, size=4, align=4
+    %4 = load %p.local, size=8, align=8
+    %5 = ptroffset %4, 4
+    %6 = load %5, size=4, align=4
+    ret %6
+}
+"
+    );
+
+    let err = lower_error("struct P { int x; }; int f(struct P p) { return p->x; }");
+    assert_eq!(err.msg, "cannot use -> on non-pointer type struct P");
+    let err = lower_error("int f(int *p) { return p->x; }");
+    assert_eq!(err.msg, "cannot use -> on pointer to non-struct type int");
+}
+
+#[test]
+fn pointer_arithmetic() {
+    let ir = lower_to_string("long *f(long *p) { return p + 1; }");
+    assert_eq!(
+        ir,
+        "def f(long * %p) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %2 = load %p.local, size=8, align=8
+    %3 = mul 1, 8
+    %4 = ptroffset %2, %3
+    ret %4
+}
+"
+    );
+
+    let ir = lower_to_string("long f(int *p, int *q) { return p - q; }");
+    assert_eq!(
+        ir,
+        "def f(int * %p, int * %q) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %q.local = alloca, size=8, align=8
+    store %q.local, %q, size=8, align=8
+    %4 = load %p.local, size=8, align=8
+    %5 = load %q.local, size=8, align=8
+    %6 = sub %4, %5
+    %7 = sdiv %6, 4
+    ret %7
+}
+"
+    );
+
+    let err = lower_error("int *f(int *p, int *q) { return p + q; }");
+    assert_eq!(err.msg, "cannot add two pointers");
+}
+
+#[test]
+fn widening_assignment() {
+    let ir = lower_to_string("long f(int x) { long y = x; y = x; return y; }");
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %y = alloca, size=8, align=8
+    %3 = load %x.local, size=4, align=4
+    %4 = sext %3
+    store %y, %4, size=8, align=8
+    %5 = load %x.local, size=4, align=4
+    %6 = sext %5
+    store %y, %6, size=8, align=8
+    %7 = load %y, size=8, align=8
+    ret %7
+}
+"
+    );
+}
+
+#[test]
+fn struct_to_int_assignment() {
+    let err = lower_error("struct P { int x; }; int f(struct P p) { int x = p; return x; }");
+    assert_eq!(err.msg, "mismatched types: expected int, found struct P");
+    assert_eq!(err.span, Some(parser::Span::start_end(49, 50)));
+}
+
+#[test]
+fn multiple_declarators() {
+    let ir = lower_to_string("int f() { int a, b, c; a = 1; b = 2; c = 3; return a; }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %a = alloca, size=4, align=4
+    %b = alloca, size=4, align=4
+    %c = alloca, size=4, align=4
+    store %a, 1, size=4, align=4
+    store %b, 2, size=4, align=4
+    store %c, 3, size=4, align=4
+    %3 = load %a, size=4, align=4
+    ret %3
+}
+"
+    );
+}
+
+#[test]
+fn multiple_globals() {
+    let ast = parser::parse_file("int a, b = 2; long c = 1 - 2; int f() { return b; }").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+
+    let mut data = ir.data.values().copied().collect::<Vec<_>>();
+    data.sort();
+    assert_eq!(data, [&[0, 0, 0, 0][..], &[2, 0, 0, 0], &[255; 8]],);
+
+    let err = lower_error("int a; long a;");
+    assert_eq!(err.msg, "variable a has already been declared");
+
+    // All declarators share the struct, also if it has no tag.
+    lower_to_string("struct P { int x; } a, b; struct { int y; } c, d; void f() { a = b; c = d; }");
+}
+
+#[test]
+fn function_def_with_multiple_declarators() {
+    let err = lower_error("int a, f() { return 0; }");
+    assert_eq!(
+        err.msg,
+        "function definition must declare exactly one function"
+    );
+}
+
+#[test]
+fn int_constant_types() {
+    let ty_of = |constant: &str| {
+        let err = lower_error(&format!("int f() {{ int *p = {constant}; return 0; }}"));
+        err.msg
+            .strip_prefix("cannot convert ")
+            .and_then(|msg| msg.strip_suffix(" to int * without a cast"))
+            .unwrap_or_else(|| panic!("unexpected error: {}", err.msg))
+            .to_owned()
+    };
+    assert_eq!(ty_of("1"), "int");
+    assert_eq!(ty_of("1u"), "unsigned int");
+    assert_eq!(ty_of("1l"), "long");
+    assert_eq!(ty_of("1ul"), "unsigned long");
+    assert_eq!(ty_of("1ll"), "long long");
+    assert_eq!(ty_of("2147483648"), "long");
+    assert_eq!(ty_of("0x80000000"), "unsigned int");
+    assert_eq!(ty_of("0xFFFFFFFFFFFFFFFF"), "unsigned long");
+    assert_eq!(ty_of("4294967296u"), "unsigned long");
+
+    let err = lower_error("int f() { return 0x10000000000000000; }");
+    assert_eq!(
+        err.msg,
+        "integer constant 18446744073709551616 is too large for unsigned long long"
+    );
+    assert_eq!(err.span, Some(parser::Span::start_end(17, 36)));
+
+    let err = lower_error("int f() { return 9223372036854775808; }");
+    assert_eq!(
+        err.msg,
+        "integer constant 9223372036854775808 is too large for long long"
+    );
+}
+
+#[test]
+fn float_constant_types() {
+    let err = lower_error("int f() { return 1.5f; }");
+    assert_eq!(err.msg, "constants of type float are not supported yet");
+    let err = lower_error("int f() { return .5; }");
+    assert_eq!(err.msg, "constants of type double are not supported yet");
+    let err = lower_error("int f() { return 1e3L; }");
+    assert_eq!(
+        err.msg,
+        "constants of type long double are not supported yet"
+    );
+}
+
+#[test]
+fn global_address_initializer() {
+    let ast = parser::parse_file(
+        "int g = 1; int *p = &g; int *q = 0; char *s = \"hi\"; int arr[2]; int *a = arr;",
+    )
+    .unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let global = |name: &str| match lcx.global_decls[&Symbol::intern(name)].kind {
+        ir::VariableInfoKind::Static { def_id } => def_id,
+        _ => unreachable!(),
+    };
+
+    assert_eq!(ir.relocations[&global("p")], [(0, global("g"), 0)]);
+    assert_eq!(ir.data[&global("p")], [0; 8]);
+    assert!(!ir.relocations.contains_key(&global("q")));
+    let (_, s_target, _) = ir.relocations[&global("s")][0];
+    assert_eq!(ir.data[&s_target], b"hi\0");
+    assert_eq!(ir.relocations[&global("a")], [(0, global("arr"), 0)]);
+
+    let ast =
+        parser::parse_file("int arr[4]; int *p = &arr[2]; int f() { return arr[3]; }").unwrap();
+    let mut lcx = LoweringCx::new(&arena);
+    let mut ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let arr = match lcx.global_decls[&Symbol::intern("arr")].kind {
+        ir::VariableInfoKind::Static { def_id } => def_id,
+        _ => unreachable!(),
+    };
+    let p = match lcx.global_decls[&Symbol::intern("p")].kind {
+        ir::VariableInfoKind::Static { def_id } => def_id,
+        _ => unreachable!(),
+    };
+    assert_eq!(ir.relocations[&p], [(0, arr, 8)]);
+    // Inside of functions, the offset is folded by the optimizer.
+    let f = ir.funcs.values_mut().next().unwrap();
+    crate::opt::const_fold(f);
+    let load_ptr = f.bbs[0].statements.iter().find_map(|stmt| match stmt.kind {
+        ir::StatementKind::Load { ptr, .. } => Some(ptr),
+        _ => None,
+    });
+    assert!(matches!(
+        load_ptr,
+        Some(ir::Operand::Const(ir::ConstValue::StaticPtr(def_id, 12))) if def_id == arr
+    ));
+
+    // Pointing one past the end is fine, but not further.
+    lower_to_string("int arr[4]; int *p = &arr[4];");
+    let err = lower_error("int arr[4]; int *p = &arr[5];");
+    assert_eq!(err.msg, "array index 5 is out of bounds for type int [4]");
+    let err = lower_error("int arr[4]; int *p = &arr[-1];");
+    assert_eq!(err.msg, "array index -1 is out of bounds for type int [4]");
+    let err = lower_error("int f() { int a[2]; return a[3]; }");
+    assert_eq!(err.msg, "array index 3 is out of bounds for type int [2]");
+    lower_to_string("int f(int i) { int a[2]; a[i] = 0; a[1] = 0; return 0; }");
+
+    let err = lower_error("int g; int *p = &g + 1;");
+    assert_eq!(err.msg, "cannot read variable g in a constant expression");
+    let err = lower_error("int *p = 1;");
+    assert_eq!(err.msg, "integer other than 0 is not an address constant");
+
+    // The address has to match the type of the pointer.
+    let err = lower_error("int *p = \"hi\";");
+    assert_eq!(err.msg, "cannot convert char * to int * without a cast");
+    let err = lower_error("int f(); int *p = f;");
+    assert_eq!(err.msg, "cannot convert int (*)() to int * without a cast");
+    let err = lower_error("int i; char *p = &i;");
+    assert_eq!(err.msg, "cannot convert int * to char * without a cast");
+    let err = lower_error("const int c = 1; int *p = &c;");
+    assert_eq!(
+        err.msg,
+        "conversion from const int * to int * discards qualifiers"
+    );
+    lower_to_string("int i; void *p = &i; const int *q = &i;");
+}
+
+#[test]
+fn mixed_pointer_declarators() {
+    let (ir, warnings) = lower_with_warnings("int f() { int *a, b; int c[2], *d; return b; }");
+    assert_eq!(warnings[0].msg, "variable b is used uninitialized");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %a = alloca, size=8, align=8
+    %b = alloca, size=4, align=4
+    %c = alloca, size=8, align=4
+    %d = alloca, size=8, align=8
+    %4 = load %b, size=4, align=4
+    ret %4
+}
+"
+    );
+}
+
+#[test]
+fn undefined_variables_in_one_expression() {
+    let errs = lower_errors("int f(int *p) { int x = a + b; p = c; return d * 2; }");
+    let errs = errs.iter().map(|err| err.msg.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        errs,
+        [
+            "cannot find variable a",
+            "cannot find variable b",
+            "cannot find variable c",
+            "cannot find variable d"
+        ]
+    );
+
+    // Using the undefined variable doesn't report more errors.
+    let errs = lower_errors("int f() { *c = 1; c->x = 2; c[0] = 3; return c(4).x + c[i]; }");
+    let errs = errs.iter().map(|err| err.msg.as_str()).collect::<Vec<_>>();
+    let mut expected = vec!["cannot find variable c"; 5];
+    expected.push("cannot find variable i");
+    assert_eq!(errs, expected);
+
+    // Other errors in subexpressions don't stop the rest of the statement from being checked
+    // either.
+    let errs = lower_errors(
+        "struct P { int x; }; int f(struct P p, int i, int *q) { int x = *i + p.y + (1 ? q : p.z); return x; }",
+    );
+    let errs = errs.iter().map(|err| err.msg.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        errs,
+        [
+            "cannot dereference value of type int",
+            "no field y on type struct P",
+            "no field z on type struct P"
+        ]
+    );
+}
+
+#[test]
+fn narrowing_warnings() {
+    let (_, warnings) = lower_with_warnings("void f() { char c = 300; }");
+    let [warning] = &*warnings else {
+        panic!("expected a single warning, found {warnings:?}");
+    };
+    assert_eq!(
+        warning.msg,
+        "implicit conversion from int to char changes value from 300 to 44"
+    );
+    assert_eq!(warning.span, Some(parser::Span::start_end(20, 23)));
+    // The stored constant is truncated like a value computed at runtime would be.
+    let (ir, _) = lower_with_warnings("void f() { char c = 300; }");
+    assert!(ir.contains("store %c, 44, size=1, align=1"), "{ir}");
+
+    let (ir, warnings) = lower_with_warnings("int f(long l) { short s = l; return l; }");
+    assert_eq!(
+        ir,
+        "def f(long %l) {
+  bb0:
+    %l.local = alloca, size=8, align=8
+    store %l.local, %l, size=8, align=8
+    %s = alloca, size=2, align=2
+    %3 = load %l.local, size=8, align=8
+    %4 = trunc %3
+    store %s, %4, size=2, align=2
+    %5 = load %l.local, size=8, align=8
+    %6 = trunc %5
+    ret %6
+}
+"
+    );
+    let warnings = warnings.iter().map(|w| w.msg.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "implicit conversion from long to short may change the value",
+            "implicit conversion from long to int may change the value"
+        ]
+    );
+
+    lower_to_string("void f(int i) { char c = 'a'; unsigned char u = -1; long l = i; c = 127; }");
+    // Constants are evaluated in their own type before they are converted.
+    let (_, warnings) = lower_with_warnings(
+        "void f() { unsigned char u = -200; unsigned char v = -1u; int i = 4294967295u; }",
+    );
+    let warnings = warnings.iter().map(|w| w.msg.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "implicit conversion from int to unsigned char changes value from -200 to 56",
+            "implicit conversion from unsigned int to unsigned char changes value from 4294967295 to 255",
+            "implicit conversion from unsigned int to int changes value from 4294967295 to -1",
+        ]
+    );
+    // The local `g` shadows the global array, so its size fits.
+    lower_to_string("char g[300]; void f() { char g; unsigned char c = sizeof g; }");
+}
+
+#[test]
+fn empty_for_body() {
+    let ir = lower_to_string("void f() { for (;;) ; }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    goto bb1
+
+  bb1:
+    goto bb2
+
+  bb2:
+    goto bb1
+
+  bb3:
+    unreachable
+}
+"
+    );
+
+    let err = lower_error("int f() { for (int i = 0; i < 2; i = i + 1) ; return i; }");
+    assert_eq!(err.msg, "cannot find variable i");
+}
+
+#[test]
+fn deterministic_output() {
+    let src = "int g = 1; char *s = \"s\"; int b(int x) { return x + g; } \
+               int a(int y) { if (y) { return b(y); } int z = y * 2; return z; } \
+               void c() { int x; int x2; }";
+    let lower = || {
+        let ast = parser::parse_file(src).unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+        ir::pretty::ir_to_string(&ir, &DefaultCustomizer::default())
+    };
+    let first = lower();
+    assert_eq!(first, lower());
+    // Functions are printed in definition order.
+    let b = first.find("def b(").unwrap();
+    let a = first.find("def a(").unwrap();
+    let c = first.find("def c(").unwrap();
+    assert!(b < a && a < c, "{first}");
+}
+
+#[test]
+fn bool_conversion() {
+    let ir = lower_to_string("void f(int x, int *p) { _Bool b = 5; b = x; b = p; b = 0; }");
+    assert_eq!(
+        ir,
+        "def f(int %x, int * %p) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %b = alloca, size=1, align=1
+    store %b, 1, size=1, align=1
+    %5 = load %x.local, size=4, align=4
+    %6 = neq %5, 0
+    store %b, %6, size=1, align=1
+    %7 = load %p.local, size=8, align=8
+    %8 = neq %7, null
+    store %b, %8, size=1, align=1
+    store %b, 0, size=1, align=1
+    ret void
+}
+"
+    );
+}
+
+#[test]
+fn parameters() {
+    let ir = lower_to_string("int add(int a, int b) { return a + b; }");
+    assert_eq!(
+        ir,
+        "def add(int %a, int %b) {
+  bb0:
+    %a.local = alloca, size=4, align=4
+    store %a.local, %a, size=4, align=4
+    %b.local = alloca, size=4, align=4
+    store %b.local, %b, size=4, align=4
+    %4 = load %a.local, size=4, align=4
+    %5 = load %b.local, size=4, align=4
+    %6 = add %4, %5
+    ret %6
+}
+"
+    );
+
+    let err = lower_error("int f(int a, int a) { return a; }");
+    assert_eq!(err.msg, "parameter a has already been declared");
+}
+
+#[test]
+fn unspecified_params() {
+    let ast = parser::parse_file("int f() { return 0; }\nint g(void) { return 0; }").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    assert_eq!(
+        lcx.global_decls[&Symbol::intern("f")].tyl.ty.to_string(),
+        "int ()"
+    );
+    assert_eq!(
+        lcx.global_decls[&Symbol::intern("g")].tyl.ty.to_string(),
+        "int (void)"
+    );
+
+    lower_to_string("int f() { return 0; }\nint main() { return f(1, 2); }");
+
+    let err = lower_error("int g(void) { return 0; }\nint main() { return g(1); }");
+    assert_eq!(err.msg, "function takes 0 arguments, but 1 were supplied");
+}
+
+#[test]
+fn void_pointer_conversions() {
+    lower_to_string(
+        "int f(void *v) {
+    int *p = v;
+    void *q = p;
+    char *c = q;
+    return 0;
+}",
+    );
+
+    let err = lower_error(
+        "int g() { return 0; }
+int f() { void *v = g; return 0; }",
+    );
+    assert_eq!(err.msg, "cannot convert int (*)() to void * without a cast");
+    let err = lower_error("int f(long *q) { int *p = q; return 0; }");
+    assert_eq!(err.msg, "cannot convert long * to int * without a cast");
+}
+
+#[test]
+fn while_loop() {
+    let ir = lower_to_string("int f(int x) { while (x) { x = x - 1; } return x; }");
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    goto bb1
+
+  bb1:
+    %2 = load %x.local, size=4, align=4
+    switch %2, then bb2, else bb3
+
+  bb2:
+    %3 = load %x.local, size=4, align=4
+    %4 = sub %3, 1
+    store %x.local, %4, size=4, align=4
+    goto bb1
+
+  bb3:
+    %5 = load %x.local, size=4, align=4
+    ret %5
+}
+"
+    );
+}
+
+#[test]
+fn pointer_to_const() {
+    // The pointer itself can be changed, the int it points to can't.
+    lower_to_string("void f(const int *p, const int *q) { p = q; }");
+    let err = lower_error("void f(const int *p) { *p = 1; }");
+    assert_eq!(err.msg, "cannot assign to const-qualified location");
+
+    let err = lower_error("void f(const int *p) { int *q = p; }");
+    assert_eq!(
+        err.msg,
+        "conversion from const int * to int * discards qualifiers"
+    );
+    lower_to_string("void f(int *p) { const int *q = p; }");
+}
+
+#[test]
+fn const_pointer() {
+    // The int can be changed through the pointer, the pointer itself can't.
+    let ir = lower_to_string("void f(int *const p) { *p = 1; }");
+    assert_eq!(
+        ir,
+        "def f(int * %p) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %2 = load %p.local, size=8, align=8
+    store %2, 1, size=4, align=4
+    ret void
+}
+"
+    );
+    let err = lower_error("void f(int *const p, int *q) { p = q; }");
+    assert_eq!(err.msg, "cannot assign to const variable p");
+
+    let err = lower_error("int f(int x) { return *x; }");
+    assert_eq!(err.msg, "cannot dereference value of type int");
+}
+
+#[test]
+fn static_assert_in_for() {
+    lower_to_string(r#"int f() { for (_Static_assert(1, "ok");;) return 0; }"#);
+
+    let err = lower_error(r#"int f() { for (_Static_assert(0, "in for");;) return 0; }"#);
+    assert_eq!(err.msg, "static assertion failed: in for");
+    let err = lower_error(r#"int f() { if (1) _Static_assert(0, "in if"); return 0; }"#);
+    assert_eq!(err.msg, "static assertion failed: in if");
+}
+
+#[test]
+fn array_initializer() {
+    let ir = lower_to_string("int f() { int a[3] = { 1, 2 }; return a[2]; }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %a = alloca, size=12, align=4
+    store %a, 1, size=4, align=4
+    %1 = ptroffset %a, 4
+    store %1, 2, size=4, align=4
+    %2 = ptroffset %a, 8
+    store %2, 0, size=4, align=4
+    %3 = mul 2, 4
+    %4 = ptroffset %a, %3
+    %5 = load %4, size=4, align=4
+    ret %5
+}
+"
+    );
+}
+
+#[test]
+fn struct_initializer() {
+    let ir = lower_to_string(
+        "struct P { char c; int *p; int a[2]; };
+        int f(char x) { struct P p = { x, 0, { 3 } }; return p.c; }",
+    );
+    assert_eq!(
+        ir,
+        "def f(char %x) {
+  bb0:
+    %x.local = alloca, size=1, align=1
+    store %x.local, %x, size=1, align=1
+    %p = alloca, size=24, align=8
+    %3 = load %x.local, size=1, align=1
+    store %p, %3, size=1, align=1
+    %4 = ptroffset %p, 8
+    store %4, null, size=8, align=8
+    %5 = ptroffset %p, 16
+    store %5, 3, size=4, align=4
+    %6 = ptroffset %p, 20
+    store %6, 0, size=4, align=4
+    %7 = ptroffset %p, 0
+    %8 = load %7, size=1, align=1
+    %9 = sext %8
+    ret %9
+}
+"
+    );
+
+    let err = lower_error("struct P { int x; }; int f() { struct P p = { 1, 2 }; return 0; }");
+    assert_eq!(err.msg, "excess elements in initializer");
+}
+
+#[test]
+fn initializer_sizes_and_globals() {
+    let ast = parser::parse_file(
+        "int a[] = { 1, 2, 3 }; int b[2][2] = { 1, 2, { 3 } }; int f() { int c[] = { 0, 0 }; return sizeof c; }",
+    )
+    .unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let mut data = ir
+        .data
+        .values()
+        .map(|data| data.to_vec())
+        .collect::<Vec<_>>();
+    data.sort();
+    assert_eq!(
+        data,
+        [
+            vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0],
+            vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0]
+        ]
+    );
+    let ir = ir::pretty::ir_to_string(&ir, &DefaultCustomizer::default());
+    assert!(ir.contains("ret 8"), "{ir}");
+}
+
+#[test]
+fn designated_array_initializer() {
+    let ir = lower_to_string("int f() { int a[4] = { [3] = 9 }; return a[0]; }");
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %a = alloca, size=16, align=4
+    %1 = ptroffset %a, 12
+    store %1, 9, size=4, align=4
+    store %a, 0, size=4, align=4
+    %2 = ptroffset %a, 4
+    store %2, 0, size=4, align=4
+    %3 = ptroffset %a, 8
+    store %3, 0, size=4, align=4
+    %4 = mul 0, 4
+    %5 = ptroffset %a, %4
+    %6 = load %5, size=4, align=4
+    ret %6
+}
+"
+    );
+
+    // Elements after a designator continue from there, the size comes from the largest index.
+    let ast = parser::parse_file("char a[] = { [2] = 5, 6, [0] = 1 };").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let data = ir.data.values().next().unwrap();
+    assert_eq!(data[..], [1, 0, 5, 6]);
+}
+
+#[test]
+fn designated_struct_initializer() {
+    let ir = lower_to_string(
+        "struct P { int x; int y; int z; };
+        int f() { struct P p = { .y = 2, 3 }; return p.x; }",
+    );
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %p = alloca, size=12, align=4
+    %1 = ptroffset %p, 4
+    store %1, 2, size=4, align=4
+    %2 = ptroffset %p, 8
+    store %2, 3, size=4, align=4
+    store %p, 0, size=4, align=4
+    %3 = ptroffset %p, 0
+    %4 = load %3, size=4, align=4
+    ret %4
+}
+"
+    );
+}
+
+#[test]
+fn designated_initializer_errors() {
+    let err = lower_error("int a[4] = { [4] = 1 };");
+    assert_eq!(err.msg, "array index 4 is out of bounds for type int [4]");
+    let err = lower_error("struct P { int x; }; struct P p = { .y = 1 };");
+    assert_eq!(err.msg, "no field y on type struct P");
+    let err = lower_error("int x = { .y = 1 };");
+    assert_eq!(err.msg, "designator does not match the type int");
+}
+
+#[test]
+fn struct_brace_elision() {
+    let ast = parser::parse_file(
+        "struct P { int x; int y; };
+        struct P ps[2] = { 1, 2, 3, 4 };
+        struct { struct P p; char z; } s = { 5, 6, 7 };",
+    )
+    .unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let mut data = ir
+        .data
+        .values()
+        .map(|data| data.to_vec())
+        .collect::<Vec<_>>();
+    data.sort();
+    assert_eq!(
+        data,
+        [
+            vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0],
+            vec![5, 0, 0, 0, 6, 0, 0, 0, 7, 0, 0, 0],
+        ]
+    );
+
+    // A struct expression initializes the whole member instead of its first field.
+    let ir = lower_to_string(
+        "struct P { int x; int y; }; struct Q { struct P p; int z; };
+        int f(struct P p) { struct Q q = { p, 3 }; return q.z; }",
+    );
+    assert!(ir.contains("store %q, %"), "{ir}");
+}
+
+#[test]
+fn string_initializer() {
+    let ast =
+        parser::parse_file("char s[] = \"hi\"; char t[2] = \"hi\"; int n = sizeof s;").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let mut data = ir
+        .data
+        .values()
+        .map(|data| data.to_vec())
+        .collect::<Vec<_>>();
+    data.sort();
+    assert_eq!(
+        data,
+        [vec![3, 0, 0, 0], vec![b'h', b'i'], vec![b'h', b'i', 0]]
+    );
+
+    let ir = lower_to_string(
+        "struct S { char name[4]; int x; };
+        int f() { struct S s = { \"ab\", 1 }; return s.x; }",
+    );
+    assert!(
+        ir.contains(
+            "store %s, 97, size=1, align=1
+    %1 = ptroffset %s, 1
+    store %1, 98, size=1, align=1
+    %2 = ptroffset %s, 2
+    store %2, 0, size=1, align=1
+    %3 = ptroffset %s, 3
+    store %3, 0, size=1, align=1
+    %4 = ptroffset %s, 4
+    store %4, 1, size=4, align=4"
+        ),
+        "{ir}"
+    );
+
+    let err = lower_error("char s[2] = \"abc\";");
+    assert_eq!(err.msg, "initializer string is too long for char [2]");
+    let err = lower_error("int a[2] = \"ab\";");
+    assert_eq!(
+        err.msg,
+        "array of type int [2] must be initialized by a list"
+    );
+}
+
+#[test]
+fn unreachable_after_returning_arms() {
+    let ir = lower_to_string("int f(int x) { if (x) { return 1; } else { return 2; } }");
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %2 = load %x.local, size=4, align=4
+    switch %2, then bb1, else bb2
+
+  bb1:
+    ret 1
+
+  bb2:
+    ret 2
+
+  bb3:
+    unreachable
+
+  bb4:
+    goto bb3
+
+  bb5:
+    goto bb3
+}
+"
+    );
+}
+
+#[test]
+fn unreachable_after_noreturn_call() {
+    let ir = lower_to_string("_Noreturn void g() { for (;;); } int f() { g(); return 1; }");
+    assert_eq!(
+        ir,
+        "def g() {
+  bb0:
+    goto bb1
+
+  bb1:
+    goto bb2
+
+  bb2:
+    goto bb1
+
+  bb3:
+    unreachable
+}
+
+def f() {
+  bb0:
+    %0 = call {0} ()
+    unreachable
+
+  bb1:
+    ret 1
+}
+"
+    );
+}
+
+#[test]
+fn missing_return() {
+    let (_, warnings) = lower_with_warnings("int f(int x) { if (x) { return 1; } }");
+    assert_eq!(
+        warnings.iter().map(|w| &*w.msg).collect::<Vec<_>>(),
+        ["function f can reach its end without returning a value"]
+    );
+
+    // Every path returns, `main` returns 0 and `void` functions don't need to return.
+    lower_to_string("int f(int x) { if (x) { return 1; } else { return 2; } }");
+    lower_to_string("int main() {}");
+    lower_to_string("void f() {}");
+
+    let ast = parser::parse_file("int f() {}").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    lcx.strict = true;
+    let errs = super::lower_translation_unit(&mut lcx, &ast).unwrap_err();
+    assert_eq!(
+        errs[0].msg,
+        "function f can reach its end without returning a value"
+    );
+}
+
+#[test]
+fn uninitialized_local() {
+    let (_, warnings) = lower_with_warnings("int f() { int x; return x + x; }");
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (&*w.msg, w.span))
+            .collect::<Vec<_>>(),
+        [(
+            "variable x is used uninitialized",
+            Some(parser::Span::start_end(24, 25))
+        )]
+    );
+
+    let (_, warnings) = lower_with_warnings("int f(int c) { int x; if (c) { x = 1; } return x; }");
+    assert_eq!(warnings[0].msg, "variable x is used uninitialized");
+
+    lower_to_string("int f() { int x = 0; return x; }");
+    lower_to_string("int f(int c) { int x; if (c) { x = 1; } else { x = 2; } return x; }");
+    lower_to_string(
+        "int f() { int x; for (int i = 0; i < 2; i = i + 1) { x = i; } x = 2; return x; }",
+    );
+    // Members are accessed through the address of `s`, so it's assumed to be initialized.
+    lower_to_string("struct S { int a; }; int f() { struct S s; return s.a; }");
+}
+
+#[test]
+fn conditional_constant() {
+    let ast = parser::parse_file("int a[1 ? 2 : (1 / 0)]; int b[0 ? 1 / 0 : 3];").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    super::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let ty = |name: &str| lcx.global_decls[&Symbol::intern(name)].tyl.ty.to_string();
+    assert_eq!(ty("a"), "int [2]");
+    assert_eq!(ty("b"), "int [3]");
+
+    let err = lower_error("int f(int x) { _Static_assert(x ? 1 : 2, \"\"); return 0; }");
+    assert_eq!(err.msg, "cannot read variable x in a constant expression");
+}
+
+#[test]
+fn conditional() {
+    let ir = lower_to_string("long f(int x, long y) { return x ? y : 1; }");
+    assert_eq!(
+        ir,
+        "def f(int %x, long %y) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %y.local = alloca, size=8, align=8
+    store %y.local, %y, size=8, align=8
+    %4 = load %x.local, size=4, align=4
+    switch %4, then bb1, else bb2
+
+  bb1:
+    %5 = load %y.local, size=8, align=8
+    goto bb3
+
+  bb2:
+    goto bb3
+
+  bb3:
+    %6 = phi [bb1: %5], [bb2: 1]
+    ret %6
+}
+"
+    );
+
+    let err = lower_error("struct S { int a; }; int f(int x, struct S s) { return x ? 1 : s; }");
+    assert_eq!(err.msg, "incompatible operand types int and struct S");
+
+    // The operands are only lowered once, so their errors aren't reported twice.
+    let err = lower_error("int f(int x) { return x ? y : 1; }");
+    assert_eq!(err.msg, "cannot find variable y");
+}
+
+#[test]
+fn conditional_pointers() {
+    // The result points to a type with the qualifiers of both pointees.
+    lower_to_string("const int *f(int c, const int *p, int *q) { return c ? p : q; }");
+    let err = lower_error("int *f(int c, const int *p, int *q) { return c ? q : p; }");
+    assert_eq!(
+        err.msg,
+        "conversion from const int * to int * discards qualifiers"
+    );
+
+    // Mixing with `void *` gives `void *`.
+    lower_to_string("int *f(int c, void *p, int *q) { return c ? p : q; }");
+    let err = lower_error("int f(int c, void *p, int *q) { return *(c ? q : p); }");
+    assert_eq!(
+        err.msg,
+        "cannot dereference pointer to incomplete type void"
+    );
+
+    let err = lower_error("int f(int c, int *p, long *q) { c ? p : q; return 0; }");
+    assert_eq!(err.msg, "incompatible operand types int * and long *");
+}
+
+#[test]
+fn increment_decrement() {
+    let ir = lower_to_string("int f(int x) { int y = x++; --x; return y-- + ++y; }");
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %y = alloca, size=4, align=4
+    %3 = load %x.local, size=4, align=4
+    %4 = add %3, 1
+    store %x.local, %4, size=4, align=4
+    store %y, %3, size=4, align=4
+    %5 = load %x.local, size=4, align=4
+    %6 = sub %5, 1
+    store %x.local, %6, size=4, align=4
+    %7 = load %y, size=4, align=4
+    %8 = sub %7, 1
+    store %y, %8, size=4, align=4
+    %9 = load %y, size=4, align=4
+    %10 = add %9, 1
+    store %y, %10, size=4, align=4
+    %11 = add %7, %10
+    ret %11
+}
+"
+    );
+
+    let ir = lower_to_string("long *f(long *p) { p--; return ++p; }");
+    assert_eq!(
+        ir,
+        "def f(long * %p) {
+  bb0:
+    %p.local = alloca, size=8, align=8
+    store %p.local, %p, size=8, align=8
+    %2 = load %p.local, size=8, align=8
+    %3 = mul 1, 8
+    %4 = negate %3
+    %5 = ptroffset %2, %4
+    store %p.local, %5, size=8, align=8
+    %6 = load %p.local, size=8, align=8
+    %7 = mul 1, 8
+    %8 = ptroffset %6, %7
+    store %p.local, %8, size=8, align=8
+    ret %8
+}
+"
+    );
+
+    // Incrementing a _Bool always stores 1.
+    let ir = lower_to_string("void f(_Bool b) { b++; }");
+    assert_eq!(
+        ir,
+        "def f(_Bool %b) {
+  bb0:
+    %b.local = alloca, size=1, align=1
+    store %b.local, %b, size=1, align=1
+    %2 = load %b.local, size=1, align=1
+    %3 = zext %2
+    %4 = add %3, 1
+    %5 = neq %4, 0
+    store %b.local, %5, size=1, align=1
+    ret void
+}
+"
+    );
+
+    let err = lower_error("struct S { int x; }; void f(struct S s) { s++; }");
+    assert_eq!(err.msg, "cannot increment struct S");
+}
+
+#[test]
+fn else_if_chain() {
+    let ir = lower_to_string(
+        "int f(int x) {
+            int y;
+            if (x) y = 1;
+            else if (x - 1) y = 2;
+            else if (x - 2) y = 3;
+            else y = 4;
+            return y;
+        }",
+    );
+    // Every arm gets a block for its body and the next condition, all of them continue in bb3.
+    assert_eq!(
+        ir,
+        "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %y = alloca, size=4, align=4
+    %3 = load %x.local, size=4, align=4
+    switch %3, then bb1, else bb2
+
+  bb1:
+    store %y, 1, size=4, align=4
+    goto bb3
+
+  bb2:
+    %4 = load %x.local, size=4, align=4
+    %5 = sub %4, 1
+    switch %5, then bb4, else bb5
+
+  bb3:
+    %8 = load %y, size=4, align=4
+    ret %8
+
+  bb4:
+    store %y, 2, size=4, align=4
+    goto bb3
+
+  bb5:
+    %6 = load %x.local, size=4, align=4
+    %7 = sub %6, 2
+    switch %7, then bb6, else bb7
+
+  bb6:
+    store %y, 3, size=4, align=4
+    goto bb3
+
+  bb7:
+    store %y, 4, size=4, align=4
+    goto bb3
+}
+"
+    );
+}
+
+#[test]
+fn goto_skips_initializer() {
+    let src = "int f(int c) { if (c) goto end; int x = 1; end: return x; }";
+    let (_, warnings) = lower_with_warnings(src);
+    let goto_start = src.find("goto").unwrap();
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (&*w.msg, w.span))
+            .collect::<Vec<_>>(),
+        [
+            ("variable x is used uninitialized", warnings[0].span),
+            (
+                "goto skips the initialization of variable x",
+                Some(parser::Span::start_end(goto_start, goto_start + 9))
+            ),
+        ]
+    );
+
+    // Jumping backwards or to a label before the declaration is fine.
+    lower_to_string("int f(int c) { int x = 1; again: x = x - 1; if (x) goto again; return x; }");
+    lower_to_string("int f(int c) { if (c) goto end; end: c = 2; int x = c; return x; }");
+    // `x` is assigned again after the label, so the skipped initializer doesn't matter.
+    lower_to_string("int f(int c) { if (c) goto end; int x = 1; end: x = 2; return x; }");
+}
+
+#[test]
+fn static_local() {
+    let src =
+        "int f() { static int counter = 0; static long big = 1 << 4; counter++; return counter; }";
+    let ast = parser::parse_file(src).unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+
+    let mut data = ir.data.values().copied().collect::<Vec<_>>();
+    data.sort();
+    assert_eq!(data, [&[0, 0, 0, 0][..], &[16, 0, 0, 0, 0, 0, 0, 0]]);
+    assert!(!lcx.global_decls.contains_key(&Symbol::intern("counter")));
+
+    // The statics are accessed through their address instead of a stack slot.
+    let ir = lower_to_string(src);
+    assert_eq!(
+        ir,
+        "def f() {
+  bb0:
+    %0 = load {1}, size=4, align=4
+    %1 = add %0, 1
+    store {1}, %1, size=4, align=4
+    %2 = load {1}, size=4, align=4
+    ret %2
+}
+"
+    );
+
+    let err = lower_error("int f(int a) { static int s = a; return 0; }");
+    assert_eq!(err.msg, "cannot read variable a in a constant expression");
+}
+
+#[test]
+fn extern_declaration() {
+    let ast = parser::parse_file("extern int x; int f() { x = 1; return x; }").unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+    let ir::VariableInfoKind::Static { def_id: x } = lcx.global_decls[&Symbol::intern("x")].kind
+    else {
+        unreachable!()
+    };
+    assert_eq!(ir.externs[&x], Symbol::intern("x"));
+    assert!(!ir.data.contains_key(&x));
+    assert_eq!(
+        ir::ir_to_string(&ir, &DefaultCustomizer::default()),
+        "def f() {
+  bb0:
+    store {0}, 1, size=4, align=4
+    %0 = load {0}, size=4, align=4
+    ret %0
+}
+extern {0} x
+"
+    );
+
+    // A later definition fills in the extern, earlier uses point to the definition.
+    let ast = parser::parse_file("extern int a[]; int f() { return a[1]; } int a[2] = { 1, 2 };")
+        .unwrap();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+    assert!(ir.externs.is_empty());
+    assert_eq!(ir.data.len(), 1);
+
+    let err = lower_error("extern int x; long x;");
+    assert_eq!(err.msg, "conflicting types for x: int and long");
+    let err = lower_error("int x = 1; int x = 2;");
+    assert_eq!(err.msg, "variable x has already been declared");
+    lower_to_string("int x; extern int x; extern int x;");
+}
+
+#[test]
+fn function_prototype() {
+    let src = "int f(int x); int g() { return f(1); } int f(int a) { return a; }";
+    let ast = parser::parse_file(src).unwrap();
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+    assert!(ir.externs.is_empty());
+    // The call refers to the definition.
+    assert_eq!(
+        ir::ir_to_string(&ir, &DefaultCustomizer::default()),
+        "def f(int %a) {
+  bb0:
+    %a.local = alloca, size=4, align=4
+    store %a.local, %a, size=4, align=4
+    %2 = load %a.local, size=4, align=4
+    ret %2
+}
+def g() {
+  bb0:
+    %0 = call {0} (1)
+    ret %0
+}
+"
+    );
+
+    // Unspecified parameters are compatible with parameters that aren't promoted.
+    lower_to_string("int f(); int f(int a, long b) { return a; }");
+    let err = lower_error("int f(); int f(char c) { return c; }");
+    assert_eq!(err.msg, "conflicting types for f: int () and int (char)");
+    let err = lower_error("int f(); int f(int a, ...) { return a; }");
+    assert_eq!(
+        err.msg,
+        "conflicting types for f: int () and int (int, ...)"
+    );
+    // Empty parentheses in a definition mean that there are no parameters.
+    lower_to_string("int f(void); int f() { return 0; }");
+    let err = lower_error("int f(int a); int f() { return 0; }");
+    assert_eq!(err.msg, "conflicting types for f: int (int) and int ()");
+    // The composite type keeps the parameters of the prototype.
+    let err = lower_error("int f(); int f(int a); int g() { return f(); }");
+    assert_eq!(err.msg, "function takes 1 arguments, but 0 were supplied");
+    // A prototype without a definition is an extern.
+    let ast = parser::parse_file("void h(void); void g() { h(); }").unwrap();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+    assert_eq!(
+        ir.externs.values().collect::<Vec<_>>(),
+        [&Symbol::intern("h")]
+    );
+
+    let src = "int f(int x); int f(long a) { return 0; }";
+    let err = lower_error(src);
+    assert_eq!(err.msg, "conflicting types for f: int (int) and int (long)");
+    assert_eq!(err.span, Some(parser::Span::start_end(18, 19)));
+
+    let err = lower_error("int f(int x); long f(int x);");
+    assert_eq!(err.msg, "conflicting types for f: int (int) and long (int)");
+}
@@ -1,178 +1,501 @@
-use std::cmp::Ordering;
-
 use parser::{
-    ast::{IntSign, IntTy, IntTyKind},
-    Error, Span,
+    ast::{self, IntSign, IntTy, IntTyKind},
+    Error, Span, Symbol,
 };
-use smallvec::{smallvec, SmallVec};
-
-use super::{FnLoweringCtxt, Result};
-use crate::ty::{Ty, TyKind};
 
-pub(super) type Coercions<'cx> = SmallVec<[(Coercion, Ty<'cx>); 2]>;
+use super::{composite_ty, FnLoweringCtxt, Result};
+use crate::{
+    ir::{BinKind, ConstValue, Operand, TyLayout},
+    ty::{Ty, TyKind},
+    LoweringCx,
+};
 
-pub(super) enum Coercion {
-    ZeroExt,
-    SignExt,
-    SignToUnsigned,
-}
+impl<'cx> LoweringCx<'cx> {
+    /// §6.3.1.1 Boolean, characters, and integers
+    /// Integer types with a lower rank than `int` are promoted to `int`, which can represent all
+    /// of their values. Other types are left alone.
+    pub(crate) fn promote(&self, ty: Ty<'cx>) -> Ty<'cx> {
+        match *ty {
+            TyKind::Char => self.types.int.signed,
+            TyKind::Int(IntTy(_, kind)) if *kind < IntTyKind::Int => self.types.int.signed,
+            TyKind::Enum(_) => todo!("enums are unimplemented"),
+            _ => ty,
+        }
+    }
 
-pub(super) struct ArithCoerce<'cx> {
-    pub result: Ty<'cx>,
-    pub lhs: Coercions<'cx>,
-    pub rhs: Coercions<'cx>,
-}
+    /// §6.4.4.1 Integer constants
+    /// The type of an integer constant is the first of its candidate types that can represent
+    /// the value. Decimal constants only have unsigned candidates with an `u` suffix.
+    pub(crate) fn int_constant_ty(&self, int: &ast::IntConstant, span: Span) -> Result<Ty<'cx>> {
+        let signs: &[IntSign] = match (int.unsigned, int.decimal) {
+            (true, _) => &[IntSign::Unsigned],
+            (false, true) => &[IntSign::Signed],
+            (false, false) => &[IntSign::Signed, IntSign::Unsigned],
+        };
+        let mut largest = None;
+        for kind in [IntTyKind::Int, IntTyKind::Long, IntTyKind::LongLong] {
+            if kind < int.kind {
+                continue;
+            }
+            for &sign in signs {
+                let ty = self.intern_ty(TyKind::Int(IntTy(sign, kind)));
+                let bits = self.layout_of(ty).layout.size * 8;
+                let max = if sign.signed() {
+                    (1_u128 << (bits - 1)) - 1
+                } else {
+                    (1_u128 << bits) - 1
+                };
+                if int.value <= max {
+                    return Ok(ty);
+                }
+                largest = Some(ty);
+            }
+        }
+        Err(Error::new(
+            format!(
+                "integer constant {} is too large for {}",
+                int.value,
+                largest.unwrap()
+            ),
+            span,
+        ))
+    }
 
-impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
-    /// Performs implicit coercion.
     /// §6.3.1.8 Usual arithmetic conversions
-    pub(super) fn arith_op(
-        &mut self,
+    /// The type that both operands of an arithmetic operation are converted to. Floating point
+    /// arithmetic is not supported yet.
+    pub(crate) fn common_arith_type(
+        &self,
         lhs: Ty<'cx>,
         rhs: Ty<'cx>,
         span: Span,
-    ) -> Result<(Ty<'cx>, Coercions<'cx>, Coercions<'cx>)> {
-        Ok(match (*lhs, *rhs) {
-            (TyKind::LongDouble, _) => (lhs, smallvec![], self.coerce(rhs, lhs)?),
-            (_, TyKind::LongDouble) => (rhs, self.coerce(lhs, rhs)?, smallvec![]),
-            (TyKind::Double, _) => (lhs, smallvec![], self.coerce(rhs, lhs)?),
-            (_, TyKind::Double) => (rhs, self.coerce(lhs, rhs)?, smallvec![]),
-            (TyKind::Float, _) => (lhs, smallvec![], self.coerce(rhs, lhs)?),
-            (_, TyKind::Float) => (rhs, self.coerce(lhs, rhs)?, smallvec![]),
-            (_, _) => {
-                let mut lhs_coerce = self.promote(lhs, span)?;
-                let mut rhs_coerce = self.promote(rhs, span)?;
-
-                let lhs_prom = lhs_coerce.last().map_or(lhs, |(_, ty)| *ty);
-                let rhs_prom = rhs_coerce.last().map_or(rhs, |(_, ty)| *ty);
-                let lhs_prom_int = lhs_prom.unwrap_int();
-                let rhs_prom_int = rhs_prom.unwrap_int();
-
-                let lhs_sign = lhs_prom_int.0;
-                let rhs_sign = rhs_prom_int.0;
-
-                let lhs_kind = lhs_prom_int.1;
-                let rhs_kind = rhs_prom_int.1;
-
-                // If both operands have the same type, then no further conversion is needed.
-                let result = if lhs_prom == rhs_prom {
-                    lhs
-                // Otherwise, if both operands have signed integer types or both have unsigned
-                // integer types, the operand with the type of lesser integer conversion rank is
-                // converted to the type of the operand with greater rank.
-                } else if (lhs_sign.unsigned() && rhs_prom_int.0.unsigned())
-                    || (lhs_sign.signed() && rhs_sign.signed())
-                {
-                    match lhs_kind.cmp(&rhs_kind) {
-                        Ordering::Greater => {
-                            rhs_coerce.extend(self.coerce(rhs_prom, lhs_prom)?);
-                            lhs
+    ) -> Result<Ty<'cx>> {
+        if let Some(float) = [lhs, rhs].into_iter().find(|ty| ty.is_floating()) {
+            return Err(Error::new(
+                format!("arithmetic on {float} is not supported yet"),
+                span,
+            ));
+        }
+
+        let lhs = self.promote(lhs);
+        let rhs = self.promote(rhs);
+        let IntTy(lhs_sign, lhs_kind) = lhs.unwrap_int();
+        let IntTy(rhs_sign, rhs_kind) = rhs.unwrap_int();
+
+        // If both operands have the same type, then no further conversion is needed.
+        if lhs == rhs {
+            return Ok(lhs);
+        }
+
+        // Otherwise, if both operands have signed integer types or both have unsigned
+        // integer types, the operand with the type of lesser integer conversion rank is
+        // converted to the type of the operand with greater rank.
+        if lhs_sign == rhs_sign {
+            return Ok(if lhs_kind > rhs_kind { lhs } else { rhs });
+        }
+
+        let (signed, unsigned) = if lhs_sign.signed() {
+            (lhs, rhs)
+        } else {
+            (rhs, lhs)
+        };
+        let signed_kind = signed.unwrap_int().1;
+
+        // Otherwise, if the operand that has unsigned integer type has rank greater or
+        // equal to the rank of the type of the other operand, then the operand with
+        // signed integer type is converted to the type of the operand with unsigned
+        // integer type.
+        if unsigned.unwrap_int().1 >= signed_kind {
+            return Ok(unsigned);
+        }
+
+        // Otherwise, if the type of the operand with signed integer type can represent
+        // all of the values of the type of the operand with unsigned integer type, then
+        // the operand with unsigned integer type is converted to the type of the
+        // operand with signed integer type.
+        if self.layout_of(signed).layout.size > self.layout_of(unsigned).layout.size {
+            return Ok(signed);
+        }
+
+        // Otherwise, both operands are converted to the unsigned integer type
+        // corresponding to the type of the operand with signed integer type.
+        Ok(self.intern_ty(TyKind::Int(IntTy(IntSign::Unsigned, signed_kind))))
+    }
+
+    /// (6.5.15p6) The type of a conditional with two pointer operands. The result points to a type
+    /// with the qualifiers of both pointees, and to `void` if one of them does.
+    pub(crate) fn composite_ptr_ty(&self, a: Ty<'cx>, b: Ty<'cx>) -> Option<Ty<'cx>> {
+        let (&TyKind::Ptr(a_pointee, a_quals), &TyKind::Ptr(b_pointee, b_quals)) = (*a, *b) else {
+            return None;
+        };
+        let pointee = if matches!(*a_pointee, TyKind::Void) {
+            a_pointee
+        } else if matches!(*b_pointee, TyKind::Void) || a_pointee == b_pointee {
+            b_pointee
+        } else {
+            return None;
+        };
+        Some(self.intern_ty(TyKind::Ptr(pointee, a_quals | b_quals)))
+    }
+
+    /// Computes the type of an expression without lowering it, as required for `sizeof`.
+    /// Variables are looked up with `resolve`. Arrays don't decay at the top level, so `sizeof`
+    /// sees their full size.
+    pub(crate) fn type_of_expr(
+        &self,
+        expr: &ast::Expr,
+        span: Span,
+        resolve: &dyn Fn(Symbol) -> Option<TyLayout<'cx>>,
+    ) -> Result<Ty<'cx>> {
+        // Operands are used as values, so arrays decay to a pointer to their first element.
+        let operand = |expr: &ast::Expr, span: Span| -> Result<Ty<'cx>> {
+            let ty = self.type_of_expr(expr, span, resolve)?;
+            Ok(match **ty {
+                TyKind::Array { elem, .. } => {
+                    self.intern_ty(TyKind::Ptr(elem, ast::Qualifiers::empty()))
+                }
+                _ => ty,
+            })
+        };
+        let int = self.types.int.signed;
+
+        let ty = match expr {
+            ast::Expr::Atom(ast::Atom::Char(_)) => self.types.char,
+            ast::Expr::Atom(ast::Atom::Int(int)) => self.int_constant_ty(int, span)?,
+            ast::Expr::Atom(ast::Atom::Float(float)) => self.intern_ty(match float.kind {
+                ast::FloatKind::Float => TyKind::Float,
+                ast::FloatKind::Double => TyKind::Double,
+                ast::FloatKind::LongDouble => TyKind::LongDouble,
+            }),
+            ast::Expr::Atom(ast::Atom::String(string)) => self.intern_ty(TyKind::Array {
+                elem: self.types.char,
+                len: Some(string.len() + 1),
+            }),
+            &ast::Expr::Atom(ast::Atom::Ident((ident, ident_span))) => match resolve(ident) {
+                Some(tyl) => tyl.ty,
+                None => {
+                    return Err(Error::new(
+                        format!("cannot find variable {ident}"),
+                        ident_span,
+                    ))
+                }
+            },
+            ast::Expr::Unary(ast::ExprUnary { op, rhs }) => match op {
+                ast::UnaryOp::Deref => {
+                    let ptr = operand(&rhs.0, rhs.1)?;
+                    match **ptr {
+                        TyKind::Ptr(pointee, _) => pointee,
+                        _ => {
+                            return Err(Error::new(
+                                format!("cannot dereference value of type {ptr}"),
+                                rhs.1,
+                            ))
+                        }
+                    }
+                }
+                ast::UnaryOp::AddrOf => {
+                    let ty = self.type_of_expr(&rhs.0, rhs.1, resolve)?;
+                    self.intern_ty(TyKind::Ptr(ty, ast::Qualifiers::empty()))
+                }
+                ast::UnaryOp::Increment | ast::UnaryOp::Decrement => {
+                    self.type_of_expr(&rhs.0, rhs.1, resolve)?
+                }
+                ast::UnaryOp::Plus | ast::UnaryOp::Minus | ast::UnaryOp::Tilde => {
+                    self.promote(operand(&rhs.0, rhs.1)?)
+                }
+                ast::UnaryOp::Bang => int,
+            },
+            ast::Expr::Binary(ast::ExprBinary { op, lhs, rhs }) => match op {
+                ast::BinaryOp::Arith(arith) => {
+                    let lhs_ty = operand(&lhs.0, lhs.1)?;
+                    let rhs_ty = operand(&rhs.0, rhs.1)?;
+                    match arith {
+                        ast::ArithOpKind::Sub if lhs_ty.is_pointer() && rhs_ty.is_pointer() => {
+                            self.types.long.signed
                         }
-                        Ordering::Less => {
-                            lhs_coerce.extend(self.coerce(lhs_prom, rhs_prom)?);
-                            rhs
+                        ast::ArithOpKind::Add | ast::ArithOpKind::Sub if lhs_ty.is_pointer() => {
+                            lhs_ty
                         }
-                        Ordering::Equal => unreachable!("integers must have different rank here"),
+                        ast::ArithOpKind::Add if rhs_ty.is_pointer() => rhs_ty,
+                        ast::ArithOpKind::Shl | ast::ArithOpKind::Shr => self.promote(lhs_ty),
+                        _ => self.common_arith_type(lhs_ty, rhs_ty, span)?,
                     }
-
-                // Otherwise, if the operand that has unsigned integer type has rank greater or
-                // equal to the rank of the type of the other operand, then the operand with
-                // signed integer type is converted to the type of the operand with unsigned
-                // integer type.
-                } else if (lhs_sign.unsigned() && lhs_kind >= rhs_kind)
-                    || (rhs_sign.unsigned() && rhs_kind >= lhs_kind)
-                {
-                    if lhs_sign.unsigned() {
-                        rhs_coerce.extend(self.coerce(rhs_prom, lhs_prom)?);
-                        lhs
-                    } else {
-                        lhs_coerce.extend(self.coerce(lhs_prom, rhs_prom)?);
-                        rhs
+                }
+                ast::BinaryOp::Comparison(_)
+                | ast::BinaryOp::LogicalAnd
+                | ast::BinaryOp::LogicalOr => int,
+                ast::BinaryOp::Comma => operand(&rhs.0, rhs.1)?,
+                ast::BinaryOp::Index => {
+                    let ptr = operand(&lhs.0, lhs.1)?;
+                    match **ptr {
+                        TyKind::Ptr(elem, _) => elem,
+                        _ => {
+                            return Err(Error::new(
+                                format!("cannot index into value of type {ptr}"),
+                                lhs.1,
+                            ))
+                        }
                     }
-                // Otherwise, if the type of the operand with signed integer type can represent
-                // all of the values of the type of the operand with unsigned integer type, then
-                // the operand with unsigned integer type is converted to the type of the
-                // operand with signed integer type.
-                } else if (lhs_sign.unsigned() && is_int_bigger(rhs_kind, lhs_kind))
-                    || (rhs_sign.unsigned() && is_int_bigger(lhs_kind, rhs_kind))
+                }
+                // (6.5.16) The value of an assignment has the type of the left operand.
+                ast::BinaryOp::Assign(_) => self.type_of_expr(&lhs.0, lhs.1, resolve)?,
+            },
+            ast::Expr::Conditional(ast::ExprConditional {
+                then, otherwise, ..
+            }) => {
+                let then_ty = operand(&then.0, then.1)?;
+                let otherwise_ty = operand(&otherwise.0, otherwise.1)?;
+                // An integer next to a pointer is a null pointer constant.
+                if then_ty.is_arithmetic() && otherwise_ty.is_arithmetic() {
+                    self.common_arith_type(then_ty, otherwise_ty, span)?
+                } else if then_ty == otherwise_ty
+                    || (then_ty.is_pointer() && otherwise_ty.is_integer())
                 {
-                    if lhs_sign.unsigned() {
-                        lhs_coerce.extend(self.coerce(lhs_prom, rhs_prom)?);
-                        rhs
-                    } else {
-                        rhs_coerce.extend(self.coerce(rhs_prom, lhs_prom)?);
-                        lhs
-                    }
-                // Otherwise, both operands are converted to the unsigned integer type
-                // corresponding to the type of the operand with signed integer type.
+                    then_ty
+                } else if otherwise_ty.is_pointer() && then_ty.is_integer() {
+                    otherwise_ty
+                } else if let Some(ptr) = self.composite_ptr_ty(then_ty, otherwise_ty) {
+                    ptr
                 } else {
-                    let kind = if lhs_sign.signed() {
-                        lhs_kind
-                    } else {
-                        rhs_kind
-                    };
-                    let ty = self
-                        .lcx
-                        .intern_ty(TyKind::Int(IntTy(IntSign::Unsigned, kind)));
-                    lhs_coerce.extend(self.coerce(lhs_prom, ty)?);
-                    ty
-                };
-
-                (result, lhs_coerce, rhs_coerce)
+                    return Err(Error::new(
+                        format!("incompatible operand types {then_ty} and {otherwise_ty}"),
+                        span,
+                    ));
+                }
             }
-        })
+            ast::Expr::SizeOfExpr(_) | ast::Expr::SizeOfType(_) => self.types.long.unsigned,
+            ast::Expr::Postfix(ast::ExprPostfix { lhs, op }) => match op {
+                ast::PostfixOp::Call(_) => {
+                    let func = operand(&lhs.0, lhs.1)?;
+                    match **func {
+                        TyKind::Func { ret, .. } => ret,
+                        _ => {
+                            return Err(Error::new(
+                                format!("cannot call value of type {func}"),
+                                lhs.1,
+                            ))
+                        }
+                    }
+                }
+                ast::PostfixOp::Member(field) => {
+                    let strukt = self.type_of_expr(&lhs.0, lhs.1, resolve)?;
+                    self.field_ty(strukt, *field)?
+                }
+                ast::PostfixOp::ArrowMember(field) => {
+                    let ptr = operand(&lhs.0, lhs.1)?;
+                    match **ptr {
+                        TyKind::Ptr(strukt, _) => self.field_ty(strukt, *field)?,
+                        _ => {
+                            return Err(Error::new(
+                                format!("cannot use -> on non-pointer type {ptr}"),
+                                field.1,
+                            ))
+                        }
+                    }
+                }
+                ast::PostfixOp::Increment | ast::PostfixOp::Decrement => {
+                    self.type_of_expr(&lhs.0, lhs.1, resolve)?
+                }
+            },
+        };
+        Ok(ty)
     }
 
-    fn coerce(&mut self, from: Ty<'cx>, to: Ty<'cx>) -> Result<Coercions<'cx>> {
-        if from == to {
-            return Ok(smallvec![]);
+    /// The type of the field of a struct, for [`Self::type_of_expr`].
+    fn field_ty(&self, strukt: Ty<'cx>, (field, field_span): ast::Ident) -> Result<Ty<'cx>> {
+        let TyKind::Struct(struct_ty) = &**strukt else {
+            return Err(Error::new(
+                format!("cannot access field {field} of non-struct type {strukt}"),
+                field_span,
+            ));
+        };
+        match self.field_offset(struct_ty, field) {
+            Some((_, field_tyl)) => Ok(field_tyl.ty),
+            None => Err(Error::new(
+                format!("no field {field} on type {strukt}"),
+                field_span,
+            )),
         }
-        Ok(match (*from, *to) {
-            (TyKind::Char, TyKind::Int(IntTy(IntSign::Signed, _))) => {
-                smallvec![(Coercion::SignExt, to)]
+    }
+
+    /// §6.5.16.1 Simple assignment
+    /// Arithmetic types convert into each other, pointers can be assigned to pointers to
+    /// compatible types and structs and unions only to the same type. Pointers and integers don't
+    /// implicitly convert into each other. Null pointer constants must have been lowered as typed
+    /// null pointers already.
+    pub(crate) fn check_assign(&self, lhs: Ty<'cx>, rhs: Ty<'cx>, span: Span) -> Result<()> {
+        // (6.3.2.1) Function designators are converted to pointers to the function.
+        let rhs = match *rhs {
+            TyKind::Func { .. } => self.intern_ty(TyKind::Ptr(rhs, ast::Qualifiers::empty())),
+            _ => rhs,
+        };
+        // (6.3.2.3) `void *` converts to and from pointers to objects, but not to functions.
+        if let (TyKind::Ptr(lhs_pointee, lhs_quals), TyKind::Ptr(rhs_pointee, rhs_quals)) =
+            (*lhs, *rhs)
+        {
+            let void_and_func = |a: Ty<'_>, b: Ty<'_>| {
+                matches!(*a, TyKind::Void) && matches!(*b, TyKind::Func { .. })
+            };
+            if void_and_func(*lhs_pointee, *rhs_pointee)
+                || void_and_func(*rhs_pointee, *lhs_pointee)
+            {
+                return Err(Error::new(
+                    format!("cannot convert {rhs} to {lhs} without a cast"),
+                    span,
+                ));
             }
-            (
-                TyKind::Int(IntTy(IntSign::Signed, from_kind)),
-                TyKind::Int(IntTy(IntSign::Signed, to_kind)),
-            ) if from_kind < to_kind => {
-                smallvec![(Coercion::SignExt, to)]
+            // (6.5.16.1) The pointee on the left must have all the qualifiers of the one on the
+            // right, `const int *` doesn't convert to `int *`.
+            if !lhs_quals.contains(*rhs_quals) {
+                return Err(Error::new(
+                    format!("conversion from {rhs} to {lhs} discards qualifiers"),
+                    span,
+                ));
             }
-            (
-                TyKind::Int(IntTy(IntSign::Unsigned, from_kind)),
-                TyKind::Int(IntTy(IntSign::Unsigned, to_kind)),
-            ) if from_kind < to_kind => {
-                smallvec![(Coercion::ZeroExt, to)]
+            // Other than that, the pointees have to be compatible unless one of them is `void`.
+            let is_void = |ty: Ty<'_>| matches!(*ty, TyKind::Void);
+            if !is_void(*lhs_pointee)
+                && !is_void(*rhs_pointee)
+                && composite_ty(self, *lhs_pointee, *rhs_pointee).is_none()
+            {
+                return Err(Error::new(
+                    format!("cannot convert {rhs} to {lhs} without a cast"),
+                    span,
+                ));
             }
-            _ => panic!("i cant coerce that"),
-        })
+        }
+        // (6.3.1.2) Pointers can be converted to _Bool, they are true if they aren't null.
+        if lhs.is_bool() && rhs.is_pointer() {
+            return Ok(());
+        }
+        if (lhs.is_pointer() && rhs.is_integer()) || (lhs.is_integer() && rhs.is_pointer()) {
+            return Err(Error::new(
+                format!("cannot convert {rhs} to {lhs} without a cast"),
+                span,
+            ));
+        }
+        let compatible = (lhs.is_arithmetic() && rhs.is_arithmetic())
+            || (lhs.is_pointer() && rhs.is_pointer())
+            || (matches!(*lhs, TyKind::Struct(_) | TyKind::Union(_)) && lhs == rhs);
+        if !compatible {
+            return Err(Error::new(
+                format!("mismatched types: expected {lhs}, found {rhs}"),
+                span,
+            ));
+        }
+        Ok(())
     }
+}
 
-    // §6.3.1.1 Boolean, characters, and integers
-    fn promote(&self, ty: Ty<'cx>, span: Span) -> Result<Coercions<'cx>> {
-        Ok(match *ty {
-            TyKind::Char => smallvec![(Coercion::SignExt, self.lcx.types.int.signed)],
-            TyKind::Int(int) if int.1 < IntTyKind::Int => match int.0 {
-                IntSign::Signed => smallvec![(Coercion::SignExt, self.lcx.types.int.signed)],
-                IntSign::Unsigned => {
-                    smallvec![(Coercion::ZeroExt, self.lcx.types.int.unsigned)]
+impl<'a, 'cx> FnLoweringCtxt<'a, 'cx> {
+    /// Converts a value of type `from` to `to` as if by assignment.
+    /// Integers are extended to bigger types and truncated to smaller ones.
+    pub(super) fn convert(
+        &mut self,
+        op: Operand<'cx>,
+        from: Ty<'cx>,
+        to: Ty<'cx>,
+        span: Span,
+    ) -> Result<Operand<'cx>> {
+        if let Operand::Const(ConstValue::Undef) = op {
+            return Ok(op);
+        }
+        self.lcx.check_assign(to, from, span)?;
+
+        // (6.3.1.2) Any nonzero value becomes 1 when converted to _Bool.
+        if to.is_bool() && !from.is_bool() {
+            return Ok(match op {
+                Operand::Const(ConstValue::Int(int)) => {
+                    Operand::Const(ConstValue::Int((int != 0).into()))
                 }
-            },
-            TyKind::Int(_) => smallvec![],
-            TyKind::Enum(_) => todo!("enums are unimplemented"),
-            _ => return Err(Error::new(format!("cannot convert {ty} to integer"), span)),
-        })
+                Operand::Const(ConstValue::StaticPtr(..)) => Operand::Const(ConstValue::Int(1)),
+                Operand::Const(ConstValue::Null(_)) => Operand::Const(ConstValue::Int(0)),
+                _ => {
+                    let zero = match from.is_pointer() {
+                        true => ConstValue::Null(from),
+                        false => ConstValue::Int(0),
+                    };
+                    let to_tyl = self.lcx.layout_of(to);
+                    Operand::Reg(self.build.binary(
+                        BinKind::Neq,
+                        op,
+                        Operand::Const(zero),
+                        span,
+                        to_tyl,
+                    ))
+                }
+            });
+        }
+
+        if from.is_integer() && to.is_integer() {
+            let from_tyl = self.lcx.layout_of(from);
+            let to_tyl = self.lcx.layout_of(to);
+            match op {
+                Operand::Const(ConstValue::Int(int)) => {
+                    let int = convert_int_const(
+                        int,
+                        from_tyl.layout.size * 8,
+                        from.is_signed(),
+                        to_tyl.layout.size * 8,
+                    );
+                    return Ok(Operand::Const(ConstValue::Int(int)));
+                }
+                Operand::Const(_) => {}
+                Operand::Reg(_) if from_tyl.layout.size != to_tyl.layout.size => {
+                    return Ok(Operand::Reg(self.build.convert(from_tyl, to_tyl, op, span)));
+                }
+                Operand::Reg(_) => {}
+            }
+        }
+        Ok(op)
+    }
+
+    /// Warns about implicit conversions to a narrower integer type, which silently change values
+    /// that don't fit. Constant expressions only warn if their value doesn't fit, so that
+    /// `char c = 'a';` is fine.
+    pub(super) fn warn_narrowing(&self, expr: &ast::Expr, from: Ty<'cx>, to: Ty<'cx>, span: Span) {
+        // Conversions to _Bool compare with zero instead of truncating.
+        if !from.is_integer() || !to.is_integer() || to.is_bool() {
+            return;
+        }
+        let bits = self.lcx.layout_of(to).layout.size * 8;
+        let warning = match self.eval_const_expr_in_scope(expr, span) {
+            // Negative values for unsigned types are a common way to get the maximum value.
+            Ok((value, _)) if !to.is_signed() && value < 0 && value >= -(1 << (bits - 1)) => return,
+            Ok((value, _)) => {
+                let truncated = if to.is_signed() {
+                    (value << (128 - bits)) >> (128 - bits)
+                } else {
+                    value & ((1 << bits) - 1)
+                };
+                if truncated == value {
+                    return;
+                }
+                format!("implicit conversion from {from} to {to} changes value from {value} to {truncated}")
+            }
+            Err(_) => {
+                if self.lcx.layout_of(from).layout.size <= self.lcx.layout_of(to).layout.size {
+                    return;
+                }
+                format!("implicit conversion from {from} to {to} may change the value")
+            }
+        };
+        self.lcx.emit_warning(Error::new(warning, span));
     }
 }
 
-fn is_int_bigger(this: IntTyKind, compared_to: IntTyKind) -> bool {
-    let num = |kind| match kind {
-        IntTyKind::Bool => 1,
-        IntTyKind::Char => 2,
-        IntTyKind::Short => 3,
-        IntTyKind::Int => 4,
-        IntTyKind::Long => 5,
-        IntTyKind::LongLong => 5,
+/// Converts an integer constant of `from_bits` bits to `to_bits` bits. Constants are stored
+/// zero-extended, so signed constants are sign-extended first and the result is truncated to the
+/// new width.
+fn convert_int_const(int: u128, from_bits: u64, signed: bool, to_bits: u64) -> u128 {
+    let int = if signed && from_bits < 128 {
+        (((int << (128 - from_bits)) as i128) >> (128 - from_bits)) as u128
+    } else {
+        int
     };
-
-    num(this) > num(compared_to)
+    if to_bits < 128 {
+        int & ((1 << to_bits) - 1)
+    } else {
+        int
+    }
 }
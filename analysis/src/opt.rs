@@ -0,0 +1,19 @@
+//! Optimization passes over the IR.
+
+mod const_fold;
+mod cse;
+mod dce;
+mod forward_stores;
+mod mem2reg;
+mod merge_blocks;
+mod pipeline;
+mod unreachable;
+
+pub use const_fold::const_fold;
+pub use cse::cse;
+pub use dce::dce;
+pub use forward_stores::forward_stores;
+pub use mem2reg::mem2reg;
+pub use merge_blocks::merge_blocks;
+pub use pipeline::{OptPipeline, Pass};
+pub use unreachable::remove_unreachable_blocks;
@@ -0,0 +1,310 @@
+use rustc_hash::FxHashMap;
+
+use crate::{
+    ir::{
+        visit::VisitorMut, BinKind, ConstValue, Func, Operand, Register, RegisterData, Statement,
+        StatementKind,
+    },
+    ty::TyKind,
+};
+
+/// Folds binary operations on two integer constants and constant offsets of static pointers,
+/// and replaces all uses of their result with the folded constant. The folded statements are
+/// left in place. Returns whether any use was replaced.
+pub fn const_fold(func: &mut Func<'_>) -> bool {
+    let mut folder = ConstFolder {
+        regs: &func.regs,
+        folded: FxHashMap::default(),
+        changed: true,
+        replaced: false,
+    };
+
+    // Blocks aren't in any particular order, so a use may come before its definition.
+    while folder.changed {
+        folder.changed = false;
+        for bb in &mut func.bbs {
+            folder.visit_bb(bb);
+        }
+    }
+    folder.replaced
+}
+
+struct ConstFolder<'a, 'cx> {
+    regs: &'a [RegisterData<'cx>],
+    folded: FxHashMap<Register, ConstValue<'cx>>,
+    changed: bool,
+    replaced: bool,
+}
+
+impl<'cx> VisitorMut<'cx> for ConstFolder<'_, 'cx> {
+    fn visit_statement(&mut self, stmt: &mut Statement<'cx>) {
+        self.super_statement(stmt);
+
+        let (result, value) = match stmt.kind {
+            StatementKind::BinOp {
+                result,
+                kind,
+                lhs: Operand::Const(ConstValue::Int(lhs)),
+                rhs: Operand::Const(ConstValue::Int(rhs)),
+            } => (
+                result,
+                self.fold(result, kind, lhs, rhs).map(ConstValue::Int),
+            ),
+            StatementKind::PtrOffset {
+                result,
+                ptr: Operand::Const(ConstValue::StaticPtr(def_id, offset)),
+                amount: Operand::Const(ConstValue::Int(amount)),
+            } => {
+                // Offsets are pointer sized, negative ones wrap around.
+                let offset = offset.wrapping_add(amount as u64);
+                (result, Some(ConstValue::StaticPtr(def_id, offset)))
+            }
+            _ => return,
+        };
+        if self.folded.contains_key(&result) {
+            return;
+        }
+        if let Some(value) = value {
+            self.folded.insert(result, value);
+            self.changed = true;
+        }
+    }
+
+    fn visit_operand(&mut self, op: &mut Operand<'cx>) {
+        if let Operand::Reg(reg) = op {
+            if let Some(&value) = self.folded.get(reg) {
+                *op = Operand::Const(value);
+                self.replaced = true;
+            }
+        }
+    }
+}
+
+impl ConstFolder<'_, '_> {
+    fn fold(&self, result: Register, kind: BinKind, lhs: u128, rhs: u128) -> Option<u128> {
+        match kind {
+            BinKind::Eq
+            | BinKind::Neq
+            | BinKind::UGt
+            | BinKind::UGeq
+            | BinKind::ULt
+            | BinKind::ULeq => {
+                // Constants are stored zero-extended, so they compare like unsigned values.
+                let value = match kind {
+                    BinKind::Eq => lhs == rhs,
+                    BinKind::Neq => lhs != rhs,
+                    BinKind::UGt => lhs > rhs,
+                    BinKind::UGeq => lhs >= rhs,
+                    BinKind::ULt => lhs < rhs,
+                    BinKind::ULeq => lhs <= rhs,
+                    _ => unreachable!(),
+                };
+                Some(value.into())
+            }
+            BinKind::SGt | BinKind::SGeq | BinKind::SLt | BinKind::SLeq => {
+                // The width of the operands isn't known here, but constants below this are the
+                // same value for every signed integer type.
+                if lhs > i32::MAX as u128 || rhs > i32::MAX as u128 {
+                    return None;
+                }
+                let value = match kind {
+                    BinKind::SGt => lhs > rhs,
+                    BinKind::SGeq => lhs >= rhs,
+                    BinKind::SLt => lhs < rhs,
+                    BinKind::SLeq => lhs <= rhs,
+                    _ => unreachable!(),
+                };
+                Some(value.into())
+            }
+            _ => {
+                let tyl = self.regs[result.as_usize()].tyl;
+                let TyKind::Int(int) = *tyl.ty else {
+                    return None;
+                };
+                let bits = u32::try_from(tyl.layout.size * 8).ok()?;
+                fold_int(kind, lhs, rhs, bits, int.0.signed())
+            }
+        }
+    }
+}
+
+/// Computes an arithmetic operation on integers of size `bits`. Constants are stored
+/// zero-extended, so signed values are sign-extended from `bits` first.
+fn fold_int(kind: BinKind, lhs: u128, rhs: u128, bits: u32, signed: bool) -> Option<u128> {
+    if bits == 0 || bits > 64 {
+        return None;
+    }
+    let mask = u128::MAX >> (128 - bits);
+    let sext = |int: u128| ((int << (128 - bits)) as i128) >> (128 - bits);
+    let (lhs, rhs) = (lhs & mask, rhs & mask);
+
+    let value = match kind {
+        BinKind::Add => lhs.wrapping_add(rhs),
+        BinKind::Sub => lhs.wrapping_sub(rhs),
+        BinKind::Mul => lhs.wrapping_mul(rhs),
+        BinKind::SDiv | BinKind::UDiv | BinKind::SMod | BinKind::UMod if rhs == 0 => return None,
+        BinKind::SDiv => sext(lhs).wrapping_div(sext(rhs)) as u128,
+        BinKind::UDiv => lhs / rhs,
+        BinKind::SMod => sext(lhs).wrapping_rem(sext(rhs)) as u128,
+        BinKind::UMod => lhs % rhs,
+        // Shifting by the width or more is undefined, leave that to the backend.
+        BinKind::Shl | BinKind::Shr if rhs >= bits.into() => return None,
+        BinKind::Shl => lhs << rhs,
+        BinKind::Shr if signed => (sext(lhs) >> rhs) as u128,
+        BinKind::Shr => lhs >> rhs,
+        BinKind::BitAnd => lhs & rhs,
+        BinKind::BitOr => lhs | rhs,
+        BinKind::BitXor => lhs ^ rhs,
+        BinKind::Eq
+        | BinKind::Neq
+        | BinKind::SGt
+        | BinKind::UGt
+        | BinKind::SGeq
+        | BinKind::UGeq
+        | BinKind::SLt
+        | BinKind::ULt
+        | BinKind::SLeq
+        | BinKind::ULeq => unreachable!("comparisons are folded separately"),
+    };
+
+    Some(value & mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::const_fold;
+    use crate::{
+        ir::{define_ir_func, BbIdx, Branch, ConstValue, DefId, Operand, StatementKind},
+        LoweringCx,
+    };
+
+    #[test]
+    fn add_constants() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(2) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(2), rhs: op(3) };
+                    BinOp { result: Register(1), kind: BinKind::Mul, lhs: op(Register(0)), rhs: op(4) }
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    => Ret(op(Register(1)))
+                }
+            }
+        };
+
+        const_fold(&mut f);
+
+        let StatementKind::BinOp { lhs, .. } = f.bbs[0].statements[1].kind else {
+            unreachable!()
+        };
+        assert!(matches!(lhs, Operand::Const(ConstValue::Int(5))));
+        assert!(matches!(
+            f.bbs[1].term,
+            Branch::Ret(Operand::Const(ConstValue::Int(20)))
+        ));
+    }
+
+    #[test]
+    fn wrapping_and_comparisons() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(3) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Sub, lhs: op(1), rhs: op(2) };
+                    BinOp { result: Register(1), kind: BinKind::SLt, lhs: op(1), rhs: op(2) };
+                    // -1 is not folded, it's only -1 as a signed int.
+                    BinOp { result: Register(2), kind: BinKind::SLt, lhs: op(Register(0)), rhs: op(2) }
+                    => Switch { cond: op(Register(1)), yes: BbIdx(1), no: BbIdx(1) }
+                }
+                1: {
+                    => Ret(op(Register(2)))
+                }
+            }
+        };
+
+        const_fold(&mut f);
+
+        let StatementKind::BinOp { lhs, .. } = f.bbs[0].statements[2].kind else {
+            unreachable!()
+        };
+        assert!(matches!(lhs, Operand::Const(ConstValue::Int(0xFFFF_FFFF))));
+        assert!(matches!(
+            f.bbs[0].term,
+            Branch::Switch {
+                cond: Operand::Const(ConstValue::Int(1)),
+                ..
+            }
+        ));
+        assert!(matches!(f.bbs[1].term, Branch::Ret(Operand::Reg(_))));
+    }
+
+    #[test]
+    fn div_by_zero() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(4) {
+                0: {
+                    Alloca { result: Register(0), size: 4, align: 4, name: None };
+                    Load { result: Register(1), ptr: op(Register(0)), size: 4, align: 4, volatile: false };
+                    BinOp { result: Register(2), kind: BinKind::SDiv, lhs: op(Register(1)), rhs: op(0) };
+                    BinOp { result: Register(3), kind: BinKind::SMod, lhs: op(1), rhs: op(0) }
+                    => Ret(op(Register(3)))
+                }
+            }
+        };
+
+        const_fold(&mut f);
+
+        let StatementKind::BinOp { lhs, rhs, .. } = f.bbs[0].statements[2].kind else {
+            unreachable!()
+        };
+        assert!(matches!(lhs, Operand::Reg(_)));
+        assert!(matches!(rhs, Operand::Const(ConstValue::Int(0))));
+        assert!(matches!(f.bbs[0].term, Branch::Ret(Operand::Reg(_))));
+    }
+
+    #[test]
+    fn static_ptr_offset() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(4) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Mul, lhs: op(2), rhs: op(4) };
+                    PtrOffset { result: Register(1), ptr: op(0), amount: op(Register(0)) };
+                    Load { result: Register(2), ptr: op(Register(1)), size: 4, align: 4, volatile: false };
+                    PtrOffset { result: Register(3), ptr: op(0), amount: op(Register(2)) }
+                    => Ret(op(Register(3)))
+                }
+            }
+        };
+        let global = Operand::Const(ConstValue::StaticPtr(DefId(0), 0));
+        for i in [1, 3] {
+            let StatementKind::PtrOffset { ptr, .. } = &mut f.bbs[0].statements[i].kind else {
+                unreachable!()
+            };
+            *ptr = global;
+        }
+
+        const_fold(&mut f);
+
+        let StatementKind::Load { ptr, .. } = f.bbs[0].statements[2].kind else {
+            unreachable!()
+        };
+        assert!(matches!(
+            ptr,
+            Operand::Const(ConstValue::StaticPtr(DefId(0), 8))
+        ));
+        // The loaded value isn't known, so the second offset is left alone.
+        assert!(matches!(f.bbs[0].term, Branch::Ret(Operand::Reg(_))));
+    }
+}
@@ -0,0 +1,139 @@
+use rustc_hash::FxHashMap;
+
+use crate::{
+    ir::{visit::VisitorMut, BinKind, Func, Operand, Register, StatementKind, UnaryKind},
+    ty::Ty,
+};
+
+/// A side effect free computation. The result type is part of it, as constant operands don't
+/// have a type: `add 1, 2` as an `int` and as a `long` are different computations.
+#[derive(PartialEq, Eq, Hash)]
+enum Computation<'cx> {
+    BinOp(BinKind, Operand<'cx>, Operand<'cx>, Ty<'cx>),
+    Unary(UnaryKind, Operand<'cx>, Ty<'cx>),
+    PtrOffset(Operand<'cx>, Operand<'cx>, Ty<'cx>),
+}
+
+/// Common subexpression elimination within a block. A side effect free computation that was
+/// already computed earlier in the same block is removed, and its result is replaced with the
+/// earlier result everywhere. Loads, stores and calls are never eliminated, as memory may have
+/// changed in between. Returns whether any statement was removed.
+pub fn cse(func: &mut Func<'_>) -> bool {
+    let mut replacements = FxHashMap::default();
+
+    for bb in &mut func.bbs {
+        // Types are hashed by their address, so the `OnceCell`s of structs don't matter.
+        #[allow(clippy::mutable_key_type)]
+        let mut computed = FxHashMap::default();
+        bb.statements.retain_mut(|stmt| {
+            // Earlier replacements in this block can make more computations identical.
+            ReplaceRegs(&replacements).visit_statement(stmt);
+
+            let ty = |result: Register| func.regs[result.as_usize()].tyl.ty;
+            let (result, computation) = match stmt.kind {
+                StatementKind::BinOp {
+                    result,
+                    kind,
+                    lhs,
+                    rhs,
+                } => (result, Computation::BinOp(kind, lhs, rhs, ty(result))),
+                StatementKind::UnaryOperation { result, kind, rhs } => {
+                    (result, Computation::Unary(kind, rhs, ty(result)))
+                }
+                StatementKind::PtrOffset {
+                    result,
+                    ptr,
+                    amount,
+                } => (result, Computation::PtrOffset(ptr, amount, ty(result))),
+                StatementKind::Alloca { .. }
+                | StatementKind::Store { .. }
+                | StatementKind::Load { .. }
+                | StatementKind::Call { .. }
+                | StatementKind::Phi { .. } => return true,
+            };
+            match computed.get(&computation) {
+                Some(&earlier) => {
+                    replacements.insert(result, earlier);
+                    false
+                }
+                None => {
+                    computed.insert(computation, result);
+                    true
+                }
+            }
+        });
+    }
+
+    // Uses in other blocks, or before the definition in the block order, still need replacing.
+    if replacements.is_empty() {
+        return false;
+    }
+    ReplaceRegs(&replacements).visit_func(func);
+    true
+}
+
+struct ReplaceRegs<'a>(&'a FxHashMap<Register, Register>);
+
+impl<'cx> VisitorMut<'cx> for ReplaceRegs<'_> {
+    fn visit_operand(&mut self, op: &mut Operand<'cx>) {
+        if let Operand::Reg(reg) = op {
+            if let Some(&replacement) = self.0.get(reg) {
+                *reg = replacement;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cse;
+    use crate::{
+        ir::{define_ir_func, func_to_string, pretty::DefaultCustomizer, BbIdx},
+        LoweringCx,
+    };
+
+    #[test]
+    fn identical_add() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (2), regs(9) {
+                0: {
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(Register(1)) };
+                    BinOp { result: Register(3), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(Register(1)) };
+                    Load { result: Register(4), ptr: op(Register(3)), size: 4, align: 4, volatile: false };
+                    Load { result: Register(5), ptr: op(Register(3)), size: 4, align: 4, volatile: false };
+                    Call { result: Register(6), func: op(0), args: vec![] };
+                    Call { result: Register(7), func: op(0), args: vec![] }
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    BinOp { result: Register(8), kind: BinKind::Mul, lhs: op(Register(3)), rhs: op(Register(3)) }
+                    => Ret(op(Register(8)))
+                }
+            }
+        };
+
+        cse(&mut f);
+
+        // Only the second add is removed, loads and calls stay.
+        assert_eq!(
+            func_to_string(&f, &DefaultCustomizer::default()),
+            "def name(unsigned int %0, unsigned int %1) {
+  bb0:
+    %2 = add %0, %1
+    %4 = load %2, size=4, align=4
+    %5 = load %2, size=4, align=4
+    %6 = call 0 ()
+    %7 = call 0 ()
+    goto bb1
+
+  bb1:
+    %8 = mul %2, %2
+    ret %8
+}
+"
+        );
+    }
+}
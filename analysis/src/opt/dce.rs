@@ -0,0 +1,117 @@
+use crate::ir::{visit::Visitor, Func, Operand, StatementKind};
+
+/// Removes side effect free statements whose result is never used. Returns whether any were
+/// removed.
+pub fn dce(func: &mut Func<'_>) -> bool {
+    let mut removed = false;
+    loop {
+        let mut counter = UseCounter {
+            uses: vec![0; func.regs.len()],
+        };
+        counter.visit_func(func);
+
+        let mut changed = false;
+        for bb in &mut func.bbs {
+            bb.statements.retain(|stmt| {
+                let result = match stmt.kind {
+                    StatementKind::BinOp { result, .. }
+                    | StatementKind::Load {
+                        result,
+                        volatile: false,
+                        ..
+                    }
+                    | StatementKind::UnaryOperation { result, .. }
+                    | StatementKind::PtrOffset { result, .. }
+                    | StatementKind::Phi { result, .. } => result,
+                    // Stores, volatile loads and calls have effects, allocas are removed together
+                    // with the local.
+                    StatementKind::Alloca { .. }
+                    | StatementKind::Load { volatile: true, .. }
+                    | StatementKind::Store { .. }
+                    | StatementKind::Call { .. } => return true,
+                };
+                let used = counter.uses[result.as_usize()] > 0;
+                changed |= !used;
+                used
+            });
+        }
+
+        // Removing a statement may have made its operands dead as well.
+        if !changed {
+            return removed;
+        }
+        removed = true;
+    }
+}
+
+struct UseCounter {
+    uses: Vec<usize>,
+}
+
+impl Visitor for UseCounter {
+    fn visit_operand(&mut self, op: Operand<'_>) {
+        if let Operand::Reg(reg) = op {
+            self.uses[reg.as_usize()] += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dce;
+    use crate::{
+        ir::{define_ir_func, func_to_string, pretty::DefaultCustomizer},
+        LoweringCx,
+    };
+
+    #[test]
+    fn dead_add() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(4) {
+                0: {
+                    Alloca { result: Register(0), size: 4, align: 4, name: None };
+                    Load { result: Register(1), ptr: op(Register(0)), size: 4, align: 4, volatile: false };
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(Register(1)), rhs: op(1) };
+                    Call { result: Register(3), func: op(0), args: vec![] }
+                    => Ret(op(0))
+                }
+            }
+        };
+
+        assert!(dce(&mut f));
+
+        assert_eq!(
+            func_to_string(&f, &DefaultCustomizer::default()),
+            "def name() {
+  bb0:
+    %0 = alloca, size=4, align=4
+    %3 = call 0 ()
+    ret 0
+}
+"
+        );
+    }
+
+    #[test]
+    fn used_in_terminator() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(2) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(1) };
+                    BinOp { result: Register(1), kind: BinKind::Mul, lhs: op(Register(0)), rhs: op(2) }
+                    => Ret(op(Register(1)))
+                }
+            }
+        };
+
+        assert!(!dce(&mut f));
+
+        assert_eq!(f.bbs[0].statements.len(), 2);
+    }
+}
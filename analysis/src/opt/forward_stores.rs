@@ -0,0 +1,202 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::ir::{
+    visit::{Visitor, VisitorMut},
+    BbIdx, Func, Operand, Register, Statement, StatementKind,
+};
+
+/// Replaces loads from a local with the value that was stored to it earlier in the same block.
+/// The loads are left in place for [`dce`](super::dce) to remove.
+///
+/// Only locals whose address is never taken are forwarded, anything else may be changed through
+/// another pointer. Calls conservatively forget all stored values. Returns whether any use was
+/// replaced.
+pub fn forward_stores(func: &mut Func<'_>) -> bool {
+    let mut finder = AddrTakenFinder {
+        allocas: FxHashSet::default(),
+        addr_taken: FxHashSet::default(),
+    };
+    for stmt in &func.bb(BbIdx::ZERO).statements {
+        if let StatementKind::Alloca { result, .. } = stmt.kind {
+            finder.allocas.insert(result);
+        }
+    }
+    finder.visit_func(func);
+
+    let mut forwarder = Forwarder {
+        replacements: FxHashMap::default(),
+        replaced: false,
+    };
+    for bb in &func.bbs {
+        // The value and size of the last store to every local.
+        let mut stored = FxHashMap::default();
+        for stmt in &bb.statements {
+            match stmt.kind {
+                StatementKind::Store {
+                    ptr: Operand::Reg(ptr),
+                    value,
+                    size,
+                    volatile,
+                    ..
+                } if finder.is_tracked(ptr) => {
+                    if volatile {
+                        stored.remove(&ptr);
+                    } else {
+                        stored.insert(ptr, (forwarder.resolve(value), size));
+                    }
+                }
+                StatementKind::Load {
+                    result,
+                    ptr: Operand::Reg(ptr),
+                    size,
+                    volatile: false,
+                    ..
+                } => {
+                    if let Some(&(value, stored_size)) = stored.get(&ptr) {
+                        if stored_size == size {
+                            forwarder.replacements.insert(result, value);
+                        }
+                    }
+                }
+                StatementKind::Call { .. } => stored.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    if !forwarder.replacements.is_empty() {
+        forwarder.visit_func(func);
+    }
+    forwarder.replaced
+}
+
+/// Finds allocas that are used for anything else than being loaded from or stored to.
+struct AddrTakenFinder {
+    allocas: FxHashSet<Register>,
+    addr_taken: FxHashSet<Register>,
+}
+
+impl AddrTakenFinder {
+    fn is_tracked(&self, ptr: Register) -> bool {
+        self.allocas.contains(&ptr) && !self.addr_taken.contains(&ptr)
+    }
+}
+
+impl Visitor for AddrTakenFinder {
+    fn visit_statement(&mut self, stmt: &Statement<'_>) {
+        match stmt.kind {
+            StatementKind::Load { .. } | StatementKind::Alloca { .. } => {}
+            // Storing the address of a local somewhere takes it.
+            StatementKind::Store { value, .. } => self.visit_operand(value),
+            _ => self.super_statement(stmt),
+        }
+    }
+
+    fn visit_operand(&mut self, op: Operand<'_>) {
+        if let Operand::Reg(reg) = op {
+            if self.allocas.contains(&reg) {
+                self.addr_taken.insert(reg);
+            }
+        }
+    }
+}
+
+struct Forwarder<'cx> {
+    replacements: FxHashMap<Register, Operand<'cx>>,
+    replaced: bool,
+}
+
+impl<'cx> Forwarder<'cx> {
+    /// The stored value may itself be a forwarded load.
+    fn resolve(&self, op: Operand<'cx>) -> Operand<'cx> {
+        match op {
+            Operand::Reg(reg) => self.replacements.get(&reg).copied().unwrap_or(op),
+            Operand::Const(_) => op,
+        }
+    }
+}
+
+impl<'cx> VisitorMut<'cx> for Forwarder<'cx> {
+    fn visit_operand(&mut self, op: &mut Operand<'cx>) {
+        let resolved = self.resolve(*op);
+        self.replaced |= resolved != *op;
+        *op = resolved;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::forward_stores;
+    use crate::{
+        ir::{define_ir_func, func_to_string, pretty::DefaultCustomizer, BbIdx},
+        opt::dce,
+        LoweringCx,
+    };
+
+    #[test]
+    fn store_load() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (1), regs(4) {
+                0: {
+                    Alloca { result: Register(1), size: 4, align: 4, name: None };
+                    Store { ptr: op(Register(1)), value: op(Register(0)), size: 4, align: 4, volatile: false };
+                    Load { result: Register(2), ptr: op(Register(1)), size: 4, align: 4, volatile: false };
+                    BinOp { result: Register(3), kind: BinKind::Add, lhs: op(Register(2)), rhs: op(1) }
+                    => Ret(op(Register(3)))
+                }
+            }
+        };
+
+        forward_stores(&mut f);
+        dce(&mut f);
+
+        assert_eq!(
+            func_to_string(&f, &DefaultCustomizer::default()),
+            "def name(unsigned int %0) {
+  bb0:
+    %1 = alloca, size=4, align=4
+    store %1, %0, size=4, align=4
+    %3 = add %0, 1
+    ret %3
+}
+"
+        );
+    }
+
+    #[test]
+    fn not_forwarded() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        // The address of %0 is passed to the call. The value stored to %1 is forgotten at the call
+        // and isn't known in bb1.
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(7) {
+                0: {
+                    Alloca { result: Register(0), size: 4, align: 4, name: None };
+                    Alloca { result: Register(1), size: 4, align: 4, name: None };
+                    Store { ptr: op(Register(0)), value: op(1), size: 4, align: 4, volatile: false };
+                    Store { ptr: op(Register(1)), value: op(2), size: 4, align: 4, volatile: false };
+                    Call { result: Register(2), func: op(0), args: vec![op(Register(0))] };
+                    Load { result: Register(3), ptr: op(Register(1)), size: 4, align: 4, volatile: false }
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    Load { result: Register(4), ptr: op(Register(1)), size: 4, align: 4, volatile: false };
+                    Store { ptr: op(Register(0)), value: op(3), size: 4, align: 4, volatile: false };
+                    Load { result: Register(5), ptr: op(Register(0)), size: 4, align: 4, volatile: false };
+                    BinOp { result: Register(6), kind: BinKind::Add, lhs: op(Register(3)), rhs: op(Register(5)) }
+                    => Ret(op(Register(6)))
+                }
+            }
+        };
+        let before = func_to_string(&f, &DefaultCustomizer::default());
+
+        forward_stores(&mut f);
+
+        assert_eq!(func_to_string(&f, &DefaultCustomizer::default()), before);
+    }
+}
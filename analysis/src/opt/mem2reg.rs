@@ -0,0 +1,354 @@
+use parser::Span;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::remove_unreachable_blocks;
+use crate::ir::{
+    dom::Dominators,
+    visit::{Visitor, VisitorMut},
+    BbIdx, ConstValue, Func, Operand, Register, RegisterData, Statement, StatementKind, TyLayout,
+};
+
+/// A local that is promoted to registers.
+struct Var<'cx> {
+    alloca: Register,
+    /// The type of the loaded values. `None` if it's never loaded.
+    tyl: Option<TyLayout<'cx>>,
+    span: Span,
+}
+
+/// Promotes allocas that are only loaded from and stored to into registers, inserting phi nodes
+/// where different values meet. Allocas whose address escapes stay in memory.
+///
+/// Unreachable blocks are removed first. Returns whether anything was removed or promoted.
+pub fn mem2reg<'cx>(func: &mut Func<'cx>) -> bool {
+    let removed_blocks = remove_unreachable_blocks(func);
+
+    let vars = promotable_allocas(func);
+    if vars.is_empty() {
+        return removed_blocks;
+    }
+    let var_of_alloca = vars
+        .iter()
+        .enumerate()
+        .map(|(i, var)| (var.alloca, i))
+        .collect::<FxHashMap<_, _>>();
+
+    let doms = Dominators::compute(func);
+    let frontiers = doms.frontiers(func);
+
+    let mut store_blocks = vec![Vec::new(); vars.len()];
+    for (i, bb) in func.bbs.iter().enumerate() {
+        for stmt in &bb.statements {
+            if let StatementKind::Store {
+                ptr: Operand::Reg(ptr),
+                ..
+            } = stmt.kind
+            {
+                if let Some(&var_idx) = var_of_alloca.get(&ptr) {
+                    store_blocks[var_idx].push(BbIdx::from_usize(i));
+                }
+            }
+        }
+    }
+
+    // Insert phis at the iterated dominance frontier of all stores.
+    let mut phis = vec![Vec::new(); func.bbs.len()];
+    for (var_idx, var) in vars.iter().enumerate() {
+        let Some(tyl) = var.tyl else {
+            continue;
+        };
+        let mut has_phi = FxHashSet::default();
+        let mut worklist = std::mem::take(&mut store_blocks[var_idx]);
+        let mut seen = worklist.iter().copied().collect::<FxHashSet<_>>();
+
+        while let Some(bb) = worklist.pop() {
+            for &frontier in &frontiers[bb.as_usize()] {
+                if has_phi.insert(frontier) {
+                    let result = Register(func.regs.len().try_into().unwrap());
+                    func.regs.push(RegisterData { tyl, name: None });
+                    phis[frontier.as_usize()].push((var_idx, result));
+                    if seen.insert(frontier) {
+                        worklist.push(frontier);
+                    }
+                }
+            }
+        }
+    }
+
+    let children = doms.children();
+
+    // Rename along the dominator tree, every block starts with the values at the end of its
+    // immediate dominator.
+    let mut renamer = Renamer {
+        replacements: FxHashMap::default(),
+    };
+    let mut incomings = vec![Vec::new(); func.bbs.len()];
+    let mut worklist = vec![(BbIdx::ZERO, vec![None; vars.len()])];
+    while let Some((bb_idx, mut values)) = worklist.pop() {
+        for &(var_idx, result) in &phis[bb_idx.as_usize()] {
+            values[var_idx] = Some(Operand::Reg(result));
+        }
+
+        let bb = func.bb_mut(bb_idx);
+        for stmt in &mut bb.statements {
+            match stmt.kind {
+                StatementKind::Load {
+                    result,
+                    ptr: Operand::Reg(ptr),
+                    ..
+                } if var_of_alloca.contains_key(&ptr) => {
+                    let var_idx = var_of_alloca[&ptr];
+                    let value = values[var_idx].unwrap_or_else(|| undef(vars[var_idx].tyl));
+                    renamer.replacements.insert(result, value);
+                }
+                StatementKind::Store {
+                    ptr: Operand::Reg(ptr),
+                    mut value,
+                    ..
+                } if var_of_alloca.contains_key(&ptr) => {
+                    renamer.visit_operand(&mut value);
+                    values[var_of_alloca[&ptr]] = Some(value);
+                }
+                _ => renamer.visit_statement(stmt),
+            }
+        }
+        renamer.visit_terminator(&mut bb.term);
+
+        for succ in bb.term.successors() {
+            for &(var_idx, result) in &phis[succ.as_usize()] {
+                let value = values[var_idx].unwrap_or_else(|| undef(vars[var_idx].tyl));
+                incomings[succ.as_usize()].push((result, bb_idx, value));
+            }
+        }
+
+        for &child in &children[bb_idx.as_usize()] {
+            worklist.push((child, values.clone()));
+        }
+    }
+
+    for (i, bb) in func.bbs.iter_mut().enumerate() {
+        bb.statements.retain(|stmt| match stmt.kind {
+            StatementKind::Alloca { result: ptr, .. }
+            | StatementKind::Load {
+                ptr: Operand::Reg(ptr),
+                ..
+            }
+            | StatementKind::Store {
+                ptr: Operand::Reg(ptr),
+                ..
+            } => !var_of_alloca.contains_key(&ptr),
+            _ => true,
+        });
+
+        let phis = phis[i].iter().map(|&(var_idx, result)| Statement {
+            span: vars[var_idx].span,
+            kind: StatementKind::Phi {
+                result,
+                incomings: incomings[i]
+                    .iter()
+                    .filter(|(phi, _, _)| *phi == result)
+                    .map(|&(_, pred, value)| (pred, value))
+                    .collect(),
+            },
+        });
+        bb.statements.splice(0..0, phis);
+    }
+    true
+}
+
+/// Reading an uninitialized local is undefined behavior, so any value can be used.
+fn undef<'cx>(tyl: Option<TyLayout<'cx>>) -> Operand<'cx> {
+    match tyl {
+        Some(tyl) if tyl.ty.is_pointer() => Operand::Const(ConstValue::Null(tyl.ty)),
+        _ => Operand::Const(ConstValue::Int(0)),
+    }
+}
+
+/// Replaces uses of promoted loads with the value that was stored.
+struct Renamer<'cx> {
+    replacements: FxHashMap<Register, Operand<'cx>>,
+}
+
+impl<'cx> VisitorMut<'cx> for Renamer<'cx> {
+    fn visit_operand(&mut self, op: &mut Operand<'cx>) {
+        if let Operand::Reg(reg) = op {
+            if let Some(&value) = self.replacements.get(reg) {
+                *op = value;
+            }
+        }
+    }
+}
+
+fn promotable_allocas<'cx>(func: &Func<'cx>) -> Vec<Var<'cx>> {
+    let mut finder = EscapeFinder {
+        allocas: FxHashMap::default(),
+        escaped: FxHashSet::default(),
+        loaded: FxHashMap::default(),
+        func,
+    };
+    for stmt in &func.bb(BbIdx::ZERO).statements {
+        if let StatementKind::Alloca { result, size, .. } = stmt.kind {
+            finder.allocas.insert(result, (size, stmt.span));
+        }
+    }
+
+    finder.visit_func(func);
+
+    let mut vars = finder
+        .allocas
+        .iter()
+        .filter(|(alloca, _)| !finder.escaped.contains(alloca))
+        .map(|(&alloca, &(_, span))| Var {
+            alloca,
+            tyl: finder.loaded.get(&alloca).copied(),
+            span,
+        })
+        .collect::<Vec<_>>();
+    vars.sort_by_key(|var| var.alloca);
+    vars
+}
+
+/// Finds allocas that are used for anything else than loading and storing the whole value.
+/// Volatile accesses must stay in memory, so they count as escaping.
+struct EscapeFinder<'a, 'cx> {
+    allocas: FxHashMap<Register, (u64, Span)>,
+    escaped: FxHashSet<Register>,
+    loaded: FxHashMap<Register, TyLayout<'cx>>,
+    func: &'a Func<'cx>,
+}
+
+impl Visitor for EscapeFinder<'_, '_> {
+    fn visit_statement(&mut self, stmt: &Statement<'_>) {
+        match stmt.kind {
+            StatementKind::Load {
+                result,
+                ptr: Operand::Reg(ptr),
+                size,
+                volatile,
+                ..
+            } if self.allocas.contains_key(&ptr) => {
+                if self.allocas[&ptr].0 != size || volatile {
+                    self.escaped.insert(ptr);
+                }
+                self.loaded
+                    .entry(ptr)
+                    .or_insert(self.func.regs[result.as_usize()].tyl);
+            }
+            StatementKind::Store {
+                ptr: Operand::Reg(ptr),
+                value,
+                size,
+                volatile,
+                ..
+            } if self.allocas.contains_key(&ptr) => {
+                if self.allocas[&ptr].0 != size || volatile {
+                    self.escaped.insert(ptr);
+                }
+                self.visit_operand(value);
+            }
+            _ => self.super_statement(stmt),
+        }
+    }
+
+    fn visit_operand(&mut self, op: Operand<'_>) {
+        if let Operand::Reg(reg) = op {
+            if self.allocas.contains_key(&reg) {
+                self.escaped.insert(reg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mem2reg;
+    use crate::{
+        ir::{define_ir_func, func_to_string, pretty::DefaultCustomizer, Func},
+        LoweringCx,
+    };
+
+    fn mem2reg_to_string(src: &str) -> String {
+        let ast = parser::parse_file(src).unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        let mut f = ir.funcs.into_values().next().unwrap();
+
+        mem2reg(&mut f);
+        assert_eq!(crate::ir::verify(&f), Ok(()));
+        print(&f)
+    }
+
+    fn print(f: &Func<'_>) -> String {
+        func_to_string(f, &DefaultCustomizer::default())
+    }
+
+    #[test]
+    fn straight_line() {
+        let ir = mem2reg_to_string("int f(int x) { int y = x + 1; y = y * 2; return y; }");
+        assert_eq!(
+            ir,
+            "def f(int %x) {
+  bb0:
+    %4 = add %x, 1
+    %6 = mul %4, 2
+    ret %6
+}
+"
+        );
+    }
+
+    #[test]
+    fn phi_at_join() {
+        let ir = mem2reg_to_string("int f(int c) { int y = 1; if (c) { y = 2; } return y; }");
+        assert_eq!(
+            ir,
+            "def f(int %c) {
+  bb0:
+    switch %c, then bb1, else bb2
+
+  bb1:
+    goto bb2
+
+  bb2:
+    %5 = phi [bb0: 1], [bb1: 2]
+    ret %5
+}
+"
+        );
+    }
+
+    #[test]
+    fn escaped_alloca() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (), regs(4) {
+                0: {
+                    Alloca { result: Register(0), size: 4, align: 4, name: None };
+                    Alloca { result: Register(1), size: 4, align: 4, name: None };
+                    Store { ptr: op(Register(0)), value: op(1), size: 4, align: 4, volatile: false };
+                    Store { ptr: op(Register(1)), value: op(2), size: 4, align: 4, volatile: false };
+                    Call { result: Register(2), func: op(0), args: vec![op(Register(0))] };
+                    Load { result: Register(3), ptr: op(Register(1)), size: 4, align: 4, volatile: false }
+                    => Ret(op(Register(3)))
+                }
+            }
+        };
+
+        mem2reg(&mut f);
+
+        assert_eq!(
+            print(&f),
+            "def name() {
+  bb0:
+    %0 = alloca, size=4, align=4
+    store %0, 1, size=4, align=4
+    %2 = call 0 (%0)
+    ret 2
+}
+"
+        );
+    }
+}
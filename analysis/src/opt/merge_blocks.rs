@@ -0,0 +1,147 @@
+use super::remove_unreachable_blocks;
+use crate::ir::{info, BbIdx, Branch, Func, StatementKind};
+
+/// Merges blocks that end with a `goto` into their target if they are its only predecessor.
+/// The merged blocks become unreachable and are removed, which renumbers the remaining ones.
+/// Returns whether any blocks were merged.
+pub fn merge_blocks(func: &mut Func<'_>) -> bool {
+    let mut preds = info::predecessors(func);
+    let mut merged = false;
+
+    for i in 0..func.bbs.len() {
+        let a = BbIdx::from_usize(i);
+        // `a` may have been merged into an earlier block already, nothing branches to it then.
+        if a != BbIdx::ZERO && preds[i].is_empty() {
+            continue;
+        }
+        while let Branch::Goto(b) = func.bb(a).term {
+            // The entry block is also entered when calling the function.
+            if b == a || b == BbIdx::ZERO || preds[b.as_usize()] != [a] {
+                break;
+            }
+            // Phis in a block with a single predecessor could be replaced by their value, but
+            // they are left alone for now.
+            if let Some(StatementKind::Phi { .. }) = func.bb(b).statements.first().map(|s| &s.kind)
+            {
+                break;
+            }
+
+            let statements = std::mem::take(&mut func.bb_mut(b).statements);
+            let term = func.bb(b).term.clone();
+            func.bb_mut(a).statements.extend(statements);
+            func.bb_mut(a).term = term;
+            preds[b.as_usize()].clear();
+
+            // The successors of `b` are now entered from `a`.
+            for succ in func.bb(a).term.successors().collect::<Vec<_>>() {
+                for pred in &mut preds[succ.as_usize()] {
+                    if *pred == b {
+                        *pred = a;
+                    }
+                }
+                for stmt in &mut func.bb_mut(succ).statements {
+                    if let StatementKind::Phi { incomings, .. } = &mut stmt.kind {
+                        for (incoming, _) in incomings {
+                            if *incoming == b {
+                                *incoming = a;
+                            }
+                        }
+                    }
+                }
+            }
+            merged = true;
+        }
+    }
+
+    if merged {
+        remove_unreachable_blocks(func);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_blocks;
+    use crate::{
+        ir::{define_ir_func, func_to_string, pretty::DefaultCustomizer, verify, BbIdx},
+        LoweringCx,
+    };
+
+    #[test]
+    fn empty_if() {
+        let ast = parser::parse_file("int f(int x) { if (x) {} else {} end: return x; }").unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        let mut f = ir.funcs.into_values().next().unwrap();
+
+        // The label starts a new block that is only entered from the end of the `if`.
+        assert_eq!(f.bbs.len(), 5);
+        merge_blocks(&mut f);
+
+        assert_eq!(
+            func_to_string(&f, &DefaultCustomizer::default()),
+            "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %2 = load %x.local, size=4, align=4
+    switch %2, then bb1, else bb2
+
+  bb1:
+    goto bb3
+
+  bb2:
+    goto bb3
+
+  bb3:
+    %3 = load %x.local, size=4, align=4
+    ret %3
+}
+"
+        );
+    }
+
+    #[test]
+    fn phi_incomings() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let mut f = define_ir_func! {
+            def(lcx) name (1), regs(2) {
+                0: {
+                    => Goto(BbIdx(1))
+                }
+                1: {
+                    => Switch { cond: op(Register(0)), yes: BbIdx(2), no: BbIdx(3) }
+                }
+                2: {
+                    => Goto(BbIdx(3))
+                }
+                3: {
+                    Phi { result: Register(1), incomings: vec![(BbIdx(1), op(1)), (BbIdx(2), op(2))] }
+                    => Ret(op(Register(1)))
+                }
+            }
+        };
+
+        merge_blocks(&mut f);
+
+        assert_eq!(
+            func_to_string(&f, &DefaultCustomizer::default()),
+            "def name(unsigned int %0) {
+  bb0:
+    switch %0, then bb1, else bb2
+
+  bb1:
+    goto bb2
+
+  bb2:
+    %1 = phi [bb0: 1], [bb1: 2]
+    ret %1
+}
+"
+        );
+        assert_eq!(verify(&f), Ok(()));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::ir::Func;
+
+/// An optimization pass, which changes the function in place and returns whether it changed
+/// anything.
+pub type Pass = for<'cx> fn(&mut Func<'cx>) -> bool;
+
+/// An ordered list of passes that are run one after another.
+pub struct OptPipeline {
+    passes: Vec<(&'static str, Pass)>,
+    fixpoint: bool,
+}
+
+/// Stops rerunning a pipeline that never settles, for example because two passes undo each other.
+const MAX_ROUNDS: usize = 16;
+
+impl OptPipeline {
+    /// A pipeline without any passes.
+    pub fn new() -> Self {
+        Self {
+            passes: Vec::new(),
+            fixpoint: false,
+        }
+    }
+
+    /// The passes for `-O1`. Stores to locals become SSA values first, which the other passes
+    /// can then see through.
+    pub fn default_o1() -> Self {
+        Self::new()
+            .pass(
+                "remove_unreachable_blocks",
+                super::remove_unreachable_blocks,
+            )
+            .pass("mem2reg", super::mem2reg)
+            .pass("forward_stores", super::forward_stores)
+            .pass("const_fold", super::const_fold)
+            .pass("dce", super::dce)
+            .pass("merge_blocks", super::merge_blocks)
+            .until_fixpoint()
+    }
+
+    /// Adds a pass at the end of the pipeline.
+    pub fn pass(mut self, name: &'static str, pass: Pass) -> Self {
+        self.passes.push((name, pass));
+        self
+    }
+
+    /// Reruns the whole pipeline until a round doesn't change the function anymore.
+    pub fn until_fixpoint(mut self) -> Self {
+        self.fixpoint = true;
+        self
+    }
+
+    /// The names of the passes in the order they run.
+    pub fn pass_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().map(|&(name, _)| name)
+    }
+
+    /// Runs the passes on `func`, repeatedly if the pipeline runs until a fixpoint.
+    pub fn run(&self, func: &mut Func<'_>) {
+        if !self.fixpoint {
+            self.run_once(func);
+            return;
+        }
+        for _ in 0..MAX_ROUNDS {
+            if !self.run_once(func) {
+                return;
+            }
+        }
+    }
+
+    /// Runs every pass once and returns whether any of them changed the function.
+    fn run_once(&self, func: &mut Func<'_>) -> bool {
+        let mut changed = false;
+        for (_, pass) in &self.passes {
+            changed |= pass(func);
+        }
+        changed
+    }
+}
+
+impl Default for OptPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OptPipeline;
+    use crate::{
+        ir::{func_to_string, pretty::DefaultCustomizer},
+        LoweringCx,
+    };
+
+    #[test]
+    fn default_o1() {
+        let ast = parser::parse_file(
+            "int f(int x) { int y = 1 + 2; int z = y * 2; if (x) { z = z + y; } return z; }",
+        )
+        .unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        let mut f = ir.funcs.into_values().next().unwrap();
+
+        let pipeline = OptPipeline::default_o1();
+        pipeline.run(&mut f);
+        assert_eq!(crate::ir::verify(&f), Ok(()));
+        let once = func_to_string(&f, &DefaultCustomizer::default());
+        assert_eq!(
+            once,
+            "def f(int %x) {
+  bb0:
+    switch %x, then bb1, else bb2
+
+  bb1:
+    goto bb2
+
+  bb2:
+    %12 = phi [bb0: 6], [bb1: 9]
+    ret %12
+}
+"
+        );
+
+        pipeline.run(&mut f);
+        assert_eq!(func_to_string(&f, &DefaultCustomizer::default()), once);
+    }
+
+    #[test]
+    fn empty_pipeline() {
+        let ast = parser::parse_file("int f(int x) { int y = x + 1; return y; }").unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        let mut f = ir.funcs.into_values().next().unwrap();
+        let before = func_to_string(&f, &DefaultCustomizer::default());
+
+        let pipeline = OptPipeline::default().until_fixpoint();
+        assert_eq!(pipeline.pass_names().count(), 0);
+        pipeline.run(&mut f);
+        assert_eq!(func_to_string(&f, &DefaultCustomizer::default()), before);
+    }
+
+    #[test]
+    fn pass_names() {
+        let pipeline = OptPipeline::new()
+            .pass("const_fold", crate::opt::const_fold)
+            .pass("dce", crate::opt::dce);
+        assert_eq!(
+            pipeline.pass_names().collect::<Vec<_>>(),
+            ["const_fold", "dce"]
+        );
+    }
+}
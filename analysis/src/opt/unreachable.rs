@@ -0,0 +1,100 @@
+use crate::ir::{BbIdx, Branch, Func, StatementKind};
+
+/// Removes all blocks that can't be reached from the entry block and renumbers the remaining
+/// ones. Returns whether any blocks were removed.
+pub fn remove_unreachable_blocks(func: &mut Func<'_>) -> bool {
+    let mut reachable = vec![false; func.bbs.len()];
+    let mut worklist = vec![BbIdx::ZERO];
+    while let Some(bb) = worklist.pop() {
+        if std::mem::replace(&mut reachable[bb.as_usize()], true) {
+            continue;
+        }
+        worklist.extend(func.bb(bb).term.successors());
+    }
+
+    if !reachable.contains(&false) {
+        return false;
+    }
+
+    // The new index of every reachable block. The entry block stays at index 0.
+    let mut new_idx = Vec::with_capacity(func.bbs.len());
+    let mut next = 0;
+    for &is_reachable in &reachable {
+        new_idx.push(is_reachable.then(|| BbIdx::from_usize(next)));
+        next += usize::from(is_reachable);
+    }
+
+    let mut i = 0;
+    func.bbs.retain(|_| {
+        i += 1;
+        reachable[i - 1]
+    });
+
+    let remap = |bb: &mut BbIdx| {
+        *bb = new_idx[bb.as_usize()].expect("reachable block branches to unreachable block");
+    };
+    for bb in &mut func.bbs {
+        match &mut bb.term {
+            Branch::Goto(target) => remap(target),
+            Branch::Switch { cond: _, yes, no } => {
+                remap(yes);
+                remap(no);
+            }
+            Branch::Ret(_) | Branch::Unreachable => {}
+        }
+        // Removed blocks aren't predecessors anymore.
+        for stmt in &mut bb.statements {
+            if let StatementKind::Phi { incomings, .. } = &mut stmt.kind {
+                incomings.retain_mut(|(bb, _)| match new_idx[bb.as_usize()] {
+                    Some(new) => {
+                        *bb = new;
+                        true
+                    }
+                    None => false,
+                });
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_unreachable_blocks;
+    use crate::{
+        ir::{func_to_string, pretty::DefaultCustomizer},
+        LoweringCx,
+    };
+
+    #[test]
+    fn if_both_arms_return() {
+        let ast =
+            parser::parse_file("int f(int x) { if (x) { return 1; } else { return 2; } }").unwrap();
+        let arena = bumpalo::Bump::new();
+        let mut lcx = LoweringCx::new(&arena);
+        let ir = crate::lower_translation_unit(&mut lcx, &ast).unwrap();
+        let mut f = ir.funcs.into_values().next().unwrap();
+
+        // Both arms start a dead block after their `return`, which jumps to the dead join block.
+        assert_eq!(f.bbs.len(), 6);
+        remove_unreachable_blocks(&mut f);
+
+        assert_eq!(
+            func_to_string(&f, &DefaultCustomizer::default()),
+            "def f(int %x) {
+  bb0:
+    %x.local = alloca, size=4, align=4
+    store %x.local, %x, size=4, align=4
+    %2 = load %x.local, size=4, align=4
+    switch %2, then bb1, else bb2
+
+  bb1:
+    ret 1
+
+  bb2:
+    ret 2
+}
+"
+        );
+    }
+}
@@ -1,12 +1,13 @@
 use std::{
-    fmt::Display,
+    cell::OnceCell,
+    fmt::{Debug, Display},
     hash::{Hash, Hasher},
     ops::Deref,
 };
 
 use indexmap::IndexMap;
 use parser::{
-    ast::{IntSign, IntTy, IntTyKind},
+    ast::{IntSign, IntTy, IntTyKind, Qualifiers},
     Symbol,
 };
 
@@ -23,8 +24,20 @@ pub enum TyKind<'cx> {
     Float,
     Double,
     LongDouble,
-    Ptr(Ty<'cx>),
-    Func(&'cx [Ty<'cx>], Ty<'cx>),
+    /// A pointer and the qualifiers of its pointee, like `const int *`.
+    Ptr(Ty<'cx>, Qualifiers),
+    Array {
+        elem: Ty<'cx>,
+        /// `None` for arrays of unknown size like `int a[]`.
+        len: Option<usize>,
+    },
+    Func {
+        ret: Ty<'cx>,
+        /// `None` for functions declared with `()`, their parameters are unspecified.
+        params: Option<&'cx [Ty<'cx>]>,
+        /// Whether there are additional arguments after the params, like `int printf(char*, ...)`.
+        variadic: bool,
+    },
     Union(UnionTy<'cx>),
     Struct(StructTy<'cx>),
     Enum(EnumTy),
@@ -36,10 +49,15 @@ pub struct UnionTy<'cx> {
     pub variants: IndexMap<Symbol, Ty<'cx>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Structs are identified by their `DefId`. The fields are set after the struct has been
+/// created, so that the fields can point to the struct itself.
+#[derive(Clone)]
 pub struct StructTy<'cx> {
     pub def_id: DefId,
-    pub fields: IndexMap<Symbol, Ty<'cx>>,
+    /// `None` for anonymous structs.
+    pub name: Option<Symbol>,
+    /// Empty while the struct is still being defined.
+    pub fields: &'cx OnceCell<IndexMap<Symbol, Ty<'cx>>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -65,6 +83,28 @@ impl Hash for StructTy<'_> {
         self.def_id.hash(state)
     }
 }
+impl PartialEq for StructTy<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.def_id == other.def_id
+    }
+}
+impl Eq for StructTy<'_> {}
+impl Debug for StructTy<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Fields can contain pointers to the struct itself, so only print their names.
+        f.debug_struct("StructTy")
+            .field("def_id", &self.def_id)
+            .field("name", &self.name)
+            .field(
+                "fields",
+                &self
+                    .fields
+                    .get()
+                    .map(|fields| fields.keys().collect::<Vec<_>>()),
+            )
+            .finish()
+    }
+}
 impl Hash for EnumTy {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.def_id.hash(state)
@@ -73,48 +113,130 @@ impl Hash for EnumTy {
 
 impl Display for Ty<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match **self {
+        Display::fmt(self.0, f)
+    }
+}
+
+/// Prints the type like an abstract declarator in C, for example `int *`, `char [4]` or
+/// `int (*)(long)`.
+impl Display for TyKind<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_declarator(f, String::new(), Qualifiers::empty())
+    }
+}
+
+impl TyKind<'_> {
+    /// C declarators are written inside out, so the declarator of the outer types is built up
+    /// in `inner` until the base type specifier is reached. `quals` are the qualifiers of `self`,
+    /// which are written after the `*` for pointers and before the specifier otherwise.
+    fn fmt_declarator(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        inner: String,
+        quals: Qualifiers,
+    ) -> std::fmt::Result {
+        match self {
+            TyKind::Ptr(pointee, pointee_quals) => {
+                let inner = match (quals_to_string(quals), inner.is_empty()) {
+                    (quals, _) if quals.is_empty() => format!("*{inner}"),
+                    (quals, true) => format!("*{quals}"),
+                    (quals, false) => format!("*{quals} {inner}"),
+                };
+                let inner = if matches!(**pointee, TyKind::Array { .. } | TyKind::Func { .. }) {
+                    format!("({inner})")
+                } else {
+                    inner
+                };
+                pointee.fmt_declarator(f, inner, *pointee_quals)
+            }
+            TyKind::Array { elem, len } => {
+                let inner = match len {
+                    Some(len) => format!("{inner}[{len}]"),
+                    None => format!("{inner}[]"),
+                };
+                // Qualifiers of an array apply to its elements.
+                elem.fmt_declarator(f, inner, quals)
+            }
+            TyKind::Func {
+                ret,
+                params,
+                variadic,
+            } => {
+                let mut params = match params {
+                    Some([]) => vec!["void".to_owned()],
+                    Some(params) => params.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    None => Vec::new(),
+                };
+                if *variadic {
+                    params.push("...".into());
+                }
+                ret.fmt_declarator(
+                    f,
+                    format!("{inner}({})", params.join(", ")),
+                    Qualifiers::empty(),
+                )
+            }
+            _ => {
+                if !quals.is_empty() {
+                    write!(f, "{} ", quals_to_string(quals))?;
+                }
+                self.fmt_specifier(f)?;
+                if !inner.is_empty() {
+                    write!(f, " {inner}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn fmt_specifier(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
             TyKind::Void => f.write_str("void"),
             TyKind::Char => f.write_str("char"),
-            TyKind::Int(int) => {
-                match int.0 {
-                    IntSign::Signed => f.write_str("signed "),
-                    IntSign::Unsigned => f.write_str("unsigned "),
-                }?;
-                match int.1 {
-                    IntTyKind::Bool => f.write_str("_Bool"),
+            TyKind::Int(IntTy(_, IntTyKind::Bool)) => f.write_str("_Bool"),
+            TyKind::Int(IntTy(sign, kind)) => {
+                // `int` is signed by default, but `char` is its own type.
+                match (sign, kind) {
+                    (IntSign::Signed, IntTyKind::Char) => f.write_str("signed ")?,
+                    (IntSign::Unsigned, _) => f.write_str("unsigned ")?,
+                    _ => {}
+                }
+                match kind {
+                    IntTyKind::Bool => unreachable!("handled above"),
                     IntTyKind::Char => f.write_str("char"),
                     IntTyKind::Short => f.write_str("short"),
                     IntTyKind::Int => f.write_str("int"),
                     IntTyKind::Long => f.write_str("long"),
                     IntTyKind::LongLong => f.write_str("long long"),
-                }?;
-                Ok(())
+                }
             }
             TyKind::Float => f.write_str("float"),
             TyKind::Double => f.write_str("double"),
             TyKind::LongDouble => f.write_str("long double"),
-            TyKind::Ptr(ty) => {
-                write!(f, "{ty}*")
+            TyKind::Union(_) => f.write_str("union (anonymous)"),
+            TyKind::Struct(StructTy { name, .. }) => match name {
+                Some(name) => write!(f, "struct {name}"),
+                None => f.write_str("struct (anonymous)"),
+            },
+            TyKind::Enum(_) => f.write_str("enum (anonymous)"),
+            TyKind::Ptr(..) | TyKind::Array { .. } | TyKind::Func { .. } => {
+                unreachable!("declarators are handled by fmt_declarator")
             }
-            TyKind::Func(args, ret) => {
-                write!(f, "{ret}(")?;
-                for (i, arg) in args.iter().enumerate() {
-                    write!(f, "{arg}")?;
-                    if (i + 1) != args.len() {
-                        write!(f, ", ")?;
-                    }
-                }
-                write!(f, ")")?;
-                Ok(())
-            }
-            TyKind::Union(_) => todo!(),
-            TyKind::Struct(_) => todo!(),
-            TyKind::Enum(_) => todo!(),
         }
     }
 }
 
+fn quals_to_string(quals: Qualifiers) -> String {
+    let mut names = Vec::new();
+    if quals.contains(Qualifiers::CONST) {
+        names.push("const");
+    }
+    if quals.contains(Qualifiers::VOLATILE) {
+        names.push("volatile");
+    }
+    names.join(" ")
+}
+
 impl PartialEq for Ty<'_> {
     fn eq(&self, other: &Self) -> bool {
         // Interning.
@@ -133,10 +255,53 @@ impl<'cx> Ty<'cx> {
     pub fn new_unchecked(kind: &'cx TyKind<'cx>) -> Self {
         Self(kind)
     }
-    pub fn is_integral(self) -> bool {
+
+    /// Integer types, including `char`. Enums are not treated as integers yet.
+    pub fn is_integer(self) -> bool {
         matches!(*self, TyKind::Char | TyKind::Int(_))
     }
 
+    pub fn is_bool(self) -> bool {
+        matches!(*self, TyKind::Int(IntTy(_, IntTyKind::Bool)))
+    }
+
+    pub fn is_floating(self) -> bool {
+        matches!(*self, TyKind::Float | TyKind::Double | TyKind::LongDouble)
+    }
+
+    pub fn is_arithmetic(self) -> bool {
+        self.is_integer() || self.is_floating()
+    }
+
+    /// Whether the type is a signed integer or floating point type.
+    pub fn is_signed(self) -> bool {
+        match *self {
+            TyKind::Char | TyKind::Float | TyKind::Double | TyKind::LongDouble => true,
+            TyKind::Int(int) => int.0.signed(),
+            _ => false,
+        }
+    }
+
+    pub fn is_pointer(self) -> bool {
+        matches!(*self, TyKind::Ptr(..))
+    }
+
+    /// Arithmetic and pointer types, the ones that can be used as conditions.
+    pub fn is_scalar(self) -> bool {
+        self.is_arithmetic() || self.is_pointer()
+    }
+
+    /// Incomplete types don't have a known size: `void`, arrays of unknown size and structs that
+    /// are still being defined.
+    pub fn is_complete(self) -> bool {
+        match *self {
+            TyKind::Void | TyKind::Array { len: None, .. } => false,
+            TyKind::Struct(strukt) => strukt.fields.get().is_some(),
+            TyKind::Array { elem, .. } => elem.is_complete(),
+            _ => true,
+        }
+    }
+
     pub fn unwrap_int(self) -> IntTy {
         match *self {
             TyKind::Int(int) => *int,
@@ -144,3 +309,91 @@ impl<'cx> Ty<'cx> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Qualifiers, TyKind};
+    use crate::LoweringCx;
+
+    #[test]
+    fn display_ptr() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let ptr = lcx.intern_ty(TyKind::Ptr(lcx.types.int.signed, Qualifiers::empty()));
+        assert_eq!(ptr.to_string(), "int *");
+
+        let ptr_ptr = lcx.intern_ty(TyKind::Ptr(
+            lcx.intern_ty(TyKind::Ptr(lcx.types.char, Qualifiers::empty())),
+            Qualifiers::empty(),
+        ));
+        assert_eq!(ptr_ptr.to_string(), "char **");
+
+        let ptr_to_const = lcx.intern_ty(TyKind::Ptr(lcx.types.int.signed, Qualifiers::CONST));
+        assert_eq!(ptr_to_const.to_string(), "const int *");
+
+        let ptr_to_const_ptr = lcx.intern_ty(TyKind::Ptr(
+            lcx.intern_ty(TyKind::Ptr(lcx.types.char, Qualifiers::empty())),
+            Qualifiers::CONST | Qualifiers::VOLATILE,
+        ));
+        assert_eq!(ptr_to_const_ptr.to_string(), "char *const volatile *");
+
+        assert_eq!(lcx.types.long.unsigned.to_string(), "unsigned long");
+    }
+
+    #[test]
+    fn display_array() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let array = lcx.intern_ty(TyKind::Array {
+            elem: lcx.types.int.signed,
+            len: Some(4),
+        });
+        assert_eq!(array.to_string(), "int [4]");
+
+        let ptr_to_array = lcx.intern_ty(TyKind::Ptr(array, Qualifiers::empty()));
+        assert_eq!(ptr_to_array.to_string(), "int (*)[4]");
+
+        let array_of_ptrs = lcx.intern_ty(TyKind::Array {
+            elem: lcx.intern_ty(TyKind::Ptr(lcx.types.int.signed, Qualifiers::empty())),
+            len: None,
+        });
+        assert_eq!(array_of_ptrs.to_string(), "int *[]");
+    }
+
+    #[test]
+    fn classify() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let int = lcx.types.int.signed;
+        let unsigned = lcx.types.long.unsigned;
+        let char = lcx.types.char;
+        let double = lcx.intern_ty(TyKind::Double);
+        let ptr = lcx.intern_ty(TyKind::Ptr(int, Qualifiers::empty()));
+        let array = lcx.intern_ty(TyKind::Array {
+            elem: int,
+            len: Some(2),
+        });
+        let void = lcx.intern_ty(TyKind::Void);
+
+        assert!(int.is_integer() && unsigned.is_integer() && char.is_integer());
+        assert!(!double.is_integer() && !ptr.is_integer());
+
+        assert!(double.is_floating());
+        assert!(!int.is_floating() && !ptr.is_floating());
+
+        assert!(int.is_arithmetic() && char.is_arithmetic() && double.is_arithmetic());
+        assert!(!ptr.is_arithmetic() && !array.is_arithmetic());
+
+        assert!(ptr.is_pointer());
+        assert!(!int.is_pointer() && !array.is_pointer());
+
+        assert!(int.is_scalar() && double.is_scalar() && ptr.is_scalar());
+        assert!(!array.is_scalar() && !void.is_scalar());
+
+        assert!(int.is_signed() && char.is_signed() && double.is_signed());
+        assert!(!unsigned.is_signed() && !ptr.is_signed());
+    }
+}
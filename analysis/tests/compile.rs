@@ -0,0 +1,42 @@
+use analysis::{
+    ir::{self, pretty::DefaultCustomizer},
+    LoweringCx,
+};
+
+#[test]
+fn whole_program() {
+    let src = "
+int square(int x) {
+    return x * x;
+}
+
+int main() {
+    int sum = 0;
+    for (int i = 0; i < 4; i = i + 1) {
+        sum = sum + square(i);
+    }
+    return sum;
+}
+";
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+    let ir = analysis::compile(&mut lcx, src).unwrap();
+
+    assert!(lcx.take_warnings().is_empty());
+    assert_eq!(ir.funcs.len(), 2);
+    let printed = ir::ir_to_string(&ir, &DefaultCustomizer::default());
+    assert!(printed.starts_with("def square(int %x) {"), "{printed}");
+}
+
+#[test]
+fn errors_of_each_phase() {
+    let arena = bumpalo::Bump::new();
+    let mut lcx = LoweringCx::new(&arena);
+
+    let errs = analysis::compile(&mut lcx, "int main() { return 0 }").unwrap_err();
+    assert_eq!(errs.len(), 1);
+
+    let errs = analysis::compile(&mut lcx, "int main() { return x + y; }").unwrap_err();
+    let msgs = errs.iter().map(|err| err.msg.as_str()).collect::<Vec<_>>();
+    assert_eq!(msgs, ["cannot find variable x", "cannot find variable y"]);
+}
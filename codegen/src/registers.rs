@@ -42,8 +42,14 @@
 
 use std::cell::Cell;
 
-use analysis::ir::{self, Func, Location, Register};
-use rustc_hash::FxHashMap;
+use analysis::ir::{
+    self, liveness::Liveness, visit::Visitor, BbIdx, Branch, Func, Location, Operand, Register,
+    Statement, StatementKind,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// The amount of machine registers that can be allocated, see `machine_reg_to_reg`.
+pub const MACHINE_REG_COUNT: usize = 7;
 
 /// A machine register from our register list described in the module documentation.
 #[derive(Debug, Clone, Copy)]
@@ -67,18 +73,175 @@ pub struct FunctionLayout {
     register_uses: FxHashMap<(Location, Register), RegValue>,
 }
 
-pub fn compute_layout(_f: &Func) -> FunctionLayout {
-    let register_uses = FxHashMap::default();
+/// Where an SSA register lives for its whole lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allocation {
+    MachineReg(usize),
+    /// The index of the 8 byte stack slot the register is spilled to.
+    Spill(usize),
+}
+
+pub fn compute_layout(f: &Func) -> FunctionLayout {
+    let allocation = allocate_registers(f, MACHINE_REG_COUNT);
+    let mut register_uses = FxHashMap::default();
+
+    for (i, bb) in f.bbs.iter().enumerate() {
+        let bb_idx = BbIdx::from_usize(i);
+        for (stmt_idx, stmt) in bb.statements.iter().enumerate() {
+            let location = Location::stmt(bb_idx, stmt_idx);
+            let regs = stmt_regs(stmt);
+            for &reg in regs.defs.iter().chain(&regs.uses) {
+                if let Some(&alloc) = allocation.get(&reg) {
+                    let value = match alloc {
+                        Allocation::MachineReg(mach) => RegValue::MachineReg(MachineReg(mach)),
+                        Allocation::Spill(slot) => RegValue::Spilled {
+                            offset: slot as u64 * 8,
+                        },
+                    };
+                    register_uses.insert((location, reg), value);
+                }
+            }
+        }
+    }
 
-    FunctionLayout {
-        register_uses,
+    FunctionLayout { register_uses }
+}
+
+/// Greedily assigns every SSA register to one of `machine_regs` machine registers. Registers
+/// interfere if they are live at the same time, and registers that don't fit are spilled.
+///
+/// The results of allocas are not allocated, they are addresses relative to the stack pointer.
+pub fn allocate_registers(func: &Func<'_>, machine_regs: usize) -> FxHashMap<Register, Allocation> {
+    let interference = interference_graph(func);
+    let allocas = func
+        .bb(BbIdx::ZERO)
+        .statements
+        .iter()
+        .filter_map(|stmt| match stmt.kind {
+            StatementKind::Alloca { result, .. } => Some(result),
+            _ => None,
+        })
+        .collect::<FxHashSet<_>>();
+
+    let mut regs = FxHashMap::default();
+    let mut spill_slots = 0;
+
+    // Registers are mostly numbered in definition order, which is a decent order for coloring.
+    for (i, neighbours) in interference.iter().enumerate() {
+        let reg = Register(i as u32);
+        if allocas.contains(&reg) {
+            continue;
+        }
+
+        let taken = neighbours
+            .iter()
+            .filter_map(|neighbour| match regs.get(neighbour) {
+                Some(&Allocation::MachineReg(mach)) => Some(mach),
+                _ => None,
+            })
+            .collect::<FxHashSet<_>>();
+
+        let allocation = match (0..machine_regs).find(|mach| !taken.contains(mach)) {
+            Some(mach) => Allocation::MachineReg(mach),
+            None => {
+                spill_slots += 1;
+                Allocation::Spill(spill_slots - 1)
+            }
+        };
+        regs.insert(reg, allocation);
     }
+
+    regs
 }
 
-pub struct LayoutPrinter<'a>(Cell<Option<&'a Func<'a>>>, &'a FunctionLayout);
+/// Computes which registers are live at the same time as every register.
+fn interference_graph(func: &Func<'_>) -> Vec<FxHashSet<Register>> {
+    let liveness = Liveness::compute(func);
+    let mut interference = vec![FxHashSet::default(); func.regs.len()];
+    let mut interfere = |a: Register, b: Register| {
+        if a != b {
+            interference[a.as_usize()].insert(b);
+            interference[b.as_usize()].insert(a);
+        }
+    };
+
+    // The parameters are all live at the same time when entering the function.
+    for &(a, _) in &func.args {
+        for &(b, _) in &func.args {
+            interfere(a, b);
+        }
+    }
+
+    for (i, bb) in func.bbs.iter().enumerate() {
+        // Walk the block backwards, a definition interferes with everything live after it.
+        let mut live = liveness.live_out(BbIdx::from_usize(i)).clone();
+        if let Branch::Switch { cond: op, .. } | Branch::Ret(op) = bb.term {
+            if let Operand::Reg(reg) = op {
+                live.insert(reg);
+            }
+        }
 
-impl<'a> ir::pretty::Customizer<'a> for LayoutPrinter<'a> {
-    fn start_func(&self, func: &'a Func<'a>) {
+        for stmt in bb.statements.iter().rev() {
+            let regs = stmt_regs(stmt);
+            for &def in &regs.defs {
+                for &other in &live {
+                    interfere(def, other);
+                }
+                live.remove(&def);
+            }
+            // Phi operands are used at the end of the predecessors, liveness accounts for that.
+            if !matches!(stmt.kind, StatementKind::Phi { .. }) {
+                live.extend(regs.uses);
+            }
+        }
+
+        // All phis of a block are defined at the same time.
+        let phis = bb
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt.kind {
+                StatementKind::Phi { result, .. } => Some(result),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for &a in &phis {
+            for &b in &phis {
+                interfere(a, b);
+            }
+        }
+    }
+
+    interference
+}
+
+#[derive(Default)]
+struct StmtRegs {
+    defs: Vec<Register>,
+    uses: Vec<Register>,
+}
+
+impl Visitor for StmtRegs {
+    fn visit_reg(&mut self, reg: Register) {
+        self.defs.push(reg);
+    }
+
+    fn visit_operand(&mut self, op: Operand<'_>) {
+        if let Operand::Reg(reg) = op {
+            self.uses.push(reg);
+        }
+    }
+}
+
+fn stmt_regs(stmt: &Statement<'_>) -> StmtRegs {
+    let mut regs = StmtRegs::default();
+    regs.visit_statement(stmt);
+    regs
+}
+
+pub struct LayoutPrinter<'a, 'cx>(Cell<Option<&'a Func<'cx>>>, &'a FunctionLayout);
+
+impl<'a, 'cx> ir::pretty::Customizer<'a, 'cx> for LayoutPrinter<'a, 'cx> {
+    fn start_func(&self, func: &'a Func<'cx>) {
         self.0.set(Some(func));
     }
 
@@ -118,3 +281,72 @@ pub fn debug_layout(func: &Func, layout: &FunctionLayout) {
 
     dbg!(layout);
 }
+
+#[cfg(test)]
+mod tests {
+    use analysis::{
+        define_ir_func,
+        ir::{BbIdx, Register},
+        LoweringCx,
+    };
+
+    use super::{allocate_registers, Allocation};
+
+    #[test]
+    fn spill() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        // %0, %1 and %2 are all live when %3 is computed.
+        let f = define_ir_func! {
+            def(lcx) name (), regs(5) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(1) };
+                    BinOp { result: Register(1), kind: BinKind::Add, lhs: op(2), rhs: op(2) };
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(3), rhs: op(3) };
+                    BinOp { result: Register(3), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(Register(1)) };
+                    BinOp { result: Register(4), kind: BinKind::Add, lhs: op(Register(3)), rhs: op(Register(2)) }
+                    => Ret(op(Register(4)))
+                }
+            }
+        };
+
+        let allocation = allocate_registers(&f, 2);
+
+        assert_eq!(allocation[&Register(0)], Allocation::MachineReg(0));
+        assert_eq!(allocation[&Register(1)], Allocation::MachineReg(1));
+        assert_eq!(allocation[&Register(2)], Allocation::Spill(0));
+        // %0 and %1 are dead after %3, so their registers can be reused.
+        assert_eq!(allocation[&Register(3)], Allocation::MachineReg(0));
+        assert_eq!(allocation[&Register(4)], Allocation::MachineReg(0));
+    }
+
+    #[test]
+    fn no_interference_across_blocks() {
+        let arena = bumpalo::Bump::new();
+        let lcx = LoweringCx::new(&arena);
+
+        let f = define_ir_func! {
+            def(lcx) name (), regs(3) {
+                0: {
+                    BinOp { result: Register(0), kind: BinKind::Add, lhs: op(1), rhs: op(1) }
+                    => Switch { cond: op(Register(0)), yes: BbIdx(1), no: BbIdx(2) }
+                }
+                1: {
+                    BinOp { result: Register(1), kind: BinKind::Add, lhs: op(1), rhs: op(1) }
+                    => Ret(op(Register(1)))
+                }
+                2: {
+                    BinOp { result: Register(2), kind: BinKind::Add, lhs: op(Register(0)), rhs: op(1) }
+                    => Ret(op(Register(2)))
+                }
+            }
+        };
+
+        let allocation = allocate_registers(&f, 1);
+
+        assert!(allocation
+            .values()
+            .all(|alloc| matches!(alloc, Allocation::MachineReg(0))));
+    }
+}
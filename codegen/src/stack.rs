@@ -21,6 +21,7 @@ pub fn allocate_stack_space<'cx>(start_align: u64, func: &Func<'cx>) -> StackLay
             result,
             size,
             align,
+            ..
         } = stmt.kind
         {
             if size != 8 || align != 8 {
@@ -190,6 +190,7 @@ impl<'cx> AsmCtxt<'_, 'cx> {
                         value,
                         size,
                         align,
+                        volatile: _,
                     } => {
                         self.gen_store(ptr, value, size, align)?;
                     }
@@ -198,6 +199,7 @@ impl<'cx> AsmCtxt<'_, 'cx> {
                         ptr,
                         size,
                         align,
+                        volatile: _,
                     } => {
                         self.gen_load(loc, result, ptr, size, align)?;
                     }
@@ -207,6 +209,13 @@ impl<'cx> AsmCtxt<'_, 'cx> {
                     }
                     StatementKind::PtrOffset { .. } => todo!("pointer offset :D"),
                     StatementKind::Call { .. } => todo!("function calls 💀"),
+                    StatementKind::Phi { .. } => {
+                        // Phis come from mem2reg and from lowering the conditional operator.
+                        return Err(Error::new(
+                            "phi nodes are not supported by codegen yet",
+                            span,
+                        ));
+                    }
                 }
             }
 
@@ -221,6 +230,10 @@ impl<'cx> AsmCtxt<'_, 'cx> {
                 }
                 Branch::Switch { .. } => todo!("switch"),
                 Branch::Goto(_) => todo!("goto"),
+                Branch::Unreachable => {
+                    self.a.ud2().sp(self)?;
+                    break;
+                }
             }
         }
 
@@ -229,7 +242,7 @@ impl<'cx> AsmCtxt<'_, 'cx> {
 }
 
 pub fn generate_func<'cx>(lcx: &'cx LoweringCx<'cx>, func: &Func<'cx>) -> Result<Vec<u8>> {
-    assert_eq!(func.arity, 0, "arguments??? in MY uwucc????");
+    assert!(func.args.is_empty(), "arguments??? in MY uwucc????");
 
     let layout = crate::registers::compute_layout(func);
     crate::registers::debug_layout(func, &layout);
@@ -12,16 +12,66 @@ pub type Ident = Spanned<Symbol>;
 // --- Expr
 //
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub enum Atom {
     Ident(Ident),
-    Int(u128),
-    Float(f64),
+    Int(IntConstant),
+    Float(FloatConstant),
     String(Vec<u8>),
     Char(u8),
 }
 
-#[derive(Debug, DebugPls)]
+/// (6.4.4.1) An integer constant like `0x1Ful`. Its type is the first one from a list that can
+/// represent the value, the list depends on the suffix and whether it's decimal.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntConstant {
+    pub value: u128,
+    /// `u` suffix.
+    pub unsigned: bool,
+    /// The smallest rank it can have, `Long` for `l` and `LongLong` for `ll` suffixes.
+    pub kind: IntTyKind,
+    /// Decimal constants without `u` suffix are always signed, others can also be unsigned.
+    pub decimal: bool,
+}
+
+impl DebugPls for IntConstant {
+    fn fmt(&self, f: dbg_pls::Formatter<'_>) {
+        // Most constants are plain decimal numbers, there the value is all that matters.
+        if self.decimal && !self.unsigned && self.kind == IntTyKind::Int {
+            DebugPls::fmt(&self.value, f);
+        } else {
+            f.debug_struct("IntConstant")
+                .field("value", &self.value)
+                .field("unsigned", &self.unsigned)
+                .field("kind", &self.kind)
+                .field("decimal", &self.decimal)
+                .finish();
+        }
+    }
+}
+
+/// (6.4.4.2) A floating constant like `1.5f`. Without a suffix, it's a `double`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone, Copy, PartialEq)]
+pub struct FloatConstant {
+    pub value: f64,
+    pub kind: FloatKind,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone, Copy, PartialEq, Eq)]
+pub enum FloatKind {
+    /// `f` suffix.
+    Float,
+    Double,
+    /// `l` suffix.
+    LongDouble,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub enum UnaryOp {
     Increment,
     Decrement,
@@ -33,7 +83,8 @@ pub enum UnaryOp {
     Bang,
 }
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub enum ArithOpKind {
     Mul,
     Div,
@@ -47,7 +98,8 @@ pub enum ArithOpKind {
     BitOr,
 }
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub enum ComparisonKind {
     Lt,
     Gt,
@@ -57,7 +109,8 @@ pub enum ComparisonKind {
     Neq,
 }
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub enum BinaryOp {
     Arith(ArithOpKind),
     LogicalAnd,
@@ -68,20 +121,23 @@ pub enum BinaryOp {
     Assign(Option<ArithOpKind>),
 }
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub struct ExprUnary {
     pub rhs: Box<Spanned<Expr>>,
     pub op: UnaryOp,
 }
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub struct ExprBinary {
     pub lhs: Box<Spanned<Expr>>,
     pub rhs: Box<Spanned<Expr>>,
     pub op: BinaryOp,
 }
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub enum PostfixOp {
     Call(Vec<Spanned<Expr>>),
     Member(Ident),
@@ -90,24 +146,41 @@ pub enum PostfixOp {
     Decrement,
 }
 
-#[derive(Debug, DebugPls)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub struct ExprPostfix {
     pub lhs: Box<Spanned<Expr>>,
     pub op: PostfixOp,
 }
 
-#[derive(Debug, DebugPls)]
+/// (6.5.15) `cond ? then : otherwise`
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
+pub struct ExprConditional {
+    pub cond: Box<Spanned<Expr>>,
+    pub then: Box<Spanned<Expr>>,
+    pub otherwise: Box<Spanned<Expr>>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
 pub enum Expr {
     Atom(Atom),
     Unary(ExprUnary),
     Binary(ExprBinary),
     Postfix(ExprPostfix),
+    Conditional(ExprConditional),
+    /// `sizeof x`
+    SizeOfExpr(Box<Spanned<Expr>>),
+    /// `sizeof(int)`
+    SizeOfType(TypeName),
 }
 
 //
 // --- Statements
 //
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls)]
 pub enum Stmt {
     Decl(Decl),
@@ -121,11 +194,26 @@ pub enum Stmt {
         then: Vec<Spanned<Stmt>>,
         otherwise: Option<Vec<Spanned<Stmt>>>,
     },
-    Switch,
+    Switch {
+        cond: Spanned<Expr>,
+        body: Vec<Spanned<Stmt>>,
+    },
+    /// `case 1: stmt`, only valid inside of a `switch`.
+    Case {
+        value: Spanned<Expr>,
+        stmt: Box<Spanned<Stmt>>,
+    },
+    /// `default: stmt`, only valid inside of a `switch`.
+    Default(Box<Spanned<Stmt>>),
     While {
-        cond: Expr,
+        cond: Spanned<Expr>,
         body: Vec<Spanned<Stmt>>,
     },
+    /// `do body while (cond);`, the body runs once before the condition is checked.
+    DoWhile {
+        body: Vec<Spanned<Stmt>>,
+        cond: Spanned<Expr>,
+    },
     For {
         init_decl: Option<Spanned<Decl>>,
         init_expr: Option<Spanned<Expr>>,
@@ -144,6 +232,7 @@ pub enum Stmt {
 // --- Types and decls and garbage whatever
 //
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IntSign {
     Signed,
@@ -157,6 +246,7 @@ impl Default for IntSign {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 // N.B: Ord, order matters.
 pub enum IntTyKind {
@@ -168,9 +258,11 @@ pub enum IntTyKind {
     LongLong,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct IntTy(pub IntSign, pub IntTyKind);
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone)]
 pub enum TypeSpecifier {
     Void,
@@ -179,19 +271,41 @@ pub enum TypeSpecifier {
     Float,
     Double,
     LongDouble,
+    Struct(Box<StructSpecifier>),
     // TODO
     // complex
     // atomic-type-specifier
-    // struct-or-union-specifier
+    // union-specifier
     // enum-specifier
     // typedef-name
 }
 
+/// (6.7.2.1) struct-or-union-specifier, only structs are supported for now.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
+pub struct StructSpecifier {
+    pub name: Option<Ident>,
+    /// `None` if the struct is only referred to and not defined: `struct S`.
+    pub fields: Option<Vec<Spanned<StructFieldDecl>>>,
+}
+
+/// A field declaration in a struct, like `int a, *b;`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
+pub struct StructFieldDecl {
+    pub decl_spec: Spanned<DeclSpec>,
+    pub declarators: Vec<Spanned<Declarator>>,
+}
+
 bitflags! {
     pub struct DeclAttr: u8 {
         const EXTERN = 0b00000001;
         const STATIC = 0b00000010;
         const THREAD_LOCAL = 0b00000100;
+        /// (6.7.4) function-specifier `inline`
+        const INLINE = 0b00001000;
+        /// (6.7.4) function-specifier `_Noreturn`
+        const NORETURN = 0b00010000;
     }
 }
 
@@ -204,57 +318,154 @@ impl DebugPls for DeclAttr {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for DeclAttr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{self:?}"))
+    }
+}
+
+bitflags! {
+    /// (6.7.3) type-qualifier
+    pub struct Qualifiers: u8 {
+        const CONST = 0b00000001;
+        const VOLATILE = 0b00000010;
+    }
+}
+
+impl DebugPls for Qualifiers {
+    fn fmt(&self, f: dbg_pls::Formatter<'_>) {
+        use std::fmt::Write;
+        let mut string = String::new();
+        write!(string, "{:?}", self).unwrap();
+        DebugPls::fmt(&string, f);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Qualifiers {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{self:?}"))
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone)]
 pub struct DeclSpec {
     pub ty: TypeSpecifier,
     pub attrs: DeclAttr,
+    pub quals: Qualifiers,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls)]
 pub enum Decl {
     Normal(NormalDecl),
-    StaticAssert,
+    StaticAssert(StaticAssertDecl),
+}
+
+/// `_Static_assert(cond, "msg")`
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls)]
+pub struct StaticAssertDecl {
+    pub cond: Box<Spanned<Expr>>,
+    pub msg: String,
+}
+
+/// (6.7.9) initializer
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls)]
+pub enum Initializer {
+    /// `int x = 1`
+    Expr(Expr),
+    /// `int a[2] = { 1, 2 }`, the elements can be lists themselves.
+    List(Vec<InitListElem>),
+}
+
+/// An element of an initializer list, optionally with a designation: `.x = 1`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls)]
+pub struct InitListElem {
+    /// Empty if the element isn't designated. `.a[1] = 0` has two designators.
+    pub designators: Vec<Designator>,
+    pub init: Spanned<Initializer>,
 }
 
+/// (6.7.9) designator
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls)]
+pub enum Designator {
+    /// `[2]`
+    Index(Spanned<Expr>),
+    /// `.field`
+    Field(Ident),
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls)]
 pub struct InitDecl {
     pub declarator: Declarator,
-    pub init: Option<Spanned<Expr>>,
+    pub init: Option<Spanned<Initializer>>,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls)]
 pub struct NormalDecl {
     pub decl_spec: DeclSpec,
     pub init_declarators: Vec<Spanned<InitDecl>>,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone)]
 pub struct FunctionParamDecl {
     pub decl_spec: Spanned<DeclSpec>,
     pub declarator: Spanned<Declarator>,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone)]
 pub enum DirectDeclarator {
     Ident(Ident),
     WithParams {
         ident: Ident,
-        params: Vec<FunctionParamDecl>,
+        /// `None` for `()`, which leaves the parameters unspecified. `(void)` has no parameters.
+        params: Option<Vec<FunctionParamDecl>>,
+        /// Whether the params end with `, ...`.
+        variadic: bool,
+    },
+    /// `decl[size]`, the size is optional: `int a[]`.
+    Array {
+        decl: Box<DirectDeclarator>,
+        size: Option<Box<Spanned<Expr>>>,
     },
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls, Clone)]
 pub struct Declarator {
     pub decl: DirectDeclarator,
     pub pointer: bool,
+    /// The qualifiers of the pointer itself, like the `const` in `int *const p`.
+    pub pointer_quals: Qualifiers,
 }
 
+/// A type without a name, like in `sizeof(int*)`.
+/// Only pointers are supported as abstract declarators for now.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, DebugPls, Clone)]
+pub struct TypeName {
+    pub decl_spec: Spanned<DeclSpec>,
+    pub pointer: bool,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls)]
 pub struct FunctionDef {
     pub decl: Decl,
     pub body: Vec<Spanned<Stmt>>,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(Debug, DebugPls)]
 pub enum ExternalDecl {
     Decl(Decl),
@@ -267,7 +478,7 @@ impl Decl {
     pub fn unwrap_normal(&self) -> &NormalDecl {
         match self {
             Decl::Normal(decl) => decl,
-            Decl::StaticAssert => {
+            Decl::StaticAssert(_) => {
                 panic!("Expected normal declaration, found static assert declaration")
             }
         }
@@ -275,19 +486,23 @@ impl Decl {
 }
 
 impl DirectDeclarator {
-    pub fn unwrap_with_params(&self) -> (&Ident, &Vec<FunctionParamDecl>) {
+    pub fn unwrap_with_params(&self) -> (&Ident, Option<&Vec<FunctionParamDecl>>) {
         match self {
             DirectDeclarator::Ident(_) => {
                 panic!("Expected declarator with parameters, found single identifier declarator1")
             }
-            DirectDeclarator::WithParams { ident, params } => (ident, params),
+            DirectDeclarator::Array { .. } => {
+                panic!("Expected declarator with parameters, found array declarator")
+            }
+            DirectDeclarator::WithParams { ident, params, .. } => (ident, params.as_ref()),
         }
     }
 
     pub fn name(&self) -> Ident {
-        match *self {
-            DirectDeclarator::Ident(ident) => ident,
-            DirectDeclarator::WithParams { ident, .. } => ident,
+        match self {
+            DirectDeclarator::Ident(ident) => *ident,
+            DirectDeclarator::WithParams { ident, .. } => *ident,
+            DirectDeclarator::Array { decl, .. } => decl.name(),
         }
     }
 }
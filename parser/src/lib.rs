@@ -12,15 +12,18 @@ pub mod ast;
 mod parser;
 mod pre;
 pub mod pretty;
+mod source_map;
 mod sym;
-mod token;
+pub mod token;
 
+pub use source_map::{LineCol, SourceMap};
 pub use sym::Symbol;
 
 pub use crate::parser::Parser;
 
 pub type Spanned<T> = (T, Span);
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[derive(PartialEq, Eq, Clone, Copy, Default)]
 pub struct Span {
     pub start: usize,
@@ -40,6 +43,11 @@ impl Span {
         Self::start_end(self.start, rhs.end)
     }
 
+    /// The smallest span covering both spans, in whatever order they are.
+    pub fn merge(self, other: Self) -> Self {
+        Self::start_end(self.start.min(other.start), self.end.max(other.end))
+    }
+
     pub fn extend_option(&self, rhs: Option<Self>) -> Self {
         rhs.map(|s| self.extend(s)).unwrap_or(*self)
     }
@@ -100,6 +108,56 @@ impl Error {
         });
         self
     }
+
+    /// Renders the error with the line of `src` it points to, underlining the span:
+    ///
+    /// ```text
+    /// error: cannot find variable x
+    ///  --> 1:18
+    ///   |
+    /// 1 | int f() { return x; }
+    ///   |                  ^
+    /// ```
+    ///
+    /// Spans over multiple lines are underlined until the end of their first line.
+    pub fn render(&self, src: &str) -> String {
+        let mut out = String::new();
+        render_message(&mut out, src, "error", &self.msg, self.span);
+        for note in &self.notes {
+            render_message(&mut out, src, "note", &note.msg, note.span);
+        }
+        out
+    }
+}
+
+fn render_message(out: &mut String, src: &str, level: &str, msg: &str, span: Option<Span>) {
+    use std::fmt::Write;
+
+    writeln!(out, "{level}: {msg}").unwrap();
+    let Some(span) = span else {
+        return;
+    };
+
+    let start = span.start.min(src.len());
+    let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+    let line = &src[line_start..line_end];
+    let line_number = src[..line_start].matches('\n').count() + 1;
+    let column = src[line_start..start].chars().count() + 1;
+
+    let end = span.end.clamp(start, line_end);
+    // Keep tabs so that the carets line up with the source line.
+    let indent = src[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    let carets = "^".repeat(src[start..end].chars().count().max(1));
+
+    let gutter = " ".repeat(line_number.to_string().len());
+    writeln!(out, "{gutter}--> {line_number}:{column}").unwrap();
+    writeln!(out, "{gutter} |").unwrap();
+    writeln!(out, "{line_number} | {line}").unwrap();
+    writeln!(out, "{gutter} | {indent}{carets}").unwrap();
 }
 
 impl DebugPls for Error {
@@ -116,7 +174,211 @@ fn lex_and_pre(src: &str) -> impl Iterator<Item = (Token<'_>, Span)> + '_ {
     token::pre_tokens_to_tokens(pre_tokens)
 }
 
+/// Splits the source into tokens, for tools that don't need the AST. Invalid tokens are returned
+/// as errors in their place. With `trivia`, whitespace and comments are returned as tokens too.
+pub fn tokenize(
+    src: &str,
+    trivia: bool,
+) -> impl Iterator<Item = Result<Spanned<Token<'_>>, Error>> + '_ {
+    let pre_tokens: Box<dyn Iterator<Item = _>> = if trivia {
+        Box::new(pre::preprocess_tokens_with_trivia(src))
+    } else {
+        Box::new(pre::preprocess_tokens(src))
+    };
+    token::pre_tokens_to_tokens(pre_tokens).map(|(token, span)| match token {
+        Token::Error => Err(Error::new("invalid token", span)),
+        token => Ok((token, span)),
+    })
+}
+
 pub fn parse_file(src: &str) -> Result<TranslationUnit, Error> {
     let lexer = lex_and_pre(src);
     parser::parse_declarations(lexer)
 }
+
+/// Parses the source into a translation unit. Malformed input is reported as errors and never
+/// panics, which makes this the entry point to use for fuzzing.
+pub fn parse(src: &str) -> Result<TranslationUnit, Vec<Error>> {
+    parse_file(src).map_err(|err| vec![err])
+}
+
+/// Renders the AST as C source. Every subexpression is parenthesized, so parsing the output
+/// again gives the same AST, just with different spans.
+pub fn print_c(tu: &TranslationUnit) -> String {
+    let mut out = Vec::new();
+    pretty::PrettyPrinter::c_source(&mut out)
+        .translation_unit(tu)
+        .expect("writing to a Vec can't fail");
+    String::from_utf8(out).expect("identifiers are UTF-8 and everything else is escaped")
+}
+
+/// Serializes the AST to JSON for other tools. Enums are tagged with their variant name, like
+/// `{"Return":...}`, and spans are `{"start":0,"end":3}`.
+#[cfg(feature = "serde")]
+pub fn ast_to_json(tu: &TranslationUnit) -> String {
+    serde_json::to_string(tu).expect("the AST serializes to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Span};
+
+    #[test]
+    fn render_error() {
+        let src = "int main() {\n    return x + 1;\n}\n";
+        let start = src.find('x').unwrap();
+        let err = Error::new("cannot find variable x", Span::start_end(start, start + 5))
+            .note_spanned("in this function", Span::start_end(4, src.len()));
+
+        assert_eq!(
+            err.render(src),
+            "error: cannot find variable x
+ --> 2:12
+  |
+2 |     return x + 1;
+  |            ^^^^^
+note: in this function
+ --> 1:5
+  |
+1 | int main() {
+  |     ^^^^^^^^
+"
+        );
+    }
+
+    #[test]
+    fn tokenize() {
+        use crate::token::{Constant, Keyword, Punctuator, Token};
+
+        let tokens = super::tokenize("int x = 1;", false)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            tokens[..],
+            [
+                (Token::Kw(Keyword::Int), _),
+                (Token::Ident("x"), _),
+                (Token::Punct(Punctuator::Eq), _),
+                (Token::Constant(Constant::Int(_)), _),
+                (Token::Punct(Punctuator::Semicolon), _),
+            ]
+        ));
+        let spans = tokens.iter().map(|&(_, span)| span).collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [(0, 3), (4, 5), (6, 7), (8, 9), (9, 10)]
+                .map(|(start, end)| Span::start_end(start, end))
+        );
+
+        let trivia = super::tokenize("x /* a */ // b\n", true)
+            .map(|token| token.unwrap().0.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(trivia, ["x", " ", "/* a */", " ", "// b", "\n"]);
+
+        let errors = super::tokenize("int @;", false)
+            .filter_map(Result::err)
+            .map(|err| (err.msg, err.span))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [("invalid token".to_owned(), Some(Span::start_end(4, 5)))]
+        );
+    }
+
+    #[test]
+    fn parse_malformed() {
+        let snippets = [
+            "int",
+            "int x",
+            "int f(",
+            "int f(int",
+            "int f(int,) {}",
+            "int f() {",
+            "int f() { return",
+            "int f() { return 1 +; }",
+            "int f() { if }",
+            "int f() { while (1 }",
+            "int f() { for (;;; ) {} }",
+            "int f() { switch }",
+            "int f() { goto; }",
+            "int f() { x[; }",
+            "int f() { f(1,; }",
+            "int f() { (int; }",
+            "int f() { sizeof; }",
+            "int f() { a ? b; }",
+            "int f() { a.; }",
+            "int f() { a->1; }",
+            "int f() { return 99999999999999999999999999999999999999999; }",
+            "int f() { return 1e; }",
+            "int f() { return 0x; }",
+            "int f() { return '; }",
+            "int f() { return 'ab'; }",
+            "int f() { return \"\\q\"; }",
+            "int f() { return \"abc; }",
+            "int a[;",
+            "int a[1;",
+            "int *;",
+            "int * const;",
+            "struct { int x; ",
+            "enum { A = };",
+            "enum",
+            "_Static_assert(1);",
+            "_Static_assert(1, );",
+            "int f() {} }",
+            "@",
+            "int f() { int x = @; }",
+            "#include <stdio.h>",
+            "int f() { /* unterminated",
+            "\\",
+            "int x = \"\\xZZ\";",
+            "int ü;",
+            ")",
+            "{",
+            "int f(void, int x) {}",
+            "int f(...) {}",
+            "int f() { default }",
+            "int f() { break }",
+            "int f() { label }",
+            "long long long x;",
+            "signed unsigned x;",
+            "int int x;",
+            "const;",
+        ];
+        for src in snippets {
+            assert!(super::parse(src).is_err(), "{src:?} parsed successfully");
+        }
+    }
+
+    #[test]
+    fn parse_deeply_nested() {
+        // This runs on the default stack of a test thread, so the limit keeps unoptimized builds
+        // from overflowing it.
+        let parens = |n| format!("int x = {}1{};", "(".repeat(n), ")".repeat(n));
+        let ifs = |n| format!("int f() {{ {}; }}", "if (1) ".repeat(n));
+        let params = |n| format!("int {}x{};", "f(int ".repeat(n), ")".repeat(n));
+        let structs = |n| format!("{}int x; {}", "struct { ".repeat(n), "} s; ".repeat(n));
+        let nests: [fn(usize) -> String; 4] = [parens, ifs, params, structs];
+        for nest in nests {
+            assert!(super::parse(&nest(90)).is_ok());
+            let errors = super::parse(&nest(110)).unwrap_err();
+            assert_eq!(errors[0].msg, "nesting is deeper than the limit of 100");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ast_to_json() {
+        let ast = crate::parse_file("int main(void) { return 1 + x; }").unwrap();
+        let json = super::ast_to_json(&ast);
+        for tag in [
+            r#"{"FunctionDef":"#,
+            r#""decl_spec":"#,
+            r#"{"Integer":["Signed","Int"]}"#,
+            r#"{"Return":[{"Binary":{"lhs":"#,
+            r#"{"Atom":{"Ident":["x",{"start":28,"end":29}]}}"#,
+            r#""op":{"Arith":"Add"}"#,
+        ] {
+            assert!(json.contains(tag), "missing {tag} in {json}");
+        }
+    }
+}
@@ -2,11 +2,12 @@ use peekmore::PeekMoreIterator;
 
 use crate::{
     ast::{
-        Decl, DeclAttr, DeclSpec, Declarator, DirectDeclarator, ExternalDecl, FunctionDef,
-        FunctionParamDecl, Ident, InitDecl, IntSign, IntTy, IntTyKind, NormalDecl, Stmt,
-        TranslationUnit, TypeSpecifier,
+        Decl, DeclAttr, DeclSpec, Declarator, Designator, DirectDeclarator, ExternalDecl,
+        FunctionDef, FunctionParamDecl, Ident, InitDecl, InitListElem, Initializer, IntSign, IntTy,
+        IntTyKind, NormalDecl, Qualifiers, StaticAssertDecl, Stmt, StructFieldDecl,
+        StructSpecifier, TranslationUnit, TypeName, TypeSpecifier,
     },
-    pre::Punctuator as P,
+    pre::{self, Punctuator as P},
     sym::Symbol,
     token::{Keyword as Kw, Token as Tok},
     Error, Span, Spanned,
@@ -26,11 +27,18 @@ impl Error {
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// How deeply statements, expressions, structs and parameter lists can be nested. Deeper input is
+/// rejected instead of overflowing the stack, this fits into the default 2 MiB stack of a thread
+/// even in unoptimized builds.
+const MAX_NESTING_DEPTH: usize = 100;
+
 pub struct Parser<'src, I>
 where
     I: Iterator<Item = (Tok<'src>, Span)>,
 {
     lex: PeekMoreIterator<I>,
+    /// The current nesting depth, see [`Parser::nested`].
+    depth: usize,
 }
 
 // HACK: It's called `_parser` as a workaround this being ambiguous with the `#[expect]` attribute
@@ -117,6 +125,22 @@ where
         self.lex.peek_nth(n).ok_or_else(Error::eof)
     }
 
+    /// Parses something that can contain itself, like the body of an `if` or the operand of a
+    /// unary operator, one nesting level deeper.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth == MAX_NESTING_DEPTH {
+            let &(_, span) = self.peek_t()?;
+            return Err(Error::new(
+                format!("nesting is deeper than the limit of {MAX_NESTING_DEPTH}"),
+                span,
+            ));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
     fn ident(&mut self) -> Result<Ident> {
         match self.next_t()? {
             (Tok::Ident(ident), span) => Ok((Symbol::intern(ident), span)),
@@ -155,8 +179,8 @@ where
     ///
     /// This does NOT eat the semicolon!
     fn declaration(&mut self) -> Result<Spanned<Decl>> {
-        if let Some((tok, span)) = eat!(self, Tok::Kw(Kw::StaticAssert)) {
-            return Err(Error::unsupported(span, &tok));
+        if let Some((_, span)) = eat!(self, Tok::Kw(Kw::StaticAssert)) {
+            return self.static_assert_declaration(span);
         }
 
         let (decl_spec, span) = self.decl_specifiers()?;
@@ -175,6 +199,36 @@ where
         ))
     }
 
+    /// (6.7.10) static_assert-declaration:
+    ///     _Static_assert ( constant-expression , string-literal ) ;
+    ///
+    /// This does NOT eat the semicolon!
+    fn static_assert_declaration(&mut self, kw_span: Span) -> Result<Spanned<Decl>> {
+        expect!(self, Tok::Punct(P::ParenOpen));
+        let cond = self.assignment_expr()?;
+        expect!(self, Tok::Punct(P::Comma));
+        let msg = match self.next_t()? {
+            (Tok::StringLiteral(msg), _) => {
+                String::from_utf8_lossy(&pre::unescape(msg)).into_owned()
+            }
+            (tok, span) => {
+                return Err(Error::new(
+                    format!("expected string literal, found `{tok}`"),
+                    span,
+                ))
+            }
+        };
+        let span = expect!(self, Tok::Punct(P::ParenClose));
+
+        Ok((
+            Decl::StaticAssert(StaticAssertDecl {
+                cond: Box::new(cond),
+                msg,
+            }),
+            kw_span.extend(span),
+        ))
+    }
+
     /// init-declarator-list:
     ///     init-declarator
     ///     init-declarator-list , init-declarator
@@ -194,8 +248,7 @@ where
 
             let (declarator, span) = self.declarator()?;
             let init = if eat!(self, Tok::Punct(P::Eq)).is_some() {
-                let expr = self.expr()?;
-                Some(expr)
+                Some(self.initializer()?)
             } else {
                 None
             };
@@ -206,141 +259,356 @@ where
         Ok(init_decls)
     }
 
+    /// (6.7.9) initializer:
+    ///     assignment-expression
+    ///     { initializer-list }
+    ///     { initializer-list , }
+    ///
+    /// initializer-list:
+    ///     designation.opt initializer
+    ///     initializer-list , designation.opt initializer
+    fn initializer(&mut self) -> Result<Spanned<Initializer>> {
+        let Some((_, open_span)) = eat!(self, Tok::Punct(P::BraceOpen)) else {
+            let (expr, span) = self.assignment_expr()?;
+            return Ok((Initializer::Expr(expr), span));
+        };
+
+        let mut elems = Vec::new();
+        let close_span = loop {
+            if let Some((_, span)) = eat!(self, Tok::Punct(P::BraceClose)) {
+                break span;
+            }
+            let designators = self.designation()?;
+            let init = self.nested(Self::initializer)?;
+            elems.push(InitListElem { designators, init });
+            if eat!(self, Tok::Punct(P::Comma)).is_none() {
+                break expect!(self, Tok::Punct(P::BraceClose));
+            }
+        };
+        Ok((Initializer::List(elems), open_span.extend(close_span)))
+    }
+
+    /// (6.7.9) designation:
+    ///     designator-list =
+    ///
+    /// designator:
+    ///     [ constant-expression ]
+    ///     . identifier
+    ///
+    /// Returns no designators if there is no designation.
+    fn designation(&mut self) -> Result<Vec<Designator>> {
+        let mut designators = Vec::new();
+        loop {
+            if eat!(self, Tok::Punct(P::BracketOpen)).is_some() {
+                let index = self.assignment_expr()?;
+                expect!(self, Tok::Punct(P::BracketClose));
+                designators.push(Designator::Index(index));
+            } else if eat!(self, Tok::Punct(P::Dot)).is_some() {
+                designators.push(Designator::Field(self.ident()?));
+            } else {
+                break;
+            }
+        }
+        if !designators.is_empty() {
+            expect!(self, Tok::Punct(P::Eq));
+        }
+        Ok(designators)
+    }
+
     /// (6.7) declaration-specifiers:
     ///   storage-class-specifier declaration-specifiers.opt
     ///   type-specifier declaration-specifiers.opt
     ///   type-qualifier declaration-specifiers.opt
     ///   function-specifier declaration-specifiers.opt
     ///   alignment-specifier declaration-specifiers.opt
+    ///
+    /// The keywords of arithmetic types can be mixed with the other specifiers, like in
+    /// `long static long`, so they are collected over the whole list and resolved at the end.
     fn decl_specifiers(&mut self) -> Result<Spanned<DeclSpec>> {
         let mut decl_attr = DeclAttr::empty();
+        let mut quals = Qualifiers::empty();
+        let mut words = Vec::new();
+        let mut strukt = None;
         let &(_, initial_span) = self.peek_t()?;
-        let (ty, span) = loop {
-            match self.peek_t()?.0 {
+        let mut end_span = initial_span;
+        loop {
+            let &(tok, span) = self.peek_t()?;
+            end_span = match tok {
                 // (6.7.1) storage-class-specifier
                 Tok::Kw(Kw::Typedef | Kw::Auto | Kw::Register) => {
                     self.next_t()?; // ignore
+                    span
                 }
                 Tok::Kw(Kw::Extern) => {
                     self.next_t()?;
                     decl_attr |= DeclAttr::EXTERN;
+                    span
                 }
                 Tok::Kw(Kw::Static) => {
                     self.next_t()?;
                     decl_attr |= DeclAttr::STATIC;
+                    span
                 }
                 Tok::Kw(Kw::ThreadLocal) => {
                     self.next_t()?;
                     decl_attr |= DeclAttr::THREAD_LOCAL;
+                    span
                 }
                 // (6.7.3) type-qualifier:
-                Tok::Kw(Kw::Const | Kw::Restrict | Kw::Volatile | Kw::Atomic) => {
-                    self.next_t()?; // ignore
+                Tok::Kw(Kw::Const | Kw::Volatile | Kw::Restrict | Kw::Atomic) => {
+                    quals |= self.type_qualifier()?;
+                    span
                 }
                 //  (6.7.4) function-specifier:
-                Tok::Kw(Kw::Inline | Kw::Noreturn) => {
-                    self.next_t()?; // ignore
+                Tok::Kw(Kw::Inline) => {
+                    self.next_t()?;
+                    decl_attr |= DeclAttr::INLINE;
+                    span
+                }
+                Tok::Kw(Kw::Noreturn) => {
+                    self.next_t()?;
+                    decl_attr |= DeclAttr::NORETURN;
+                    span
                 }
                 // (6.7.5) alignment-specifier:
                 Tok::Kw(Kw::Alignas) => {
                     let (token, span) = self.next_t()?;
                     return Err(Error::unsupported(span, &token));
                 }
-                // if it's neither of the above, it has to be a type-specifier
-                _ => {
-                    let ty = self.type_specifier()?;
-                    break ty;
+                // (6.7.2) type-specifier
+                Tok::Kw(Kw::Struct) => {
+                    self.next_t()?;
+                    if strukt.is_some() || !words.is_empty() {
+                        return Err(Error::new(
+                            "cannot combine `struct` with other type specifiers",
+                            span,
+                        ));
+                    }
+                    let (ty, span) = self.nested(|this| this.struct_specifier(span))?;
+                    strukt = Some(ty);
+                    span
+                }
+                Tok::Kw(kw) if is_arithmetic_specifier(kw) => {
+                    self.next_t()?;
+                    if strukt.is_some() {
+                        return Err(Error::new(
+                            format!("cannot combine `{kw}` with a struct"),
+                            span,
+                        ));
+                    }
+                    words.push((kw, span));
+                    span
                 }
+                _ => break,
+            };
+        }
+
+        let ty = match strukt {
+            Some(ty) => ty,
+            None if words.is_empty() => {
+                let (tok, span) = self.next_t()?;
+                return Err(Error::new(format!("Invalid token: `{tok}`"), span));
             }
+            None => Self::arithmetic_type_specifier(&words)?,
         };
 
         Ok((
             DeclSpec {
                 ty,
                 attrs: decl_attr,
+                quals,
             },
-            initial_span.extend(span),
+            initial_span.extend(end_span),
         ))
     }
 
-    fn type_specifier(&mut self) -> Result<Spanned<TypeSpecifier>> {
-        // todo: less shit code and better span handling
-        let mut signedness = None;
+    /// (6.7.3) type-qualifier:
+    ///     const
+    ///     restrict
+    ///     volatile
+    ///     _Atomic
+    ///
+    /// `restrict` and `_Atomic` are ignored.
+    fn type_qualifier(&mut self) -> Result<Qualifiers> {
+        Ok(match self.next_t()? {
+            (Tok::Kw(Kw::Const), _) => Qualifiers::CONST,
+            (Tok::Kw(Kw::Volatile), _) => Qualifiers::VOLATILE,
+            (Tok::Kw(Kw::Restrict | Kw::Atomic), _) => Qualifiers::empty(),
+            (tok, span) => {
+                return Err(Error::new(
+                    format!("expected type qualifier, found `{tok}`"),
+                    span,
+                ))
+            }
+        })
+    }
 
-        loop {
-            let (token, span) = self.next_t()?;
-            let ty = match token {
-                Tok::Kw(Kw::Void) => TypeSpecifier::Void,
-                Tok::Kw(Kw::Char) => match signedness {
-                    Some(signedness) => TypeSpecifier::Integer(IntTy(signedness, IntTyKind::Char)),
-                    None => TypeSpecifier::Char,
-                },
-                Tok::Kw(Kw::Short) => {
-                    eat!(self, Tok::Kw(Kw::Int));
-                    TypeSpecifier::Integer(IntTy(signedness.unwrap_or_default(), IntTyKind::Short))
-                }
-                Tok::Kw(Kw::Int) => {
-                    TypeSpecifier::Integer(IntTy(signedness.unwrap_or_default(), IntTyKind::Int))
-                }
-                Tok::Kw(Kw::Long) => {
-                    if eat!(self, Tok::Kw(Kw::Long)).is_some() {
-                        eat!(self, Tok::Kw(Kw::Int));
-                        TypeSpecifier::Integer(IntTy(
-                            signedness.unwrap_or_default(),
-                            IntTyKind::LongLong,
-                        ))
-                    } else {
-                        eat!(self, Tok::Kw(Kw::Int));
-                        TypeSpecifier::Integer(IntTy(
-                            signedness.unwrap_or_default(),
-                            IntTyKind::Long,
-                        ))
-                    }
-                }
-                Tok::Kw(Kw::Signed) => {
-                    if signedness.is_some() {
+    /// (6.7.2) type-specifier
+    ///
+    /// Arithmetic types can be spelled with several keywords in any order, like `long unsigned int`,
+    /// so the combination of all of them is resolved at once.
+    fn arithmetic_type_specifier(words: &[(Kw, Span)]) -> Result<TypeSpecifier> {
+        let full_span = words[0].1.extend(words.last().unwrap().1);
+
+        let mut sign = None;
+        // `short`, `long` and `long long` can be combined with `int`, which is the default.
+        let mut int_kind = None;
+        let mut has_int = false;
+        let mut other = None;
+        let invalid = || {
+            let words = words
+                .iter()
+                .map(|(kw, _)| kw.to_string())
+                .collect::<Vec<_>>();
+            Error::new(
+                format!(
+                    "invalid combination of type specifiers: `{}`",
+                    words.join(" ")
+                ),
+                full_span,
+            )
+        };
+
+        for &(kw, span) in words {
+            match kw {
+                Kw::Signed | Kw::Unsigned => {
+                    if sign.is_some() {
                         return Err(Error::new("cannot specify signedness twice", span));
                     }
-                    if let Ok((Tok::Kw(Kw::Char | Kw::Short | Kw::Int | Kw::Long), _)) =
-                        self.peek_t()
-                    {
-                        // the signed is an integer qualifier
-                        signedness = Some(IntSign::Signed);
-                        continue;
-                    }
-                    TypeSpecifier::Integer(IntTy(IntSign::Signed, IntTyKind::Int))
+                    sign = Some(match kw {
+                        Kw::Signed => IntSign::Signed,
+                        _ => IntSign::Unsigned,
+                    });
                 }
-                Tok::Kw(Kw::Unsigned) => {
-                    if signedness.is_some() {
-                        return Err(Error::new("cannot specify signedness twice", span));
-                    }
-                    if let Ok((Tok::Kw(Kw::Char | Kw::Short | Kw::Int | Kw::Long), _)) =
-                        self.peek_t()
-                    {
-                        // the unsigned is an integer qualifier
-                        signedness = Some(IntSign::Unsigned);
-                        continue;
+                Kw::Int if !has_int => has_int = true,
+                Kw::Short if int_kind.is_none() => int_kind = Some(IntTyKind::Short),
+                Kw::Long => {
+                    int_kind = match int_kind {
+                        None => Some(IntTyKind::Long),
+                        Some(IntTyKind::Long) => Some(IntTyKind::LongLong),
+                        Some(_) => return Err(invalid()),
                     }
-                    TypeSpecifier::Integer(IntTy(IntSign::Unsigned, IntTyKind::Int))
                 }
-                Tok::Kw(Kw::Float) => TypeSpecifier::Float,
-                Tok::Kw(Kw::Double) => TypeSpecifier::Double,
-                Tok::Kw(Kw::Bool) => {
-                    TypeSpecifier::Integer(IntTy(IntSign::Unsigned, IntTyKind::Bool))
-                }
-                Tok::Kw(Kw::Complex) => {
+                Kw::Complex => {
                     return Err(Error::new("tf are you doing with complex numbers", span))
                 }
-                tok => return Err(Error::new(format!("Invalid token: `{tok}`"), span)),
+                Kw::Void | Kw::Char | Kw::Float | Kw::Double | Kw::Bool if other.is_none() => {
+                    other = Some(kw)
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        let ty = match (other, int_kind) {
+            (None, kind) => TypeSpecifier::Integer(IntTy(
+                sign.unwrap_or_default(),
+                kind.unwrap_or(IntTyKind::Int),
+            )),
+            (Some(_), _) if has_int => return Err(invalid()),
+            (Some(Kw::Char), None) => match sign {
+                Some(sign) => TypeSpecifier::Integer(IntTy(sign, IntTyKind::Char)),
+                None => TypeSpecifier::Char,
+            },
+            (Some(_), _) if sign.is_some() => return Err(invalid()),
+            (Some(Kw::Void), None) => TypeSpecifier::Void,
+            (Some(Kw::Float), None) => TypeSpecifier::Float,
+            (Some(Kw::Double), None) => TypeSpecifier::Double,
+            (Some(Kw::Double), Some(IntTyKind::Long)) => TypeSpecifier::LongDouble,
+            (Some(Kw::Bool), None) => {
+                TypeSpecifier::Integer(IntTy(IntSign::Unsigned, IntTyKind::Bool))
+            }
+            (Some(_), _) => return Err(invalid()),
+        };
+
+        Ok(ty)
+    }
+
+    /// (6.7.2.1) struct-or-union-specifier:
+    ///     struct-or-union identifier.opt { struct-declaration-list }
+    ///     struct-or-union identifier
+    ///
+    /// struct-declaration:
+    ///     specifier-qualifier-list struct-declarator-list.opt ;
+    ///
+    /// The `struct` keyword must already have been eaten. Bit-fields are not supported.
+    fn struct_specifier(&mut self, kw_span: Span) -> Result<Spanned<TypeSpecifier>> {
+        let name = match self.peek_t()? {
+            (Tok::Ident(_), _) => Some(self.ident()?),
+            _ => None,
+        };
+
+        let Some(_) = eat!(self, Tok::Punct(P::BraceOpen)) else {
+            return match name {
+                Some((_, name_span)) => Ok((
+                    TypeSpecifier::Struct(Box::new(StructSpecifier { name, fields: None })),
+                    kw_span.extend(name_span),
+                )),
+                None => {
+                    let (tok, span) = self.next_t()?;
+                    Err(Error::new(
+                        format!("expected identifier or `{{` after `struct`, found `{tok}`"),
+                        span,
+                    ))
+                }
             };
+        };
 
-            break Ok((ty, span));
-        }
+        let mut fields = Vec::new();
+        let end_span = loop {
+            if let Some((_, span)) = eat!(self, Tok::Punct(P::BraceClose)) {
+                break span;
+            }
+
+            let decl_spec = self.decl_specifiers()?;
+            let mut declarators = Vec::new();
+            while self.is_peek_tok_start_of_declarator() || self.is_peek_comma() {
+                if !declarators.is_empty() {
+                    expect!(self, Tok::Punct(P::Comma));
+                }
+                declarators.push(self.declarator()?);
+            }
+            let semi_span = expect!(self, Tok::Punct(P::Semicolon));
+
+            let span = decl_spec.1.extend(semi_span);
+            fields.push((
+                StructFieldDecl {
+                    decl_spec,
+                    declarators,
+                },
+                span,
+            ));
+        };
+
+        Ok((
+            TypeSpecifier::Struct(Box::new(StructSpecifier {
+                name,
+                fields: Some(fields),
+            })),
+            kw_span.extend(end_span),
+        ))
+    }
+
+    /// (6.7.7) type-name:
+    ///     specifier-qualifier-list abstract-declarator.opt
+    fn type_name(&mut self) -> Result<Spanned<TypeName>> {
+        let decl_spec = self.decl_specifiers()?;
+        let pointer_span = eat!(self, Tok::Punct(P::Asterisk)).map(|(_, span)| span);
+
+        let span = decl_spec.1.extend_option(pointer_span);
+        let type_name = TypeName {
+            decl_spec,
+            pointer: pointer_span.is_some(),
+        };
+
+        Ok((type_name, span))
     }
 
     /// (6.7.6) declarator:
     ///     pointer.opt direct-declarator
     ///
+    /// pointer:
+    ///     `*` type-qualifier-list.opt
+    ///
     /// It's only really known after the parsing of a declarator which kind of declaration we are
     /// facing. For example: `int uwu` vs `int uwu()`. The parentheses indicate a function
     /// declaration. Therefore, we have no idea what we're parsing before entering this function.
@@ -351,11 +619,21 @@ where
             None
         };
 
+        let mut pointer_quals = Qualifiers::empty();
+        if pointer_span.is_some() {
+            while let Ok((Tok::Kw(Kw::Const | Kw::Volatile | Kw::Restrict | Kw::Atomic), _)) =
+                self.peek_t()
+            {
+                pointer_quals |= self.type_qualifier()?;
+            }
+        }
+
         let (decl, span) = self.direct_declarator()?;
 
         let declarator = Declarator {
             decl,
             pointer: pointer_span.is_some(),
+            pointer_quals,
         };
 
         let span = pointer_span.map(|s| s.extend(span)).unwrap_or(span);
@@ -377,26 +655,42 @@ where
 
         if (eat!(self, Tok::Punct(P::ParenOpen))).is_some() {
             let mut params = Vec::new();
+            let mut variadic = false;
             let mut first = true;
 
-            while self.is_peek_tok_start_of_ty() || self.is_peek_comma() {
-                if first {
-                    // the wrong way around because borrowing
-                    if let (Tok::Punct(P::ParenClose), _) = self.peek_t_n(1)? {
-                        if let &(ref tok @ Tok::Kw(Kw::Void), span) = self.peek_t()? {
-                            return Err(Error::unsupported(span, tok));
-                        }
-                    }
+            // (6.7.6.3) `()` leaves the parameters unspecified, while `(void)` has none.
+            if let (Tok::Punct(P::ParenClose), _) = self.peek_t()? {
+                self.next_t()?;
+                return Ok((
+                    DirectDeclarator::WithParams {
+                        ident: (ident, span),
+                        params: None,
+                        variadic: false,
+                    },
+                    span,
+                ));
+            }
+            if let (Tok::Kw(Kw::Void), _) = self.peek_t()? {
+                if let (Tok::Punct(P::ParenClose), _) = self.peek_t_n(1)? {
+                    self.next_t()?;
                 }
+            }
 
+            while self.is_peek_tok_start_of_ty() || self.is_peek_comma() {
                 if !first {
                     expect!(self, Tok::Punct(P::Comma));
+                    // (6.7.6) parameter-type-list:
+                    //     parameter-list , ...
+                    if eat!(self, Tok::Punct(P::DotDotDot)).is_some() {
+                        variadic = true;
+                        break;
+                    }
                 }
                 first = false;
 
                 let decl_spec = self.decl_specifiers()?;
                 // abstract declarator actually
-                let declarator = self.declarator()?;
+                let declarator = self.nested(Self::declarator)?;
 
                 let function_param_decl = FunctionParamDecl {
                     decl_spec,
@@ -405,18 +699,35 @@ where
                 params.push(function_param_decl);
             }
 
-            // nothing in the params supported yet.
             expect!(self, Tok::Punct(P::ParenClose));
             return Ok((
                 DirectDeclarator::WithParams {
                     ident: (ident, span),
-                    params,
+                    params: Some(params),
+                    variadic,
                 },
                 span,
             ));
         }
 
-        Ok((DirectDeclarator::Ident((ident, span)), span))
+        let mut decl = DirectDeclarator::Ident((ident, span));
+        let mut decl_span = span;
+
+        while eat!(self, Tok::Punct(P::BracketOpen)).is_some() {
+            let size = match self.peek_t()? {
+                (Tok::Punct(P::BracketClose), _) => None,
+                _ => Some(Box::new(self.assignment_expr()?)),
+            };
+            let close_span = expect!(self, Tok::Punct(P::BracketClose));
+
+            decl = DirectDeclarator::Array {
+                decl: Box::new(decl),
+                size,
+            };
+            decl_span = decl_span.extend(close_span);
+        }
+
+        Ok((decl, decl_span))
     }
 
     // -----------------------
@@ -435,7 +746,13 @@ where
     ///     iteration-statement
     ///     jump-statement
     fn statement(&mut self) -> Result<Spanned<Stmt>> {
-        if self.is_peek_tok_start_of_ty() {
+        self.nested(Self::statement_unnested)
+    }
+
+    fn statement_unnested(&mut self) -> Result<Spanned<Stmt>> {
+        if self.is_peek_tok_start_of_ty()
+            || matches!(self.peek_t(), Ok((Tok::Kw(Kw::StaticAssert), _)))
+        {
             let (decl, span) = self.declaration()?;
             let span2 = expect!(self, Tok::Punct(P::Semicolon));
             return Ok((Stmt::Decl(decl), span.extend(span2)));
@@ -446,6 +763,87 @@ where
             return self.if_statement();
         }
 
+        if let (Tok::Kw(Kw::Switch), _) = self.peek_t()? {
+            return self.switch_statement();
+        }
+
+        if let (Tok::Kw(Kw::While), _) = self.peek_t()? {
+            return self.while_statement();
+        }
+
+        if let (Tok::Kw(Kw::Do), _) = self.peek_t()? {
+            return self.do_while_statement();
+        }
+
+        if let (Tok::Kw(Kw::For), _) = self.peek_t()? {
+            return self.for_statement();
+        }
+
+        // (6.8.3) expression-statement:
+        //      expression.opt ;
+        // A null statement does nothing, like an empty block.
+        if let Some((_, span)) = eat!(self, Tok::Punct(P::Semicolon)) {
+            return Ok((Stmt::Compound(Vec::new()), span));
+        }
+
+        // (6.8.1) labeled-statement:
+        //     case constant-expression : statement
+        //     default : statement
+        if let Some((_, span)) = eat!(self, Tok::Kw(Kw::Case)) {
+            let value = self.assignment_expr()?;
+            expect!(self, Tok::Punct(P::Colon));
+            let stmt = self.statement()?;
+            let span = span.extend(stmt.1);
+            return Ok((
+                Stmt::Case {
+                    value,
+                    stmt: Box::new(stmt),
+                },
+                span,
+            ));
+        }
+
+        if let Some((_, span)) = eat!(self, Tok::Kw(Kw::Default)) {
+            expect!(self, Tok::Punct(P::Colon));
+            let stmt = self.statement()?;
+            let span = span.extend(stmt.1);
+            return Ok((Stmt::Default(Box::new(stmt)), span));
+        }
+
+        // (6.8.1) labeled-statement:
+        //     identifier : statement
+        if matches!(self.peek_t()?.0, Tok::Ident(_))
+            && matches!(self.peek_t_n(1), Ok((Tok::Punct(P::Colon), _)))
+        {
+            let label = self.ident()?;
+            expect!(self, Tok::Punct(P::Colon));
+            let stmt = self.statement()?;
+            let span = label.1.extend(stmt.1);
+            return Ok((
+                Stmt::Labeled {
+                    label,
+                    stmt: Box::new(stmt),
+                },
+                span,
+            ));
+        }
+
+        if let Some((_, span)) = eat!(self, Tok::Kw(Kw::Goto)) {
+            let label = self.ident()?;
+            let semi_span = expect!(self, Tok::Punct(P::Semicolon));
+            return Ok((Stmt::Goto(label), span.extend(semi_span)));
+        }
+
+        if let Some((_, span)) = eat!(self, Tok::Kw(Kw::Continue)) {
+            let semi_span = expect!(self, Tok::Punct(P::Semicolon));
+            return Ok((Stmt::Continue, span.extend(semi_span)));
+        }
+
+        if let Some((_, span)) = eat!(self, Tok::Kw(Kw::Break)) {
+            let semi_span = expect!(self, Tok::Punct(P::Semicolon));
+            return Ok((Stmt::Break, span.extend(semi_span)));
+        }
+
         if let Some((_, span)) = eat!(self, Tok::Kw(Kw::Return)) {
             if let Some((_, semi_span)) = eat!(self, Tok::Punct(P::Semicolon)) {
                 return Ok((Stmt::Return(None), span.extend(semi_span)));
@@ -560,6 +958,96 @@ where
             span,
         ))
     }
+
+    /// (6.8.4) selection-statement:
+    ///     switch ( expression ) statement
+    fn switch_statement(&mut self) -> Result<Spanned<Stmt>> {
+        let switch_span = expect!(self, Tok::Kw(Kw::Switch));
+        let _paren_span = expect!(self, Tok::Punct(P::ParenOpen));
+        let cond = self.expr()?;
+        let _paren_span = expect!(self, Tok::Punct(P::ParenClose));
+        let body = self.compount_or_single_statement()?;
+
+        let span = switch_span
+            .extend(cond.1)
+            .extend_option(body.last().map(|s| s.1));
+        Ok((Stmt::Switch { cond, body }, span))
+    }
+
+    /// (6.8.5) iteration-statement:
+    ///     while ( expression ) statement
+    fn while_statement(&mut self) -> Result<Spanned<Stmt>> {
+        let while_span = expect!(self, Tok::Kw(Kw::While));
+        let _paren_span = expect!(self, Tok::Punct(P::ParenOpen));
+        let cond = self.expr()?;
+        let _paren_span = expect!(self, Tok::Punct(P::ParenClose));
+        let body = self.compount_or_single_statement()?;
+
+        let span = while_span
+            .extend(cond.1)
+            .extend_option(body.last().map(|s| s.1));
+        Ok((Stmt::While { cond, body }, span))
+    }
+
+    /// (6.8.5) iteration-statement:
+    ///     do statement while ( expression ) ;
+    fn do_while_statement(&mut self) -> Result<Spanned<Stmt>> {
+        let do_span = expect!(self, Tok::Kw(Kw::Do));
+        let body = self.compount_or_single_statement()?;
+        expect!(self, Tok::Kw(Kw::While));
+        let _paren_span = expect!(self, Tok::Punct(P::ParenOpen));
+        let cond = self.expr()?;
+        let _paren_span = expect!(self, Tok::Punct(P::ParenClose));
+        let semi_span = expect!(self, Tok::Punct(P::Semicolon));
+
+        Ok((Stmt::DoWhile { body, cond }, do_span.extend(semi_span)))
+    }
+
+    /// (6.8.5) iteration-statement:
+    ///     for ( expression.opt ; expression.opt ; expression.opt ) statement
+    ///     for ( declaration expression.opt ; expression.opt ) statement
+    ///
+    /// The declaration can also be a `_Static_assert`.
+    fn for_statement(&mut self) -> Result<Spanned<Stmt>> {
+        let for_span = expect!(self, Tok::Kw(Kw::For));
+        let _paren_span = expect!(self, Tok::Punct(P::ParenOpen));
+
+        let mut init_decl = None;
+        let mut init_expr = None;
+        if self.is_peek_tok_start_of_ty() || matches!(self.peek_t()?.0, Tok::Kw(Kw::StaticAssert)) {
+            init_decl = Some(self.declaration()?);
+        } else if !matches!(self.peek_t()?.0, Tok::Punct(P::Semicolon)) {
+            init_expr = Some(self.expr()?);
+        }
+        expect!(self, Tok::Punct(P::Semicolon));
+
+        let cond = match self.peek_t()?.0 {
+            Tok::Punct(P::Semicolon) => None,
+            _ => Some(self.expr()?),
+        };
+        expect!(self, Tok::Punct(P::Semicolon));
+
+        let post = match self.peek_t()?.0 {
+            Tok::Punct(P::ParenClose) => None,
+            _ => Some(self.expr()?),
+        };
+        let paren_span = expect!(self, Tok::Punct(P::ParenClose));
+
+        let body = self.compount_or_single_statement()?;
+        let span = for_span
+            .extend(paren_span)
+            .extend_option(body.last().map(|s| s.1));
+        Ok((
+            Stmt::For {
+                init_decl,
+                init_expr,
+                cond,
+                post,
+                body,
+            },
+            span,
+        ))
+    }
 }
 
 impl<'src, I> Iterator for Parser<'src, I>
@@ -585,10 +1073,28 @@ pub fn parse_declarations<'src>(
 
     let mut parser = Parser {
         lex: src.peekmore(),
+        depth: 0,
     };
 
     parser.external_declarations()
 }
 
+fn is_arithmetic_specifier(kw: Kw) -> bool {
+    matches!(
+        kw,
+        Kw::Void
+            | Kw::Char
+            | Kw::Short
+            | Kw::Int
+            | Kw::Long
+            | Kw::Float
+            | Kw::Double
+            | Kw::Signed
+            | Kw::Unsigned
+            | Kw::Bool
+            | Kw::Complex
+    )
+}
+
 #[cfg(test)]
 mod tests;
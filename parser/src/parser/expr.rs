@@ -4,13 +4,13 @@
 
 use crate::{
     ast::{
-        ArithOpKind, Atom, BinaryOp, ComparisonKind, Expr, ExprBinary, ExprPostfix, ExprUnary,
-        PostfixOp, UnaryOp,
+        ArithOpKind, Atom, BinaryOp, ComparisonKind, Expr, ExprBinary, ExprConditional,
+        ExprPostfix, ExprUnary, PostfixOp, UnaryOp,
     },
-    parser::{eat, expect, Error, Parser, Result},
-    pre::Punctuator as P,
+    parser::{eat, expect, is_tok_start_of_ty, Error, Parser, Result},
+    pre::{self, Punctuator as P},
     sym::Symbol,
-    token::{Constant, Token as Tok},
+    token::{Constant, Keyword as Kw, Token as Tok},
     Span, Spanned,
 };
 
@@ -22,21 +22,31 @@ where
         self.expr_bp(0)
     }
 
+    /// (6.5.16) assignment-expression
+    ///
+    /// An expression that doesn't contain a top-level comma operator, for places where the comma
+    /// is a separator, like function arguments or initializers.
+    pub fn assignment_expr(&mut self) -> Result<Spanned<Expr>> {
+        self.expr_bp(powers::ASSIGN.0)
+    }
+
     fn get_lhs(&mut self) -> Result<Spanned<Expr>> {
         let (typ, span) = match self.peek_t()? {
             &(Tok::Ident(ident), span) => (Atom::Ident((Symbol::intern(ident), span)), span),
-            &(Tok::StringLiteral(literal), span) => {
-                (Atom::String(literal.to_string().into_bytes()), span)
-            }
+            &(Tok::StringLiteral(literal), span) => (Atom::String(pre::unescape(literal)), span),
             &(Tok::Constant(Constant::Int(int)), span) => (Atom::Int(int), span),
             &(Tok::Constant(Constant::Float(float)), span) => (Atom::Float(float), span),
             &(Tok::Constant(Constant::Char(char)), span) => (Atom::Char(char), span),
+            &(Tok::Kw(Kw::Sizeof), span) => {
+                self.next_t()?;
+                return self.sizeof(span);
+            }
             &(Tok::Punct(P::ParenOpen), _) => {
                 // TODO: casts... yikes
                 self.next_t()?;
-                let lhs = self.expr_bp(0);
+                let lhs = self.expr_bp(0)?;
                 expect!(self, Tok::Punct(P::ParenClose));
-                return lhs;
+                return Ok(lhs);
             }
             &(Tok::Punct(punct), span) => {
                 let r_bp = prefix_binding_power(&Tok::Punct(punct)).ok_or_else(|| {
@@ -45,9 +55,9 @@ where
                 let Some(op) = unary_op_from_token(&Tok::Punct(punct)) else {
                     panic!()
                 };
-                let rhs = self.expr_bp(r_bp)?;
-
                 self.next_t()?;
+                let rhs = self.expr_bp(r_bp)?;
+                let span = span.merge(rhs.1);
 
                 return Ok((
                     Expr::Unary(ExprUnary {
@@ -69,7 +79,31 @@ where
         Ok((Expr::Atom(typ), span))
     }
 
+    /// (6.5.3) unary-expression:
+    ///     sizeof unary-expression
+    ///     sizeof ( type-name )
+    ///
+    /// The `sizeof` must already have been eaten.
+    fn sizeof(&mut self, sizeof_span: Span) -> Result<Spanned<Expr>> {
+        if let (Tok::Punct(P::ParenOpen), _) = self.peek_t()? {
+            if is_tok_start_of_ty(&self.peek_t_n(1)?.0) {
+                self.next_t()?;
+                let (type_name, _) = self.type_name()?;
+                let close_span = expect!(self, Tok::Punct(P::ParenClose));
+                return Ok((Expr::SizeOfType(type_name), sizeof_span.extend(close_span)));
+            }
+        }
+
+        let expr = self.expr_bp(powers::UNARY_OPERATOR)?;
+        let span = sizeof_span.extend(expr.1);
+        Ok((Expr::SizeOfExpr(Box::new(expr)), span))
+    }
+
     fn expr_bp(&mut self, min_bp: u8) -> Result<Spanned<Expr>> {
+        self.nested(|this| this.expr_bp_unnested(min_bp))
+    }
+
+    fn expr_bp_unnested(&mut self, min_bp: u8) -> Result<Spanned<Expr>> {
         let mut lhs = self.get_lhs()?;
 
         #[allow(clippy::while_let_loop)] // idc
@@ -88,7 +122,7 @@ where
                     Tok::Punct(P::BracketOpen) => {
                         let rhs = self.expr_bp(0)?;
                         let span = expect!(self, Tok::Punct(P::BracketClose));
-                        let span = lhs.1.extend(span);
+                        let span = lhs.1.merge(span);
                         lhs = (
                             Expr::Binary(ExprBinary {
                                 lhs: Box::new(lhs),
@@ -112,10 +146,10 @@ where
                             }
                             first = false;
 
-                            let arg = self.expr_bp(0)?;
+                            let arg = self.assignment_expr()?;
                             arguments.push(arg);
                         }
-                        let span = tok_span.extend(last_span);
+                        let span = lhs.1.merge(last_span);
 
                         lhs = (
                             Expr::Postfix(ExprPostfix {
@@ -125,11 +159,66 @@ where
                             span,
                         )
                     }
-                    _ => {}
+                    Tok::Punct(P::Dot | P::Arrow) => {
+                        let field = self.ident()?;
+                        let span = lhs.1.merge(field.1);
+                        let op = if let Tok::Punct(P::Dot) = tok {
+                            PostfixOp::Member(field)
+                        } else {
+                            PostfixOp::ArrowMember(field)
+                        };
+                        lhs = (
+                            Expr::Postfix(ExprPostfix {
+                                lhs: Box::new(lhs),
+                                op,
+                            }),
+                            span,
+                        );
+                    }
+                    Tok::Punct(P::PlusPlus | P::MinusMinus) => {
+                        let op = if let Tok::Punct(P::PlusPlus) = tok {
+                            PostfixOp::Increment
+                        } else {
+                            PostfixOp::Decrement
+                        };
+                        let span = lhs.1.merge(tok_span);
+                        lhs = (
+                            Expr::Postfix(ExprPostfix {
+                                lhs: Box::new(lhs),
+                                op,
+                            }),
+                            span,
+                        );
+                    }
+                    _ => unreachable!("token has a postfix binding power: {tok:?}"),
                 }
                 continue;
             }
 
+            if let Tok::Punct(P::QuestionMark) = tok {
+                if powers::TERNARY.0 < min_bp {
+                    break;
+                }
+                self.next_t()?;
+
+                // (6.5.15) The middle operand is a full expression, the last one can be another
+                // conditional expression, which makes it right associative.
+                let then = self.expr_bp(0)?;
+                expect!(self, Tok::Punct(P::Colon));
+                let otherwise = self.expr_bp(powers::TERNARY.0)?;
+
+                let span = lhs.1.merge(otherwise.1);
+                lhs = (
+                    Expr::Conditional(ExprConditional {
+                        cond: Box::new(lhs),
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise),
+                    }),
+                    span,
+                );
+                continue;
+            }
+
             if let Some(op) = binary_op_from_token(&tok) {
                 let (l_bp, r_bp) = infix_binding_power(&tok);
                 if l_bp < min_bp {
@@ -140,7 +229,7 @@ where
 
                 let rhs = self.expr_bp(r_bp)?;
 
-                let span = lhs.1.extend(rhs.1);
+                let span = lhs.1.merge(rhs.1);
 
                 lhs = (
                     Expr::Binary(ExprBinary {
@@ -162,6 +251,8 @@ where
 
 fn unary_op_from_token(tok: &Tok<'_>) -> Option<UnaryOp> {
     match tok {
+        Tok::Punct(P::PlusPlus) => Some(UnaryOp::Increment),
+        Tok::Punct(P::MinusMinus) => Some(UnaryOp::Decrement),
         Tok::Punct(P::Ampersand) => Some(UnaryOp::AddrOf),
         Tok::Punct(P::Asterisk) => Some(UnaryOp::Deref),
         Tok::Punct(P::Plus) => Some(UnaryOp::Plus),
@@ -187,6 +278,8 @@ fn binary_op_from_token(tok: &Tok<'_>) -> Option<BinaryOp> {
         // logical operators
         Tok::Punct(P::AmpersandAmpersand) => Some(BinaryOp::LogicalAnd),
         Tok::Punct(P::PipePipe) => Some(BinaryOp::LogicalOr),
+        // comma
+        Tok::Punct(P::Comma) => Some(BinaryOp::Comma),
         // assignment
         Tok::Punct(P::Eq) => Some(BinaryOp::Assign(None)),
         Tok::Punct(P::PlusEq) => Some(BinaryOp::Assign(Some(ArithOpKind::Add))),
@@ -212,7 +305,8 @@ fn binary_op_from_token(tok: &Tok<'_>) -> Option<BinaryOp> {
 
 mod powers {
     pub const COMMA: (u8, u8) = (1, 2);
-    pub const ASSIGN: (u8, u8) = (3, 4);
+    /// Right associative, `a = b = c` is `a = (b = c)`.
+    pub const ASSIGN: (u8, u8) = (4, 3);
     pub const TERNARY: (u8, u8) = (5, 6);
     pub const LOGICAL_OR: (u8, u8) = (7, 8);
     pub const LOGICAL_AND: (u8, u8) = (9, 10);
@@ -231,9 +325,16 @@ mod powers {
 
 fn prefix_binding_power(tok: &Tok<'_>) -> Option<u8> {
     Some(match tok {
-        Tok::Punct(P::Ampersand | P::Asterisk | P::Plus | P::Minus | P::Tilde | P::Bang) => {
-            powers::UNARY_OPERATOR
-        }
+        Tok::Punct(
+            P::PlusPlus
+            | P::MinusMinus
+            | P::Ampersand
+            | P::Asterisk
+            | P::Plus
+            | P::Minus
+            | P::Tilde
+            | P::Bang,
+        ) => powers::UNARY_OPERATOR,
         _ => return None,
     })
 }
@@ -256,7 +357,7 @@ fn infix_binding_power(tok: &Tok<'_>) -> (u8, u8) {
         | P::AmpersandEq
         | P::CaretEq
         | P::PipeEq => powers::ASSIGN,
-        // then, ternary here
+        // then the conditional operator, which is parsed separately
         P::PipePipe => powers::LOGICAL_OR,
         P::AmpersandAmpersand => powers::LOGICAL_AND,
         P::Pipe => powers::BIT_OR,
@@ -1,5 +1,12 @@
 use super::Tok;
-use crate::{ast::ExternalDecl, parser::Error, Span, Spanned};
+use crate::{
+    ast::{
+        Atom, BinaryOp, Decl, DeclAttr, Designator, DirectDeclarator, Expr, ExprBinary,
+        ExternalDecl, InitListElem, Initializer, IntConstant, Qualifiers, Stmt,
+    },
+    parser::Error,
+    Span, Spanned, Symbol,
+};
 
 fn lex_and_pre(src: &str) -> impl Iterator<Item = (Tok<'_>, Span)> + '_ {
     let pre_tokens = crate::pre::preprocess_tokens(src);
@@ -224,3 +231,543 @@ int main() {
     "#
     );
 }
+
+#[test]
+fn array_declarator() {
+    let ast = super::parse_declarations(lex_and_pre("char buf[256];")).unwrap();
+
+    let [(ExternalDecl::Decl(Decl::Normal(decl)), _)] = &*ast else {
+        panic!("expected a single declaration, found {ast:?}");
+    };
+    let DirectDeclarator::Array {
+        decl,
+        size: Some(size),
+    } = &decl.init_declarators[0].0.declarator.decl
+    else {
+        panic!("expected an array declarator, found {decl:?}");
+    };
+    assert!(matches!(**decl, DirectDeclarator::Ident((name, _)) if name == Symbol::intern("buf")));
+    assert!(matches!(
+        size.0,
+        Expr::Atom(Atom::Int(IntConstant { value: 256, .. }))
+    ));
+
+    assert_eq!(pretty_print(&Ok(ast)), "char buf[256];\n");
+}
+
+#[test]
+fn array_declarator_without_size() {
+    let ast = super::parse_declarations(lex_and_pre("int a[], b[2][3];"));
+    assert_eq!(pretty_print(&ast), "int a[], b[2][3];\n");
+}
+
+#[test]
+fn sizeof() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "unsigned long a = sizeof(int*), b = sizeof x + 1, c = sizeof(x);",
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "unsigned long a = sizeof(int*), b = (sizeof(x) + 1), c = sizeof(x);\n"
+    );
+}
+
+#[test]
+fn comma_operator() {
+    let ast = super::parse_declarations(lex_and_pre("int x = f(1, 2), y = a[1, 2];"));
+    assert_eq!(pretty_print(&ast), "int x = f(1, 2), y = a[(1 , 2)];\n");
+}
+
+#[test]
+fn static_assert() {
+    let ast = super::parse_declarations(lex_and_pre(
+        r#"_Static_assert(sizeof(int) == 4, "int is 4 bytes");
+void f() { _Static_assert(1, "inside"); }"#,
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "_Static_assert((sizeof(int) == 4), \"int is 4 bytes\");\nvoid f() {\n    _Static_assert(1, \"inside\");\n}\n"
+    );
+}
+
+#[test]
+fn switch() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "void f() { switch (x) { case 1: a = 1; case 1 + 1: break; default: b = 2; } }",
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "void f() {\n    switch (x) {\n        case 1:\n        (a = 1)\n        case (1 + 1):\n        break\n        default:\n        (b = 2)\n    }\n}\n"
+    );
+}
+
+#[test]
+fn do_while() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "void f() { do { x = x + 1; continue; } while (x < 10); do y = 1; while (0); }",
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "void f() {\n    do {\n        (x = (x + 1))\n        continue\n    } while ((x < 10))\n    do {\n        (y = 1)\n    } while (0)\n}\n"
+    );
+}
+
+#[test]
+fn goto_and_label() {
+    let ast = super::parse_declarations(lex_and_pre("void f() { goto end; x = 1; end: return; }"));
+    assert_eq!(
+        pretty_print(&ast),
+        "void f() {\n    goto end\n    (x = 1)\n    end:\n    return\n}\n"
+    );
+}
+
+#[test]
+fn variadic_function() {
+    let ast = super::parse_declarations(lex_and_pre("int printf(char *fmt, ...) {}"));
+    let ExternalDecl::FunctionDef(def) = &ast.as_ref().unwrap()[0].0 else {
+        unreachable!()
+    };
+    let declarator = &def.decl.unwrap_normal().init_declarators[0].0.declarator;
+    assert!(matches!(
+        declarator.decl,
+        DirectDeclarator::WithParams { variadic: true, .. }
+    ));
+    assert_eq!(pretty_print(&ast), "int printf(char *fmt, ...) {\n}\n");
+}
+
+#[test]
+fn function_specifiers() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "inline int f() { return 1; } _Noreturn void g() {} static inline long h();",
+    ));
+    let attrs = ast
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|(decl, _)| match decl {
+            ExternalDecl::Decl(decl) => decl.unwrap_normal().decl_spec.attrs,
+            ExternalDecl::FunctionDef(def) => def.decl.unwrap_normal().decl_spec.attrs,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        attrs,
+        [
+            DeclAttr::INLINE,
+            DeclAttr::NORETURN,
+            DeclAttr::STATIC | DeclAttr::INLINE
+        ]
+    );
+    assert_eq!(
+        pretty_print(&ast),
+        "inline int f() {\n    return 1\n}\n_Noreturn void g() {\n}\nstatic inline long h();\n"
+    );
+}
+
+#[test]
+fn type_qualifiers() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "const int a; int volatile b; volatile const int c;",
+    ));
+    let quals = ast
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|(decl, _)| match decl {
+            ExternalDecl::Decl(decl) => decl.unwrap_normal().decl_spec.quals,
+            ExternalDecl::FunctionDef(_) => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        quals,
+        [
+            Qualifiers::CONST,
+            Qualifiers::VOLATILE,
+            Qualifiers::CONST | Qualifiers::VOLATILE
+        ]
+    );
+    assert_eq!(
+        pretty_print(&ast),
+        "const int a;\nvolatile int b;\nconst volatile int c;\n"
+    );
+}
+
+#[test]
+fn struct_definition_and_member() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "struct P { int x, *y; char c; }; int f(struct P p) { return p.x; }",
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "struct P {
+    int x, *y;
+    char c;
+};
+int f(struct P p) {
+    return p.x
+}
+"
+    );
+}
+
+#[test]
+fn type_specifier_combinations() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "unsigned long long a; unsigned b; long unsigned int long c; int long d; long double e;",
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "unsigned long long a;\nunsigned int b;\nunsigned long long c;\nlong d;\nlong double e;\n"
+    );
+
+    // The keywords can be mixed with the other specifiers.
+    let ast = super::parse_declarations(lex_and_pre(
+        "unsigned const int a; long static long b; int const c;",
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "const unsigned int a;\nstatic long long b;\nconst int c;\n"
+    );
+
+    let err = super::parse_declarations(lex_and_pre("long float x;")).unwrap_err();
+    assert_eq!(
+        (err.msg.as_str(), err.span),
+        (
+            "invalid combination of type specifiers: `long float`",
+            Some(Span::start_end(0, 10))
+        )
+    );
+    let err = super::parse_declarations(lex_and_pre("signed unsigned x;")).unwrap_err();
+    assert_eq!(
+        (err.msg.as_str(), err.span),
+        (
+            "cannot specify signedness twice",
+            Some(Span::start_end(7, 15))
+        )
+    );
+    let err = super::parse_declarations(lex_and_pre("struct P int x;")).unwrap_err();
+    assert_eq!(
+        (err.msg.as_str(), err.span),
+        (
+            "cannot combine `int` with a struct",
+            Some(Span::start_end(9, 12))
+        )
+    );
+}
+
+#[test]
+fn unary_operators() {
+    let ast = super::parse_declarations(lex_and_pre("int *p = &g; int x = -*p + !~1;"));
+    assert_eq!(
+        pretty_print(&ast),
+        "int *p = &(g);\nint x = (-(*(p)) + !(~(1)));\n"
+    );
+}
+
+#[test]
+fn null_statements() {
+    let ast = super::parse_declarations(lex_and_pre(
+        "void f() { ;; if (x) ; else ; for (;;) ; for (int i = 0; i < 2; i = i + 1) x = i; }",
+    ));
+    assert_eq!(
+        pretty_print(&ast),
+        "void f() {
+    if (x) {
+        {
+        }
+    } else {
+        {
+        }
+    }
+    for (;;) {
+        {
+        }
+    }
+    for (int i = 0; (i < 2); (i = (i + 1))) {
+        (x = i)
+    }
+}
+"
+    );
+}
+
+#[test]
+fn unspecified_and_void_params() {
+    let ast = super::parse_declarations(lex_and_pre("int f() {}\nint g(void) {}"));
+    let params = ast
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|(decl, _)| {
+            let ExternalDecl::FunctionDef(def) = decl else {
+                unreachable!()
+            };
+            let declarator = &def.decl.unwrap_normal().init_declarators[0].0.declarator;
+            declarator.decl.unwrap_with_params().1.map(Vec::len)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(params, [None, Some(0)]);
+    assert_eq!(pretty_print(&ast), "int f() {\n}\nint g(void) {\n}\n");
+}
+
+#[test]
+fn while_condition_span() {
+    let src = "void f() { while (x < 3) x = x + 1; }";
+    let ast = super::parse_declarations(lex_and_pre(src));
+    let ExternalDecl::FunctionDef(def) = &ast.as_ref().unwrap()[0].0 else {
+        unreachable!()
+    };
+    let Stmt::While { cond, .. } = &def.body[0].0 else {
+        panic!("expected while loop: {:?}", def.body[0])
+    };
+    assert_eq!(&src[cond.1.start..cond.1.end], "x < 3");
+    assert_eq!(
+        pretty_print(&ast),
+        "void f() {\n    while ((x < 3)) {\n        (x = (x + 1))\n    }\n}\n"
+    );
+}
+
+#[test]
+fn pointer_qualifiers() {
+    let ast = super::parse_declarations(lex_and_pre("const int *p;\nint *const volatile q;"));
+    let quals = ast
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|(decl, _)| {
+            let ExternalDecl::Decl(decl) = decl else {
+                unreachable!()
+            };
+            let decl = decl.unwrap_normal();
+            (
+                decl.decl_spec.quals,
+                decl.init_declarators[0].0.declarator.pointer_quals,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        quals,
+        [
+            (Qualifiers::CONST, Qualifiers::empty()),
+            (
+                Qualifiers::empty(),
+                Qualifiers::CONST | Qualifiers::VOLATILE
+            )
+        ]
+    );
+    assert_eq!(
+        pretty_print(&ast),
+        "const int *p;\nint *const volatile q;\n"
+    );
+}
+
+#[test]
+fn brace_initializers() {
+    let ast =
+        super::parse_declarations(lex_and_pre("int a[2][2] = { { 1, 2 }, 3, };\nint b = {};"));
+    assert_eq!(
+        pretty_print(&ast),
+        "int a[2][2] = {{1, 2}, 3};\nint b = {};\n"
+    );
+    let ExternalDecl::Decl(decl) = &ast.as_ref().unwrap()[0].0 else {
+        unreachable!()
+    };
+    let (init, span) = decl.unwrap_normal().init_declarators[0]
+        .0
+        .init
+        .as_ref()
+        .unwrap();
+    assert_eq!(*span, Span::start_end(14, 30));
+    let Initializer::List(elems) = init else {
+        unreachable!()
+    };
+    assert!(matches!(
+        elems[..],
+        [
+            InitListElem {
+                init: (Initializer::List(_), _),
+                ..
+            },
+            InitListElem {
+                init: (Initializer::Expr(_), _),
+                ..
+            }
+        ]
+    ));
+
+    let err = super::parse_declarations(lex_and_pre("int a[2] = { 1 2 };")).unwrap_err();
+    assert_eq!(err.msg, "expected `Tok::Punct(P::BraceClose)`, found 2");
+}
+
+#[test]
+fn designated_initializers() {
+    let src = r#"
+int a[4] = { [3] = 9, 1, };
+struct P p = { .y = 2, .a[1].x = 3 };
+"#;
+    let ast = super::parse_declarations(lex_and_pre(src));
+    assert_eq!(
+        pretty_print(&ast),
+        "int a[4] = {[3] = 9, 1};\nstruct P p = {.y = 2, .a[1].x = 3};\n"
+    );
+    let ExternalDecl::Decl(decl) = &ast.as_ref().unwrap()[1].0 else {
+        unreachable!()
+    };
+    let (Initializer::List(elems), _) = decl.unwrap_normal().init_declarators[0]
+        .0
+        .init
+        .as_ref()
+        .unwrap()
+    else {
+        unreachable!()
+    };
+    assert!(matches!(
+        elems[1].designators[..],
+        [
+            Designator::Field(_),
+            Designator::Index(_),
+            Designator::Field(_)
+        ]
+    ));
+
+    let err = super::parse_declarations(lex_and_pre("int a[4] = { [3] 9 };")).unwrap_err();
+    assert_eq!(err.msg, "expected `Tok::Punct(P::Eq)`, found 9");
+}
+
+#[test]
+fn conditional_expr() {
+    let src = "int x = a ? b : c ? d : e, y = a || b ? c = 1 : d;";
+    let ast = super::parse_declarations(lex_and_pre(src));
+    assert_eq!(
+        pretty_print(&ast),
+        "int x = (a ? b : (c ? d : e)), y = ((a || b) ? (c = 1) : d);\n"
+    );
+}
+
+#[test]
+fn print_c_round_trip() {
+    // Spans differ between the two parses, so they are removed from the debug output.
+    fn without_spans(ast: &[Spanned<ExternalDecl>]) -> String {
+        let debug = format!("{ast:?}");
+        let mut out = String::new();
+        let mut rest = debug.as_str();
+        while let Some(pos) = rest.find("..") {
+            let (before, after) = rest.split_at(pos);
+            let before = before.trim_end_matches(|c: char| c.is_ascii_digit());
+            out.push_str(before);
+            rest = after[2..].trim_start_matches(|c: char| c.is_ascii_digit());
+        }
+        out.push_str(rest);
+        out
+    }
+
+    let src = r#"
+struct point { int x, y; };
+_Static_assert(sizeof(struct point) == 8, "\"point\"\tis\n8 bytes");
+static const char *names[3] = { "a\0011", "b", [2] = "c" };
+struct point origin = { .y = 1 };
+extern int printf(char *fmt, ...);
+int f(int *const p, long n) {
+    int i, j = -~n;
+    char c = '\'';
+    for (i = 0; i < n; i++) *p++ = i * 2 + 1 << 3, j += -*p;
+    for (int k = 0; ; ) break;
+    while (j > 0) j--;
+    while (j);
+    do { j = j ? !j : sizeof(int *); } while (0);
+    switch (n) { case 1: return 1; default: j = (j & 1) | (j ^ 2); }
+    if (n) if (j) goto end; else continue; else ;
+end:
+    return p[0] + (*p)++ + sizeof(int) + sizeof j + origin.x + (&origin)->y;
+}
+"#;
+    let ast = crate::parse_file(src).unwrap();
+    let printed = crate::print_c(&ast);
+    let reparsed = crate::parse_file(&printed)
+        .unwrap_or_else(|err| panic!("printed source doesn't parse: {err:?}\n{printed}"));
+
+    assert_eq!(without_spans(&ast), without_spans(&reparsed));
+    assert_eq!(crate::print_c(&reparsed), printed);
+}
+
+#[test]
+fn operator_precedence() {
+    let cases = [
+        ("a - b - c", "((a - b) - c)"),
+        ("a = b = c", "(a = (b = c))"),
+        ("a += b -= c", "(a += (b -= c))"),
+        ("a + b * c", "(a + (b * c))"),
+        ("a * b + c % d", "((a * b) + (c % d))"),
+        ("a << b + c", "(a << (b + c))"),
+        ("a < b == c > d", "((a < b) == (c > d))"),
+        ("a <= b != c >= d", "((a <= b) != (c >= d))"),
+        ("a & b ^ c | d", "(((a & b) ^ c) | d)"),
+        ("a == b & c", "((a == b) & c)"),
+        ("a || b && c | d", "(a || (b && (c | d)))"),
+        ("a = b || c ? d : e", "(a = ((b || c) ? d : e))"),
+        ("-a * b", "(-(a) * b)"),
+        ("*p++ + --q", "(*(p++) + --(q))"),
+        ("a[i]++ - b.c--", "(a[i]++ - b.c--)"),
+        ("!a == sizeof b + 1", "(!(a) == (sizeof(b) + 1))"),
+        ("(a, b = c, d)", "((a , (b = c)) , d)"),
+    ];
+
+    for (expr, expected) in cases {
+        let ast = super::parse_declarations(lex_and_pre(&format!("int x = {expr};")));
+        assert_eq!(
+            pretty_print(&ast),
+            format!("int x = {expected};\n"),
+            "{expr}"
+        );
+    }
+
+    let ast = super::parse_declarations(lex_and_pre("int x = a = b = c;")).unwrap();
+    let ExternalDecl::Decl(Decl::Normal(decl)) = &ast[0].0 else {
+        panic!("expected a declaration, found {ast:?}");
+    };
+    let Some((Initializer::Expr(expr), _)) = &decl.init_declarators[0].0.init else {
+        panic!("expected an expression initializer, found {decl:?}");
+    };
+    let Expr::Binary(ExprBinary {
+        op: BinaryOp::Assign(None),
+        lhs,
+        rhs,
+    }) = expr
+    else {
+        panic!("expected an assignment, found {expr:?}");
+    };
+    assert!(matches!(lhs.0, Expr::Atom(Atom::Ident((name, _))) if name == Symbol::intern("a")));
+    assert!(matches!(
+        rhs.0,
+        Expr::Binary(ExprBinary {
+            op: BinaryOp::Assign(None),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn expression_spans() {
+    let src = "int x = a + -b * f(c);";
+    let ast = super::parse_declarations(lex_and_pre(src)).unwrap();
+    let ExternalDecl::Decl(Decl::Normal(decl)) = &ast[0].0 else {
+        panic!("expected a declaration, found {ast:?}");
+    };
+    let Some((Initializer::Expr(Expr::Binary(add)), span)) = &decl.init_declarators[0].0.init
+    else {
+        panic!("expected a binary initializer, found {decl:?}");
+    };
+    assert_eq!(&src[span.start..span.end], "a + -b * f(c)");
+    assert_eq!(&src[add.lhs.1.start..add.lhs.1.end], "a");
+
+    let Expr::Binary(mul) = &add.rhs.0 else {
+        panic!("expected a multiplication, found {add:?}");
+    };
+    assert_eq!(&src[add.rhs.1.start..add.rhs.1.end], "-b * f(c)");
+    assert_eq!(&src[mul.lhs.1.start..mul.lhs.1.end], "-b");
+    assert_eq!(&src[mul.rhs.1.start..mul.rhs.1.end], "f(c)");
+
+    assert_eq!(
+        Span::start_end(4, 6).merge(Span::start_end(1, 5)),
+        Span::start_end(1, 6)
+    );
+}
@@ -3,7 +3,7 @@
 //!
 //! Code might be bad. Possibly.
 
-use std::{fmt::Display, ops::Not};
+use std::fmt::Display;
 
 use peekmore::PeekMore;
 
@@ -19,6 +19,10 @@ pub enum PToken<'src> {
     Punctuator(Punctuator),
     OtherNonWs(u8),
     Error,
+    /// Only emitted when the lexer keeps trivia.
+    Whitespace(&'src str),
+    /// Only emitted when the lexer keeps trivia.
+    Comment(&'src str),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -182,6 +186,8 @@ where
 {
     src_str: &'src str,
     src: peekmore::PeekMoreIterator<I>,
+    /// Whether whitespace and comments are returned as tokens instead of being skipped.
+    trivia: bool,
 }
 
 impl<'src, I> PLexer<'src, I>
@@ -192,9 +198,15 @@ where
         Self {
             src_str,
             src: src_iter.peekmore(),
+            trivia: false,
         }
     }
 
+    pub fn keep_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
     /// 6.4.2 Identifiers
     /// TODO: 6.4.3 Universal character names
     fn identifier(&mut self, mut last_span: usize) -> (PToken<'src>, usize) {
@@ -217,14 +229,19 @@ where
     }
 
     /// 6.4.8 Preprocessing numbers
+    /// Prefixes, suffixes and exponents like `0x`, `ul` and `e-3` are part of the number.
     fn number(&mut self, mut last_span: usize) -> (PToken<'src>, usize) {
         let first_span = last_span;
+        let mut prev = self.src_str.as_bytes()[first_span];
 
         while let Some((span, c)) = self.src.peek() {
             let (span, c) = (*span, *c);
-            if c.is_ascii_digit() {
+            let is_exponent_sign =
+                matches!(c, b'+' | b'-') && matches!(prev, b'e' | b'E' | b'p' | b'P');
+            if c.is_c_identifier() || c == b'.' || is_exponent_sign {
                 self.src.next();
                 last_span = span;
+                prev = c;
             } else {
                 break;
             }
@@ -237,26 +254,88 @@ where
     }
 
     /// 6.4.5 String literals
-    fn string_literal(&mut self, mut last_span: usize) -> (PToken<'src>, usize) {
-        let first_span = last_span;
+    /// Escape sequences are only checked here, the contents stay encoded in the token. Returns the
+    /// span of the first invalid escape sequence as the error.
+    fn string_literal(&mut self, first_span: usize) -> Result<(PToken<'src>, usize), Span> {
+        let mut error = None;
+
+        let last_span = loop {
+            match self.src.next() {
+                Some((span, b'"')) => break span,
+                Some((span, b'\\')) => match self.escape(span) {
+                    Ok(_) => {}
+                    Err(span) => {
+                        error.get_or_insert(span);
+                    }
+                },
+                Some(_) => {}
+                None => return Err(Span::start_end(first_span, self.src_str.len())),
+            }
+        };
 
+        match error {
+            Some(span) => Err(span),
+            None => Ok((
+                PToken::StringLiteral(&self.src_str[first_span + 1..last_span]),
+                last_span,
+            )),
+        }
+    }
+
+    /// 6.4.4.4 Character constants
+    /// Only constants containing a single character are supported. The rest of an invalid
+    /// constant is skipped up to the closing quote.
+    fn char_constant(&mut self, first_span: usize) -> Result<(PToken<'src>, usize), Span> {
+        let whole = |end| Span::start_end(first_span, end);
+        let mut value = match self.src.next() {
+            Some((span, b'\\')) => self.escape(span).map_err(Some),
+            Some((span, b'\'')) => return Err(whole(span + 1)),
+            Some((_, b'\n')) | None => return Err(whole(first_span + 1)),
+            Some((_, c)) => Ok(c),
+        };
         loop {
-            let next = self.src.next();
-            match next {
-                Some((span, c)) => {
-                    if c == b'"' {
-                        break;
+            match self.src.peek() {
+                Some(&(last_span, b'\'')) => {
+                    self.src.next();
+                    return match value {
+                        Ok(value) => Ok((PToken::CharConstant(value), last_span)),
+                        Err(span) => Err(span.unwrap_or(whole(last_span + 1))),
+                    };
+                }
+                Some(&(_, b'\n')) | None => {
+                    return Err(value.err().flatten().unwrap_or(whole(first_span + 1)))
+                }
+                Some(&(span, c)) => {
+                    self.src.next();
+                    if c == b'\\' {
+                        let _ = self.escape(span);
                     }
-                    last_span = span;
+                    // Multi-character constants have an implementation-defined value.
+                    value = value.and(Err(None));
                 }
-                None => return (PToken::Error, last_span),
             }
         }
+    }
 
-        (
-            PToken::StringLiteral(&self.src_str[first_span + 1..=last_span]),
-            last_span,
-        )
+    /// Consumes the escape sequence after the backslash at `backslash_span`.
+    fn escape(&mut self, backslash_span: usize) -> Result<u8, Span> {
+        let rest = &self.src_str.as_bytes()[backslash_span + 1..];
+        match escape_sequence(rest) {
+            Some((value, len)) => {
+                for _ in 0..len {
+                    self.src.next();
+                }
+                Ok(value)
+            }
+            None => {
+                // Skip the escaped character so that `\"` doesn't end the literal.
+                let len = if rest.is_empty() { 0 } else { 1 };
+                for _ in 0..len {
+                    self.src.next();
+                }
+                Err(Span::start_end(backslash_span, backslash_span + 1 + len))
+            }
+        }
     }
 
     /// source peek
@@ -316,7 +395,7 @@ where
     ///   header-name         TODO
     ///   identifier
     ///   pp-number
-    ///   character-constant  TODO
+    ///   character-constant
     ///   string-literal
     ///   punctuator
     ///   each non-white-space character that cannot be one of the above
@@ -338,20 +417,58 @@ where
                 }
                 // NUMBER
                 (c, _, _) if c.is_c_identifier_digit() => break self.number(start_span),
+                (b'.', Some(c), _) if c.is_c_identifier_digit() => break self.number(start_span),
                 // STRING
-                (b'"', _, _) => break self.string_literal(start_span),
+                (b'"', _, _) => match self.string_literal(start_span) {
+                    Ok(token) => break token,
+                    Err(span) => return Some((PToken::Error, span)),
+                },
+                // CHARACTER
+                (b'\'', _, _) => match self.char_constant(start_span) {
+                    Ok(token) => break token,
+                    Err(span) => return Some((PToken::Error, span)),
+                },
                 // WHITESPACE
-                (c, _, _) if c.is_c_whitespace() => {}
+                (c, _, _) if c.is_c_whitespace() => {
+                    if self.trivia {
+                        let mut end = start_span;
+                        while let Some(&(span, c)) = self.src.peek() {
+                            if !c.is_c_whitespace() {
+                                break;
+                            }
+                            self.src.next();
+                            end = span;
+                        }
+                        break (PToken::Whitespace(&self.src_str[start_span..=end]), end);
+                    }
+                }
                 // COMMENTS
-                (b'/', Some(b'*'), _) => loop {
-                    let (_, first) = self.src.next()?;
-                    let second = self.s_p().map(|(_, c)| *c);
-                    if first == b'*' && second == Some(b'/') {
+                (b'/', Some(b'*'), _) => {
+                    let end = loop {
+                        let (_, first) = self.src.next()?;
+                        let second = self.s_p().map(|(_, c)| *c);
+                        if first == b'*' && second == Some(b'/') {
+                            break self.src.next()?.0;
+                        }
+                    };
+                    if self.trivia {
+                        break (PToken::Comment(&self.src_str[start_span..=end]), end);
+                    }
+                }
+                (b'/', Some(b'/'), _) => {
+                    // The newline isn't part of the comment.
+                    let mut end = start_span;
+                    while let Some(&(span, c)) = self.src.peek() {
+                        if c == b'\n' {
+                            break;
+                        }
                         self.src.next();
-                        break;
+                        end = span;
                     }
-                },
-                (b'/', Some(b'/'), _) => while matches!(self.src.next(), Some((_, b'\n'))).not() {},
+                    if self.trivia {
+                        break (PToken::Comment(&self.src_str[start_span..=end]), end);
+                    }
+                }
                 // TRIPLE CHARACTER PUNCTUATION
                 (b'.', Some(b'.'), Some(b'.')) => triple_punct!(self, DotDotDot),
                 (b'<', Some(b'<'), Some(b'=')) => triple_punct!(self, LeftLeftChevronEq),
@@ -368,7 +485,7 @@ where
                 (b'-', Some(b'-'), _) => double_punct!(self, MinusMinus),
                 (b'<', Some(b'<'), _) => double_punct!(self, LeftLeftChevron),
                 (b'>', Some(b'>'), _) => double_punct!(self, RightRightChevron),
-                (b'<', Some(b'='), _) => double_punct!(self, PlusPlus),
+                (b'<', Some(b'='), _) => double_punct!(self, LeftChevronEq),
                 (b'>', Some(b'='), _) => double_punct!(self, RightChevronEq),
                 (b'=', Some(b'='), _) => double_punct!(self, EqEq),
                 (b'!', Some(b'='), _) => double_punct!(self, BangEq),
@@ -416,6 +533,64 @@ where
     }
 }
 
+/// 6.4.4.4 Escape sequences
+/// Decodes the escape sequence following a backslash at the start of `rest`. Returns the value
+/// and the length of the sequence without the backslash, or `None` for unknown escapes and
+/// values that don't fit into a byte.
+pub fn escape_sequence(rest: &[u8]) -> Option<(u8, usize)> {
+    let simple = match rest.first()? {
+        b'\'' => b'\'',
+        b'"' => b'"',
+        b'?' => b'?',
+        b'\\' => b'\\',
+        b'a' => 0x07,
+        b'b' => 0x08,
+        b'f' => 0x0c,
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        b'v' => 0x0b,
+        b'0'..=b'7' => {
+            let len = rest
+                .iter()
+                .take(3)
+                .take_while(|c| matches!(c, b'0'..=b'7'))
+                .count();
+            let value = u32::from_str_radix(std::str::from_utf8(&rest[..len]).ok()?, 8).ok()?;
+            return Some((u8::try_from(value).ok()?, len));
+        }
+        b'x' => {
+            let len = rest[1..]
+                .iter()
+                .take_while(|c| c.is_ascii_hexdigit())
+                .count();
+            let digits = std::str::from_utf8(&rest[1..][..len]).ok()?;
+            return Some((u8::from_str_radix(digits, 16).ok()?, len + 1));
+        }
+        _ => return None,
+    };
+    Some((simple, 1))
+}
+
+/// Decodes the escape sequences of a string literal that has already been checked by the lexer.
+pub fn unescape(literal: &str) -> Vec<u8> {
+    let bytes = literal.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            let (value, len) =
+                escape_sequence(&bytes[i + 1..]).expect("invalid escape in string literal");
+            result.push(value);
+            i += len + 1;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     macro_rules! lex_test {
@@ -470,4 +645,68 @@ struct_ptr→field;
 
         lex_test!(src);
     }
+
+    fn lex_one(src: &str) -> (super::PToken<'_>, crate::Span) {
+        let mut tokens = super::super::preprocess_tokens(src);
+        let token = tokens.next().unwrap();
+        assert!(tokens.next().is_none());
+        token
+    }
+
+    fn lex_string(src: &str) -> Vec<u8> {
+        match lex_one(src).0 {
+            super::PToken::StringLiteral(lit) => super::unescape(lit),
+            token => panic!("expected string literal, found {token:?}"),
+        }
+    }
+
+    fn lex_char(src: &str) -> u8 {
+        match lex_one(src).0 {
+            super::PToken::CharConstant(c) => c,
+            token => panic!("expected character constant, found {token:?}"),
+        }
+    }
+
+    #[test]
+    fn char_escapes() {
+        assert_eq!(lex_char(r"'a'"), b'a');
+        assert_eq!(lex_char(r"'\n'"), 10);
+        assert_eq!(lex_char(r"'\''"), b'\'');
+        assert_eq!(lex_char(r"'\\'"), b'\\');
+        assert_eq!(lex_char(r"'\0'"), 0);
+        assert_eq!(lex_char(r"'\101'"), b'A');
+        assert_eq!(lex_char(r"'\x7f'"), 0x7f);
+        assert_eq!(lex_char(r"'\a'"), 7);
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(lex_string(r#""a\tb""#), [97, 9, 98]);
+        assert_eq!(lex_string(r#""""#), []);
+        assert_eq!(lex_string(r#""\"hi\"\r\n""#), b"\"hi\"\r\n");
+        assert_eq!(lex_string(r#""\x41\1011\?""#), b"AA1?");
+    }
+
+    #[test]
+    fn comparison_operators() {
+        use super::{PToken::Punctuator as TokP, Punctuator as P};
+
+        assert!(matches!(lex_one("<").0, TokP(P::LeftChevron)));
+        assert!(matches!(lex_one("<=").0, TokP(P::LeftChevronEq)));
+        assert!(matches!(lex_one(">").0, TokP(P::RightChevron)));
+        assert!(matches!(lex_one(">=").0, TokP(P::RightChevronEq)));
+        assert!(matches!(lex_one("==").0, TokP(P::EqEq)));
+        assert!(matches!(lex_one("!=").0, TokP(P::BangEq)));
+    }
+
+    #[test]
+    fn unknown_escape() {
+        let (token, span) = lex_one(r#""ab\qc""#);
+        assert!(matches!(token, super::PToken::Error));
+        assert_eq!(span, crate::Span::start_end(3, 5));
+
+        let (token, span) = lex_one(r"'\q'");
+        assert!(matches!(token, super::PToken::Error));
+        assert_eq!(span, crate::Span::start_end(1, 3));
+    }
 }
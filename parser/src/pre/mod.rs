@@ -3,7 +3,7 @@ mod lexer;
 use std::{io, path::Path};
 
 use lexer::PLexer;
-pub use lexer::{PToken, Punctuator};
+pub use lexer::{unescape, PToken, Punctuator};
 
 use crate::Span;
 
@@ -50,3 +50,13 @@ pub fn preprocess_tokens(src: &str) -> impl Iterator<Item = (PToken<'_>, Span)>
 
     preprocessor
 }
+
+/// Like [`preprocess_tokens`], but whitespace and comments are kept as tokens.
+pub fn preprocess_tokens_with_trivia(src: &str) -> impl Iterator<Item = (PToken<'_>, Span)> {
+    let lexer = PLexer::new(src, src.bytes().enumerate()).keep_trivia();
+
+    Preprocessor {
+        lexer,
+        resolver: Box::new(Todo),
+    }
+}
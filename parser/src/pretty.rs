@@ -5,8 +5,9 @@ type Result = std::io::Result<()>;
 use crate::{
     ast::{
         ArithOpKind, Atom, BinaryOp, ComparisonKind, Decl, DeclAttr, DeclSpec, Declarator,
-        DirectDeclarator, Expr, ExprBinary, ExprPostfix, ExprUnary, ExternalDecl, FunctionDef,
-        FunctionParamDecl, InitDecl, IntSign, IntTyKind, NormalDecl, PostfixOp, Stmt,
+        Designator, DirectDeclarator, Expr, ExprBinary, ExprConditional, ExprPostfix, ExprUnary,
+        ExternalDecl, FloatKind, FunctionDef, FunctionParamDecl, InitDecl, Initializer, IntSign,
+        IntTyKind, NormalDecl, PostfixOp, Qualifiers, Stmt, StructSpecifier, TypeName,
         TypeSpecifier, UnaryOp,
     },
     sym::Symbol,
@@ -17,6 +18,8 @@ pub struct PrettyPrinter<W> {
     indent: usize,
     output: W,
     force_parens: bool,
+    /// Emit valid C, with statement semicolons and null statements that parse back.
+    c_source: bool,
 }
 
 const INDENT: &str = "    ";
@@ -27,6 +30,17 @@ impl<W: Write> PrettyPrinter<W> {
             indent: 0,
             output,
             force_parens,
+            c_source: false,
+        }
+    }
+
+    /// A printer whose output parses back to the same AST, see [`crate::print_c`].
+    pub fn c_source(output: W) -> Self {
+        Self {
+            indent: 0,
+            output,
+            force_parens: true,
+            c_source: true,
         }
     }
 
@@ -98,7 +112,35 @@ impl<W: Write> PrettyPrinter<W> {
         Ok(())
     }
 
+    /// prints the body of a control statement, which the parser stores as a list of statements
+    fn body(&mut self, body: &[(Stmt, Span)]) -> Result {
+        // `while (x);` has an empty compound as its body, but an empty block has no statements.
+        if let (true, [(Stmt::Compound(stmts), _)]) = (self.c_source, body) {
+            if stmts.is_empty() {
+                return self.string(";");
+            }
+        }
+        self.block(body)
+    }
+
     fn stmt(&mut self, stmt: &Stmt) -> Result {
+        self.stmt_inner(stmt)?;
+        let needs_semi = matches!(
+            stmt,
+            Stmt::Goto(_)
+                | Stmt::Continue
+                | Stmt::Break
+                | Stmt::Return(_)
+                | Stmt::Expr(_)
+                | Stmt::DoWhile { .. }
+        );
+        if self.c_source && needs_semi {
+            self.string(";")?;
+        }
+        Ok(())
+    }
+
+    fn stmt_inner(&mut self, stmt: &Stmt) -> Result {
         match stmt {
             Stmt::Decl(decl) => self.decl(decl, false),
             Stmt::Labeled { label, stmt } => {
@@ -109,6 +151,7 @@ impl<W: Write> PrettyPrinter<W> {
                 self.stmt(&stmt.0)?;
                 Ok(())
             }
+            Stmt::Compound(body) if body.is_empty() && self.c_source => self.string(";"),
             Stmt::Compound(body) => self.block(body),
             Stmt::If {
                 cond: (cond, _),
@@ -118,19 +161,55 @@ impl<W: Write> PrettyPrinter<W> {
                 self.string("if (")?;
                 self.expr(cond)?;
                 self.string(") ")?;
-                self.block(then)?;
+                self.body(then)?;
                 if let Some(block) = otherwise {
                     self.string(" else ")?;
-                    self.block(block)?;
+                    self.body(block)?;
                 }
                 Ok(())
             }
-            Stmt::Switch => todo!(),
+            Stmt::Switch {
+                cond: (cond, _),
+                body,
+            } => {
+                self.string("switch (")?;
+                self.expr(cond)?;
+                self.string(") ")?;
+                self.body(body)?;
+                Ok(())
+            }
+            Stmt::Case {
+                value: (value, _),
+                stmt,
+            } => {
+                self.string("case ")?;
+                self.expr(value)?;
+                self.string(":")?;
+                self.linebreak()?;
+                self.print_indent()?;
+                self.stmt(&stmt.0)?;
+                Ok(())
+            }
+            Stmt::Default(stmt) => {
+                self.string("default:")?;
+                self.linebreak()?;
+                self.print_indent()?;
+                self.stmt(&stmt.0)?;
+                Ok(())
+            }
             Stmt::While { cond, body } => {
                 self.string("while (")?;
-                self.expr(cond)?;
+                self.expr(&cond.0)?;
                 self.string(") ")?;
-                self.block(body)?;
+                self.body(body)?;
+                Ok(())
+            }
+            Stmt::DoWhile { body, cond } => {
+                self.string("do ")?;
+                self.body(body)?;
+                self.string(" while (")?;
+                self.expr(&cond.0)?;
+                self.string(")")?;
                 Ok(())
             }
             Stmt::For {
@@ -140,14 +219,13 @@ impl<W: Write> PrettyPrinter<W> {
                 post,
                 body,
             } => {
-                if init_expr.is_some() {
-                    todo!()
-                }
-
                 self.string("for (")?;
                 if let Some((decl, _)) = init_decl {
                     self.decl(decl, false)?;
                 } else {
+                    if let Some((init, _)) = init_expr {
+                        self.expr(init)?;
+                    }
                     self.string(";")?;
                 }
 
@@ -163,7 +241,7 @@ impl<W: Write> PrettyPrinter<W> {
                 }
 
                 self.string(") ")?;
-                self.block(body)?;
+                self.body(body)?;
 
                 Ok(())
             }
@@ -188,7 +266,13 @@ impl<W: Write> PrettyPrinter<W> {
 
     fn decl(&mut self, decl: &Decl, func: bool) -> Result {
         match decl {
-            Decl::StaticAssert => todo!(),
+            Decl::StaticAssert(assert) => {
+                self.string("_Static_assert(")?;
+                self.expr(&assert.cond.0)?;
+                self.string(", ")?;
+                self.string_literal(assert.msg.as_bytes())?;
+                self.string(")")?;
+            }
             Decl::Normal(normal_decl) => self.normal_decl(normal_decl)?,
         }
         if !func {
@@ -199,7 +283,9 @@ impl<W: Write> PrettyPrinter<W> {
 
     fn normal_decl(&mut self, decl: &NormalDecl) -> Result {
         self.decl_spec(&decl.decl_spec)?;
-        self.string(" ")?;
+        if !decl.init_declarators.is_empty() {
+            self.string(" ")?;
+        }
         let mut first = true;
         for declarator in &decl.init_declarators {
             if !first {
@@ -214,6 +300,7 @@ impl<W: Write> PrettyPrinter<W> {
 
     fn decl_spec(&mut self, decl_spec: &DeclSpec) -> Result {
         self.decl_attr(&decl_spec.attrs)?;
+        self.qualifiers(&decl_spec.quals)?;
         self.type_specifier(&decl_spec.ty)?;
         Ok(())
     }
@@ -222,11 +309,45 @@ impl<W: Write> PrettyPrinter<W> {
         self.declarator(&init_declarator.declarator)?;
         if let Some(init) = &init_declarator.init {
             self.string(" = ")?;
-            self.expr(&init.0)?;
+            self.initializer(&init.0)?;
         }
         Ok(())
     }
 
+    fn initializer(&mut self, init: &Initializer) -> Result {
+        match init {
+            Initializer::Expr(expr) => self.expr(expr),
+            Initializer::List(elems) => {
+                self.string("{")?;
+                let mut first = true;
+                for elem in elems {
+                    if !first {
+                        self.string(", ")?;
+                    }
+                    first = false;
+                    for designator in &elem.designators {
+                        match designator {
+                            Designator::Index((index, _)) => {
+                                self.string("[")?;
+                                self.expr(index)?;
+                                self.string("]")?;
+                            }
+                            Designator::Field((field, _)) => {
+                                self.string(".")?;
+                                self.sym(*field)?;
+                            }
+                        }
+                    }
+                    if !elem.designators.is_empty() {
+                        self.string(" = ")?;
+                    }
+                    self.initializer(&elem.init.0)?;
+                }
+                self.string("}")
+            }
+        }
+    }
+
     fn type_specifier(&mut self, spec: &TypeSpecifier) -> Result {
         match spec {
             TypeSpecifier::Void => self.string("void"),
@@ -249,7 +370,40 @@ impl<W: Write> PrettyPrinter<W> {
             TypeSpecifier::Float => self.string("float"),
             TypeSpecifier::Double => self.string("double"),
             TypeSpecifier::LongDouble => self.string("long double"),
+            TypeSpecifier::Struct(spec) => self.struct_specifier(spec),
+        }
+    }
+
+    fn struct_specifier(&mut self, spec: &StructSpecifier) -> Result {
+        self.string("struct")?;
+        if let Some((name, _)) = spec.name {
+            self.string(" ")?;
+            self.sym(name)?;
         }
+        if let Some(fields) = &spec.fields {
+            self.string(" {")?;
+            self.linebreak()?;
+            self.indent();
+            for (field, _) in fields {
+                self.print_indent()?;
+                self.decl_spec(&field.decl_spec.0)?;
+                self.string(" ")?;
+                let mut first = true;
+                for (declarator, _) in &field.declarators {
+                    if !first {
+                        self.string(", ")?;
+                    }
+                    first = false;
+                    self.declarator(declarator)?;
+                }
+                self.string(";")?;
+                self.linebreak()?;
+            }
+            self.dedent();
+            self.print_indent()?;
+            self.string("}")?;
+        }
+        Ok(())
     }
 
     fn decl_attr(&mut self, attr: &DeclAttr) -> Result {
@@ -263,6 +417,12 @@ impl<W: Write> PrettyPrinter<W> {
         if attr.contains(DeclAttr::THREAD_LOCAL) {
             attrs.push("_Thread_local");
         }
+        if attr.contains(DeclAttr::INLINE) {
+            attrs.push("inline");
+        }
+        if attr.contains(DeclAttr::NORETURN) {
+            attrs.push("_Noreturn");
+        }
         self.string(&attrs.join(" "))?;
         if !attrs.is_empty() {
             self.string(" ")?;
@@ -271,9 +431,20 @@ impl<W: Write> PrettyPrinter<W> {
         Ok(())
     }
 
+    fn qualifiers(&mut self, quals: &Qualifiers) -> Result {
+        if quals.contains(Qualifiers::CONST) {
+            self.string("const ")?;
+        }
+        if quals.contains(Qualifiers::VOLATILE) {
+            self.string("volatile ")?;
+        }
+        Ok(())
+    }
+
     fn declarator(&mut self, declarator: &Declarator) -> Result {
         if declarator.pointer {
             self.string("*")?;
+            self.qualifiers(&declarator.pointer_quals)?;
         }
         self.direct_declarator(&declarator.decl)?;
         Ok(())
@@ -282,13 +453,33 @@ impl<W: Write> PrettyPrinter<W> {
     fn direct_declarator(&mut self, declarator: &DirectDeclarator) -> Result {
         match declarator {
             DirectDeclarator::Ident(ident) => self.sym(ident.0),
-            DirectDeclarator::WithParams { ident, params } => {
+            DirectDeclarator::WithParams {
+                ident,
+                params,
+                variadic,
+            } => {
                 self.sym(ident.0)?;
                 self.string("(")?;
-                self.function_param_decls(params)?;
+                match params {
+                    Some(params) if params.is_empty() => self.string("void")?,
+                    Some(params) => self.function_param_decls(params)?,
+                    None => {}
+                }
+                if *variadic {
+                    self.string(", ...")?;
+                }
                 self.string(")")?;
                 Ok(())
             }
+            DirectDeclarator::Array { decl, size } => {
+                self.direct_declarator(decl)?;
+                self.string("[")?;
+                if let Some(size) = size {
+                    self.expr(&size.0)?;
+                }
+                self.string("]")?;
+                Ok(())
+            }
         }
     }
 
@@ -310,25 +501,36 @@ impl<W: Write> PrettyPrinter<W> {
         match expr {
             Expr::Atom(atom) => match atom {
                 Atom::Ident((ident, _)) => self.sym(*ident),
-                Atom::Int(int) => write!(self.output, "{}", int),
-                Atom::Float(float) => write!(self.output, "{}", float),
-                Atom::String(string) => {
-                    self.string("\"")?;
-                    // bare attempt at escpaing
-                    self.string(
-                        &std::str::from_utf8(string)
-                            .unwrap_or("<invalid utf-8>")
-                            .replace('\"', "\\\""),
-                    )?;
-                    self.string("\"")?;
-                    Ok(())
+                Atom::Int(int) => {
+                    // Octal and binary constants have the same types as hexadecimal ones.
+                    if int.decimal || int.value == 0 {
+                        write!(self.output, "{}", int.value)?;
+                    } else {
+                        write!(self.output, "{:#x}", int.value)?;
+                    }
+                    if int.unsigned {
+                        self.string("u")?;
+                    }
+                    match int.kind {
+                        IntTyKind::Long => self.string("l"),
+                        IntTyKind::LongLong => self.string("ll"),
+                        _ => Ok(()),
+                    }
+                }
+                Atom::Float(float) => {
+                    // Debug always prints a dot or exponent, so it's not mistaken for an int.
+                    write!(self.output, "{:?}", float.value)?;
+                    match float.kind {
+                        FloatKind::Float => self.string("f"),
+                        FloatKind::Double => Ok(()),
+                        FloatKind::LongDouble => self.string("l"),
+                    }
                 }
+                Atom::String(string) => self.string_literal(string),
                 Atom::Char(char) => {
                     self.string("'")?;
-                    let ascii = *char as char;
-                    write!(self.output, "{}", ascii)?;
-                    self.string("'")?;
-                    Ok(())
+                    self.escaped(*char)?;
+                    self.string("'")
                 }
             },
             Expr::Unary(unary) => self.unary(unary),
@@ -337,15 +539,33 @@ impl<W: Write> PrettyPrinter<W> {
                 lhs,
                 rhs,
             }) => {
-                self.expr(&lhs.as_ref().0)?;
+                self.postfix_lhs(&lhs.as_ref().0)?;
                 self.string("[")?;
                 self.expr(&rhs.as_ref().0)?;
                 self.string("]")?;
                 Ok(())
             }
             Expr::Binary(binary) => self.binary(binary),
+            Expr::Conditional(ExprConditional {
+                cond,
+                then,
+                otherwise,
+            }) => {
+                if self.force_parens {
+                    self.string("(")?;
+                }
+                self.expr(&cond.0)?;
+                self.string(" ? ")?;
+                self.expr(&then.0)?;
+                self.string(" : ")?;
+                self.expr(&otherwise.0)?;
+                if self.force_parens {
+                    self.string(")")?;
+                }
+                Ok(())
+            }
             Expr::Postfix(ExprPostfix { lhs, op }) => {
-                self.expr(&lhs.0)?;
+                self.postfix_lhs(&lhs.0)?;
                 match op {
                     PostfixOp::Call(args) => {
                         self.string("(")?;
@@ -374,9 +594,63 @@ impl<W: Write> PrettyPrinter<W> {
                     PostfixOp::Decrement => self.string("--"),
                 }
             }
+            Expr::SizeOfExpr(expr) => {
+                self.string("sizeof(")?;
+                self.expr(&expr.0)?;
+                self.string(")")
+            }
+            Expr::SizeOfType(type_name) => {
+                self.string("sizeof(")?;
+                self.type_name(type_name)?;
+                self.string(")")
+            }
         }
     }
 
+    /// Postfix operators bind tighter than prefix ones, `*(p)++` would increment `p`.
+    fn postfix_lhs(&mut self, lhs: &Expr) -> Result {
+        let prefix = matches!(
+            lhs,
+            Expr::Unary(_) | Expr::SizeOfExpr(_) | Expr::SizeOfType(_)
+        );
+        if self.force_parens && prefix {
+            self.string("(")?;
+            self.expr(lhs)?;
+            self.string(")")
+        } else {
+            self.expr(lhs)
+        }
+    }
+
+    fn string_literal(&mut self, string: &[u8]) -> Result {
+        self.string("\"")?;
+        for &c in string {
+            self.escaped(c)?;
+        }
+        self.string("\"")
+    }
+
+    fn escaped(&mut self, c: u8) -> Result {
+        match c {
+            b'\n' => self.string("\\n"),
+            b'\t' => self.string("\\t"),
+            b'\r' => self.string("\\r"),
+            b'\\' | b'\'' | b'"' => write!(self.output, "\\{}", c as char),
+            b' '..=b'~' => write!(self.output, "{}", c as char),
+            // Octal escapes end after three digits, unlike hex escapes, which would swallow a
+            // following hex digit.
+            _ => write!(self.output, "\\{c:03o}"),
+        }
+    }
+
+    fn type_name(&mut self, type_name: &TypeName) -> Result {
+        self.decl_spec(&type_name.decl_spec.0)?;
+        if type_name.pointer {
+            self.string("*")?;
+        }
+        Ok(())
+    }
+
     fn unary(&mut self, unary: &ExprUnary) -> Result {
         self.string(match unary.op {
             UnaryOp::Increment => "++",
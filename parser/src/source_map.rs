@@ -0,0 +1,114 @@
+use crate::Span;
+
+/// A 1-based line and column. Columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+/// Maps byte offsets into the source to lines and columns. The start of every line and the
+/// multibyte characters are found once, so lookups are only binary searches.
+pub struct SourceMap<'src> {
+    src: &'src str,
+    line_starts: Vec<usize>,
+    /// The offset of every character that takes more than one byte, with the number of extra
+    /// bytes taken by it and all multibyte characters before it.
+    multibyte_chars: Vec<(usize, usize)>,
+}
+
+impl<'src> SourceMap<'src> {
+    pub fn new(src: &'src str) -> Self {
+        // `\r\n` ends a line at the `\n` as well, the `\r` is the last character of the line.
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let mut extra_bytes = 0;
+        let multibyte_chars = src
+            .char_indices()
+            .filter(|(_, c)| c.len_utf8() > 1)
+            .map(|(i, c)| {
+                extra_bytes += c.len_utf8() - 1;
+                (i, extra_bytes)
+            })
+            .collect();
+        Self {
+            src,
+            line_starts,
+            multibyte_chars,
+        }
+    }
+
+    /// Offsets past the end of the source are clamped to the end.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.src.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        // Every character is one column, no matter how many bytes it takes.
+        let col = offset
+            - line_start
+            - (self.extra_bytes_before(offset) - self.extra_bytes_before(line_start));
+        LineCol {
+            line: (line + 1).try_into().unwrap(),
+            col: (col + 1).try_into().unwrap(),
+        }
+    }
+
+    /// The number of bytes that multibyte characters starting before `offset` take beyond their
+    /// first one.
+    fn extra_bytes_before(&self, offset: usize) -> usize {
+        match self
+            .multibyte_chars
+            .partition_point(|&(start, _)| start < offset)
+        {
+            0 => 0,
+            i => self.multibyte_chars[i - 1].1,
+        }
+    }
+
+    pub fn line_col_span(&self, span: Span) -> (LineCol, LineCol) {
+        (self.line_col(span.start), self.line_col(span.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineCol, SourceMap};
+    use crate::Span;
+
+    fn lc(line: u32, col: u32) -> LineCol {
+        LineCol { line, col }
+    }
+
+    #[test]
+    fn line_col() {
+        let src = "int x;\nint f() {\r\n    return x;\r\n}";
+        let map = SourceMap::new(src);
+
+        assert_eq!(map.line_col(0), lc(1, 1));
+        assert_eq!(map.line_col(src.find('f').unwrap()), lc(2, 5));
+        // The `\r` is still part of the line.
+        assert_eq!(map.line_col(src.find('\r').unwrap()), lc(2, 10));
+
+        let ret = src.find("return").unwrap();
+        assert_eq!(
+            map.line_col_span(Span::start_end(ret, ret + 6)),
+            (lc(3, 5), lc(3, 11))
+        );
+        assert_eq!(map.line_col(src.len()), lc(4, 2));
+        assert_eq!(map.line_col(src.len() + 10), lc(4, 2));
+    }
+
+    #[test]
+    fn multibyte_chars() {
+        let src = "// ü\nint ö;";
+        let map = SourceMap::new(src);
+        assert_eq!(map.line_col(src.find(';').unwrap()), lc(2, 6));
+        assert_eq!(map.line_col(src.find('ö').unwrap()), lc(2, 5));
+        assert_eq!(map.line_col(src.find('\n').unwrap()), lc(1, 5));
+
+        let src = "\"€𝄞\" x";
+        let map = SourceMap::new(src);
+        assert_eq!(map.line_col(src.find('x').unwrap()), lc(1, 6));
+    }
+}
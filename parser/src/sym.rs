@@ -7,6 +7,10 @@ use std::{
 use dbg_pls::DebugPls;
 use lasso::Spur;
 
+/// An interned string, used for identifiers.
+///
+/// The interner is thread local, so symbols are only meaningful on the thread that interned them
+/// and they are neither `Send` nor `Sync`.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Symbol {
     spur: Spur,
@@ -25,14 +29,31 @@ impl Symbol {
         })
     }
 
+    /// Returns the symbol for `s` if it has been interned already, without interning it.
+    pub fn get(s: &str) -> Option<Self> {
+        INTERNER.with(|i| {
+            i.borrow().get(s).map(|spur| Symbol {
+                spur,
+                not_send: PhantomData,
+            })
+        })
+    }
+
+    /// Calls `f` with the string of the symbol. The interner is borrowed while `f` runs, so `f`
+    /// must not intern new symbols. Use `to_string` to get an owned copy instead.
     pub fn as_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
-        INTERNER.with(|i| f(i.borrow_mut().resolve(&self.spur)))
+        INTERNER.with(|i| f(i.borrow().resolve(&self.spur)))
+    }
+
+    /// The amount of distinct strings interned on this thread.
+    pub fn interned_count() -> usize {
+        INTERNER.with(|i| i.borrow().len())
     }
 }
 
 impl Debug for Symbol {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        INTERNER.with(|i| f.write_str(i.borrow_mut().resolve(&self.spur)))
+        self.as_str(|s| f.write_str(s))
     }
 }
 
@@ -42,8 +63,36 @@ impl DebugPls for Symbol {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_str(|s| serializer.serialize_str(s))
+    }
+}
+
 impl Display for Symbol {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        INTERNER.with(|i| f.write_str(i.borrow_mut().resolve(&self.spur)))
+        self.as_str(|s| f.write_str(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Symbol;
+
+    #[test]
+    fn intern_and_resolve() {
+        let count = Symbol::interned_count();
+        assert_eq!(Symbol::get("intern_and_resolve"), None);
+
+        let a = Symbol::intern("intern_and_resolve");
+        let b = Symbol::intern(&String::from("intern_and_resolve"));
+        assert_eq!(a, b);
+        assert_ne!(a, Symbol::intern("intern_and_resolve2"));
+        assert_eq!(Symbol::interned_count(), count + 2);
+
+        assert_eq!(Symbol::get("intern_and_resolve"), Some(a));
+        assert_eq!(a.as_str(str::len), 18);
+        assert_eq!(a.to_string(), "intern_and_resolve");
     }
 }
@@ -1,7 +1,9 @@
 use std::fmt::Display;
 
+pub use crate::pre::Punctuator;
 use crate::{
-    pre::{PToken, Punctuator},
+    ast::{FloatConstant, FloatKind, IntConstant, IntTyKind},
+    pre::PToken,
     Span,
 };
 
@@ -19,6 +21,10 @@ pub enum Token<'src> {
     StringLiteral(&'src str),
     Punct(Punctuator),
     Error,
+    /// Only returned by [`tokenize`](crate::tokenize) with trivia.
+    Whitespace(&'src str),
+    /// Only returned by [`tokenize`](crate::tokenize) with trivia.
+    Comment(&'src str),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -71,8 +77,8 @@ pub enum Keyword {
 
 #[derive(Debug, Clone, Copy)]
 pub enum Constant {
-    Int(u128),
-    Float(f64),
+    Int(IntConstant),
+    Float(FloatConstant),
     Char(u8),
     // adding enumerations here makes no sense.
 }
@@ -127,9 +133,101 @@ fn ident_to_keyword(ident: &str) -> Option<Keyword> {
     }
 }
 
+/// Returns `None` for invalid constants and integers that don't even fit into a `u128`.
 fn pp_number_to_constant(number: &str) -> Option<Constant> {
-    let n = number.parse().ok()?;
-    Some(Constant::Int(n))
+    let hex = strip_prefix_ignore_case(number, "0x");
+    let is_float = match hex {
+        Some(hex) => hex.contains(['.', 'p', 'P']),
+        None => number.contains(['.', 'e', 'E']),
+    };
+    if is_float {
+        float_constant(number, hex.is_some()).map(Constant::Float)
+    } else {
+        int_constant(number).map(Constant::Int)
+    }
+}
+
+/// (6.4.4.2) Floating constants. Hexadecimal ones must have a binary exponent like `0x1.8p3`.
+fn float_constant(number: &str, is_hex: bool) -> Option<FloatConstant> {
+    let (number, kind) = match number.as_bytes().last()? {
+        b'f' | b'F' => (&number[..number.len() - 1], FloatKind::Float),
+        b'l' | b'L' => (&number[..number.len() - 1], FloatKind::LongDouble),
+        _ => (number, FloatKind::Double),
+    };
+
+    let value = if !is_hex {
+        // Rust accepts a few more forms like `inf`, but numbers always start with a digit or
+        // a dot, so that's fine.
+        if !number
+            .bytes()
+            .all(|c| c.is_ascii_digit() || b".eE+-".contains(&c))
+        {
+            return None;
+        }
+        number.parse().ok()?
+    } else {
+        // The suffix was removed, but the prefix is still there.
+        let (mantissa, exponent) = number[2..].split_once(['p', 'P'])?;
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+        let mut value = 0.0_f64;
+        for c in int_part.chars().chain(frac_part.chars()) {
+            value = value * 16.0 + f64::from(c.to_digit(16)?);
+        }
+        let exponent = exponent.parse::<i32>().ok()?;
+        value * 2_f64.powi(exponent - 4 * frac_part.len() as i32)
+    };
+    Some(FloatConstant { value, kind })
+}
+
+/// (6.4.4.1) Integer constants.
+fn int_constant(number: &str) -> Option<IntConstant> {
+    let (radix, digits) = if let Some(hex) = strip_prefix_ignore_case(number, "0x") {
+        (16, hex)
+    } else if let Some(binary) = strip_prefix_ignore_case(number, "0b") {
+        (2, binary)
+    } else if number.starts_with('0') {
+        (8, number)
+    } else {
+        (10, number)
+    };
+
+    let (digits, suffix) = digits.split_at(
+        digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len()),
+    );
+    if digits.is_empty() {
+        return None;
+    }
+    let (unsigned, kind) = match suffix {
+        "" => (false, IntTyKind::Int),
+        "u" | "U" => (true, IntTyKind::Int),
+        "l" | "L" => (false, IntTyKind::Long),
+        "ul" | "uL" | "Ul" | "UL" | "lu" | "lU" | "Lu" | "LU" => (true, IntTyKind::Long),
+        "ll" | "LL" => (false, IntTyKind::LongLong),
+        "ull" | "uLL" | "Ull" | "ULL" | "llu" | "llU" | "LLu" | "LLU" => {
+            (true, IntTyKind::LongLong)
+        }
+        _ => return None,
+    };
+
+    let value = u128::from_str_radix(digits, radix).ok()?;
+    Some(IntConstant {
+        value,
+        unsigned,
+        kind,
+        decimal: radix == 10,
+    })
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    match s.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&s[prefix.len()..]),
+        _ => None,
+    }
 }
 
 pub fn pre_tokens_to_tokens<'src>(
@@ -150,6 +248,8 @@ pub fn pre_tokens_to_tokens<'src>(
             PToken::Punctuator(p) => Token::Punct(p),
             PToken::OtherNonWs(_) => Token::Error,
             PToken::Error => Token::Error,
+            PToken::Whitespace(ws) => Token::Whitespace(ws),
+            PToken::Comment(comment) => Token::Comment(comment),
         };
         (token, span)
     })
@@ -164,6 +264,8 @@ impl Display for Token<'_> {
             Token::StringLiteral(str) => write!(f, "\"{}\"", str),
             Token::Punct(p) => Display::fmt(p, f),
             Token::Error => f.write_str("<invalid token>"),
+            Token::Whitespace(ws) => f.write_str(ws),
+            Token::Comment(comment) => f.write_str(comment),
         }
     }
 }
@@ -222,8 +324,8 @@ impl Display for Keyword {
 impl Display for Constant {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Constant::Int(int) => Display::fmt(int, f),
-            Constant::Float(float) => Display::fmt(float, f),
+            Constant::Int(int) => Display::fmt(&int.value, f),
+            Constant::Float(float) => Display::fmt(&float.value, f),
             Constant::Char(c) => write!(f, "'{}'", *c as char),
         }
     }
@@ -231,6 +333,9 @@ impl Display for Constant {
 
 #[cfg(test)]
 mod tests {
+    use super::{Constant, Token};
+    use crate::ast::{FloatConstant, FloatKind, IntConstant, IntTyKind};
+
     macro_rules! lex_test {
         ($src:expr) => {
             let pre_tokens = crate::pre::preprocess_tokens($src);
@@ -249,4 +354,90 @@ int main() {
 "#;
         lex_test!(src);
     }
+
+    fn lex_int(src: &str) -> Option<IntConstant> {
+        let pre_tokens = crate::pre::preprocess_tokens(src);
+        let tokens = super::pre_tokens_to_tokens(pre_tokens).collect::<Vec<_>>();
+        match tokens[..] {
+            [(Token::Constant(Constant::Int(int)), _)] => Some(int),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn int_bases() {
+        let value = |src| lex_int(src).map(|int| (int.value, int.decimal));
+        assert_eq!(value("1234"), Some((1234, true)));
+        assert_eq!(value("0x1F"), Some((31, false)));
+        assert_eq!(value("0XaBc"), Some((0xabc, false)));
+        assert_eq!(value("0755"), Some((0o755, false)));
+        assert_eq!(value("0b1010"), Some((10, false)));
+        assert_eq!(value("0"), Some((0, false)));
+
+        assert_eq!(value("09"), None);
+        assert_eq!(value("0x"), None);
+        assert_eq!(value("0b102"), None);
+        assert_eq!(value("1a"), None);
+        assert_eq!(value("0x1_0000_0000_0000_0000_0000_0000_0000_0000"), None);
+        assert_eq!(value("340282366920938463463374607431768211456"), None);
+    }
+
+    #[test]
+    fn int_suffixes() {
+        let suffix = |src| lex_int(src).map(|int| (int.unsigned, int.kind));
+        assert_eq!(suffix("1"), Some((false, IntTyKind::Int)));
+        assert_eq!(suffix("1u"), Some((true, IntTyKind::Int)));
+        assert_eq!(suffix("1U"), Some((true, IntTyKind::Int)));
+        assert_eq!(suffix("1l"), Some((false, IntTyKind::Long)));
+        assert_eq!(suffix("1L"), Some((false, IntTyKind::Long)));
+        assert_eq!(suffix("1ul"), Some((true, IntTyKind::Long)));
+        assert_eq!(suffix("1Lu"), Some((true, IntTyKind::Long)));
+        assert_eq!(suffix("1ll"), Some((false, IntTyKind::LongLong)));
+        assert_eq!(suffix("1LL"), Some((false, IntTyKind::LongLong)));
+        assert_eq!(suffix("0x1ull"), Some((true, IntTyKind::LongLong)));
+        assert_eq!(suffix("0b1LLU"), Some((true, IntTyKind::LongLong)));
+
+        assert_eq!(suffix("1lL"), None);
+        assert_eq!(suffix("1uu"), None);
+        assert_eq!(suffix("1lul"), None);
+        assert_eq!(suffix("1lll"), None);
+    }
+
+    fn lex_float(src: &str) -> Option<FloatConstant> {
+        let pre_tokens = crate::pre::preprocess_tokens(src);
+        let tokens = super::pre_tokens_to_tokens(pre_tokens).collect::<Vec<_>>();
+        match tokens[..] {
+            [(Token::Constant(Constant::Float(float)), _)] => Some(float),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn floats() {
+        let float = |src| lex_float(src).map(|float| (float.value, float.kind));
+        assert_eq!(float("1.5"), Some((1.5, FloatKind::Double)));
+        assert_eq!(float("1."), Some((1.0, FloatKind::Double)));
+        assert_eq!(float(".5f"), Some((0.5, FloatKind::Float)));
+        assert_eq!(float("2.0L"), Some((2.0, FloatKind::LongDouble)));
+        assert_eq!(float("1e3"), Some((1000.0, FloatKind::Double)));
+        assert_eq!(float("1.5E-3F"), Some((0.0015, FloatKind::Float)));
+        assert_eq!(float("2e+2l"), Some((200.0, FloatKind::LongDouble)));
+
+        assert_eq!(float("1e"), None);
+        assert_eq!(float("1.5x"), None);
+        assert_eq!(float("1.5ff"), None);
+        assert_eq!(float("1..5"), None);
+    }
+
+    #[test]
+    fn hex_floats() {
+        let float = |src| lex_float(src).map(|float| (float.value, float.kind));
+        assert_eq!(float("0x1.8p3"), Some((12.0, FloatKind::Double)));
+        assert_eq!(float("0X.8P-1f"), Some((0.25, FloatKind::Float)));
+        assert_eq!(float("0xAp0L"), Some((10.0, FloatKind::LongDouble)));
+
+        // The binary exponent is required.
+        assert_eq!(float("0x1.8"), None);
+        assert_eq!(float("0x.p1"), None);
+    }
 }
@@ -1,10 +1,16 @@
 use std::io::Read;
 
 use analysis::LoweringCx;
+use ariadne::ReportKind;
 use parser::Error;
 
 fn main() {
-    let input_file = std::env::args().nth(1).expect("first argument");
+    // `--strict` turns warnings about functions that don't return a value into errors.
+    let strict = std::env::args().skip(1).any(|arg| arg == "--strict");
+    let input_file = std::env::args()
+        .skip(1)
+        .find(|arg| arg != "--strict")
+        .expect("first argument");
 
     let (filename, src) = if input_file == "-" {
         let mut buf = String::new();
@@ -33,17 +39,35 @@ fn main() {
 
     let arena = bumpalo::Bump::new();
     let mut lcx = LoweringCx::new(&arena);
+    lcx.strict = strict;
 
     println!("-------- IR");
-    let ir = analysis::lower_translation_unit(&mut lcx, &ast)
-        .unwrap_or_else(|err| report_fatal(&filename, &src, err));
+    let ir = analysis::lower_translation_unit(&mut lcx, &ast);
+    for warning in lcx.take_warnings() {
+        report(&filename, &src, warning, ReportKind::Warning);
+    }
+    let ir = ir.unwrap_or_else(|errs| {
+        for err in errs {
+            report(&filename, &src, err, ReportKind::Error);
+        }
+        std::process::exit(1);
+    });
+    print!(
+        "{}",
+        analysis::ir::ir_to_string(&ir, &analysis::ir::pretty::DefaultCustomizer::default())
+    );
 
     println!("-------- ASM");
     codegen::generate(&lcx, &ir).unwrap_or_else(|err| report_fatal(&filename, &src, err));
 }
 
 fn report_fatal(filename: &str, source: &str, error: Error) -> ! {
-    use ariadne::{Label, Report, ReportKind, Source};
+    report(filename, source, error, ReportKind::Error);
+    std::process::exit(1);
+}
+
+fn report(filename: &str, source: &str, error: Error, kind: ReportKind) {
+    use ariadne::{Label, Report, Source};
 
     let line = match error.span {
         Some(span) => {
@@ -60,7 +84,7 @@ fn report_fatal(filename: &str, source: &str, error: Error) -> ! {
         None => 0,
     };
 
-    let mut rep = Report::build(ReportKind::Error, filename, line).with_message(&error.msg);
+    let mut rep = Report::build(kind, filename, line).with_message(&error.msg);
 
     if let Some(span) = error.span {
         rep = rep
@@ -71,6 +95,4 @@ fn report_fatal(filename: &str, source: &str, error: Error) -> ! {
     rep.finish()
         .eprint((filename, Source::from(source)))
         .unwrap();
-
-    std::process::exit(1);
 }